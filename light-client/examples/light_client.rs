@@ -93,6 +93,7 @@ fn make_instance(
         trust_threshold: TrustThreshold::default(),
         trusting_period: Duration::from_secs(36000),
         clock_drift: Duration::from_secs(1),
+        verification_mode: light_client::VerificationMode::default(),
     };
 
     let builder =
@@ -1,5 +1,8 @@
 //! DSL for building light clients and supervisor
 
+mod checkpoint;
+pub use checkpoint::Checkpoint;
+
 mod light_client;
 pub use light_client::LightClientBuilder;
 
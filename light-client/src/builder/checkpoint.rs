@@ -0,0 +1,243 @@
+//! Weak-subjectivity checkpoints to bootstrap light clients from
+
+use serde::{Deserialize, Serialize};
+use tendermint::{block::Height, chain, Hash};
+
+use crate::bail;
+use crate::builder::error::{self, Error};
+use crate::operations::Hasher;
+use crate::types::LightBlock;
+
+/// A weak-subjectivity checkpoint, ie. a header obtained out-of-band from a trusted source,
+/// which light clients can be bootstrapped from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Identifier of the chain
+    pub chain_id: chain::Id,
+    /// Height of the header
+    pub height: Height,
+    /// Hash of the header
+    pub hash: Hash,
+    /// Hash of the validator set at that height, if known
+    #[serde(default)]
+    pub validators_hash: Option<Hash>,
+}
+
+impl Checkpoint {
+    /// Check that the given light block is the one this checkpoint refers to.
+    pub fn check(&self, light_block: &LightBlock, hasher: &dyn Hasher) -> Result<(), Error> {
+        let header = &light_block.signed_header.header;
+
+        if header.height != self.height {
+            bail!(error::Kind::HeightMismatch {
+                given: self.height,
+                found: header.height,
+            });
+        }
+
+        if header.chain_id != self.chain_id {
+            bail!(error::Kind::ChainIdMismatch {
+                given: self.chain_id.clone(),
+                found: header.chain_id.clone(),
+            });
+        }
+
+        let header_hash = hasher.hash_header(header);
+        if header_hash != self.hash {
+            bail!(error::Kind::HashMismatch {
+                given: self.hash,
+                found: header_hash,
+            });
+        }
+
+        match self.validators_hash {
+            Some(validators_hash) if validators_hash != header.validators_hash => {
+                bail!(error::Kind::ValidatorsHashMismatch {
+                    given: validators_hash,
+                    found: header.validators_hash,
+                });
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+    use std::time::Duration;
+
+    use tendermint_testgen::{light_block::default_peer_id, Generator, LightChain};
+
+    use crate::builder::light_client::{LightClientBuilder, NoTrustedState};
+    use crate::components::clock::Clock;
+    use crate::components::io::{AtHeight, IoError};
+    use crate::components::scheduler;
+    use crate::components::verifier::ProdVerifier;
+    use crate::light_client::Options;
+    use crate::operations::ProdHasher;
+    use crate::predicates::ProdPredicates;
+    use crate::store::{sled::SledStore, LightStore};
+    use crate::types::{PeerId, Status, Time};
+
+    #[derive(Clone, Debug)]
+    struct MockClock {
+        now: Time,
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Time {
+            self.now
+        }
+    }
+
+    fn make_block(chain: &LightChain) -> LightBlock {
+        let lb = chain.light_blocks.last().unwrap().generate().unwrap();
+        LightBlock::new(
+            lb.signed_header,
+            lb.validators,
+            lb.next_validators,
+            default_peer_id(),
+        )
+    }
+
+    fn checkpoint_of(light_block: &LightBlock) -> Checkpoint {
+        let header = &light_block.signed_header.header;
+
+        Checkpoint {
+            chain_id: header.chain_id.clone(),
+            height: header.height,
+            hash: ProdHasher.hash_header(header),
+            validators_hash: Some(header.validators_hash),
+        }
+    }
+
+    fn make_builder(
+        peer_id: PeerId,
+        light_block: LightBlock,
+        db: &sled::Db,
+    ) -> LightClientBuilder<NoTrustedState> {
+        let options = Options {
+            trust_threshold: Default::default(),
+            trusting_period: Duration::from_secs(60 * 60 * 24 * 10),
+            clock_drift: Duration::from_secs(10),
+            verification_mode: Default::default(),
+        };
+
+        let now = light_block.signed_header.header.time;
+        let io = move |_: AtHeight| -> Result<LightBlock, IoError> { Ok(light_block.clone()) };
+
+        LightClientBuilder::custom(
+            peer_id,
+            options,
+            Box::new(SledStore::new(db.clone())),
+            Box::new(io),
+            Box::new(ProdHasher),
+            Box::new(MockClock { now }),
+            Box::new(ProdVerifier::default()),
+            Box::new(scheduler::basic_bisecting_schedule),
+            Box::new(ProdPredicates),
+        )
+    }
+
+    fn peer(n: u8) -> PeerId {
+        PeerId::new([n; 20])
+    }
+
+    #[test]
+    fn check_rejects_other_headers() {
+        let light_block = make_block(&LightChain::default_with_length(3));
+        let checkpoint = checkpoint_of(&light_block);
+
+        assert!(checkpoint.check(&light_block, &ProdHasher).is_ok());
+
+        let other = Checkpoint {
+            height: 2u32.into(),
+            ..checkpoint.clone()
+        };
+        assert!(matches!(
+            other.check(&light_block, &ProdHasher).unwrap_err().kind(),
+            error::Kind::HeightMismatch { .. }
+        ));
+
+        let other = Checkpoint {
+            chain_id: "other-chain".parse().unwrap(),
+            ..checkpoint.clone()
+        };
+        assert!(matches!(
+            other.check(&light_block, &ProdHasher).unwrap_err().kind(),
+            error::Kind::ChainIdMismatch { .. }
+        ));
+
+        let other = Checkpoint {
+            validators_hash: Some(Hash::Sha256([0; 32])),
+            ..checkpoint
+        };
+        assert!(matches!(
+            other.check(&light_block, &ProdHasher).unwrap_err().kind(),
+            error::Kind::ValidatorsHashMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn cross_checked_checkpoint_is_trusted_by_all_peers() {
+        let light_block = make_block(&LightChain::default_with_length(3));
+        let checkpoint = checkpoint_of(&light_block);
+
+        let dbs: Vec<_> = (0..3)
+            .map(|_| sled::Config::new().temporary(true).open().unwrap())
+            .collect();
+
+        let builders = dbs
+            .iter()
+            .enumerate()
+            .map(|(i, db)| make_builder(peer(i as u8), light_block.clone(), db))
+            .collect();
+
+        let builders =
+            LightClientBuilder::trust_checkpoint_cross_checked(builders, &checkpoint).unwrap();
+        assert_eq!(builders.len(), 3);
+
+        for db in &dbs {
+            let store = SledStore::new(db.clone());
+            assert_eq!(
+                store.get(checkpoint.height, Status::Trusted),
+                Some(light_block.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn cross_checked_checkpoint_is_rejected_on_disagreement() {
+        let light_block = make_block(&LightChain::default_with_length(3));
+        let checkpoint = checkpoint_of(&light_block);
+
+        let mut forked_block = light_block.clone();
+        forked_block.signed_header.header.app_hash = vec![1, 2, 3].try_into().unwrap();
+
+        let dbs: Vec<_> = (0..3)
+            .map(|_| sled::Config::new().temporary(true).open().unwrap())
+            .collect();
+
+        let builders = vec![
+            make_builder(peer(0), light_block.clone(), &dbs[0]),
+            make_builder(peer(1), forked_block, &dbs[1]),
+            make_builder(peer(2), light_block, &dbs[2]),
+        ];
+
+        let err = LightClientBuilder::trust_checkpoint_cross_checked(builders, &checkpoint)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.kind(),
+            &error::Kind::CheckpointRejected { peer: peer(1) }
+        );
+
+        for db in &dbs {
+            let store = SledStore::new(db.clone());
+            assert!(store.latest_trusted_or_verified().is_none());
+        }
+    }
+}
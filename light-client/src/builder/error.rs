@@ -3,10 +3,11 @@
 use anomaly::BoxError;
 use anomaly::Context;
 use tendermint::block::Height;
-use tendermint::Hash;
+use tendermint::{chain, Hash};
 use thiserror::Error;
 
 use crate::components::io::IoError;
+use crate::types::PeerId;
 
 /// An error raised by the builder
 pub type Error = anomaly::Error<Kind>;
@@ -36,6 +37,31 @@ pub enum Kind {
         found: Hash,
     },
 
+    /// Chain identifier mismatch
+    #[error("chain id mismatch: given = {given}, found = {found}")]
+    ChainIdMismatch {
+        /// Chain identifier of trusted header
+        given: chain::Id,
+        /// Chain identifier of fetched header
+        found: chain::Id,
+    },
+
+    /// Validator set hash mismatch
+    #[error("validators hash mismatch: given = {given}, found = {found}")]
+    ValidatorsHashMismatch {
+        /// Validator set hash of trusted header
+        given: Hash,
+        /// Validator set hash of fetched header
+        found: Hash,
+    },
+
+    /// A peer disagrees with the checkpoint
+    #[error("peer {peer} disagrees with the checkpoint")]
+    CheckpointRejected {
+        /// Peer which disagrees with the checkpoint
+        peer: PeerId,
+    },
+
     /// Invalid light block
     #[error("invalid light block")]
     InvalidLightBlock,
@@ -3,24 +3,30 @@
 use tendermint::{block::Height, Hash};
 
 use crate::bail;
+use crate::builder::checkpoint::Checkpoint;
 use crate::builder::error::{self, Error};
 use crate::components::clock::Clock;
 use crate::components::io::{AtHeight, Io};
 use crate::components::scheduler::Scheduler;
 use crate::components::verifier::Verifier;
 use crate::light_client::{LightClient, Options};
+use crate::metrics::Metrics;
 use crate::operations::Hasher;
 use crate::predicates::VerificationPredicates;
 use crate::state::{State, VerificationTrace};
-use crate::store::LightStore;
+use crate::store::{LightStore, TraceStore};
 use crate::supervisor::Instance;
 use crate::types::{LightBlock, PeerId, Status};
 
 #[cfg(feature = "rpc-client")]
 use {
-    crate::components::clock::SystemClock, crate::components::io::ProdIo,
-    crate::components::scheduler, crate::components::verifier::ProdVerifier,
-    crate::operations::ProdHasher, crate::predicates::ProdPredicates, std::time::Duration,
+    crate::components::clock::SystemClock,
+    crate::components::io::{CachingIo, ProdIo},
+    crate::components::scheduler,
+    crate::components::verifier::ProdVerifier,
+    crate::operations::ProdHasher,
+    crate::predicates::ProdPredicates,
+    std::time::Duration,
     tendermint_rpc as rpc,
 };
 
@@ -42,6 +48,8 @@ pub struct LightClientBuilder<State> {
     scheduler: Box<dyn Scheduler>,
     predicates: Box<dyn VerificationPredicates>,
     light_store: Box<dyn LightStore>,
+    trace_store: Option<Box<dyn TraceStore>>,
+    metrics: Option<Metrics>,
 
     #[allow(dead_code)]
     state: State,
@@ -60,13 +68,30 @@ impl<Current> LightClientBuilder<Current> {
             scheduler: self.scheduler,
             predicates: self.predicates,
             light_store: self.light_store,
+            trace_store: self.trace_store,
+            metrics: self.metrics,
             state,
         }
     }
+
+    /// Persist the traces of the verified blocks in the given trace store
+    pub fn trace_store(mut self, trace_store: Box<dyn TraceStore>) -> Self {
+        self.trace_store = Some(trace_store);
+        self
+    }
+
+    /// Record the metrics of the verifications performed by the light client
+    /// in the given metrics
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
 }
 
 impl LightClientBuilder<NoTrustedState> {
     /// Initialize a builder for a production (non-mock) light client.
+    ///
+    /// The light blocks are fetched by a `ProdIo` component, and cached by a `CachingIo` one.
     #[cfg(feature = "rpc-client")]
     pub fn prod(
         peer_id: PeerId,
@@ -79,13 +104,42 @@ impl LightClientBuilder<NoTrustedState> {
             peer_id,
             options,
             light_store,
-            Box::new(ProdIo::new(peer_id, rpc_client, timeout)),
+            Box::new(CachingIo::new(ProdIo::new(peer_id, rpc_client, timeout))),
+            Box::new(ProdHasher),
+            Box::new(SystemClock),
+            Box::new(ProdVerifier::default()),
+            Box::new(scheduler::basic_bisecting_schedule),
+            Box::new(ProdPredicates),
+        )
+    }
+
+    /// Initialize a builder for a production (non-mock) light client, which records
+    /// the metrics of its verifications and of its RPC requests in the given metrics.
+    ///
+    /// The light blocks are fetched by a `ProdIo` component, and cached by a `CachingIo` one.
+    #[cfg(feature = "rpc-client")]
+    pub fn prod_with_metrics(
+        peer_id: PeerId,
+        rpc_client: rpc::HttpClient,
+        light_store: Box<dyn LightStore>,
+        options: Options,
+        timeout: Option<Duration>,
+        metrics: Metrics,
+    ) -> Self {
+        let io = ProdIo::new(peer_id, rpc_client, timeout).with_metrics(metrics.clone());
+
+        Self::custom(
+            peer_id,
+            options,
+            light_store,
+            Box::new(CachingIo::new(io)),
             Box::new(ProdHasher),
             Box::new(SystemClock),
             Box::new(ProdVerifier::default()),
             Box::new(scheduler::basic_bisecting_schedule),
             Box::new(ProdPredicates),
         )
+        .metrics(metrics)
     }
 
     /// Initialize a builder for a custom light client, by providing all dependencies upfront.
@@ -111,6 +165,8 @@ impl LightClientBuilder<NoTrustedState> {
             scheduler,
             options,
             predicates,
+            trace_store: None,
+            metrics: None,
             state: NoTrustedState,
         }
     }
@@ -169,6 +225,59 @@ impl LightClientBuilder<NoTrustedState> {
         self.trust_light_block(trusted_state)
     }
 
+    /// Set the block from the primary peer at the given checkpoint as the trusted state.
+    pub fn trust_checkpoint(
+        self,
+        checkpoint: &Checkpoint,
+    ) -> Result<LightClientBuilder<HasTrustedState>, Error> {
+        let trusted_state = self.fetch_checkpoint(checkpoint)?;
+        self.trust_light_block(trusted_state)
+    }
+
+    /// Set the block at the given checkpoint as the trusted state of all the given builders,
+    /// provided the peers of all of them agree on it.
+    ///
+    /// The block is fetched from every peer and checked against the checkpoint before
+    /// any trusted state is set, so that no light store is written to if a single peer
+    /// disagrees with the checkpoint.
+    pub fn trust_checkpoint_cross_checked(
+        builders: Vec<Self>,
+        checkpoint: &Checkpoint,
+    ) -> Result<Vec<LightClientBuilder<HasTrustedState>>, Error> {
+        let trusted_states = builders
+            .iter()
+            .map(|builder| {
+                builder.fetch_checkpoint(checkpoint).map_err(|e| {
+                    error::Kind::CheckpointRejected {
+                        peer: builder.peer_id,
+                    }
+                    .context(e)
+                    .into()
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        builders
+            .into_iter()
+            .zip(trusted_states)
+            .map(|(builder, trusted_state)| builder.trust_light_block(trusted_state))
+            .collect()
+    }
+
+    /// Fetch the block at the given checkpoint from the primary peer,
+    /// and check that it matches the checkpoint and is valid.
+    fn fetch_checkpoint(&self, checkpoint: &Checkpoint) -> Result<LightBlock, Error> {
+        let light_block = self
+            .io
+            .fetch_light_block(AtHeight::At(checkpoint.height))
+            .map_err(error::Kind::Io)?;
+
+        checkpoint.check(&light_block, &*self.hasher)?;
+        self.validate(&light_block)?;
+
+        Ok(light_block)
+    }
+
     fn validate(&self, light_block: &LightBlock) -> Result<(), Error> {
         let header = &light_block.signed_header.header;
         let now = self.clock.now();
@@ -200,6 +309,7 @@ impl LightClientBuilder<HasTrustedState> {
         let state = State {
             light_store: self.light_store,
             verification_trace: VerificationTrace::new(),
+            trace_store: self.trace_store,
         };
 
         let light_client = LightClient::from_boxed(
@@ -208,9 +318,15 @@ impl LightClientBuilder<HasTrustedState> {
             self.clock,
             self.scheduler,
             self.verifier,
+            self.hasher,
             self.io,
         );
 
+        let light_client = match self.metrics {
+            Some(metrics) => light_client.with_metrics(metrics),
+            None => light_client,
+        };
+
         Instance::new(light_client, state)
     }
 }
@@ -3,7 +3,9 @@ use std::time::Duration;
 use tendermint::net;
 
 use crate::builder::error::{self, Error};
+use crate::metrics::Metrics;
 use crate::peer_list::{PeerList, PeerListBuilder};
+use crate::store::RetentionPolicy;
 use crate::supervisor::Instance;
 use crate::types::PeerId;
 
@@ -23,6 +25,8 @@ pub struct SupervisorBuilder<State> {
     instances: PeerListBuilder<Instance>,
     addresses: PeerListBuilder<net::Address>,
     evidence_reporting_timeout: Option<Duration>,
+    retention: Option<(RetentionPolicy, Duration)>,
+    metrics: Option<Metrics>,
     #[allow(dead_code)]
     state: State,
 }
@@ -34,6 +38,8 @@ impl<Current> SupervisorBuilder<Current> {
             instances: self.instances,
             addresses: self.addresses,
             evidence_reporting_timeout: self.evidence_reporting_timeout,
+            retention: self.retention,
+            metrics: self.metrics,
             state,
         }
     }
@@ -43,6 +49,18 @@ impl<Current> SupervisorBuilder<Current> {
         self.evidence_reporting_timeout = timeout;
         self
     }
+
+    /// Set the retention policy of the light stores, and the interval at which they are pruned
+    pub fn retention_policy(mut self, policy: RetentionPolicy, interval: Duration) -> Self {
+        self.retention = Some((policy, interval));
+        self
+    }
+
+    /// Record the outcome of fork detection and the replacements of peers in the given metrics
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
 }
 
 impl Default for SupervisorBuilder<Init> {
@@ -58,6 +76,8 @@ impl SupervisorBuilder<Init> {
             instances: PeerListBuilder::default(),
             addresses: PeerListBuilder::default(),
             evidence_reporting_timeout: None,
+            retention: None,
+            metrics: None,
             state: Init,
         }
     }
@@ -115,13 +135,25 @@ impl SupervisorBuilder<Done> {
     #[cfg(feature = "rpc-client")]
     pub fn build_prod(self) -> Supervisor {
         let timeout = self.evidence_reporting_timeout;
+        let retention = self.retention;
+        let metrics = self.metrics.clone();
         let (instances, addresses) = self.inner();
 
-        Supervisor::new(
+        let supervisor = Supervisor::new(
             instances,
             ProdForkDetector::default(),
             ProdEvidenceReporter::new(addresses.into_values(), timeout),
-        )
+        );
+
+        let supervisor = match retention {
+            Some((policy, interval)) => supervisor.with_retention_policy(policy, interval),
+            None => supervisor,
+        };
+
+        match metrics {
+            Some(metrics) => supervisor.with_metrics(metrics),
+            None => supervisor,
+        }
     }
 
     /// Get the underlying list of instances and addresses.
@@ -1,7 +1,8 @@
 //! Provides an interface and a default implementation of the `Io` component
 
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{future::Future, sync::Arc, time::Duration};
 use thiserror::Error;
 
 #[cfg(feature = "rpc-client")]
@@ -9,7 +10,10 @@ use tendermint_rpc::Client;
 
 use tendermint_rpc as rpc;
 
-use crate::types::{Height, LightBlock};
+use crate::types::{Hash, Height, LightBlock, ValidatorSet};
+
+mod caching;
+pub use caching::CachingIo;
 
 /// Type for selecting either a specific height or the latest one
 pub enum AtHeight {
@@ -60,10 +64,42 @@ impl IoError {
     }
 }
 
+/// Validator sets already known to the caller of an `Io` or `AsyncIo` component,
+/// by hash (see `Io::fetch_light_blocks_given_validators`).
+pub trait KnownValidatorSets: Send + Sync {
+    /// Get the known validator set with the given hash, if any.
+    fn get(&self, hash: &Hash) -> Option<ValidatorSet>;
+}
+
 /// Interface for fetching light blocks from a full node, typically via the RPC client.
 pub trait Io: Send + Sync {
     /// Fetch a light block at the given height from a peer
     fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError>;
+
+    /// Fetch the light blocks at the given heights from a peer, in the same order.
+    ///
+    /// The default implementation fetches them one after the other,
+    /// implementations may fetch them concurrently.
+    fn fetch_light_blocks(&self, heights: Vec<Height>) -> Vec<Result<LightBlock, IoError>> {
+        heights
+            .into_iter()
+            .map(|height| self.fetch_light_block(AtHeight::At(height)))
+            .collect()
+    }
+
+    /// Fetch the light blocks at the given heights from a peer, in the same order,
+    /// given the validator sets already known to the caller.
+    ///
+    /// Implementations may use the known validator set whose hash the header of a light block
+    /// commits to instead of fetching it, for both its validators and its next validators.
+    /// The default implementation ignores the known validator sets, see `fetch_light_blocks`.
+    fn fetch_light_blocks_given_validators(
+        &self,
+        heights: Vec<Height>,
+        _known: Arc<dyn KnownValidatorSets>,
+    ) -> Vec<Result<LightBlock, IoError>> {
+        self.fetch_light_blocks(heights)
+    }
 }
 
 impl<F: Send + Sync> Io for F
@@ -75,6 +111,55 @@ where
     }
 }
 
+/// Interface for fetching light blocks from a full node, typically via the RPC client.
+///
+/// This is the asynchronous counterpart of `Io`, for use with the `AsyncLightClient`.
+/// Implementations must not block the calling task.
+#[async_trait]
+pub trait AsyncIo: Send + Sync {
+    /// Fetch a light block at the given height from a peer
+    async fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError>;
+
+    /// Fetch the light blocks at the given heights from a peer, in the same order.
+    ///
+    /// The default implementation fetches them one after the other,
+    /// implementations may fetch them concurrently.
+    async fn fetch_light_blocks(&self, heights: Vec<Height>) -> Vec<Result<LightBlock, IoError>> {
+        let mut light_blocks = Vec::with_capacity(heights.len());
+
+        for height in heights {
+            light_blocks.push(self.fetch_light_block(AtHeight::At(height)).await);
+        }
+
+        light_blocks
+    }
+
+    /// Fetch the light blocks at the given heights from a peer, in the same order,
+    /// given the validator sets already known to the caller.
+    ///
+    /// Implementations may use the known validator set whose hash the header of a light block
+    /// commits to instead of fetching it, for both its validators and its next validators.
+    /// The default implementation ignores the known validator sets, see `fetch_light_blocks`.
+    async fn fetch_light_blocks_given_validators(
+        &self,
+        heights: Vec<Height>,
+        _known: Arc<dyn KnownValidatorSets>,
+    ) -> Vec<Result<LightBlock, IoError>> {
+        self.fetch_light_blocks(heights).await
+    }
+}
+
+#[async_trait]
+impl<F, R> AsyncIo for F
+where
+    F: Fn(AtHeight) -> R + Send + Sync,
+    R: Future<Output = Result<LightBlock, IoError>> + Send,
+{
+    async fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+        self(height).await
+    }
+}
+
 #[cfg(feature = "rpc-client")]
 pub use self::prod::ProdIo;
 
@@ -82,46 +167,91 @@ pub use self::prod::ProdIo;
 mod prod {
     use super::*;
 
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
-    use crate::bail;
+    use futures::{stream, StreamExt};
+
+    use crate::metrics::Metrics;
     use crate::types::PeerId;
     use crate::utils::block_on;
 
-    use tendermint::account::Id as TMAccountId;
     use tendermint::block::signed_header::SignedHeader as TMSignedHeader;
     use tendermint::validator::Set as TMValidatorSet;
 
     /// Production implementation of the Io component, which fetches
     /// light blocks from full nodes via RPC.
+    ///
+    /// Implements both `Io` and `AsyncIo`. The former runs the RPC requests
+    /// to completion on a separate thread, while the latter runs them on the
+    /// executor of the caller.
+    ///
+    /// The RPC requests needed to build a light block are performed concurrently,
+    /// and so are the fetches of up to `max_concurrency` light blocks at a time.
+    /// The validator sets of a light block are not fetched if they are known to the caller
+    /// and its header commits to them (see `Io::fetch_light_blocks_given_validators`).
+    ///
+    /// The timeout, if any, applies to each RPC request on its own.
+    ///
+    /// If metrics are set, the time it takes to fetch every light block is recorded,
+    /// and so are the errors which occur, by peer.
     #[derive(Clone, Debug)]
     pub struct ProdIo {
         peer_id: PeerId,
         rpc_client: rpc::HttpClient,
         timeout: Option<Duration>,
+        max_concurrency: usize,
+        metrics: Option<Metrics>,
     }
 
     impl Io for ProdIo {
         fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
-            let signed_header = self.fetch_signed_header(height)?;
-            let height = signed_header.header.height;
-            let proposer_address = signed_header.header.proposer_address;
+            let io = self.clone();
 
-            let validator_set = self.fetch_validator_set(height.into(), Some(proposer_address))?;
-            let next_validator_set = self.fetch_validator_set(height.increment().into(), None)?;
+            // The timeout is enforced on each RPC request by `AsyncIo::fetch_light_block`
+            block_on(None, async move {
+                AsyncIo::fetch_light_block(&io, height).await
+            })?
+        }
 
-            let light_block = LightBlock::new(
-                signed_header,
-                validator_set,
-                next_validator_set,
-                self.peer_id,
-            );
+        fn fetch_light_blocks(&self, heights: Vec<Height>) -> Vec<Result<LightBlock, IoError>> {
+            self.block_on_fetches(heights, None)
+        }
 
-            Ok(light_block)
+        fn fetch_light_blocks_given_validators(
+            &self,
+            heights: Vec<Height>,
+            known: Arc<dyn KnownValidatorSets>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            self.block_on_fetches(heights, Some(known))
+        }
+    }
+
+    #[async_trait]
+    impl AsyncIo for ProdIo {
+        async fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+            self.fetch_observed(height, None).await
+        }
+
+        async fn fetch_light_blocks(
+            &self,
+            heights: Vec<Height>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            self.fetch_concurrently(heights, None).await
+        }
+
+        async fn fetch_light_blocks_given_validators(
+            &self,
+            heights: Vec<Height>,
+            known: Arc<dyn KnownValidatorSets>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            self.fetch_concurrently(heights, Some(known)).await
         }
     }
 
     impl ProdIo {
+        /// Default maximum number of light blocks fetched concurrently.
+        pub const DEFAULT_MAX_CONCURRENCY: usize = 4;
+
         /// Constructs a new ProdIo component.
         ///
         /// A peer map which maps peer IDS to their network address must be supplied.
@@ -135,17 +265,138 @@ mod prod {
                 peer_id,
                 rpc_client,
                 timeout,
+                max_concurrency: Self::DEFAULT_MAX_CONCURRENCY,
+                metrics: None,
+            }
+        }
+
+        /// Record the latency and the errors of the fetches in the given metrics.
+        pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+            self.metrics = Some(metrics);
+            self
+        }
+
+        /// Set the maximum number of light blocks to fetch concurrently.
+        /// Defaults to `ProdIo::DEFAULT_MAX_CONCURRENCY`.
+        pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+            self.max_concurrency = max_concurrency.max(1);
+            self
+        }
+
+        /// Fetch the light blocks at the given heights on a separate thread, given the known
+        /// validator sets if any.
+        fn block_on_fetches(
+            &self,
+            heights: Vec<Height>,
+            known: Option<Arc<dyn KnownValidatorSets>>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            let len = heights.len();
+            let io = self.clone();
+
+            // Each RPC request is subject to the timeout on its own
+            match block_on(
+                None,
+                async move { io.fetch_concurrently(heights, known).await },
+            ) {
+                Ok(light_blocks) => light_blocks,
+                Err(e) => vec![Err(e); len],
             }
         }
 
-        fn fetch_signed_header(&self, height: AtHeight) -> Result<TMSignedHeader, IoError> {
-            let client = self.rpc_client.clone();
-            let res = block_on(self.timeout, async move {
-                match height {
-                    AtHeight::Highest => client.latest_commit().await,
-                    AtHeight::At(height) => client.commit(height).await,
+        /// Fetch up to `max_concurrency` of the light blocks at the given heights at a time,
+        /// given the known validator sets if any.
+        async fn fetch_concurrently(
+            &self,
+            heights: Vec<Height>,
+            known: Option<Arc<dyn KnownValidatorSets>>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            stream::iter(heights)
+                .map(|height| self.fetch_observed(AtHeight::At(height), known.clone()))
+                .buffered(self.max_concurrency)
+                .collect()
+                .await
+        }
+
+        /// Fetch the light block at the given height, given the known validator sets if any,
+        /// and record the latency of the fetch and its error if any in the metrics, if set.
+        async fn fetch_observed(
+            &self,
+            height: AtHeight,
+            known: Option<Arc<dyn KnownValidatorSets>>,
+        ) -> Result<LightBlock, IoError> {
+            let start = Instant::now();
+            let result = self.fetch_light_block_async(height, known).await;
+
+            if let Some(metrics) = &self.metrics {
+                metrics.observe_fetch(self.peer_id, start.elapsed(), result.as_ref().err());
+            }
+
+            result
+        }
+
+        /// Fetch the signed header and the validator sets of the light block at the given
+        /// height concurrently. The height of the latest block is not known in advance,
+        /// so its signed header must be fetched before its validator sets.
+        ///
+        /// If validator sets are known, the signed header is fetched first as well, and the
+        /// known validator sets the header commits to are used instead of fetching them.
+        async fn fetch_light_block_async(
+            &self,
+            height: AtHeight,
+            known: Option<Arc<dyn KnownValidatorSets>>,
+        ) -> Result<LightBlock, IoError> {
+            let (signed_header, validators, next_validators) = match (height, known) {
+                (AtHeight::At(height), None) => futures::try_join!(
+                    self.fetch_signed_header(AtHeight::At(height)),
+                    self.fetch_validators(height),
+                    self.fetch_validators(height.increment()),
+                )?,
+                (height, known) => {
+                    let signed_header = self.fetch_signed_header(height).await?;
+                    let header = &signed_header.header;
+                    let known = known.as_deref();
+
+                    let (validators, next_validators) = futures::try_join!(
+                        self.fetch_validators_unless_known(
+                            known,
+                            &header.validators_hash,
+                            header.height
+                        ),
+                        self.fetch_validators_unless_known(
+                            known,
+                            &header.next_validators_hash,
+                            header.height.increment()
+                        ),
+                    )?;
+
+                    (signed_header, validators, next_validators)
                 }
-            })?;
+            };
+
+            let validator_set =
+                TMValidatorSet::with_proposer(validators, signed_header.header.proposer_address)
+                    .map_err(|e| IoError::InvalidValidatorSet(e.to_string()))?;
+            let next_validator_set = TMValidatorSet::without_proposer(next_validators);
+
+            let light_block = LightBlock::new(
+                signed_header,
+                validator_set,
+                next_validator_set,
+                self.peer_id,
+            );
+
+            Ok(light_block)
+        }
+
+        async fn fetch_signed_header(&self, height: AtHeight) -> Result<TMSignedHeader, IoError> {
+            let res = self
+                .with_timeout(async {
+                    match height {
+                        AtHeight::Highest => self.rpc_client.latest_commit().await,
+                        AtHeight::At(height) => self.rpc_client.commit(height).await,
+                    }
+                })
+                .await?;
 
             match res {
                 Ok(response) => Ok(response.signed_header),
@@ -153,31 +404,40 @@ mod prod {
             }
         }
 
-        fn fetch_validator_set(
+        /// Fetch the validators at the given height, unless the validator set with the given
+        /// hash is known.
+        async fn fetch_validators_unless_known(
             &self,
-            height: AtHeight,
-            proposer_address: Option<TMAccountId>,
-        ) -> Result<TMValidatorSet, IoError> {
-            let height = match height {
-                AtHeight::Highest => bail!(IoError::InvalidHeight(
-                    "given height must be greater than 0".to_string()
-                )),
-                AtHeight::At(height) => height,
-            };
+            known: Option<&dyn KnownValidatorSets>,
+            hash: &Hash,
+            height: Height,
+        ) -> Result<Vec<tendermint::validator::Info>, IoError> {
+            match known.and_then(|known| known.get(hash)) {
+                Some(validator_set) => Ok(validator_set.validators().clone()),
+                None => self.fetch_validators(height).await,
+            }
+        }
 
-            let client = self.rpc_client.clone();
-            let response = block_on(self.timeout, async move { client.validators(height).await })?
+        async fn fetch_validators(
+            &self,
+            height: Height,
+        ) -> Result<Vec<tendermint::validator::Info>, IoError> {
+            let response = self
+                .with_timeout(self.rpc_client.validators(height))
+                .await?
                 .map_err(IoError::RpcError)?;
 
-            let validator_set = match proposer_address {
-                Some(proposer_address) => {
-                    TMValidatorSet::with_proposer(response.validators, proposer_address)
-                        .map_err(|e| IoError::InvalidValidatorSet(e.to_string()))?
-                }
-                None => TMValidatorSet::without_proposer(response.validators),
-            };
+            Ok(response.validators)
+        }
 
-            Ok(validator_set)
+        /// Run the given RPC request to completion, within the timeout of this component if any.
+        async fn with_timeout<F: Future>(&self, request: F) -> Result<F::Output, IoError> {
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, request)
+                    .await
+                    .map_err(|_| IoError::Timeout(timeout)),
+                None => Ok(request.await),
+            }
         }
     }
 }
@@ -0,0 +1,522 @@
+//! Caching decorator of the `Io` and `AsyncIo` components.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash as StdHash;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::types::{Hash, Height, LightBlock, ValidatorSet};
+
+use super::{AsyncIo, AtHeight, Io, IoError, KnownValidatorSets};
+
+/// Io component which keeps the most recently used light blocks fetched by the underlying
+/// `Io` or `AsyncIo` component in a bounded cache, along with their validator sets.
+///
+/// The validator sets are cached by hash in a cache of their own, and handed to the underlying
+/// component as the known validator sets (see `Io::fetch_light_blocks_given_validators`), so
+/// that `ProdIo` does not fetch them again as long as the validator set does not change,
+/// whether the light blocks it fetches are adjacent or not.
+///
+/// The latest light block is always fetched, and then cached under its height.
+/// The light blocks missing from the cache are fetched in a single batch, so that
+/// the underlying component may fetch them concurrently.
+#[derive(Debug)]
+pub struct CachingIo<I> {
+    io: I,
+    cache: Mutex<LruMap<Height, LightBlock>>,
+    validator_sets: Arc<ValidatorSetCache>,
+}
+
+impl<I> CachingIo<I> {
+    /// Default maximum number of light blocks kept in the cache.
+    pub const DEFAULT_CAPACITY: usize = 128;
+
+    /// Default maximum number of validator sets kept in the cache.
+    pub const DEFAULT_VALIDATOR_SETS_CAPACITY: usize = 16;
+
+    /// Constructs a new CachingIo component on top of the given one,
+    /// with a capacity of `CachingIo::DEFAULT_CAPACITY`.
+    pub fn new(io: I) -> Self {
+        Self::with_capacity(io, Self::DEFAULT_CAPACITY)
+    }
+
+    /// Constructs a new CachingIo component on top of the given one,
+    /// which keeps at most `capacity` light blocks in its cache, and at most
+    /// `CachingIo::DEFAULT_VALIDATOR_SETS_CAPACITY` validator sets.
+    pub fn with_capacity(io: I, capacity: usize) -> Self {
+        Self {
+            io,
+            cache: Mutex::new(LruMap::new(capacity)),
+            validator_sets: Arc::new(ValidatorSetCache(Mutex::new(LruMap::new(
+                Self::DEFAULT_VALIDATOR_SETS_CAPACITY,
+            )))),
+        }
+    }
+
+    /// Keep at most `capacity` validator sets in the cache.
+    /// Defaults to `CachingIo::DEFAULT_VALIDATOR_SETS_CAPACITY`.
+    pub fn with_validator_sets_capacity(mut self, capacity: usize) -> Self {
+        self.validator_sets = Arc::new(ValidatorSetCache(Mutex::new(LruMap::new(capacity))));
+        self
+    }
+
+    /// The underlying component
+    pub fn inner(&self) -> &I {
+        &self.io
+    }
+
+    /// Look up the light blocks at the given heights in the cache, and return them along
+    /// with the heights of the missing ones.
+    fn lookup(&self, heights: Vec<Height>) -> (Vec<Lookup>, Vec<Height>) {
+        let mut cache = self.cache.lock().unwrap();
+        let mut lookups = Vec::with_capacity(heights.len());
+        let mut missing = Vec::new();
+
+        for height in heights {
+            match cache.get(&height) {
+                Some(light_block) => lookups.push(Lookup::Cached(Box::new(light_block.clone()))),
+                None => {
+                    lookups.push(Lookup::Missing(height));
+                    missing.push(height);
+                }
+            }
+        }
+
+        (lookups, missing)
+    }
+
+    /// The validator sets known to this component, to hand to the underlying one.
+    fn known_validator_sets(&self) -> Arc<dyn KnownValidatorSets> {
+        self.validator_sets.clone()
+    }
+
+    /// Fill in the given lookups with the light blocks fetched for the missing ones,
+    /// and cache the latter.
+    fn complete(
+        &self,
+        lookups: Vec<Lookup>,
+        fetched: Vec<Result<LightBlock, IoError>>,
+    ) -> Vec<Result<LightBlock, IoError>> {
+        let mut fetched = fetched.into_iter();
+
+        lookups
+            .into_iter()
+            .map(|lookup| match lookup {
+                Lookup::Cached(light_block) => Ok(*light_block),
+                Lookup::Missing(height) => {
+                    let result = fetched.next().unwrap_or_else(|| {
+                        Err(IoError::InvalidHeight(format!(
+                            "no light block was fetched at height {}",
+                            height
+                        )))
+                    });
+
+                    self.remember(&result);
+                    result
+                }
+            })
+            .collect()
+    }
+
+    /// Cache the given light block and its validator sets, if it was fetched successfully.
+    fn remember(&self, result: &Result<LightBlock, IoError>) {
+        if let Ok(light_block) = result {
+            self.validator_sets.insert(&light_block.validators);
+            self.validator_sets.insert(&light_block.next_validators);
+
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(light_block.height(), light_block.clone());
+        }
+    }
+}
+
+impl<I: Io> Io for CachingIo<I> {
+    fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+        match height {
+            AtHeight::At(height) => self
+                .fetch_light_blocks(vec![height])
+                .pop()
+                .expect("one light block is fetched per height"),
+            AtHeight::Highest => {
+                let result = self.io.fetch_light_block(AtHeight::Highest);
+                self.remember(&result);
+                result
+            }
+        }
+    }
+
+    fn fetch_light_blocks(&self, heights: Vec<Height>) -> Vec<Result<LightBlock, IoError>> {
+        let (lookups, missing) = self.lookup(heights);
+
+        let fetched = if missing.is_empty() {
+            Vec::new()
+        } else {
+            self.io
+                .fetch_light_blocks_given_validators(missing, self.known_validator_sets())
+        };
+
+        self.complete(lookups, fetched)
+    }
+}
+
+#[async_trait]
+impl<I: AsyncIo> AsyncIo for CachingIo<I> {
+    async fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+        match height {
+            AtHeight::At(height) => self
+                .fetch_light_blocks(vec![height])
+                .await
+                .pop()
+                .expect("one light block is fetched per height"),
+            AtHeight::Highest => {
+                let result = self.io.fetch_light_block(AtHeight::Highest).await;
+                self.remember(&result);
+                result
+            }
+        }
+    }
+
+    async fn fetch_light_blocks(&self, heights: Vec<Height>) -> Vec<Result<LightBlock, IoError>> {
+        let (lookups, missing) = self.lookup(heights);
+
+        let fetched = if missing.is_empty() {
+            Vec::new()
+        } else {
+            self.io
+                .fetch_light_blocks_given_validators(missing, self.known_validator_sets())
+                .await
+        };
+
+        self.complete(lookups, fetched)
+    }
+}
+
+/// Outcome of the lookup of a light block in the cache.
+enum Lookup {
+    /// The light block was found in the cache
+    Cached(Box<LightBlock>),
+    /// The light block at the given height must be fetched
+    Missing(Height),
+}
+
+/// Most recently used validator sets, by hash.
+#[derive(Debug)]
+struct ValidatorSetCache(Mutex<LruMap<Hash, ValidatorSet>>);
+
+impl ValidatorSetCache {
+    /// Cache the given validator set under its hash.
+    fn insert(&self, validator_set: &ValidatorSet) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(validator_set.hash(), validator_set.clone());
+    }
+}
+
+impl KnownValidatorSets for ValidatorSetCache {
+    fn get(&self, hash: &Hash) -> Option<ValidatorSet> {
+        self.0.lock().unwrap().get(hash).cloned()
+    }
+}
+
+/// Map holding at most `capacity` entries, which evicts the least recently used entry when full.
+#[derive(Debug)]
+struct LruMap<K, V> {
+    capacity: usize,
+    /// The entries, along with the time at which they were last used
+    entries: HashMap<K, (V, u64)>,
+    /// The keys of the entries, by the time at which they were last used
+    recency: BTreeMap<u64, K>,
+    /// Incremented every time an entry is used
+    clock: u64,
+}
+
+impl<K: StdHash + Eq + Clone, V> LruMap<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Get the value of the given key, which becomes the most recently used one.
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let clock = self.tick();
+        let (value, last_used) = self.entries.get_mut(key)?;
+
+        self.recency.remove(last_used);
+        self.recency.insert(clock, key.clone());
+        *last_used = clock;
+
+        Some(value)
+    }
+
+    /// Insert the given value under the given key, which becomes the most recently used one,
+    /// and evict the least recently used entry if the map is full.
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let clock = self.tick();
+
+        if let Some((_, last_used)) = self.entries.remove(&key) {
+            self.recency.remove(&last_used);
+        } else if self.entries.len() == self.capacity {
+            let least_recent = self.recency.keys().next().copied();
+
+            if let Some(key) = least_recent.and_then(|last_used| self.recency.remove(&last_used)) {
+                self.entries.remove(&key);
+            }
+        }
+
+        self.recency.insert(clock, key.clone());
+        self.entries.insert(key, (value, clock));
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use tendermint_testgen::{light_block::default_peer_id, Generator, LightChain};
+
+    use futures::executor::block_on;
+
+    /// Io component serving a static chain, which records the requests it receives,
+    /// as the height of the light block and the number of validator sets it had to fetch,
+    /// ie. which were not known to the caller.
+    struct CountingIo {
+        light_blocks: HashMap<Height, LightBlock>,
+        latest: Height,
+        requests: Mutex<Vec<(u64, usize)>>,
+        batches: Mutex<usize>,
+    }
+
+    impl CountingIo {
+        fn new(light_blocks: Vec<LightBlock>) -> Self {
+            let latest = light_blocks.last().unwrap().height();
+
+            Self {
+                light_blocks: light_blocks
+                    .into_iter()
+                    .map(|lb| (lb.height(), lb))
+                    .collect(),
+                latest,
+                requests: Mutex::new(Vec::new()),
+                batches: Mutex::new(0),
+            }
+        }
+
+        fn light_block(
+            &self,
+            height: Height,
+            known: Option<&dyn KnownValidatorSets>,
+        ) -> Result<LightBlock, IoError> {
+            let light_block = self
+                .light_blocks
+                .get(&height)
+                .cloned()
+                .ok_or_else(|| IoError::InvalidHeight(height.to_string()))?;
+
+            let header = &light_block.signed_header.header;
+            let fetched = [header.validators_hash, header.next_validators_hash]
+                .iter()
+                .filter(|hash| match known.and_then(|known| known.get(hash)) {
+                    Some(validator_set) => {
+                        assert_eq!(validator_set.hash(), **hash);
+                        false
+                    }
+                    None => true,
+                })
+                .count();
+
+            self.requests
+                .lock()
+                .unwrap()
+                .push((height.value(), fetched));
+            Ok(light_block)
+        }
+
+        fn requests(&self) -> Vec<(u64, usize)> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl Io for CountingIo {
+        fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+            match height {
+                AtHeight::At(height) => self.light_block(height, None),
+                AtHeight::Highest => self.light_block(self.latest, None),
+            }
+        }
+
+        fn fetch_light_blocks_given_validators(
+            &self,
+            heights: Vec<Height>,
+            known: Arc<dyn KnownValidatorSets>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            *self.batches.lock().unwrap() += 1;
+
+            heights
+                .into_iter()
+                .map(|height| self.light_block(height, Some(known.as_ref())))
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl AsyncIo for CountingIo {
+        async fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+            Io::fetch_light_block(self, height)
+        }
+
+        async fn fetch_light_blocks_given_validators(
+            &self,
+            heights: Vec<Height>,
+            known: Arc<dyn KnownValidatorSets>,
+        ) -> Vec<Result<LightBlock, IoError>> {
+            Io::fetch_light_blocks_given_validators(self, heights, known)
+        }
+    }
+
+    fn make_chain(length: u64) -> Vec<LightBlock> {
+        LightChain::default_with_length(length)
+            .light_blocks
+            .into_iter()
+            .map(|lb| {
+                let lb = lb.generate().unwrap();
+                LightBlock::new(
+                    lb.signed_header,
+                    lb.validators,
+                    lb.next_validators,
+                    default_peer_id(),
+                )
+            })
+            .collect()
+    }
+
+    fn h(height: u64) -> Height {
+        height.try_into().unwrap()
+    }
+
+    fn heights(io: &CachingIo<CountingIo>) -> Vec<u64> {
+        io.inner()
+            .requests()
+            .into_iter()
+            .map(|(height, _)| height)
+            .collect()
+    }
+
+    #[test]
+    fn hands_cached_validator_sets_over() {
+        let chain = make_chain(5);
+        let io = CachingIo::new(CountingIo::new(chain.clone()));
+
+        for height in 1..=4 {
+            let light_block = Io::fetch_light_block(&io, AtHeight::At(h(height))).unwrap();
+            assert_eq!(light_block, chain[height as usize - 1]);
+        }
+
+        assert_eq!(io.inner().requests(), vec![(1, 2), (2, 0), (3, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn hands_cached_validator_sets_over_at_non_adjacent_heights() {
+        let chain = make_chain(9);
+
+        let io = CachingIo::new(CountingIo::new(chain.clone()));
+        for height in &[1, 5, 9] {
+            Io::fetch_light_block(&io, AtHeight::At(h(*height))).unwrap();
+        }
+        assert_eq!(io.inner().requests(), vec![(1, 2), (5, 0), (9, 0)]);
+
+        let io = CachingIo::new(CountingIo::new(chain)).with_validator_sets_capacity(0);
+        for height in &[1, 5, 9] {
+            Io::fetch_light_block(&io, AtHeight::At(h(*height))).unwrap();
+        }
+        assert_eq!(io.inner().requests(), vec![(1, 2), (5, 2), (9, 2)]);
+    }
+
+    #[test]
+    fn caches_light_blocks_but_always_fetches_the_latest_one() {
+        let io = CachingIo::new(CountingIo::new(make_chain(3)));
+
+        Io::fetch_light_block(&io, AtHeight::At(h(2))).unwrap();
+        Io::fetch_light_block(&io, AtHeight::At(h(2))).unwrap();
+        Io::fetch_light_block(&io, AtHeight::Highest).unwrap();
+        Io::fetch_light_block(&io, AtHeight::Highest).unwrap();
+        Io::fetch_light_block(&io, AtHeight::At(h(3))).unwrap();
+
+        assert_eq!(heights(&io), vec![2, 3, 3]);
+    }
+
+    #[test]
+    fn fetches_missing_light_blocks_in_a_single_batch() {
+        let chain = make_chain(5);
+        let io = CachingIo::new(CountingIo::new(chain.clone()));
+
+        Io::fetch_light_block(&io, AtHeight::At(h(3))).unwrap();
+
+        let light_blocks: Vec<_> = Io::fetch_light_blocks(&io, vec![h(5), h(3), h(1)])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            light_blocks,
+            vec![chain[4].clone(), chain[2].clone(), chain[0].clone()]
+        );
+
+        assert_eq!(heights(&io), vec![3, 5, 1]);
+        assert_eq!(*io.inner().batches.lock().unwrap(), 2);
+
+        // Nothing is fetched when all the light blocks are cached
+        Io::fetch_light_blocks(&io, vec![h(1), h(3), h(5)]);
+        assert_eq!(*io.inner().batches.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_light_blocks() {
+        let io = CachingIo::with_capacity(CountingIo::new(make_chain(4)), 2);
+
+        for height in &[1, 2, 1, 3, 1, 2] {
+            Io::fetch_light_block(&io, AtHeight::At(h(*height))).unwrap();
+        }
+
+        assert_eq!(heights(&io), vec![1, 2, 3, 2]);
+    }
+
+    #[test]
+    fn caches_light_blocks_fetched_asynchronously() {
+        let chain = make_chain(3);
+        let io = CachingIo::new(CountingIo::new(chain.clone()));
+
+        for _ in 0..2 {
+            let light_blocks = block_on(AsyncIo::fetch_light_blocks(&io, vec![h(1), h(2)]))
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>();
+            assert_eq!(light_blocks, chain[..2].to_vec());
+        }
+
+        let latest = block_on(AsyncIo::fetch_light_block(&io, AtHeight::Highest));
+        assert_eq!(latest.unwrap(), chain[2]);
+
+        // Both light blocks are fetched in the same batch, so the validator sets of the first
+        // one are not known yet when the second one is fetched, and the latest light block
+        // is fetched without the known validator sets
+        assert_eq!(io.inner().requests(), vec![(1, 2), (2, 2), (3, 2)]);
+    }
+}
@@ -53,6 +53,10 @@ pub trait Verifier: Send + Sync {
         options: &Options,
         now: Time,
     ) -> Verdict;
+
+    /// Check that the validator sets and the commit of the given light block match
+    /// its header, without checking whether it can be trusted.
+    fn validate(&self, light_block: &LightBlock) -> Verdict;
 }
 
 /// Production implementation of the verifier.
@@ -119,4 +123,14 @@ impl Verifier for ProdVerifier {
         )
         .into()
     }
+
+    fn validate(&self, light_block: &LightBlock) -> Verdict {
+        preds::validate(
+            &*self.predicates,
+            &*self.commit_validator,
+            &*self.hasher,
+            light_block,
+        )
+        .into()
+    }
 }
@@ -9,9 +9,10 @@ use thiserror::Error;
 
 use crate::{
     components::io::IoError,
+    evidence::Attack,
     light_client::Options,
     predicates::errors::VerificationError,
-    types::{Height, LightBlock, PeerId, Status},
+    types::{Hash, Height, LightBlock, PeerId, Status},
 };
 
 /// An error raised by this library
@@ -40,9 +41,30 @@ pub enum ErrorKind {
     #[error("no witness left")]
     NoWitnessLeft,
 
+    /// The peer is already known
+    #[error("peer {0} already exists")]
+    PeerAlreadyExists(PeerId),
+
+    /// The peer is unknown, or does not have the expected role
+    #[error("peer {0} not found")]
+    PeerNotFound(PeerId),
+
+    /// The peer sent provably invalid data, and is banned for good
+    #[error("peer {0} is faulty")]
+    PeerFaulty(PeerId),
+
     /// A fork has been detected between some peers
-    #[error("fork detected peers={0:?}")]
-    ForkDetected(Vec<PeerId>),
+    #[error("fork detected peers={peers:?} attacks=[{}]", display_attacks(.attacks))]
+    ForkDetected {
+        /// Peers which are on a fork
+        peers: Vec<PeerId>,
+        /// Light client attacks found while processing the fork
+        attacks: Vec<Attack>,
+    },
+
+    /// Evidence of a fork could not be constructed
+    #[error("invalid evidence")]
+    InvalidEvidence,
 
     /// No initial trusted state
     #[error("no initial trusted state")]
@@ -52,13 +74,19 @@ pub enum ErrorKind {
     #[error("no trusted state")]
     NoTrustedState(Status),
 
-    /// Target height for the light client lower than latest trusted state height
-    #[error("target height ({target_height}) is lower than trusted state ({trusted_height})")]
-    TargetLowerThanTrustedState {
-        /// Target height
-        target_height: Height,
-        /// Latest trusted state height
-        trusted_height: Height,
+    /// Missing last_block_id field for header at given height
+    #[error("missing last_block_id for header at height {0}")]
+    MissingLastBlockId(Height),
+
+    /// The hash of a header does not match the `last_block_id` of the header above it
+    #[error("invalid adjacent headers: header_hash={header_hash} last_block_id_hash={last_block_id_hash}")]
+    InvalidAdjacentHeaders {
+        /// Hash of the lower header
+        #[serde(with = "tendermint::serializers::hash")]
+        header_hash: Hash,
+        /// Hash of the `last_block_id` of the higher header
+        #[serde(with = "tendermint::serializers::hash")]
+        last_block_id_hash: Hash,
     },
 
     /// The trusted state is outside of the trusting period
@@ -78,11 +106,31 @@ pub enum ErrorKind {
     #[error("invalid light block: {0}")]
     InvalidLightBlock(#[source] VerificationError),
 
+    /// The result of an ABCI query could not be verified
+    #[error("invalid ABCI query result: {0}")]
+    InvalidQueryResult(String),
+
+    /// Invalid snapshot of the trusted state
+    #[error("invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
+    /// The operation is not supported by this implementation of a trait
+    #[error("unsupported operation: {0}")]
+    Unsupported(String),
+
     /// Internal channel disconnected
     #[error("internal channel disconnected")]
     ChannelDisconnected,
 }
 
+fn display_attacks(attacks: &[Attack]) -> String {
+    attacks
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 impl ErrorKind {
     /// Add additional context (i.e. include a source error and capture a backtrace).
     /// You can convert the resulting `Context` into an `Error` by calling `.into()`.
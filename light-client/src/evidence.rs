@@ -1,19 +1,181 @@
 //! Fork evidence data structures and interfaces.
 
-use crate::{components::io::IoError, types::PeerId};
+use crate::{
+    components::io::IoError,
+    types::{Height, LightBlock, PeerId, Validator},
+};
 
+use std::{collections::HashSet, fmt};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use tendermint::abci::transaction::Hash;
 
-use contracts::contract_trait;
+use contracts::{contract_trait, pre};
 
 pub use tendermint::evidence::Evidence;
 
+/// The type of a light client attack
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttackType {
+    /// The conflicting block contains state which cannot have been derived
+    /// from the common block, eg. a different validator set or app hash.
+    Lunatic,
+    /// Validators signed both conflicting blocks in the same round.
+    Equivocation,
+    /// Validators signed the conflicting blocks in different rounds.
+    Amnesia,
+}
+
+impl fmt::Display for AttackType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lunatic => write!(f, "lunatic"),
+            Self::Equivocation => write!(f, "equivocation"),
+            Self::Amnesia => write!(f, "amnesia"),
+        }
+    }
+}
+
+/// A light client attack, as found by comparing a conflicting block
+/// against a trusted block at the same height.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attack {
+    /// The type of the attack
+    pub attack_type: AttackType,
+    /// Height of the last block both chains have in common
+    pub common_height: Height,
+    /// Height of the conflicting block
+    pub conflicting_height: Height,
+    /// Validators which took part in the attack, sorted by voting power.
+    ///
+    /// Always empty for an amnesia attack, as the faulty validators
+    /// cannot be identified from the commits alone.
+    pub byzantine_validators: Vec<Validator>,
+}
+
+impl Attack {
+    /// Total voting power of the byzantine validators
+    pub fn byzantine_power(&self) -> u64 {
+        self.byzantine_validators.iter().map(|v| v.power()).sum()
+    }
+}
+
+impl fmt::Display for Attack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} attack at height {} (common height {}) by validators [",
+            self.attack_type, self.conflicting_height, self.common_height
+        )?;
+
+        for (i, validator) in self.byzantine_validators.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} ({})", validator.address, validator.power())?;
+        }
+
+        write!(f, "]")
+    }
+}
+
+/// Classify the attack by which the `conflicting` block conflicts with the `trusted` block,
+/// and extract the byzantine validators from the commits of both blocks.
+///
+/// The `common` block is the last block both chains have in common, ie. the bifurcation point.
+///
+/// - If the conflicting header commits to a different state than the trusted header,
+///   this is a lunatic attack, and the byzantine validators are the validators of the
+///   common block which signed the conflicting commit.
+/// - Otherwise, if both commits were made in the same round, this is an equivocation attack,
+///   and the byzantine validators are the ones which signed both commits.
+/// - Otherwise, this is an amnesia attack, and no byzantine validators can be found.
+#[pre(trusted.height() == conflicting.height())]
+pub fn classify_attack(
+    common: &LightBlock,
+    trusted: &LightBlock,
+    conflicting: &LightBlock,
+) -> Attack {
+    let trusted_commit = &trusted.signed_header.commit;
+    let conflicting_commit = &conflicting.signed_header.commit;
+
+    let (attack_type, mut byzantine_validators) = if is_lunatic(trusted, conflicting) {
+        let validators = conflicting_commit
+            .signatures
+            .iter()
+            .filter(|sig| sig.is_commit())
+            .filter_map(|sig| sig.validator_address())
+            .filter_map(|address| common.validators.validator(address))
+            .collect();
+
+        (AttackType::Lunatic, validators)
+    } else if trusted_commit.round == conflicting_commit.round {
+        let trusted_signers = trusted_commit
+            .signatures
+            .iter()
+            .filter(|sig| sig.is_commit())
+            .filter_map(|sig| sig.validator_address())
+            .collect::<HashSet<_>>();
+
+        let validators = conflicting_commit
+            .signatures
+            .iter()
+            .filter(|sig| sig.is_commit())
+            .filter_map(|sig| sig.validator_address())
+            .filter(|address| trusted_signers.contains(address))
+            .filter_map(|address| conflicting.validators.validator(address))
+            .collect();
+
+        (AttackType::Equivocation, validators)
+    } else {
+        (AttackType::Amnesia, Vec::new())
+    };
+
+    byzantine_validators.sort_by_key(|v| (std::cmp::Reverse(v.voting_power), v.address));
+
+    Attack {
+        attack_type,
+        common_height: common.height(),
+        conflicting_height: conflicting.height(),
+        byzantine_validators,
+    }
+}
+
+/// Whether the conflicting header commits to a different state than the trusted one,
+/// which can only be the case if it was not produced by following the state machine.
+fn is_lunatic(trusted: &LightBlock, conflicting: &LightBlock) -> bool {
+    let trusted = &trusted.signed_header.header;
+    let conflicting = &conflicting.signed_header.header;
+
+    trusted.validators_hash != conflicting.validators_hash
+        || trusted.next_validators_hash != conflicting.next_validators_hash
+        || trusted.consensus_hash != conflicting.consensus_hash
+        || trusted.app_hash != conflicting.app_hash
+        || trusted.last_results_hash != conflicting.last_results_hash
+}
+
 /// Interface for reporting evidence to full nodes, typically via the RPC client.
 #[contract_trait]
 #[allow(missing_docs)] // This is required because of the `contracts` crate (TODO: open/link issue)
 pub trait EvidenceReporter: Send + Sync {
     /// Report evidence to all connected full nodes.
     fn report(&self, e: Evidence, peer: PeerId) -> Result<Hash, IoError>;
+
+    /// Register the network address of a peer which was added at runtime.
+    fn add_peer(&mut self, _peer: PeerId, _address: tendermint::net::Address) {}
+}
+
+/// Interface for reporting evidence to full nodes, typically via the RPC client.
+///
+/// This is the asynchronous counterpart of `EvidenceReporter`, for use with the `AsyncSupervisor`.
+#[async_trait]
+pub trait AsyncEvidenceReporter: Send + Sync {
+    /// Report evidence to all connected full nodes.
+    async fn report(&self, e: Evidence, peer: PeerId) -> Result<Hash, IoError>;
+
+    /// Register the network address of a peer which was added at runtime.
+    fn add_peer(&mut self, _peer: PeerId, _address: tendermint::net::Address) {}
 }
 
 #[cfg(feature = "rpc-client")]
@@ -24,7 +186,6 @@ mod prod {
     use super::*;
     use crate::utils::block_on;
 
-    use contracts::pre;
     use std::{collections::HashMap, time::Duration};
 
     use tendermint_rpc as rpc;
@@ -54,6 +215,33 @@ mod prod {
                 Err(err) => Err(IoError::RpcError(err)),
             }
         }
+
+        fn add_peer(&mut self, peer: PeerId, address: tendermint::net::Address) {
+            self.peer_map.insert(peer, address);
+        }
+    }
+
+    #[async_trait]
+    impl AsyncEvidenceReporter for ProdEvidenceReporter {
+        async fn report(&self, e: Evidence, peer: PeerId) -> Result<Hash, IoError> {
+            let client = self.rpc_client_for(peer)?;
+
+            let res = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, client.broadcast_evidence(e))
+                    .await
+                    .map_err(|_| IoError::Timeout(timeout))?,
+                None => client.broadcast_evidence(e).await,
+            };
+
+            match res {
+                Ok(response) => Ok(response.hash),
+                Err(err) => Err(IoError::RpcError(err)),
+            }
+        }
+
+        fn add_peer(&mut self, peer: PeerId, address: tendermint::net::Address) {
+            self.peer_map.insert(peer, address);
+        }
     }
 
     impl ProdEvidenceReporter {
@@ -74,3 +262,97 @@ mod prod {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tendermint_testgen::{
+        light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header,
+        Validator as TestgenValidator,
+    };
+
+    fn validators() -> Vec<TestgenValidator> {
+        vec![
+            TestgenValidator::new("1").voting_power(30),
+            TestgenValidator::new("2").voting_power(50),
+        ]
+    }
+
+    fn make_block(
+        validators: &[TestgenValidator],
+        height: u64,
+        time: u64,
+        round: u32,
+    ) -> LightBlock {
+        let header = Header::new(validators)
+            .chain_id("test-chain")
+            .height(height)
+            .time(time);
+
+        let commit = Commit::new(header.clone(), round);
+
+        TestgenLightBlock::new(header, commit)
+            .generate()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn classify_equivocation() {
+        let common = make_block(&validators(), 1, 1, 1);
+        let trusted = make_block(&validators(), 3, 3, 1);
+        let conflicting = make_block(&validators(), 3, 4, 1);
+
+        let attack = classify_attack(&common, &trusted, &conflicting);
+
+        assert_eq!(attack.attack_type, AttackType::Equivocation);
+        assert_eq!(attack.common_height, common.height());
+        assert_eq!(attack.conflicting_height, conflicting.height());
+
+        // Sorted by voting power
+        let powers: Vec<_> = attack
+            .byzantine_validators
+            .iter()
+            .map(|v| v.power())
+            .collect();
+        assert_eq!(powers, vec![50, 30]);
+        assert_eq!(attack.byzantine_power(), 80);
+    }
+
+    #[test]
+    fn classify_amnesia() {
+        let common = make_block(&validators(), 1, 1, 1);
+        let trusted = make_block(&validators(), 3, 3, 1);
+        let conflicting = make_block(&validators(), 3, 4, 2);
+
+        let attack = classify_attack(&common, &trusted, &conflicting);
+
+        assert_eq!(attack.attack_type, AttackType::Amnesia);
+        assert!(attack.byzantine_validators.is_empty());
+    }
+
+    #[test]
+    fn classify_lunatic() {
+        let lunatic_validators = [
+            TestgenValidator::new("1").voting_power(30),
+            TestgenValidator::new("3").voting_power(50),
+        ];
+
+        let common = make_block(&validators(), 1, 1, 1);
+        let trusted = make_block(&validators(), 3, 3, 1);
+        let conflicting = make_block(&lunatic_validators, 3, 3, 1);
+
+        let attack = classify_attack(&common, &trusted, &conflicting);
+
+        assert_eq!(attack.attack_type, AttackType::Lunatic);
+
+        // Only the validators from the common block which signed the conflicting block
+        let expected = common
+            .validators
+            .validator(lunatic_validators[0].generate().unwrap().address)
+            .unwrap();
+
+        assert_eq!(attack.byzantine_validators, vec![expected]);
+    }
+}
@@ -1,13 +1,15 @@
 //! Fork detection data structures and implementation.
 
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     errors::{Error, ErrorExt, ErrorKind},
+    evidence::{classify_attack, Attack},
     operations::{Hasher, ProdHasher},
     state::State,
     store::memory::MemoryStore,
-    supervisor::Instance,
+    supervisor::{AsyncInstance, Instance},
     types::{LightBlock, PeerId, Status},
 };
 
@@ -25,13 +27,18 @@ pub enum ForkDetection {
 pub enum Fork {
     /// An actual fork was found for this `LightBlock`
     Forked {
-        /// Light block fetched from the primary
-        primary: LightBlock,
-        /// Light block fetched from a witness
-        witness: LightBlock,
+        /// First light block of the primary's trace which conflicts with the witness
+        primary: Box<LightBlock>,
+        /// Light block fetched from the witness, at the same height as `primary`
+        witness: Box<LightBlock>,
+        /// Highest light block both the primary and the witness agree on,
+        /// ie. the bifurcation point of the fork
+        common: Box<LightBlock>,
+        /// Attack by which the primary's block conflicts with the witness's block
+        attack: Attack,
     },
     /// The node has been deemed faulty for this `LightBlock`
-    Faulty(LightBlock, ErrorKind),
+    Faulty(Box<LightBlock>, ErrorKind),
     /// The node has timed out
     Timeout(PeerId, ErrorKind),
 }
@@ -39,24 +46,52 @@ pub enum Fork {
 /// Interface for a fork detector
 pub trait ForkDetector: Send + Sync {
     /// Detect forks using the given verified block, trusted block,
+    /// verification trace of the primary for the verified block,
     /// and list of witnesses to verify the given light block against.
+    ///
+    /// The verified block must not be lower than the trusted block.
     fn detect_forks(
         &self,
         verified_block: &LightBlock,
         trusted_block: &LightBlock,
+        primary_trace: Vec<LightBlock>,
         witnesses: Vec<&Instance>,
     ) -> Result<ForkDetection, Error>;
 }
 
+/// Interface for a fork detector.
+///
+/// This is the asynchronous counterpart of `ForkDetector`, for use with the `AsyncSupervisor`.
+#[async_trait]
+pub trait AsyncForkDetector: Send + Sync {
+    /// Detect forks using the given verified block, trusted block,
+    /// verification trace of the primary for the verified block,
+    /// and list of witnesses to verify the given light block against.
+    ///
+    /// The verified block must not be lower than the trusted block.
+    async fn detect_forks(
+        &self,
+        verified_block: &LightBlock,
+        trusted_block: &LightBlock,
+        primary_trace: Vec<LightBlock>,
+        witnesses: Vec<&AsyncInstance>,
+    ) -> Result<ForkDetection, Error>;
+}
+
 /// A production-ready fork detector which compares
 /// light blocks fetched from the witnesses by hash.
 /// If the hashes don't match, this fork detector
-/// then attempts to verify the light block pulled from
-/// the witness against a light block containing only
-/// the given trusted state, and then:
+/// then replays the verification trace of the primary against
+/// the witness, starting from the given trusted state. At each
+/// height of the trace, the block of the witness is verified
+/// against the last block both peers agree on, and then:
 ///
-/// - If the verification succeeds, we have a real fork
-/// - If verification fails because of lack of trust, we have a potential fork.
+/// - If the verification succeeds and the hashes differ, we have found
+///   the bifurcation point of a real fork.
+/// - If the verification succeeds and the hashes match, we move on to the
+///   next height of the trace.
+/// - If the trusted state has expired, we still report a fork at the height
+///   of the verified block, as it might be too late to punish but not to warn.
 /// - If verification fails for any other reason, the witness is deemed faulty.
 pub struct ProdForkDetector {
     hasher: Box<dyn Hasher>,
@@ -77,68 +112,271 @@ impl Default for ProdForkDetector {
     }
 }
 
+/// Outcome of the examination of a single block of the primary's trace against the witness
+enum Examination {
+    /// The witness agrees with the primary on this block
+    Agreed(Box<LightBlock>),
+    /// A fork was found, or the witness is faulty
+    Done(Box<Fork>),
+}
+
+impl ProdForkDetector {
+    /// Replay the given trace of the primary against the witness, in order to find
+    /// the first block of the trace which conflicts with the one of the witness,
+    /// as well as the last block both peers agree on.
+    ///
+    /// ## Precondition
+    /// - The trace is sorted by ascending height, starts with the trusted block
+    ///   and ends with the verified block.
+    /// - The hash of the given witness block, which is at the height of the verified block,
+    ///   differs from the one of the verified block.
+    fn examine_conflicting_header_against_trace(
+        &self,
+        trace: &[LightBlock],
+        witness_block: LightBlock,
+        witness: &Instance,
+    ) -> Fork {
+        // By [LCD-INV-TRUSTED-AGREED.1], the witness agrees with us on the trusted block,
+        // which is the first block of the trace.
+        let (trusted_block, trace) = trace
+            .split_first()
+            .expect("the trace starts with the trusted block, by precondition");
+        let verified_block = trace.last().unwrap_or(trusted_block);
+
+        let mut state = examination_state(trusted_block, &witness_block);
+        let mut common = trusted_block.clone();
+
+        for trace_block in trace {
+            let result = witness
+                .light_client
+                .verify_to_target(trace_block.height(), &mut state);
+
+            match self.examine(result, trace_block, verified_block, &witness_block, common) {
+                Examination::Agreed(source_block) => common = *source_block,
+                Examination::Done(fork) => return *fork,
+            }
+        }
+
+        // The trace ends at the height of the witness block, whose hash differs from the one of
+        // the verified block, so we can only get here if the trace is empty.
+        forked(verified_block, witness_block, common)
+    }
+
+    /// Asynchronous counterpart of `examine_conflicting_header_against_trace`.
+    async fn examine_conflicting_header_against_trace_async(
+        &self,
+        trace: &[LightBlock],
+        witness_block: LightBlock,
+        witness: &AsyncInstance,
+    ) -> Fork {
+        let (trusted_block, trace) = trace
+            .split_first()
+            .expect("the trace starts with the trusted block, by precondition");
+        let verified_block = trace.last().unwrap_or(trusted_block);
+
+        let mut state = examination_state(trusted_block, &witness_block);
+        let mut common = trusted_block.clone();
+
+        for trace_block in trace {
+            let result = witness
+                .light_client
+                .verify_to_target(trace_block.height(), &mut state)
+                .await;
+
+            match self.examine(result, trace_block, verified_block, &witness_block, common) {
+                Examination::Agreed(source_block) => common = *source_block,
+                Examination::Done(fork) => return *fork,
+            }
+        }
+
+        forked(verified_block, witness_block, common)
+    }
+
+    /// Examine the outcome of verifying the block of the witness at the height
+    /// of the given block of the primary's trace.
+    fn examine(
+        &self,
+        result: Result<LightBlock, Error>,
+        trace_block: &LightBlock,
+        verified_block: &LightBlock,
+        witness_block: &LightBlock,
+        common: LightBlock,
+    ) -> Examination {
+        let source_block = match result {
+            Ok(source_block) => source_block,
+            Err(e) if e.kind().has_expired() => {
+                let fork = forked(verified_block, witness_block.clone(), common);
+                return Examination::Done(Box::new(fork));
+            }
+            Err(e) if e.kind().is_timeout() => {
+                let fork = Fork::Timeout(witness_block.provider, e.kind().clone());
+                return Examination::Done(Box::new(fork));
+            }
+            Err(e) => {
+                let fork = Fork::Faulty(Box::new(witness_block.clone()), e.kind().clone());
+                return Examination::Done(Box::new(fork));
+            }
+        };
+
+        let trace_hash = self.hasher.hash_header(&trace_block.signed_header.header);
+        let source_hash = self.hasher.hash_header(&source_block.signed_header.header);
+
+        if trace_hash != source_hash {
+            // Bifurcation point found
+            Examination::Done(Box::new(forked(trace_block, source_block, common)))
+        } else {
+            Examination::Agreed(Box::new(source_block))
+        }
+    }
+
+    /// Whether the given witness block conflicts with the verified block.
+    fn conflicts(&self, verified_block: &LightBlock, witness_block: &LightBlock) -> bool {
+        let primary_hash = self
+            .hasher
+            .hash_header(&verified_block.signed_header.header);
+        let witness_hash = self.hasher.hash_header(&witness_block.signed_header.header);
+
+        primary_hash != witness_hash
+    }
+}
+
+/// Build a fork between the given primary and witness blocks at the same height,
+/// which diverge right after the given common block.
+fn forked(primary: &LightBlock, witness: LightBlock, common: LightBlock) -> Fork {
+    let attack = classify_attack(&common, &witness, primary);
+
+    Fork::Forked {
+        primary: Box::new(primary.clone()),
+        witness: Box::new(witness),
+        common: Box::new(common),
+        attack,
+    }
+}
+
+/// Build the state in which a witness replays the primary's trace, which contains
+/// the trusted block and the block of the witness which conflicts with the verified block.
+fn examination_state(trusted_block: &LightBlock, witness_block: &LightBlock) -> State {
+    let mut state = State::new(MemoryStore::new());
+    state
+        .light_store
+        .insert(trusted_block.clone(), Status::Verified);
+    state
+        .light_store
+        .insert(witness_block.clone(), Status::Unverified);
+
+    state
+}
+
+/// Only keep the intermediate blocks of the primary's trace, sorted by ascending height,
+/// and surround them with the trusted block and the verified block.
+fn make_trace(
+    primary_trace: Vec<LightBlock>,
+    verified_block: &LightBlock,
+    trusted_block: &LightBlock,
+) -> Vec<LightBlock> {
+    let mut trace = primary_trace
+        .into_iter()
+        .filter(|lb| lb.height() > trusted_block.height() && lb.height() < verified_block.height())
+        .collect::<Vec<_>>();
+
+    trace.sort_by_key(|lb| lb.height());
+    trace.insert(0, trusted_block.clone());
+    if verified_block.height() != trusted_block.height() {
+        trace.push(verified_block.clone());
+    }
+
+    trace
+}
+
+fn into_detection(forks: Vec<Fork>) -> ForkDetection {
+    if forks.is_empty() {
+        ForkDetection::NotDetected
+    } else {
+        ForkDetection::Detected(forks)
+    }
+}
+
 impl ForkDetector for ProdForkDetector {
     /// Perform fork detection. See the documentation `ProdForkDetector` for details.
     fn detect_forks(
         &self,
         verified_block: &LightBlock,
         trusted_block: &LightBlock,
+        primary_trace: Vec<LightBlock>,
         witnesses: Vec<&Instance>,
     ) -> Result<ForkDetection, Error> {
-        let primary_hash = self
-            .hasher
-            .hash_header(&verified_block.signed_header.header);
-
+        let trace = make_trace(primary_trace, verified_block, trusted_block);
         let mut forks = Vec::with_capacity(witnesses.len());
 
         for witness in witnesses {
             let mut state = State::new(MemoryStore::new());
 
-            let (witness_block, _) = witness
+            let witness_block = match witness
                 .light_client
-                .get_or_fetch_block(verified_block.height(), &mut state)?;
-
-            let witness_hash = self.hasher.hash_header(&witness_block.signed_header.header);
+                .get_or_fetch_block(verified_block.height(), &mut state)
+            {
+                Ok((witness_block, _)) => witness_block,
+                Err(e) if e.kind().is_timeout() => {
+                    forks.push(Fork::Timeout(witness.light_client.peer, e.kind().clone()));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
 
-            if primary_hash == witness_hash {
+            if !self.conflicts(verified_block, &witness_block) {
                 // Hashes match, continue with next witness, if any.
                 continue;
             }
 
-            state
-                .light_store
-                .insert(trusted_block.clone(), Status::Verified);
+            let fork =
+                self.examine_conflicting_header_against_trace(&trace, witness_block, witness);
+            forks.push(fork);
+        }
 
-            state
-                .light_store
-                .insert(witness_block.clone(), Status::Unverified);
+        Ok(into_detection(forks))
+    }
+}
 
-            let result = witness
+#[async_trait]
+impl AsyncForkDetector for ProdForkDetector {
+    /// Perform fork detection. See the documentation `ProdForkDetector` for details.
+    async fn detect_forks(
+        &self,
+        verified_block: &LightBlock,
+        trusted_block: &LightBlock,
+        primary_trace: Vec<LightBlock>,
+        witnesses: Vec<&AsyncInstance>,
+    ) -> Result<ForkDetection, Error> {
+        let trace = make_trace(primary_trace, verified_block, trusted_block);
+        let mut forks = Vec::with_capacity(witnesses.len());
+
+        for witness in witnesses {
+            let mut state = State::new(MemoryStore::new());
+
+            let witness_block = match witness
                 .light_client
-                .verify_to_target(verified_block.height(), &mut state);
-
-            match result {
-                Ok(_) => forks.push(Fork::Forked {
-                    primary: verified_block.clone(),
-                    witness: witness_block,
-                }),
-                Err(e) if e.kind().has_expired() => {
-                    forks.push(Fork::Forked {
-                        primary: verified_block.clone(),
-                        witness: witness_block,
-                    });
-                }
+                .get_or_fetch_block(verified_block.height(), &mut state)
+                .await
+            {
+                Ok((witness_block, _)) => witness_block,
                 Err(e) if e.kind().is_timeout() => {
-                    forks.push(Fork::Timeout(witness_block.provider, e.kind().clone()))
+                    forks.push(Fork::Timeout(witness.light_client.peer, e.kind().clone()));
+                    continue;
                 }
-                Err(e) => forks.push(Fork::Faulty(witness_block, e.kind().clone())),
+                Err(e) => return Err(e),
+            };
+
+            if !self.conflicts(verified_block, &witness_block) {
+                // Hashes match, continue with next witness, if any.
+                continue;
             }
-        }
 
-        if forks.is_empty() {
-            Ok(ForkDetection::NotDetected)
-        } else {
-            Ok(ForkDetection::Detected(forks))
+            let fork = self
+                .examine_conflicting_header_against_trace_async(&trace, witness_block, witness)
+                .await;
+            forks.push(fork);
         }
+
+        Ok(into_detection(forks))
     }
 }
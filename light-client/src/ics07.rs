@@ -0,0 +1,572 @@
+//! Store-agnostic implementation of the ICS-07 Tendermint client, for IBC modules
+//! embedding the light client verification logic.
+//!
+//! The client tracks a chain through a `ClientState` and a `ConsensusState` per verified
+//! height, which the embedding module is responsible for storing. Headers are verified
+//! against the consensus state at their trusted height with `check_header_and_update_state`,
+//! and evidence of misbehaviour is checked with `check_misbehaviour`.
+//!
+//! See the [ICS-07 specification](https://github.com/cosmos/ics/tree/master/spec/client/ics-007-tendermint-client).
+
+use std::convert::TryFrom;
+
+use crate::{
+    bail, ensure,
+    operations::{CommitValidator, Hasher, VotingPowerCalculator},
+    predicates::{errors::VerificationError, VerificationPredicates},
+    types::{LightBlock, SignedHeader, Time},
+};
+
+mod client_state;
+mod consensus_state;
+mod error;
+mod header;
+mod height;
+mod misbehaviour;
+
+pub mod proto;
+
+pub use client_state::ClientState;
+pub use consensus_state::ConsensusState;
+pub use error::{Error, Kind};
+pub use header::Header;
+pub use height::Height;
+pub use misbehaviour::Misbehaviour;
+
+/// Verify the given header against the consensus state of the client at the trusted
+/// height of the header, and return the updated client state along with the consensus
+/// state at the height of the header, for the caller to store.
+///
+/// - Ensure the client is not frozen, and tracks the chain and revision of the header
+/// - Ensure the trusted validators of the header match the consensus state
+/// - Verify the header against the consensus state as per `predicates::verify`
+#[allow(clippy::too_many_arguments)]
+pub fn check_header_and_update_state(
+    vp: &dyn VerificationPredicates,
+    voting_power_calculator: &dyn VotingPowerCalculator,
+    commit_validator: &dyn CommitValidator,
+    hasher: &dyn Hasher,
+    client_state: ClientState,
+    consensus_state: &ConsensusState,
+    header: &Header,
+    now: Time,
+) -> Result<(ClientState, ConsensusState), Error> {
+    if let Some(frozen_height) = client_state.frozen_height {
+        bail!(Kind::Frozen(frozen_height));
+    }
+
+    verify_header(
+        vp,
+        voting_power_calculator,
+        commit_validator,
+        hasher,
+        &client_state,
+        consensus_state,
+        header,
+        now,
+    )?;
+
+    let client_state = client_state.with_header_height(header.height());
+    let consensus_state = ConsensusState::from(header);
+
+    Ok((client_state, consensus_state))
+}
+
+/// Check that the headers of the given misbehaviour are both valid, as per their
+/// respective trusted consensus states, and that they conflict with each other, ie.
+/// that they have the same height but different hashes, or that the time of the
+/// highest one is not after the time of the lowest one.
+///
+/// Return the client state frozen at the lowest height of the two headers.
+#[allow(clippy::too_many_arguments)]
+pub fn check_misbehaviour(
+    vp: &dyn VerificationPredicates,
+    voting_power_calculator: &dyn VotingPowerCalculator,
+    commit_validator: &dyn CommitValidator,
+    hasher: &dyn Hasher,
+    client_state: ClientState,
+    misbehaviour: &Misbehaviour,
+    consensus_state_1: &ConsensusState,
+    consensus_state_2: &ConsensusState,
+    now: Time,
+) -> Result<ClientState, Error> {
+    if let Some(frozen_height) = client_state.frozen_height {
+        bail!(Kind::Frozen(frozen_height));
+    }
+
+    let headers = [
+        (&misbehaviour.header_1, consensus_state_1),
+        (&misbehaviour.header_2, consensus_state_2),
+    ];
+
+    for (header, consensus_state) in headers.iter() {
+        verify_header(
+            vp,
+            voting_power_calculator,
+            commit_validator,
+            hasher,
+            &client_state,
+            consensus_state,
+            header,
+            now,
+        )?;
+    }
+
+    let (mut lowest, mut highest) = (&misbehaviour.header_1, &misbehaviour.header_2);
+    if lowest.height() > highest.height() {
+        std::mem::swap(&mut lowest, &mut highest);
+    }
+
+    let (lowest_header, highest_header) =
+        (&lowest.signed_header.header, &highest.signed_header.header);
+
+    let conflicting = if lowest.height() == highest.height() {
+        hasher.hash_header(lowest_header) != hasher.hash_header(highest_header)
+    } else {
+        highest_header.time <= lowest_header.time
+    };
+
+    ensure!(
+        conflicting,
+        Kind::NotMisbehaviour(lowest.height(), highest.height())
+    );
+
+    Ok(client_state.with_frozen_height(lowest.height()))
+}
+
+/// Verify the given header against the given consensus state, with the parameters
+/// of the given client state.
+#[allow(clippy::too_many_arguments)]
+fn verify_header(
+    vp: &dyn VerificationPredicates,
+    voting_power_calculator: &dyn VotingPowerCalculator,
+    commit_validator: &dyn CommitValidator,
+    hasher: &dyn Hasher,
+    client_state: &ClientState,
+    consensus_state: &ConsensusState,
+    header: &Header,
+    now: Time,
+) -> Result<(), Error> {
+    let chain_id = &header.signed_header.header.chain_id;
+    ensure!(
+        *chain_id == client_state.chain_id,
+        Kind::ChainIdMismatch {
+            client: client_state.chain_id.clone(),
+            header: chain_id.clone(),
+        }
+    );
+
+    let revision_number = header.trusted_height.revision_number;
+    ensure!(
+        revision_number == client_state.latest_height.revision_number,
+        Kind::RevisionMismatch {
+            client: client_state.latest_height.revision_number,
+            header: revision_number,
+        }
+    );
+
+    let trusted = trusted_light_block(header, consensus_state)?;
+
+    // The next validators of the header are unknown, and not looked at below
+    let untrusted = LightBlock::new(
+        header.signed_header.clone(),
+        header.validator_set.clone(),
+        header.validator_set.clone(),
+        trusted.provider,
+    );
+
+    verify_light_block(
+        vp,
+        voting_power_calculator,
+        commit_validator,
+        hasher,
+        client_state,
+        &trusted,
+        &untrusted,
+        now,
+    )
+    .map_err(|e| Kind::InvalidHeader(e).into())
+}
+
+/// Verify the untrusted light block against the trusted one, as per `predicates::verify`,
+/// save for the next validators of the untrusted light block, which are unknown.
+#[allow(clippy::too_many_arguments)]
+fn verify_light_block(
+    vp: &dyn VerificationPredicates,
+    voting_power_calculator: &dyn VotingPowerCalculator,
+    commit_validator: &dyn CommitValidator,
+    hasher: &dyn Hasher,
+    client_state: &ClientState,
+    trusted: &LightBlock,
+    untrusted: &LightBlock,
+    now: Time,
+) -> Result<(), VerificationError> {
+    // Ensure the trusted validators match the consensus state
+    vp.next_validators_match(trusted, hasher)?;
+
+    vp.is_within_trust_period(
+        &trusted.signed_header.header,
+        client_state.trusting_period,
+        now,
+    )?;
+
+    vp.is_header_from_past(
+        &untrusted.signed_header.header,
+        client_state.max_clock_drift,
+        now,
+    )?;
+
+    vp.validator_sets_match(untrusted, hasher)?;
+    vp.header_matches_commit(&untrusted.signed_header, hasher)?;
+    vp.valid_commit(
+        &untrusted.signed_header,
+        &untrusted.validators,
+        commit_validator,
+    )?;
+
+    vp.is_monotonic_bft_time(
+        &untrusted.signed_header.header,
+        &trusted.signed_header.header,
+    )?;
+
+    if untrusted.height() == trusted.height().increment() {
+        vp.valid_next_validator_set(untrusted, trusted)?;
+    } else {
+        vp.is_monotonic_height(
+            &untrusted.signed_header.header,
+            &trusted.signed_header.header,
+        )?;
+
+        vp.has_sufficient_validators_overlap(
+            &untrusted.signed_header,
+            &trusted.next_validators,
+            &client_state.trust_level,
+            voting_power_calculator,
+        )?;
+    }
+
+    vp.has_sufficient_signers_overlap(
+        &untrusted.signed_header,
+        &untrusted.validators,
+        voting_power_calculator,
+    )?;
+
+    Ok(())
+}
+
+/// The light block at the trusted height of the given header, as far as the predicates
+/// are concerned: the consensus state only records the time, commitment root and next
+/// validators hash of the trusted block, which are set on a copy of the given header,
+/// and the trusted validators of the header stand for the next validators of the block.
+fn trusted_light_block(
+    header: &Header,
+    consensus_state: &ConsensusState,
+) -> Result<LightBlock, Error> {
+    let height = tendermint::block::Height::try_from(header.trusted_height.revision_height)
+        .map_err(|e| Kind::InvalidField("trusted_height").context(e))?;
+
+    let mut trusted_header = header.signed_header.header.clone();
+    trusted_header.height = height;
+    trusted_header.time = consensus_state.timestamp;
+    trusted_header.app_hash = consensus_state.root.clone();
+    trusted_header.next_validators_hash = consensus_state.next_validators_hash;
+
+    let mut trusted_commit = header.signed_header.commit.clone();
+    trusted_commit.height = height;
+
+    let signed_header = SignedHeader::new(trusted_header, trusted_commit)
+        .map_err(|e| Kind::InvalidField("trusted_height").context(e))?;
+
+    Ok(LightBlock::new(
+        signed_header,
+        header.trusted_validators.clone(),
+        header.trusted_validators.clone(),
+        tendermint::node::Id::new([0; 20]),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tendermint::Hash;
+    use tendermint_proto::Protobuf;
+    use tendermint_testgen::{
+        light_block::{LightBlock as TestgenLightBlock, TMLightBlock},
+        Commit, Generator, LightChain,
+    };
+
+    use super::*;
+    use crate::operations::{ProdCommitValidator, ProdHasher, ProdVotingPowerCalculator};
+    use crate::predicates::ProdPredicates;
+    use crate::types::TrustThreshold;
+
+    fn light_blocks(length: u64) -> Vec<TestgenLightBlock> {
+        LightChain::default_with_length(length).light_blocks
+    }
+
+    /// The given light block, with its header set at the given time.
+    fn at_time(light_block: &TestgenLightBlock, time: u64) -> TestgenLightBlock {
+        let header = light_block.header.clone().unwrap().time(time);
+        TestgenLightBlock {
+            commit: Some(Commit::new(header.clone(), 1)),
+            header: Some(header),
+            ..light_block.clone()
+        }
+    }
+
+    fn client(trusted: &TestgenLightBlock) -> (ClientState, ConsensusState) {
+        let trusted = trusted.generate().unwrap();
+        let header = &trusted.signed_header.header;
+
+        let client_state = ClientState::new(
+            header.chain_id.clone(),
+            TrustThreshold::default(),
+            Duration::from_secs(3600),
+            Duration::from_secs(7200),
+            Duration::from_secs(5),
+            Height::new(0, header.height.value()),
+        )
+        .unwrap();
+
+        let consensus_state = ConsensusState::new(
+            header.time,
+            header.app_hash.clone(),
+            header.next_validators_hash,
+        );
+
+        (client_state, consensus_state)
+    }
+
+    fn header(trusted: &TestgenLightBlock, untrusted: &TestgenLightBlock) -> Header {
+        let trusted: TMLightBlock = trusted.generate().unwrap();
+        let untrusted: TMLightBlock = untrusted.generate().unwrap();
+
+        Header {
+            signed_header: untrusted.signed_header,
+            validator_set: untrusted.validators,
+            trusted_height: Height::new(0, trusted.signed_header.header.height.value()),
+            trusted_validators: trusted.next_validators,
+        }
+    }
+
+    fn now(headers: &[&Header]) -> Time {
+        let latest = headers
+            .iter()
+            .map(|header| header.signed_header.header.time)
+            .max()
+            .unwrap();
+
+        latest + Duration::from_secs(1)
+    }
+
+    fn update(
+        client_state: ClientState,
+        consensus_state: &ConsensusState,
+        header: &Header,
+        now: Time,
+    ) -> Result<(ClientState, ConsensusState), Error> {
+        check_header_and_update_state(
+            &ProdPredicates,
+            &ProdVotingPowerCalculator,
+            &ProdCommitValidator::default(),
+            &ProdHasher,
+            client_state,
+            consensus_state,
+            header,
+            now,
+        )
+    }
+
+    fn misbehaviour(
+        client_state: ClientState,
+        consensus_state: &ConsensusState,
+        header_1: Header,
+        header_2: Header,
+    ) -> Result<ClientState, Error> {
+        let now = now(&[&header_1, &header_2]);
+        let misbehaviour = Misbehaviour {
+            client_id: "07-tendermint-0".to_string(),
+            header_1,
+            header_2,
+        };
+
+        check_misbehaviour(
+            &ProdPredicates,
+            &ProdVotingPowerCalculator,
+            &ProdCommitValidator::default(),
+            &ProdHasher,
+            client_state,
+            &misbehaviour,
+            consensus_state,
+            consensus_state,
+            now,
+        )
+    }
+
+    #[test]
+    fn headers_update_the_client() {
+        let light_blocks = light_blocks(5);
+        let (client_state, consensus_state_1) = client(&light_blocks[0]);
+
+        // Adjacent header
+        let header_2 = header(&light_blocks[0], &light_blocks[1]);
+        let (client_state, consensus_state_2) = update(
+            client_state,
+            &consensus_state_1,
+            &header_2,
+            now(&[&header_2]),
+        )
+        .unwrap();
+
+        assert_eq!(client_state.latest_height, Height::new(0, 2));
+        assert_eq!(consensus_state_2, ConsensusState::from(&header_2));
+
+        // Non-adjacent header
+        let header_5 = header(&light_blocks[1], &light_blocks[4]);
+        let (client_state, _) = update(
+            client_state,
+            &consensus_state_2,
+            &header_5,
+            now(&[&header_5]),
+        )
+        .unwrap();
+
+        assert_eq!(client_state.latest_height, Height::new(0, 5));
+
+        // Header below the latest height
+        let header_3 = header(&light_blocks[0], &light_blocks[2]);
+        let (client_state, consensus_state_3) = update(
+            client_state,
+            &consensus_state_1,
+            &header_3,
+            now(&[&header_3]),
+        )
+        .unwrap();
+
+        assert_eq!(client_state.latest_height, Height::new(0, 5));
+        assert_eq!(consensus_state_3, ConsensusState::from(&header_3));
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let light_blocks = light_blocks(3);
+        let (client_state, consensus_state) = client(&light_blocks[0]);
+        let header = header(&light_blocks[0], &light_blocks[2]);
+        let now = now(&[&header]);
+
+        let frozen = client_state.clone().with_frozen_height(Height::new(0, 2));
+        let e = update(frozen, &consensus_state, &header, now).unwrap_err();
+        assert_eq!(e.kind(), &Kind::Frozen(Height::new(0, 2)));
+
+        let other_chain = ClientState {
+            chain_id: "other-chain".parse().unwrap(),
+            ..client_state.clone()
+        };
+        let e = update(other_chain, &consensus_state, &header, now).unwrap_err();
+        assert!(matches!(e.kind(), Kind::ChainIdMismatch { .. }));
+
+        let other_revision = ClientState {
+            latest_height: Height::new(1, 1),
+            ..client_state.clone()
+        };
+        let e = update(other_revision, &consensus_state, &header, now).unwrap_err();
+        assert!(matches!(e.kind(), Kind::RevisionMismatch { .. }));
+
+        let other_validators = ConsensusState {
+            next_validators_hash: Hash::None,
+            ..consensus_state.clone()
+        };
+        let e = update(client_state.clone(), &other_validators, &header, now).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            Kind::InvalidHeader(VerificationError::InvalidNextValidatorSet { .. })
+        ));
+
+        let expired = consensus_state.timestamp + client_state.trusting_period;
+        let e = update(client_state, &consensus_state, &header, expired).unwrap_err();
+        assert!(matches!(
+            e.kind(),
+            Kind::InvalidHeader(VerificationError::NotWithinTrustPeriod { .. })
+        ));
+    }
+
+    #[test]
+    fn conflicting_headers_freeze_the_client() {
+        let light_blocks = light_blocks(3);
+        let (client_state, consensus_state) = client(&light_blocks[0]);
+        let header_2 = header(&light_blocks[0], &light_blocks[1]);
+        let header_3 = header(&light_blocks[0], &light_blocks[2]);
+
+        // Two headers at the same height
+        let forked_header_3 = header(&light_blocks[0], &at_time(&light_blocks[2], 10));
+        let frozen = misbehaviour(
+            client_state.clone(),
+            &consensus_state,
+            header_3.clone(),
+            forked_header_3,
+        )
+        .unwrap();
+        assert_eq!(frozen.frozen_height, Some(Height::new(0, 3)));
+
+        let e = update(frozen, &consensus_state, &header_3, now(&[&header_3])).unwrap_err();
+        assert_eq!(e.kind(), &Kind::Frozen(Height::new(0, 3)));
+
+        // A header at a greater height but not at a later time
+        let late_header_3 = header(&light_blocks[0], &at_time(&light_blocks[2], 2));
+        let frozen = misbehaviour(
+            client_state.clone(),
+            &consensus_state,
+            late_header_3,
+            header_2.clone(),
+        )
+        .unwrap();
+        assert_eq!(frozen.frozen_height, Some(Height::new(0, 2)));
+
+        // Consistent headers
+        let e = misbehaviour(client_state, &consensus_state, header_2, header_3).unwrap_err();
+        assert_eq!(
+            e.kind(),
+            &Kind::NotMisbehaviour(Height::new(0, 2), Height::new(0, 3))
+        );
+    }
+
+    #[test]
+    fn protobuf_encodings_round_trip() {
+        let light_blocks = light_blocks(2);
+        let (client_state, consensus_state) = client(&light_blocks[0]);
+        let header = header(&light_blocks[0], &light_blocks[1]);
+
+        let bytes = client_state.encode_vec().unwrap();
+        assert_eq!(ClientState::decode_vec(&bytes).unwrap(), client_state);
+
+        let frozen = client_state.with_frozen_height(Height::new(0, 2));
+        let bytes = frozen.encode_vec().unwrap();
+        assert_eq!(ClientState::decode_vec(&bytes).unwrap(), frozen);
+
+        let bytes = consensus_state.encode_vec().unwrap();
+        assert_eq!(ConsensusState::decode_vec(&bytes).unwrap(), consensus_state);
+
+        let bytes = header.encode_vec().unwrap();
+        assert_eq!(Header::decode_vec(&bytes).unwrap(), header);
+
+        let misbehaviour = Misbehaviour {
+            client_id: "07-tendermint-0".to_string(),
+            header_1: header.clone(),
+            header_2: header,
+        };
+        let bytes = misbehaviour.encode_vec().unwrap();
+        assert_eq!(Misbehaviour::decode_vec(&bytes).unwrap(), misbehaviour);
+
+        let invalid_trust_level = proto::ClientState {
+            trust_level: Some(proto::Fraction {
+                numerator: 1,
+                denominator: 4,
+            }),
+            ..frozen.into()
+        };
+        let mut bytes = Vec::new();
+        prost::Message::encode(&invalid_trust_level, &mut bytes).unwrap();
+        assert!(ClientState::decode_vec(&bytes).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! State of an ICS-07 client
+
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+
+use tendermint::{chain, evidence};
+use tendermint_proto::google::protobuf::Duration as RawDuration;
+use tendermint_proto::Protobuf;
+
+use super::error::{Error, Kind};
+use super::proto::{ClientState as RawClientState, Fraction};
+use super::Height;
+use crate::{bail, types::TrustThreshold};
+
+/// State of an ICS-07 client, ie. the parameters it verifies headers with,
+/// and the heights it reached.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientState {
+    /// Identifier of the tracked chain
+    pub chain_id: chain::Id,
+    /// Fraction of the trusted voting power which must have signed a header
+    /// for it to be trusted when skipping over intermediate headers
+    pub trust_level: TrustThreshold,
+    /// How long a consensus state can be used to verify headers
+    pub trusting_period: Duration,
+    /// Unbonding period of the tracked chain, which the trusting period must be shorter than
+    pub unbonding_period: Duration,
+    /// Maximum amount of time headers can be ahead of the local clock
+    pub max_clock_drift: Duration,
+    /// Height of the latest header the client verified
+    pub latest_height: Height,
+    /// Height of the misbehaviour the client was frozen for, if any
+    pub frozen_height: Option<Height>,
+}
+
+impl ClientState {
+    /// Construct the state of an unfrozen client at the given height,
+    /// after checking that its parameters are valid.
+    pub fn new(
+        chain_id: chain::Id,
+        trust_level: TrustThreshold,
+        trusting_period: Duration,
+        unbonding_period: Duration,
+        max_clock_drift: Duration,
+        latest_height: Height,
+    ) -> Result<Self, Error> {
+        if TrustThreshold::new(trust_level.numerator, trust_level.denominator).is_none() {
+            bail!(Kind::InvalidParameters(format!(
+                "trust level {} is not within [1/3, 1]",
+                trust_level
+            )));
+        }
+
+        if trusting_period >= unbonding_period {
+            bail!(Kind::InvalidParameters(format!(
+                "trusting period {:?} is not shorter than unbonding period {:?}",
+                trusting_period, unbonding_period
+            )));
+        }
+
+        if latest_height.revision_height == 0 {
+            bail!(Kind::InvalidParameters("latest height is zero".to_string()));
+        }
+
+        Ok(Self {
+            chain_id,
+            trust_level,
+            trusting_period,
+            unbonding_period,
+            max_clock_drift,
+            latest_height,
+            frozen_height: None,
+        })
+    }
+
+    /// Whether the client was frozen for misbehaviour.
+    pub fn is_frozen(&self) -> bool {
+        self.frozen_height.is_some()
+    }
+
+    /// This client state, with its latest height raised to the given height if higher.
+    pub fn with_header_height(self, height: Height) -> Self {
+        Self {
+            latest_height: self.latest_height.max(height),
+            ..self
+        }
+    }
+
+    /// This client state, frozen at the given height.
+    pub fn with_frozen_height(self, height: Height) -> Self {
+        Self {
+            frozen_height: Some(height),
+            ..self
+        }
+    }
+}
+
+impl Protobuf<RawClientState> for ClientState {}
+
+impl TryFrom<RawClientState> for ClientState {
+    type Error = Error;
+
+    fn try_from(value: RawClientState) -> Result<Self, Self::Error> {
+        let chain_id = value
+            .chain_id
+            .parse()
+            .map_err(|e| Kind::InvalidField("chain_id").context(e))?;
+
+        let trust_level = value.trust_level.ok_or(Kind::MissingField("trust_level"))?;
+        let trust_level = TrustThreshold::new(trust_level.numerator, trust_level.denominator)
+            .ok_or(Kind::InvalidField("trust_level"))?;
+
+        let latest_height = value
+            .latest_height
+            .ok_or(Kind::MissingField("latest_height"))?
+            .into();
+
+        let frozen_height = value
+            .frozen_height
+            .map(Height::from)
+            .filter(|height| !height.is_zero());
+
+        let client_state = Self::new(
+            chain_id,
+            trust_level,
+            duration(value.trusting_period, "trusting_period")?,
+            duration(value.unbonding_period, "unbonding_period")?,
+            duration(value.max_clock_drift, "max_clock_drift")?,
+            latest_height,
+        )?;
+
+        Ok(Self {
+            frozen_height,
+            ..client_state
+        })
+    }
+}
+
+impl From<ClientState> for RawClientState {
+    fn from(value: ClientState) -> Self {
+        Self {
+            chain_id: value.chain_id.to_string(),
+            trust_level: Some(Fraction {
+                numerator: value.trust_level.numerator,
+                denominator: value.trust_level.denominator,
+            }),
+            trusting_period: Some(evidence::Duration(value.trusting_period).into()),
+            unbonding_period: Some(evidence::Duration(value.unbonding_period).into()),
+            max_clock_drift: Some(evidence::Duration(value.max_clock_drift).into()),
+            frozen_height: Some(value.frozen_height.unwrap_or_default().into()),
+            latest_height: Some(value.latest_height.into()),
+        }
+    }
+}
+
+fn duration(value: Option<RawDuration>, field: &'static str) -> Result<Duration, Error> {
+    let value: evidence::Duration = value
+        .ok_or(Kind::MissingField(field))?
+        .try_into()
+        .map_err(|e| Kind::InvalidField(field).context(e))?;
+
+    Ok(value.into())
+}
@@ -0,0 +1,84 @@
+//! Consensus states of an ICS-07 client
+
+use std::convert::{TryFrom, TryInto};
+
+use tendermint::{AppHash, Hash, Time};
+use tendermint_proto::Protobuf;
+
+use super::error::{Error, Kind};
+use super::proto::{ConsensusState as RawConsensusState, MerkleRoot};
+use super::Header;
+
+/// Consensus state of an ICS-07 client at a given height, ie. what the client
+/// needs to know about the block at that height to verify the headers above it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsensusState {
+    /// Time of the block
+    pub timestamp: Time,
+    /// Commitment root of the block, ie. its application hash
+    pub root: AppHash,
+    /// Hash of the validator set of the next block
+    pub next_validators_hash: Hash,
+}
+
+impl ConsensusState {
+    /// Construct a consensus state.
+    pub fn new(timestamp: Time, root: AppHash, next_validators_hash: Hash) -> Self {
+        Self {
+            timestamp,
+            root,
+            next_validators_hash,
+        }
+    }
+}
+
+impl From<&Header> for ConsensusState {
+    fn from(header: &Header) -> Self {
+        let header = &header.signed_header.header;
+        Self::new(
+            header.time,
+            header.app_hash.clone(),
+            header.next_validators_hash,
+        )
+    }
+}
+
+impl Protobuf<RawConsensusState> for ConsensusState {}
+
+impl TryFrom<RawConsensusState> for ConsensusState {
+    type Error = Error;
+
+    fn try_from(value: RawConsensusState) -> Result<Self, Self::Error> {
+        let timestamp = value
+            .timestamp
+            .ok_or(Kind::MissingField("timestamp"))?
+            .try_into()
+            .map_err(|e| Kind::InvalidField("timestamp").context(e))?;
+
+        let root = value
+            .root
+            .ok_or(Kind::MissingField("root"))?
+            .hash
+            .try_into()
+            .map_err(|e| Kind::InvalidField("root").context(e))?;
+
+        let next_validators_hash = value
+            .next_validators_hash
+            .try_into()
+            .map_err(|e| Kind::InvalidField("next_validators_hash").context(e))?;
+
+        Ok(Self::new(timestamp, root, next_validators_hash))
+    }
+}
+
+impl From<ConsensusState> for RawConsensusState {
+    fn from(value: ConsensusState) -> Self {
+        Self {
+            timestamp: Some(value.timestamp.into()),
+            root: Some(MerkleRoot {
+                hash: value.root.into(),
+            }),
+            next_validators_hash: value.next_validators_hash.into(),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Errors raised by the ICS-07 client
+
+use anomaly::BoxError;
+use anomaly::Context;
+use tendermint::chain;
+use thiserror::Error;
+
+use super::Height;
+use crate::predicates::errors::VerificationError;
+
+/// An error raised by the ICS-07 client
+pub type Error = anomaly::Error<Kind>;
+
+/// The various error kinds raised by the ICS-07 client
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum Kind {
+    /// The client is frozen
+    #[error("client is frozen at height {0}")]
+    Frozen(Height),
+
+    /// Chain identifier mismatch
+    #[error("chain id mismatch: client = {client}, header = {header}")]
+    ChainIdMismatch {
+        /// Chain identifier of the client
+        client: chain::Id,
+        /// Chain identifier of the header
+        header: chain::Id,
+    },
+
+    /// Revision mismatch
+    #[error("revision mismatch: client = {client}, header = {header}")]
+    RevisionMismatch {
+        /// Revision of the latest height of the client
+        client: u64,
+        /// Revision of the trusted height of the header
+        header: u64,
+    },
+
+    /// The header failed verification
+    #[error("invalid header: {0}")]
+    InvalidHeader(#[from] VerificationError),
+
+    /// The headers of a misbehaviour do not conflict
+    #[error("headers at heights {0} and {1} do not conflict")]
+    NotMisbehaviour(Height, Height),
+
+    /// Invalid client parameters
+    #[error("invalid client parameters: {0}")]
+    InvalidParameters(String),
+
+    /// A field is missing from a protobuf message
+    #[error("missing field: {0}")]
+    MissingField(&'static str),
+
+    /// A field of a protobuf message is invalid
+    #[error("invalid field: {0}")]
+    InvalidField(&'static str),
+}
+
+impl Kind {
+    /// Add additional context (i.e. include a source error and capture a backtrace).
+    /// You can convert the resulting `Context` into an `Error` by calling `.into()`.
+    pub fn context(self, source: impl Into<BoxError>) -> Context<Self> {
+        Context::new(self, Some(source.into()))
+    }
+}
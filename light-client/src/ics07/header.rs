@@ -0,0 +1,76 @@
+//! Headers submitted to an ICS-07 client
+
+use std::convert::{TryFrom, TryInto};
+
+use tendermint_proto::Protobuf;
+
+use super::error::{Error, Kind};
+use super::proto::Header as RawHeader;
+use super::Height;
+use crate::types::{SignedHeader, ValidatorSet};
+
+/// Header submitted to an ICS-07 client, to be verified against the consensus state
+/// of the client at `trusted_height`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    /// Signed header of the block
+    pub signed_header: SignedHeader,
+    /// Validator set of the block
+    pub validator_set: ValidatorSet,
+    /// Height of the consensus state the header is verified against
+    pub trusted_height: Height,
+    /// Validator set of the block following the one at the trusted height,
+    /// whose hash is recorded in the consensus state at the trusted height
+    pub trusted_validators: ValidatorSet,
+}
+
+impl Header {
+    /// Height of this header, within the revision of its trusted height.
+    pub fn height(&self) -> Height {
+        Height::new(
+            self.trusted_height.revision_number,
+            self.signed_header.header.height.value(),
+        )
+    }
+}
+
+impl Protobuf<RawHeader> for Header {}
+
+impl TryFrom<RawHeader> for Header {
+    type Error = Error;
+
+    fn try_from(value: RawHeader) -> Result<Self, Self::Error> {
+        Ok(Self {
+            signed_header: value
+                .signed_header
+                .ok_or(Kind::MissingField("signed_header"))?
+                .try_into()
+                .map_err(|e| Kind::InvalidField("signed_header").context(e))?,
+            validator_set: value
+                .validator_set
+                .ok_or(Kind::MissingField("validator_set"))?
+                .try_into()
+                .map_err(|e| Kind::InvalidField("validator_set").context(e))?,
+            trusted_height: value
+                .trusted_height
+                .ok_or(Kind::MissingField("trusted_height"))?
+                .into(),
+            trusted_validators: value
+                .trusted_validators
+                .ok_or(Kind::MissingField("trusted_validators"))?
+                .try_into()
+                .map_err(|e| Kind::InvalidField("trusted_validators").context(e))?,
+        })
+    }
+}
+
+impl From<Header> for RawHeader {
+    fn from(value: Header) -> Self {
+        Self {
+            signed_header: Some(value.signed_header.into()),
+            validator_set: Some(value.validator_set.into()),
+            trusted_height: Some(value.trusted_height.into()),
+            trusted_validators: Some(value.trusted_validators.into()),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Heights of an ICS-07 client
+
+use std::fmt;
+
+use tendermint_proto::Protobuf;
+
+use super::proto::Height as RawHeight;
+
+/// Height of an ICS-07 client, ie. the height of a block within a revision of its chain.
+///
+/// Heights are ordered by revision first, then by height within the revision.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Height {
+    /// Revision of the chain the height belongs to
+    pub revision_number: u64,
+    /// Height of the block within its revision
+    pub revision_height: u64,
+}
+
+impl Height {
+    /// Construct a height within the given revision.
+    pub fn new(revision_number: u64, revision_height: u64) -> Self {
+        Self {
+            revision_number,
+            revision_height,
+        }
+    }
+
+    /// Whether this is the zero height, which stands for no height in protobuf encodings.
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.revision_number, self.revision_height)
+    }
+}
+
+impl Protobuf<RawHeight> for Height {}
+
+impl From<RawHeight> for Height {
+    fn from(value: RawHeight) -> Self {
+        Self::new(value.revision_number, value.revision_height)
+    }
+}
+
+impl From<Height> for RawHeight {
+    fn from(value: Height) -> Self {
+        Self {
+            revision_number: value.revision_number,
+            revision_height: value.revision_height,
+        }
+    }
+}
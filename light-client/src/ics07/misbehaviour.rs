@@ -0,0 +1,51 @@
+//! Misbehaviours submitted to an ICS-07 client
+
+use std::convert::{TryFrom, TryInto};
+
+use tendermint_proto::Protobuf;
+
+use super::error::{Error, Kind};
+use super::proto::Misbehaviour as RawMisbehaviour;
+use super::Header;
+
+/// Pair of conflicting headers submitted to an ICS-07 client, as evidence that
+/// the tracked chain misbehaved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Misbehaviour {
+    /// Identifier of the client
+    pub client_id: String,
+    /// First conflicting header
+    pub header_1: Header,
+    /// Second conflicting header
+    pub header_2: Header,
+}
+
+impl Protobuf<RawMisbehaviour> for Misbehaviour {}
+
+impl TryFrom<RawMisbehaviour> for Misbehaviour {
+    type Error = Error;
+
+    fn try_from(value: RawMisbehaviour) -> Result<Self, Self::Error> {
+        Ok(Self {
+            client_id: value.client_id,
+            header_1: value
+                .header_1
+                .ok_or(Kind::MissingField("header_1"))?
+                .try_into()?,
+            header_2: value
+                .header_2
+                .ok_or(Kind::MissingField("header_2"))?
+                .try_into()?,
+        })
+    }
+}
+
+impl From<Misbehaviour> for RawMisbehaviour {
+    fn from(value: Misbehaviour) -> Self {
+        Self {
+            client_id: value.client_id,
+            header_1: Some(value.header_1.into()),
+            header_2: Some(value.header_2.into()),
+        }
+    }
+}
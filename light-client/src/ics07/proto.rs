@@ -0,0 +1,109 @@
+//! Protobuf messages of the ICS-07 Tendermint client, as per the
+//! `ibc.lightclients.tendermint.v1` and `ibc.core.client.v1` packages of ibc-go.
+//!
+//! The proof specs and upgrade fields of the client state (fields 8 to 11) are
+//! not supported, and are thus skipped when decoding.
+
+use tendermint_proto::google::protobuf::{Duration, Timestamp};
+use tendermint_proto::types::{SignedHeader, ValidatorSet};
+
+/// Height of a client, as per `ibc.core.client.v1.Height`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Height {
+    /// Revision of the chain the height belongs to
+    #[prost(uint64, tag = "1")]
+    pub revision_number: u64,
+    /// Height of the block within its revision
+    #[prost(uint64, tag = "2")]
+    pub revision_height: u64,
+}
+
+/// Trust level of a client, as per `ibc.lightclients.tendermint.v1.Fraction`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Fraction {
+    /// Numerator of the fraction
+    #[prost(uint64, tag = "1")]
+    pub numerator: u64,
+    /// Denominator of the fraction
+    #[prost(uint64, tag = "2")]
+    pub denominator: u64,
+}
+
+/// Client state, as per `ibc.lightclients.tendermint.v1.ClientState`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClientState {
+    /// Identifier of the tracked chain
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+    /// Trust level of the client
+    #[prost(message, optional, tag = "2")]
+    pub trust_level: Option<Fraction>,
+    /// Trusting period of the client
+    #[prost(message, optional, tag = "3")]
+    pub trusting_period: Option<Duration>,
+    /// Unbonding period of the tracked chain
+    #[prost(message, optional, tag = "4")]
+    pub unbonding_period: Option<Duration>,
+    /// Maximum clock drift between the client and the tracked chain
+    #[prost(message, optional, tag = "5")]
+    pub max_clock_drift: Option<Duration>,
+    /// Height the client was frozen at, zero if the client is not frozen
+    #[prost(message, optional, tag = "6")]
+    pub frozen_height: Option<Height>,
+    /// Latest height of the client
+    #[prost(message, optional, tag = "7")]
+    pub latest_height: Option<Height>,
+}
+
+/// Commitment root, as per `ibc.core.commitment.v1.MerkleRoot`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MerkleRoot {
+    /// Hash of the root
+    #[prost(bytes, tag = "1")]
+    pub hash: Vec<u8>,
+}
+
+/// Consensus state, as per `ibc.lightclients.tendermint.v1.ConsensusState`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ConsensusState {
+    /// Time of the block
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: Option<Timestamp>,
+    /// Commitment root of the block, ie. its application hash
+    #[prost(message, optional, tag = "2")]
+    pub root: Option<MerkleRoot>,
+    /// Hash of the validator set of the next block
+    #[prost(bytes, tag = "3")]
+    pub next_validators_hash: Vec<u8>,
+}
+
+/// Header, as per `ibc.lightclients.tendermint.v1.Header`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Header {
+    /// Signed header of the block
+    #[prost(message, optional, tag = "1")]
+    pub signed_header: Option<SignedHeader>,
+    /// Validator set of the block
+    #[prost(message, optional, tag = "2")]
+    pub validator_set: Option<ValidatorSet>,
+    /// Height of the consensus state the header is verified against
+    #[prost(message, optional, tag = "3")]
+    pub trusted_height: Option<Height>,
+    /// Validator set of the block following the one at the trusted height
+    #[prost(message, optional, tag = "4")]
+    pub trusted_validators: Option<ValidatorSet>,
+}
+
+/// Misbehaviour, as per `ibc.lightclients.tendermint.v1.Misbehaviour`
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Misbehaviour {
+    /// Identifier of the client
+    #[prost(string, tag = "1")]
+    pub client_id: String,
+    /// First conflicting header
+    #[prost(message, optional, tag = "2")]
+    pub header_1: Option<Header>,
+    /// Second conflicting header
+    #[prost(message, optional, tag = "3")]
+    pub header_2: Option<Header>,
+}
@@ -22,10 +22,14 @@ pub mod contracts;
 pub mod errors;
 pub mod evidence;
 pub mod fork_detector;
+pub mod ics07;
 pub mod light_client;
+pub mod metrics;
 pub mod operations;
 pub mod peer_list;
 pub mod predicates;
+pub mod query;
+pub mod snapshot;
 pub mod state;
 pub mod store;
 pub mod supervisor;
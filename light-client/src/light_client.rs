@@ -12,10 +12,16 @@ use crate::contracts::*;
 use crate::{
     bail,
     errors::{Error, ErrorKind},
+    metrics::Metrics,
+    operations::Hasher,
     state::State,
+    store::RetentionPolicy,
     types::{Height, LightBlock, PeerId, Status, TrustThreshold},
 };
 
+mod verification;
+use verification::{Core, Step, Verification};
+
 /// Verification parameters
 ///
 /// TODO: Find a better name than `Options`
@@ -36,8 +42,32 @@ pub struct Options {
     /// is the maximum amount that the local clock may drift behind a timestamp from the
     /// blockchain.
     pub clock_drift: Duration,
+
+    /// Whether to verify headers with bisection, skipping over intermediate headers,
+    /// or to verify every header against its predecessor.
+    #[serde(default)]
+    pub verification_mode: VerificationMode,
 }
 
+/// How the light client verifies a target header given a trusted one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMode {
+    /// Skipping verification: the target header is verified directly against the trusted one
+    /// if their validator sets overlap enough, otherwise the `Scheduler` picks an intermediate
+    /// header to verify first, with bisection.
+    #[default]
+    #[display(fmt = "skipping")]
+    Skipping,
+    /// Sequential verification: every header between the trusted and target heights is
+    /// verified against its predecessor, as per the sequential verification of the spec.
+    #[display(fmt = "sequential")]
+    Sequential,
+}
+
+/// Default maximum number of light blocks fetched ahead of time during verification.
+pub const DEFAULT_PREFETCH_DEPTH: usize = 3;
+
 /// The light client implements a read operation of a header from the blockchain,
 /// by communicating with full nodes. As full nodes may be faulty, it cannot trust
 /// the received information, but the light client has to check whether the header
@@ -56,7 +86,10 @@ pub struct LightClient {
     clock: Box<dyn Clock>,
     scheduler: Box<dyn Scheduler>,
     verifier: Box<dyn Verifier>,
+    hasher: Box<dyn Hasher>,
     io: Box<dyn Io>,
+    prefetch_depth: usize,
+    metrics: Option<Metrics>,
 }
 
 impl fmt::Debug for LightClient {
@@ -76,6 +109,7 @@ impl LightClient {
         clock: impl Clock + 'static,
         scheduler: impl Scheduler + 'static,
         verifier: impl Verifier + 'static,
+        hasher: impl Hasher + 'static,
         io: impl Io + 'static,
     ) -> Self {
         Self {
@@ -84,7 +118,10 @@ impl LightClient {
             clock: Box::new(clock),
             scheduler: Box::new(scheduler),
             verifier: Box::new(verifier),
+            hasher: Box::new(hasher),
             io: Box::new(io),
+            prefetch_depth: DEFAULT_PREFETCH_DEPTH,
+            metrics: None,
         }
     }
 
@@ -95,6 +132,7 @@ impl LightClient {
         clock: Box<dyn Clock>,
         scheduler: Box<dyn Scheduler>,
         verifier: Box<dyn Verifier>,
+        hasher: Box<dyn Hasher>,
         io: Box<dyn Io>,
     ) -> Self {
         Self {
@@ -103,10 +141,29 @@ impl LightClient {
             clock,
             scheduler,
             verifier,
+            hasher,
             io,
+            prefetch_depth: DEFAULT_PREFETCH_DEPTH,
+            metrics: None,
         }
     }
 
+    /// Set the maximum number of light blocks to fetch along with the one being verified,
+    /// among the ones which will likely have to be verified next. Those are fetched
+    /// concurrently if the `Io` component supports it. Defaults to `DEFAULT_PREFETCH_DEPTH`,
+    /// and prefetching is disabled if set to zero.
+    pub fn with_prefetch_depth(mut self, prefetch_depth: usize) -> Self {
+        self.prefetch_depth = prefetch_depth;
+        self
+    }
+
+    /// Record the latency and the number of steps of the verifications, as well as
+    /// the time left until the latest trusted block expires, in the given metrics.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Attempt to update the light client to the highest block of the primary node.
     ///
     /// Note: This function delegates the actual work to `verify_to_target`.
@@ -130,6 +187,16 @@ impl LightClient {
     /// - The Scheduler component decides which height to try to verify next, in case the current
     ///   block pass verification but cannot be trusted yet.
     ///
+    /// If the target height is lower than the height of the latest trusted state, the light
+    /// client instead performs backward verification, by checking the hash chain from the
+    /// lowest trusted or verified block above the target height down to the target height.
+    ///
+    /// The verification logic does not perform any I/O and is shared with the `AsyncLightClient`,
+    /// this method merely fetches the light blocks it asks for with the I/O component.
+    ///
+    /// Once the target block is verified forward, its verification trace is persisted in the
+    /// trace store of the state, if any. See `State::persist_trace`.
+    ///
     /// ## Implements
     /// - [LCV-DIST-SAFE.1]
     /// - [LCV-DIST-LIFE.1]
@@ -158,84 +225,15 @@ impl LightClient {
         target_height: Height,
         state: &mut State,
     ) -> Result<LightBlock, Error> {
-        // Let's first look in the store to see whether we have already successfully verified this
-        // block.
-        if let Some(light_block) = state.light_store.get_trusted_or_verified(target_height) {
-            return Ok(light_block);
-        }
-
-        let mut current_height = target_height;
+        let core = self.core();
+        let mut verification = Verification::new(target_height);
+        let mut fetched = Vec::new();
 
         loop {
-            let now = self.clock.now();
-
-            // Get the latest trusted state
-            let trusted_state = state
-                .light_store
-                .latest_trusted_or_verified()
-                .ok_or(ErrorKind::NoInitialTrustedState)?;
-
-            if target_height < trusted_state.height() {
-                bail!(ErrorKind::TargetLowerThanTrustedState {
-                    target_height,
-                    trusted_height: trusted_state.height()
-                });
-            }
-
-            // Check invariant [LCV-INV-TP.1]
-            if !is_within_trust_period(&trusted_state, self.options.trusting_period, now) {
-                bail!(ErrorKind::TrustedStateOutsideTrustingPeriod {
-                    trusted_state: Box::new(trusted_state),
-                    options: self.options,
-                });
-            }
-
-            // Log the current height as a dependency of the block at the target height
-            state.trace_block(target_height, current_height);
-
-            // If the trusted state is now at a height equal to the target height, we are done.
-            // [LCV-DIST-LIFE.1]
-            if target_height == trusted_state.height() {
-                return Ok(trusted_state);
+            match core.step(&mut verification, state, fetched) {
+                Step::Fetch(heights) => fetched = self.io.fetch_light_blocks(heights),
+                Step::Done(result) => return *result,
             }
-
-            // Fetch the block at the current height from the light store if already present,
-            // or from the primary peer otherwise.
-            let (current_block, status) = self.get_or_fetch_block(current_height, state)?;
-
-            // Validate and verify the current block
-            let verdict = self
-                .verifier
-                .verify(&current_block, &trusted_state, &self.options, now);
-
-            match verdict {
-                Verdict::Success => {
-                    // Verification succeeded, add the block to the light store with
-                    // the `Verified` status or higher if already trusted.
-                    let new_status = Status::most_trusted(Status::Verified, status);
-                    state.light_store.update(&current_block, new_status);
-                }
-                Verdict::Invalid(e) => {
-                    // Verification failed, add the block to the light store with `Failed` status,
-                    // and abort.
-                    state.light_store.update(&current_block, Status::Failed);
-
-                    bail!(ErrorKind::InvalidLightBlock(e))
-                }
-                Verdict::NotEnoughTrust(_) => {
-                    // The current block cannot be trusted because of a missing overlap in the
-                    // validator sets. Add the block to the light store with
-                    // the `Unverified` status. This will engage bisection in an
-                    // attempt to raise the height of the highest trusted state
-                    // until there is enough overlap.
-                    state.light_store.update(&current_block, Status::Unverified);
-                }
-            }
-
-            // Compute the next height to fetch and verify
-            current_height =
-                self.scheduler
-                    .schedule(state.light_store.as_ref(), current_height, target_height);
         }
     }
 
@@ -268,4 +266,224 @@ impl LightClient {
 
         Ok((block, Status::Unverified))
     }
+
+    /// Prune the light store of the given state according to the given retention policy,
+    /// relative to the current time as given by the clock of this light client.
+    ///
+    /// The persisted traces of the removed light blocks are removed as well.
+    ///
+    /// Returns the number of light blocks which were removed from the store, or an error
+    /// if the traces could not be removed from the trace store.
+    pub fn prune(&self, state: &mut State, policy: &RetentionPolicy) -> Result<usize, Error> {
+        self.core().prune(state, policy)
+    }
+
+    /// Check that the peer is responsive, by fetching its latest light block.
+    pub fn health_check(&self) -> Result<(), Error> {
+        self.io
+            .fetch_light_block(AtHeight::Highest)
+            .map_err(ErrorKind::Io)?;
+
+        Ok(())
+    }
+
+    /// The components of this light client, except for its `Io` component.
+    fn core(&self) -> Core<'_> {
+        Core {
+            peer: self.peer,
+            options: &self.options,
+            clock: self.clock.as_ref(),
+            scheduler: self.scheduler.as_ref(),
+            verifier: self.verifier.as_ref(),
+            hasher: self.hasher.as_ref(),
+            prefetch_depth: self.prefetch_depth,
+            metrics: self.metrics.as_ref(),
+        }
+    }
+}
+
+/// Asynchronous counterpart of the `LightClient`, which fetches light blocks via an `AsyncIo`
+/// component instead of an `Io` one, and thus never blocks the executor it runs on.
+///
+/// It otherwise relies on the same components as the `LightClient`, and implements the
+/// exact same verification logic. See the documentation of `LightClient` for details.
+pub struct AsyncLightClient {
+    /// The peer id of the peer this client is connected to
+    pub peer: PeerId,
+    /// Options for this light client
+    pub options: Options,
+    clock: Box<dyn Clock>,
+    scheduler: Box<dyn Scheduler>,
+    verifier: Box<dyn Verifier>,
+    hasher: Box<dyn Hasher>,
+    io: Box<dyn AsyncIo>,
+    prefetch_depth: usize,
+    metrics: Option<Metrics>,
+}
+
+impl fmt::Debug for AsyncLightClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncLightClient")
+            .field("peer", &self.peer)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl AsyncLightClient {
+    /// Constructs a new asynchronous light client
+    pub fn new(
+        peer: PeerId,
+        options: Options,
+        clock: impl Clock + 'static,
+        scheduler: impl Scheduler + 'static,
+        verifier: impl Verifier + 'static,
+        hasher: impl Hasher + 'static,
+        io: impl AsyncIo + 'static,
+    ) -> Self {
+        Self {
+            peer,
+            options,
+            clock: Box::new(clock),
+            scheduler: Box::new(scheduler),
+            verifier: Box::new(verifier),
+            hasher: Box::new(hasher),
+            io: Box::new(io),
+            prefetch_depth: DEFAULT_PREFETCH_DEPTH,
+            metrics: None,
+        }
+    }
+
+    /// Constructs a new asynchronous light client from boxed components
+    pub fn from_boxed(
+        peer: PeerId,
+        options: Options,
+        clock: Box<dyn Clock>,
+        scheduler: Box<dyn Scheduler>,
+        verifier: Box<dyn Verifier>,
+        hasher: Box<dyn Hasher>,
+        io: Box<dyn AsyncIo>,
+    ) -> Self {
+        Self {
+            peer,
+            options,
+            clock,
+            scheduler,
+            verifier,
+            hasher,
+            io,
+            prefetch_depth: DEFAULT_PREFETCH_DEPTH,
+            metrics: None,
+        }
+    }
+
+    /// Set the maximum number of light blocks to fetch along with the one being verified,
+    /// among the ones which will likely have to be verified next. Those are fetched
+    /// concurrently if the `Io` component supports it. Defaults to `DEFAULT_PREFETCH_DEPTH`,
+    /// and prefetching is disabled if set to zero.
+    pub fn with_prefetch_depth(mut self, prefetch_depth: usize) -> Self {
+        self.prefetch_depth = prefetch_depth;
+        self
+    }
+
+    /// Record the latency and the number of steps of the verifications, as well as
+    /// the time left until the latest trusted block expires, in the given metrics.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Attempt to update the light client to the highest block of the primary node.
+    ///
+    /// See `LightClient::verify_to_highest`.
+    pub async fn verify_to_highest(&self, state: &mut State) -> Result<LightBlock, Error> {
+        let target_block = match self.io.fetch_light_block(AtHeight::Highest).await {
+            Ok(last_block) => last_block,
+            Err(io_error) => bail!(ErrorKind::Io(io_error)),
+        };
+
+        self.verify_to_target(target_block.height(), state).await
+    }
+
+    /// Update the light client to a block of the primary node at the given height.
+    ///
+    /// See `LightClient::verify_to_target`.
+    pub async fn verify_to_target(
+        &self,
+        target_height: Height,
+        state: &mut State,
+    ) -> Result<LightBlock, Error> {
+        let core = self.core();
+        let mut verification = Verification::new(target_height);
+        let mut fetched = Vec::new();
+
+        loop {
+            match core.step(&mut verification, state, fetched) {
+                Step::Fetch(heights) => fetched = self.io.fetch_light_blocks(heights).await,
+                Step::Done(result) => return *result,
+            }
+        }
+    }
+
+    /// Look in the light store for a block from the given peer at the given height,
+    /// which has not previously failed verification (ie. its status is not `Failed`).
+    ///
+    /// If one cannot be found, fetch the block from the given peer and store
+    /// it in the light store with `Unverified` status.
+    pub async fn get_or_fetch_block(
+        &self,
+        height: Height,
+        state: &mut State,
+    ) -> Result<(LightBlock, Status), Error> {
+        let block = state.light_store.get_non_failed(height);
+
+        if let Some(block) = block {
+            return Ok(block);
+        }
+
+        let block = self
+            .io
+            .fetch_light_block(AtHeight::At(height))
+            .await
+            .map_err(ErrorKind::Io)?;
+
+        state.light_store.insert(block.clone(), Status::Unverified);
+
+        Ok((block, Status::Unverified))
+    }
+
+    /// Prune the light store of the given state according to the given retention policy,
+    /// relative to the current time as given by the clock of this light client.
+    ///
+    /// The persisted traces of the removed light blocks are removed as well.
+    ///
+    /// Returns the number of light blocks which were removed from the store, or an error
+    /// if the traces could not be removed from the trace store.
+    pub fn prune(&self, state: &mut State, policy: &RetentionPolicy) -> Result<usize, Error> {
+        self.core().prune(state, policy)
+    }
+
+    /// Check that the peer is responsive, by fetching its latest light block.
+    pub async fn health_check(&self) -> Result<(), Error> {
+        self.io
+            .fetch_light_block(AtHeight::Highest)
+            .await
+            .map_err(ErrorKind::Io)?;
+
+        Ok(())
+    }
+
+    /// The components of this light client, except for its `Io` component.
+    fn core(&self) -> Core<'_> {
+        Core {
+            peer: self.peer,
+            options: &self.options,
+            clock: self.clock.as_ref(),
+            scheduler: self.scheduler.as_ref(),
+            verifier: self.verifier.as_ref(),
+            hasher: self.hasher.as_ref(),
+            prefetch_depth: self.prefetch_depth,
+            metrics: self.metrics.as_ref(),
+        }
+    }
 }
@@ -0,0 +1,660 @@
+//! The verification logic shared by the `LightClient` and the `AsyncLightClient`.
+//!
+//! It does not perform any I/O: a `Verification` is driven step by step by either client,
+//! which fetches the light blocks the `Core` asks for with its own `Io` or `AsyncIo`
+//! component, and hands them over to the next step.
+
+use contracts::*;
+use std::{convert::TryFrom, time::Instant};
+
+use crate::components::{clock::Clock, io::*, scheduler::*, verifier::*};
+use crate::contracts::*;
+use crate::{
+    bail,
+    errors::{Error, ErrorKind},
+    metrics::Metrics,
+    operations::Hasher,
+    predicates::errors::VerificationError,
+    state::State,
+    store::{LightStore, RetentionPolicy},
+    types::{Height, LightBlock, PeerId, Status, Time},
+    utils::std_ext,
+};
+
+use super::{Options, VerificationMode};
+
+/// The components of a light client, except for its `Io` component.
+pub(super) struct Core<'a> {
+    pub peer: PeerId,
+    pub options: &'a Options,
+    pub clock: &'a dyn Clock,
+    pub scheduler: &'a dyn Scheduler,
+    pub verifier: &'a dyn Verifier,
+    pub hasher: &'a dyn Hasher,
+    pub prefetch_depth: usize,
+    pub metrics: Option<&'a Metrics>,
+}
+
+/// The verification of the block at a target height, while it is in progress.
+pub(super) struct Verification {
+    target_height: Height,
+    start: Instant,
+    steps: usize,
+    progress: Progress,
+    fetching: Option<Height>,
+}
+
+impl Verification {
+    /// Start the verification of the block at the given target height.
+    pub fn new(target_height: Height) -> Self {
+        Self {
+            target_height,
+            start: Instant::now(),
+            steps: 0,
+            progress: Progress::Pending,
+            fetching: None,
+        }
+    }
+}
+
+/// How far a `Verification` went.
+enum Progress {
+    /// Nothing was looked at yet
+    Pending,
+    /// Forward verification from the block trusted at `trusted_height`,
+    /// with the block at `current_height` to verify next
+    Forward {
+        trusted_height: Height,
+        current_height: Height,
+    },
+    /// Backward verification, down from the `latest` block checked so far
+    Backward { latest: Box<LightBlock> },
+}
+
+/// What a client must do to carry on with a `Verification`.
+pub(super) enum Step {
+    /// Fetch the light blocks at the given heights from the peer, in this order, and hand
+    /// the results over to the next step. The first light block is required to carry on,
+    /// while the others are prefetched as they will likely be needed later on.
+    Fetch(Vec<Height>),
+    /// The verification is over, with the given outcome.
+    Done(Box<Result<LightBlock, Error>>),
+}
+
+/// Outcome of the verification of as many blocks as possible without fetching any.
+enum Next {
+    /// The light blocks at the given heights must be fetched, see `Step::Fetch`
+    Fetch(Vec<Height>),
+    /// The block at the target height was verified
+    Verified(Box<LightBlock>),
+}
+
+impl Core<'_> {
+    /// Carry on with the given verification, given the results of the fetch requested
+    /// by the previous step, if any.
+    ///
+    /// ## Postcondition
+    /// - If the verification succeeded, the light store contains a light block that corresponds
+    ///   to a block of the blockchain of the target height [LCV-POST-LS.1]
+    #[post(
+        matches!(&ret, Step::Done(result) if result.is_ok())
+            ==> trusted_store_contains_block_at_target_height(
+                state.light_store.as_ref(),
+                verification.target_height,
+            )
+    )]
+    pub fn step(
+        &self,
+        verification: &mut Verification,
+        state: &mut State,
+        fetched: Vec<Result<LightBlock, IoError>>,
+    ) -> Step {
+        let next = self
+            .store_fetched(verification, state, fetched)
+            .and_then(|()| self.advance(verification, state));
+
+        match next {
+            Ok(Next::Fetch(heights)) => {
+                verification.fetching = heights.first().copied();
+                Step::Fetch(heights)
+            }
+            Ok(Next::Verified(light_block)) => {
+                Step::Done(Box::new(self.finish(verification, state, Ok(*light_block))))
+            }
+            Err(e) => Step::Done(Box::new(self.finish(verification, state, Err(e)))),
+        }
+    }
+
+    /// Store the light blocks fetched for the given verification, if any, with `Unverified`
+    /// status. If the first one, which is required to carry on, could not be fetched,
+    /// the verification fails.
+    fn store_fetched(
+        &self,
+        verification: &mut Verification,
+        state: &mut State,
+        fetched: Vec<Result<LightBlock, IoError>>,
+    ) -> Result<(), Error> {
+        let height = match verification.fetching.take() {
+            Some(height) => height,
+            None => return Ok(()),
+        };
+
+        let mut results = fetched.into_iter();
+        let result = results.next().unwrap_or_else(|| {
+            Err(IoError::InvalidHeight(format!(
+                "no light block was fetched at height {}",
+                height
+            )))
+        });
+
+        store_prefetched_blocks(state, results);
+
+        let block = result.map_err(ErrorKind::Io)?;
+        state.light_store.insert(block, Status::Unverified);
+
+        Ok(())
+    }
+
+    /// Verify as many blocks as possible, until either a block must be fetched
+    /// or the block at the target height is verified.
+    fn advance(&self, verification: &mut Verification, state: &mut State) -> Result<Next, Error> {
+        let target_height = verification.target_height;
+
+        loop {
+            let next = match &mut verification.progress {
+                Progress::Pending => {
+                    // Let's first look in the store to see whether we have already
+                    // successfully verified this block.
+                    if let Some(light_block) =
+                        state.light_store.get_trusted_or_verified(target_height)
+                    {
+                        return Ok(Next::Verified(Box::new(light_block)));
+                    }
+
+                    // Get the latest trusted state
+                    let trusted_state = state
+                        .light_store
+                        .latest_trusted_or_verified()
+                        .ok_or(ErrorKind::NoInitialTrustedState)?;
+
+                    verification.progress = if target_height < trusted_state.height() {
+                        let now = self.clock.now();
+                        let root = backward_root(state, target_height, self.options, now)?;
+
+                        Progress::Backward {
+                            latest: Box::new(root),
+                        }
+                    } else {
+                        Progress::Forward {
+                            trusted_height: trusted_state.height(),
+                            current_height: target_height,
+                        }
+                    };
+
+                    None
+                }
+                Progress::Forward {
+                    trusted_height,
+                    current_height,
+                } => {
+                    let trusted_height = *trusted_height;
+                    let steps = &mut verification.steps;
+
+                    let next = match self.options.verification_mode {
+                        VerificationMode::Skipping => {
+                            self.verify_forward(target_height, current_height, steps, state)?
+                        }
+                        VerificationMode::Sequential => {
+                            self.verify_sequential(target_height, steps, state)?
+                        }
+                    };
+
+                    if let Some(Next::Verified(_)) = next {
+                        self.record_steps(verification.steps);
+                        state.persist_trace(target_height, trusted_height)?;
+                    }
+
+                    next
+                }
+                Progress::Backward { latest } => {
+                    self.verify_backward(target_height, latest, state)?
+                }
+            };
+
+            if let Some(next) = next {
+                return Ok(next);
+            }
+        }
+    }
+
+    /// Perform a step of forward verification with bisection, by verifying the block at
+    /// the current height against the latest trusted state, and scheduling the next height.
+    ///
+    /// ## Precondition
+    /// - The light store contains a trusted or verified block at or below `target_height`.
+    fn verify_forward(
+        &self,
+        target_height: Height,
+        current_height: &mut Height,
+        steps: &mut usize,
+        state: &mut State,
+    ) -> Result<Option<Next>, Error> {
+        let now = self.clock.now();
+
+        // Get the latest trusted state, and check invariant [LCV-INV-TP.1]
+        let trusted_state = latest_trusted_within_period(state, self.options, now)?;
+
+        // Log the current height as a dependency of the block at the target height
+        state.trace_block(target_height, *current_height);
+
+        // If the trusted state is now at a height equal to the target height, we are done.
+        // [LCV-DIST-LIFE.1]
+        if target_height == trusted_state.height() {
+            return Ok(Some(Next::Verified(Box::new(trusted_state))));
+        }
+
+        // Get the block at the current height from the light store if already present,
+        // or fetch it from the primary peer otherwise.
+        let (current_block, status) = match state.light_store.get_non_failed(*current_height) {
+            Some(block) => block,
+            None => return Ok(Some(self.fetch(*current_height, target_height, state))),
+        };
+
+        // Validate and verify the current block
+        let verdict = self
+            .verifier
+            .verify(&current_block, &trusted_state, self.options, now);
+
+        process_verdict(state, &current_block, status, verdict)?;
+        *steps += 1;
+
+        // Compute the next height to fetch and verify
+        *current_height =
+            self.scheduler
+                .schedule(state.light_store.as_ref(), *current_height, target_height);
+
+        Ok(None)
+    }
+
+    /// Perform a step of sequential forward verification, by verifying the block right above
+    /// the latest trusted state against it.
+    ///
+    /// Unlike with bisection, a header which cannot be trusted given its predecessor
+    /// makes the verification fail right away.
+    ///
+    /// ## Precondition
+    /// - The light store contains a trusted or verified block at or below `target_height`.
+    fn verify_sequential(
+        &self,
+        target_height: Height,
+        steps: &mut usize,
+        state: &mut State,
+    ) -> Result<Option<Next>, Error> {
+        let now = self.clock.now();
+
+        // Get the latest trusted state, and check invariant [LCV-INV-TP.1]
+        let trusted_state = latest_trusted_within_period(state, self.options, now)?;
+
+        // If the trusted state is now at a height equal to the target height, we are done.
+        if target_height == trusted_state.height() {
+            return Ok(Some(Next::Verified(Box::new(trusted_state))));
+        }
+
+        // Log the next height as a dependency of the block at the target height
+        let current_height = trusted_state.height().increment();
+        state.trace_block(target_height, current_height);
+
+        let (current_block, status) = match state.light_store.get_non_failed(current_height) {
+            Some(block) => block,
+            None => return Ok(Some(self.fetch(current_height, target_height, state))),
+        };
+
+        let verdict = self
+            .verifier
+            .verify(&current_block, &trusted_state, self.options, now);
+
+        process_verdict(state, &current_block, status, sequential_verdict(verdict))?;
+        *steps += 1;
+
+        Ok(None)
+    }
+
+    /// Perform a step of sequential backward verification, by checking the block right below
+    /// the `latest` one checked so far, starting from the lowest trusted or verified block
+    /// above the target height.
+    ///
+    /// Each block is checked by comparing its hash against the `last_block_id` of the block
+    /// right above it, which was itself verified in the previous step, and by validating
+    /// its validator sets and commit against its header.
+    ///
+    /// ## Error conditions
+    /// - If a header is missing its `last_block_id`
+    /// - If the hash of a header does not match the `last_block_id` of the header above it
+    /// - If the BFT time of a header is not lower than the one of the header above it
+    /// - If the validator sets or the commit of a block do not match its header
+    fn verify_backward(
+        &self,
+        target_height: Height,
+        latest: &mut Box<LightBlock>,
+        state: &mut State,
+    ) -> Result<Option<Next>, Error> {
+        if latest.height() == target_height {
+            return Ok(Some(Next::Verified(latest.clone())));
+        }
+
+        // Cannot fail as the latest height is above the target height
+        let height = Height::try_from(latest.height().value() - 1).unwrap();
+
+        let (current, status) = match state.light_store.get_non_failed(height) {
+            Some(block) => block,
+            None => return Ok(Some(Next::Fetch(vec![height]))),
+        };
+
+        check_adjacent(self.verifier, self.hasher, state, latest, &current, status)?;
+        **latest = current;
+
+        Ok(None)
+    }
+
+    /// The block at the given height must be fetched from the peer, together with the
+    /// blocks which will likely have to be verified next (see `prefetch_heights`).
+    fn fetch(&self, height: Height, target_height: Height, state: &State) -> Next {
+        let mut heights = vec![height];
+        heights.extend(prefetch_heights(
+            self.scheduler,
+            self.options.verification_mode,
+            state.light_store.as_ref(),
+            height,
+            target_height,
+            self.prefetch_depth,
+        ));
+
+        Next::Fetch(heights)
+    }
+
+    /// Record the outcome of the given verification, if metrics are set, and return it.
+    fn finish(
+        &self,
+        verification: &Verification,
+        state: &State,
+        result: Result<LightBlock, Error>,
+    ) -> Result<LightBlock, Error> {
+        if let Some(metrics) = self.metrics {
+            let now = self.clock.now();
+            record_verification(
+                metrics,
+                self.peer,
+                self.options,
+                now,
+                state,
+                verification.start,
+                &result,
+            );
+        }
+
+        result
+    }
+
+    /// Record the number of blocks verified to reach a target height, if metrics are set.
+    fn record_steps(&self, steps: usize) {
+        if let Some(metrics) = self.metrics {
+            metrics.observe_bisection_steps(self.peer, steps);
+        }
+    }
+
+    /// Prune the light store of the given state according to the given retention policy,
+    /// relative to the current time as given by the clock, along with the persisted traces
+    /// of the removed light blocks. Returns the number of light blocks which were removed.
+    pub fn prune(&self, state: &mut State, policy: &RetentionPolicy) -> Result<usize, Error> {
+        let pruned = state.light_store.prune(policy, self.clock.now());
+        state.prune_traces()?;
+        Ok(pruned)
+    }
+}
+
+/// Record the outcome of the verification of a block, which started at `start`,
+/// and the time left until the latest trusted block of the given state expires.
+fn record_verification(
+    metrics: &Metrics,
+    peer: PeerId,
+    options: &Options,
+    now: Time,
+    state: &State,
+    start: Instant,
+    result: &Result<LightBlock, Error>,
+) {
+    metrics.observe_verification(peer, start.elapsed(), result.is_ok());
+
+    if let Some(trusted) = state.light_store.latest_trusted_or_verified() {
+        let expires_at = trusted.signed_header.header.time + options.trusting_period;
+
+        let headroom = match expires_at.duration_since(now) {
+            Ok(headroom) => headroom.as_secs_f64(),
+            Err(_) => -now
+                .duration_since(expires_at)
+                .map_or(0.0, |expired| expired.as_secs_f64()),
+        };
+
+        metrics.set_trusting_period_headroom(peer, headroom);
+    }
+}
+
+/// Get the latest trusted or verified state from the light store,
+/// and check that it is within the trusting period [LCV-INV-TP.1].
+fn latest_trusted_within_period(
+    state: &State,
+    options: &Options,
+    now: Time,
+) -> Result<LightBlock, Error> {
+    let trusted_state = state
+        .light_store
+        .latest_trusted_or_verified()
+        .ok_or(ErrorKind::NoInitialTrustedState)?;
+
+    if !is_within_trust_period(&trusted_state, options.trusting_period, now) {
+        bail!(ErrorKind::TrustedStateOutsideTrustingPeriod {
+            trusted_state: Box::new(trusted_state),
+            options: *options,
+        });
+    }
+
+    Ok(trusted_state)
+}
+
+/// Record the outcome of the verification of the given block in the light store.
+fn process_verdict(
+    state: &mut State,
+    current_block: &LightBlock,
+    status: Status,
+    verdict: Verdict,
+) -> Result<(), Error> {
+    match verdict {
+        Verdict::Success => {
+            // Verification succeeded, add the block to the light store with
+            // the `Verified` status or higher if already trusted.
+            let new_status = Status::most_trusted(Status::Verified, status);
+            state.light_store.update(current_block, new_status);
+        }
+        Verdict::Invalid(e) => {
+            // Verification failed, add the block to the light store with `Failed` status,
+            // and abort.
+            state.light_store.update(current_block, Status::Failed);
+
+            bail!(ErrorKind::InvalidLightBlock(e))
+        }
+        Verdict::NotEnoughTrust(_) => {
+            // The current block cannot be trusted because of a missing overlap in the
+            // validator sets. Add the block to the light store with
+            // the `Unverified` status. This will engage bisection in an
+            // attempt to raise the height of the highest trusted state
+            // until there is enough overlap.
+            state.light_store.update(current_block, Status::Unverified);
+        }
+    }
+
+    Ok(())
+}
+
+/// Heights of the light blocks which will likely have to be verified after the one at
+/// `current_height` in order to reach `target_height`, and which are not in the light store yet,
+/// up to `depth` of them.
+///
+/// With skipping verification, these are the heights the scheduler would pick next if the
+/// block at `current_height`, and then each of these blocks, could not be trusted yet.
+/// With sequential verification, these are the heights right above `current_height`.
+fn prefetch_heights(
+    scheduler: &dyn Scheduler,
+    verification_mode: VerificationMode,
+    light_store: &dyn LightStore,
+    current_height: Height,
+    target_height: Height,
+    depth: usize,
+) -> Vec<Height> {
+    let trusted_height = match light_store.latest_trusted_or_verified() {
+        Some(trusted_state) => trusted_state.height(),
+        None => return Vec::new(),
+    };
+
+    let mut heights = Vec::with_capacity(depth);
+    let mut height = current_height;
+
+    while heights.len() < depth {
+        height = match verification_mode {
+            // The scheduler must pick a height strictly between the trusted and current ones
+            VerificationMode::Skipping if height.value() > trusted_height.value() + 1 => {
+                scheduler.schedule(light_store, height, target_height)
+            }
+            VerificationMode::Sequential if height < target_height => height.increment(),
+            _ => break,
+        };
+
+        if light_store.get_non_failed(height).is_none() {
+            heights.push(height);
+        }
+    }
+
+    heights
+}
+
+/// Store the light blocks which were successfully prefetched with `Unverified` status.
+fn store_prefetched_blocks(
+    state: &mut State,
+    results: impl Iterator<Item = Result<LightBlock, IoError>>,
+) {
+    for light_block in results.flatten() {
+        if state
+            .light_store
+            .get_non_failed(light_block.height())
+            .is_none()
+        {
+            state.light_store.insert(light_block, Status::Unverified);
+        }
+    }
+}
+
+/// With sequential verification, there is no intermediate header to fall back to when
+/// a header cannot be trusted given its predecessor, so such a header is deemed invalid.
+fn sequential_verdict(verdict: Verdict) -> Verdict {
+    match verdict {
+        Verdict::NotEnoughTrust(e) => Verdict::Invalid(e),
+        verdict => verdict,
+    }
+}
+
+/// Find the lowest trusted or verified block above the given target height, from which
+/// to start backward verification, and check that it is within the trusting period.
+fn backward_root(
+    state: &State,
+    target_height: Height,
+    options: &Options,
+    now: Time,
+) -> Result<LightBlock, Error> {
+    let above_target = target_height.increment();
+    let lowest_trusted = state
+        .light_store
+        .lowest_at_or_above(above_target, Status::Trusted);
+    let lowest_verified = state
+        .light_store
+        .lowest_at_or_above(above_target, Status::Verified);
+
+    let root = std_ext::option::select(lowest_trusted, lowest_verified, |t, v| {
+        std_ext::cmp::min_by_key(t, v, |lb| lb.height())
+    })
+    .ok_or(ErrorKind::NoInitialTrustedState)?;
+
+    // Check invariant [LCV-INV-TP.1]
+    if !is_within_trust_period(&root, options.trusting_period, now) {
+        bail!(ErrorKind::TrustedStateOutsideTrustingPeriod {
+            trusted_state: Box::new(root),
+            options: *options,
+        });
+    }
+
+    Ok(root)
+}
+
+/// Check that the `current` block is linked to the `latest` one through the
+/// `last_block_id` of the latter, that its BFT time is lower than the one
+/// of the latter, and that its validator sets and commit match its header.
+/// Record the outcome in the light store.
+fn check_adjacent(
+    verifier: &dyn Verifier,
+    hasher: &dyn Hasher,
+    state: &mut State,
+    latest: &LightBlock,
+    current: &LightBlock,
+    status: Status,
+) -> Result<(), Error> {
+    let latest_last_block_id = latest
+        .signed_header
+        .header
+        .last_block_id
+        .ok_or_else(|| ErrorKind::MissingLastBlockId(latest.height()))?;
+
+    let current_hash = hasher.hash_header(&current.signed_header.header);
+
+    if current_hash != latest_last_block_id.hash {
+        state.light_store.update(current, Status::Failed);
+
+        bail!(ErrorKind::InvalidAdjacentHeaders {
+            header_hash: current_hash,
+            last_block_id_hash: latest_last_block_id.hash,
+        });
+    }
+
+    // The BFT time of a block must be strictly lower than the one of the block above it
+    let current_time = current.signed_header.header.time;
+    let latest_time = latest.signed_header.header.time;
+
+    if current_time >= latest_time {
+        state.light_store.update(current, Status::Failed);
+
+        bail!(ErrorKind::InvalidLightBlock(
+            VerificationError::NonMonotonicBftTime {
+                header_bft_time: current_time,
+                trusted_header_bft_time: latest_time,
+            }
+        ));
+    }
+
+    // The hash link only covers the header, so the validator sets and the commit which come
+    // along with it must be checked against the header as well
+    match verifier.validate(current) {
+        Verdict::Success => {}
+        Verdict::NotEnoughTrust(e) | Verdict::Invalid(e) => {
+            state.light_store.update(current, Status::Failed);
+            bail!(ErrorKind::InvalidLightBlock(e));
+        }
+    }
+
+    // The current block is linked to the latest one through its hash, add it to the
+    // light store with the `Verified` status or higher if already trusted.
+    //
+    // No verification trace is recorded: the trace of a block holds the lower blocks its
+    // verification relied on, which the fork detector replays against the witnesses,
+    // whereas backward verification only relies on the hash links to higher blocks.
+    let new_status = Status::most_trusted(Status::Verified, status);
+    state.light_store.update(current, new_status);
+
+    Ok(())
+}
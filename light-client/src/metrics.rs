@@ -0,0 +1,319 @@
+//! Prometheus metrics of the light client, its I/O and the supervisor.
+
+use std::time::Duration;
+
+use prometheus::{
+    exponential_buckets, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::{
+    components::io::IoError,
+    fork_detector::{Fork, ForkDetection},
+    types::PeerId,
+};
+
+/// Prefix of the names of all the metrics
+const NAMESPACE: &str = "tendermint_light_client";
+
+/// Content type of the metrics encoded by `Metrics::encode`
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Metrics of the light client, its I/O and the supervisor, recorded in their own registry.
+///
+/// The metrics are cheap to clone, and all clones record to the same registry,
+/// so a single instance can be shared by all the components of a light node.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    registry: Registry,
+    verification_duration: HistogramVec,
+    bisection_steps: HistogramVec,
+    fetch_duration: HistogramVec,
+    fetch_errors: IntCounterVec,
+    fork_detections: IntCounterVec,
+    forks: IntCounterVec,
+    peer_replacements: IntCounterVec,
+    trusting_period_headroom: GaugeVec,
+}
+
+impl Metrics {
+    /// Create the metrics, and register them in a new registry.
+    pub fn new() -> Self {
+        // The options below are all valid, and the metrics all have distinct names,
+        // so neither their creation nor their registration can fail.
+        let verification_duration = HistogramVec::new(
+            histogram_opts(
+                "verification_duration_seconds",
+                "Time taken to verify a block up to a target height",
+                exponential_buckets(0.01, 2.0, 12).unwrap(),
+            ),
+            &["peer", "result"],
+        )
+        .unwrap();
+
+        let bisection_steps = HistogramVec::new(
+            histogram_opts(
+                "bisection_steps",
+                "Number of blocks verified to reach a target height",
+                exponential_buckets(1.0, 2.0, 10).unwrap(),
+            ),
+            &["peer"],
+        )
+        .unwrap();
+
+        let fetch_duration = HistogramVec::new(
+            histogram_opts(
+                "fetch_duration_seconds",
+                "Time taken to fetch a light block from a peer",
+                exponential_buckets(0.005, 2.0, 12).unwrap(),
+            ),
+            &["peer"],
+        )
+        .unwrap();
+
+        let fetch_errors = IntCounterVec::new(
+            opts(
+                "fetch_errors_total",
+                "Number of light blocks which could not be fetched from a peer",
+            ),
+            &["peer", "kind"],
+        )
+        .unwrap();
+
+        let fork_detections = IntCounterVec::new(
+            opts(
+                "fork_detections_total",
+                "Number of fork detections performed, by outcome",
+            ),
+            &["outcome"],
+        )
+        .unwrap();
+
+        let forks = IntCounterVec::new(
+            opts(
+                "forks_total",
+                "Number of forks reported by the fork detector, by kind",
+            ),
+            &["kind"],
+        )
+        .unwrap();
+
+        let peer_replacements = IntCounterVec::new(
+            opts(
+                "peer_replacements_total",
+                "Number of times the primary or a witness was replaced, by reason",
+            ),
+            &["role", "reason"],
+        )
+        .unwrap();
+
+        let trusting_period_headroom = GaugeVec::new(
+            opts(
+                "trusting_period_headroom_seconds",
+                "Time left until the latest trusted block falls out of the trusting period",
+            ),
+            &["peer"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(verification_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(bisection_steps.clone()))
+            .unwrap();
+        registry.register(Box::new(fetch_duration.clone())).unwrap();
+        registry.register(Box::new(fetch_errors.clone())).unwrap();
+        registry
+            .register(Box::new(fork_detections.clone()))
+            .unwrap();
+        registry.register(Box::new(forks.clone())).unwrap();
+        registry
+            .register(Box::new(peer_replacements.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(trusting_period_headroom.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            verification_duration,
+            bisection_steps,
+            fetch_duration,
+            fetch_errors,
+            fork_detections,
+            forks,
+            peer_replacements,
+            trusting_period_headroom,
+        }
+    }
+
+    /// The registry the metrics are recorded in
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Encode the current value of the metrics in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+
+        // Encoding in the text format cannot fail, except on I/O errors
+        // which cannot occur when writing to a `Vec`.
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+
+        String::from_utf8(buffer).unwrap() // Cannot fail, the text format is UTF-8
+    }
+
+    /// Record the verification of a block by the light client of the given peer.
+    pub(crate) fn observe_verification(&self, peer: PeerId, elapsed: Duration, success: bool) {
+        let result = if success { "success" } else { "failure" };
+
+        self.verification_duration
+            .with_label_values(&[&peer.to_string(), result])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record the number of blocks the light client of the given peer verified
+    /// to reach a target height.
+    pub(crate) fn observe_bisection_steps(&self, peer: PeerId, steps: usize) {
+        self.bisection_steps
+            .with_label_values(&[&peer.to_string()])
+            .observe(steps as f64);
+    }
+
+    /// Record the time it took to fetch a light block from the given peer,
+    /// along with the error which occured, if any.
+    pub(crate) fn observe_fetch(&self, peer: PeerId, elapsed: Duration, error: Option<&IoError>) {
+        let peer = peer.to_string();
+
+        self.fetch_duration
+            .with_label_values(&[&peer])
+            .observe(elapsed.as_secs_f64());
+
+        if let Some(error) = error {
+            self.fetch_errors
+                .with_label_values(&[&peer, io_error_kind(error)])
+                .inc();
+        }
+    }
+
+    /// Record the outcome of a fork detection.
+    pub(crate) fn record_fork_detection(&self, outcome: &ForkDetection) {
+        match outcome {
+            ForkDetection::NotDetected => {
+                self.fork_detections
+                    .with_label_values(&["not_detected"])
+                    .inc();
+            }
+            ForkDetection::Detected(forks) => {
+                self.fork_detections.with_label_values(&["detected"]).inc();
+
+                for fork in forks {
+                    let kind = match fork {
+                        Fork::Forked { .. } => "forked",
+                        Fork::Faulty(_, _) => "faulty",
+                        Fork::Timeout(_, _) => "timeout",
+                    };
+
+                    self.forks.with_label_values(&[kind]).inc();
+                }
+            }
+        }
+    }
+
+    /// Record the replacement of a peer with the given role, `primary` or `witness`,
+    /// which was either marked as `faulty` or put on `probation`.
+    pub(crate) fn record_peer_replacement(&self, role: &str, reason: &str) {
+        self.peer_replacements
+            .with_label_values(&[role, reason])
+            .inc();
+    }
+
+    /// Record how long the latest trusted block of the light client of the given peer
+    /// remains within the trusting period, which is negative if it already expired.
+    pub(crate) fn set_trusting_period_headroom(&self, peer: PeerId, headroom: f64) {
+        self.trusting_period_headroom
+            .with_label_values(&[&peer.to_string()])
+            .set(headroom);
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn opts(name: &str, help: &str) -> Opts {
+    Opts::new(name, help).namespace(NAMESPACE)
+}
+
+fn histogram_opts(name: &str, help: &str, buckets: Vec<f64>) -> HistogramOpts {
+    HistogramOpts::new(name, help)
+        .namespace(NAMESPACE)
+        .buckets(buckets)
+}
+
+fn io_error_kind(error: &IoError) -> &'static str {
+    match error {
+        IoError::RpcError(_) => "rpc",
+        IoError::InvalidHeight(_) => "invalid_height",
+        IoError::InvalidValidatorSet(_) => "invalid_validator_set",
+        IoError::Timeout(_) => "timeout",
+        IoError::Runtime => "runtime",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tendermint_testgen::light_block::default_peer_id;
+
+    #[test]
+    fn encodes_recorded_metrics() {
+        let metrics = Metrics::new();
+        let peer = default_peer_id();
+
+        metrics.observe_verification(peer, Duration::from_millis(250), true);
+        metrics.observe_bisection_steps(peer, 3);
+        metrics.observe_fetch(
+            peer,
+            Duration::from_millis(20),
+            Some(&IoError::Timeout(Duration::from_secs(1))),
+        );
+        metrics.record_fork_detection(&ForkDetection::NotDetected);
+        metrics.record_peer_replacement("witness", "probation");
+        metrics.set_trusting_period_headroom(peer, 3600.0);
+
+        let encoded = metrics.encode();
+        let peer = peer.to_string();
+
+        for line in &[
+            format!(
+                "tendermint_light_client_verification_duration_seconds_count{{peer=\"{}\",result=\"success\"}} 1",
+                peer
+            ),
+            format!(
+                "tendermint_light_client_bisection_steps_sum{{peer=\"{}\"}} 3",
+                peer
+            ),
+            format!(
+                "tendermint_light_client_fetch_errors_total{{kind=\"timeout\",peer=\"{}\"}} 1",
+                peer
+            ),
+            "tendermint_light_client_fork_detections_total{outcome=\"not_detected\"} 1".to_string(),
+            "tendermint_light_client_peer_replacements_total{reason=\"probation\",role=\"witness\"} 1"
+                .to_string(),
+            format!(
+                "tendermint_light_client_trusting_period_headroom_seconds{{peer=\"{}\"}} 3600",
+                peer
+            ),
+        ] {
+            assert!(encoded.contains(line.as_str()), "missing `{}`", line);
+        }
+    }
+}
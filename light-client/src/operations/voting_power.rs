@@ -3,7 +3,7 @@
 use crate::{
     bail,
     predicates::errors::VerificationError,
-    types::{Commit, SignedHeader, TrustThreshold, ValidatorSet},
+    types::{Commit, SignedHeader, TrustThreshold, Validator, ValidatorSet},
 };
 
 use serde::{Deserialize, Serialize};
@@ -12,8 +12,13 @@ use std::fmt;
 
 use std::convert::TryFrom;
 use tendermint::block::CommitSig;
+use tendermint::public_key::Ed25519 as Ed25519PublicKey;
+use tendermint::signature::Ed25519Signature;
 use tendermint::trust_threshold::TrustThreshold as _;
 use tendermint::vote::{SignedVote, ValidatorIndex, Vote};
+use tendermint::{PublicKey, Signature};
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
 
 /// Tally for the voting power computed by the `VotingPowerCalculator`
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -184,6 +189,204 @@ impl VotingPowerCalculator for ProdVotingPowerCalculator {
     }
 }
 
+/// Implementation of a `VotingPowerCalculator` which verifies all the Ed25519 signatures
+/// of a commit in a single batch, and only falls back to verifying them one by one to find
+/// the first invalid one, if any. It accepts the same commits, computes the same tallies and
+/// raises the same errors as the `ProdVotingPowerCalculator`.
+///
+/// The batch verification equation may accept signatures which single verification rejects
+/// when the nonce commitment or the public key has a small-order component. Such signatures
+/// are verified on their own, as are the signatures of non-Ed25519 keys.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BatchVotingPowerCalculator;
+
+impl VotingPowerCalculator for BatchVotingPowerCalculator {
+    fn voting_power_in(
+        &self,
+        signed_header: &SignedHeader,
+        validator_set: &ValidatorSet,
+        trust_threshold: TrustThreshold,
+    ) -> Result<VotingPowerTally, VerificationError> {
+        let (votes, duplicate) = collect_votes(signed_header, validator_set);
+
+        if !verify_batch(&votes) {
+            for vote in &votes {
+                vote.verify()?;
+            }
+        }
+
+        tally(
+            &votes,
+            duplicate,
+            self.total_power_of(validator_set),
+            trust_threshold,
+        )
+    }
+}
+
+/// A non-absent vote of a commit, along with the validator which cast it.
+struct ValidatorVote {
+    validator: Validator,
+    signed_vote: SignedVote,
+    sign_bytes: Vec<u8>,
+    is_commit: bool,
+}
+
+impl ValidatorVote {
+    /// Verify the signature of this vote on its own.
+    fn verify(&self) -> Result<(), VerificationError> {
+        if self
+            .validator
+            .verify_signature(&self.sign_bytes, self.signed_vote.signature())
+            .is_err()
+        {
+            bail!(VerificationError::InvalidSignature {
+                signature: self.signed_vote.signature().to_bytes(),
+                validator: Box::new(self.validator),
+                sign_bytes: self.sign_bytes.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The Ed25519 public key and signature of this vote, if it can be verified in a batch,
+    /// ie. if neither the public key nor the nonce commitment of the signature has a
+    /// small-order component.
+    fn batch_item(&self) -> Option<(Ed25519PublicKey, Ed25519Signature)> {
+        match (self.validator.pub_key, self.signed_vote.signature()) {
+            (PublicKey::Ed25519(public_key), Signature::Ed25519(signature))
+                if is_torsion_free(public_key.as_bytes())
+                    && is_torsion_free(&signature.to_bytes()[..32]) =>
+            {
+                Some((public_key, *signature))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether the given bytes encode a point of the prime-order subgroup of the Ed25519 curve.
+fn is_torsion_free(bytes: &[u8]) -> bool {
+    let point = CompressedEdwardsY::from_slice(bytes).decompress();
+    matches!(point, Some(point) if point.is_torsion_free())
+}
+
+/// Collect the non-absent votes of the given commit which were cast by a validator
+/// of the given set.
+///
+/// Collection stops at the first vote of a validator which already voted, and the
+/// resulting error is returned alongside the votes before it, to be raised only once
+/// their signatures are verified.
+fn collect_votes(
+    signed_header: &SignedHeader,
+    validator_set: &ValidatorSet,
+) -> (Vec<ValidatorVote>, Option<VerificationError>) {
+    let signatures = &signed_header.commit.signatures;
+
+    let mut votes = Vec::with_capacity(signatures.len());
+    let mut seen_validators = HashSet::new();
+
+    // Get non-absent votes from the signatures
+    let non_absent_votes = signatures
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, signature)| {
+            non_absent_vote(
+                signature,
+                ValidatorIndex::try_from(idx).unwrap(),
+                &signed_header.commit,
+            )
+            .map(|vote| (signature, vote))
+        });
+
+    for (signature, vote) in non_absent_votes {
+        // Ensure we only count a validator's power once
+        if seen_validators.contains(&vote.validator_address) {
+            let duplicate = VerificationError::DuplicateValidator(vote.validator_address);
+            return (votes, Some(duplicate));
+        } else {
+            seen_validators.insert(vote.validator_address);
+        }
+
+        let validator = match validator_set.validator(vote.validator_address) {
+            Some(validator) => validator,
+            None => continue, // Cannot find matching validator, so we skip the vote
+        };
+
+        let signed_vote = SignedVote::new(
+            vote.clone(),
+            signed_header.header.chain_id.clone(),
+            vote.validator_address,
+            vote.signature,
+        );
+
+        votes.push(ValidatorVote {
+            validator,
+            sign_bytes: signed_vote.sign_bytes(),
+            signed_vote,
+            is_commit: signature.is_commit(),
+        });
+    }
+
+    (votes, None)
+}
+
+/// Verify the signatures of the given votes, batching the Ed25519 ones together
+/// with `ed25519_dalek::verify_batch`. Returns whether they are all valid.
+fn verify_batch(votes: &[ValidatorVote]) -> bool {
+    let mut messages = Vec::with_capacity(votes.len());
+    let mut signatures = Vec::with_capacity(votes.len());
+    let mut public_keys = Vec::with_capacity(votes.len());
+
+    for vote in votes {
+        match vote.batch_item() {
+            Some((public_key, signature)) => {
+                messages.push(vote.sign_bytes.as_slice());
+                signatures.push(signature);
+                public_keys.push(public_key);
+            }
+            None => {
+                if vote.verify().is_err() {
+                    return false;
+                }
+            }
+        }
+    }
+
+    signatures.is_empty()
+        || ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_ok()
+}
+
+/// Tally the voting power of the given votes, unless a validator voted twice.
+fn tally(
+    votes: &[ValidatorVote],
+    duplicate: Option<VerificationError>,
+    total: u64,
+    trust_threshold: TrustThreshold,
+) -> Result<VotingPowerTally, VerificationError> {
+    if let Some(duplicate) = duplicate {
+        return Err(duplicate);
+    }
+
+    // If the vote is neither absent nor nil, tally its power.
+    // We include stray signatures (~votes for nil) to measure validator availability.
+    //
+    // TODO: Break out of the loop when we have enough voting power.
+    // See https://github.com/informalsystems/tendermint-rs/issues/235
+    let tallied = votes
+        .iter()
+        .filter(|vote| vote.is_commit)
+        .map(|vote| vote.validator.power())
+        .sum();
+
+    Ok(VotingPowerTally {
+        total,
+        tallied,
+        trust_threshold,
+    })
+}
+
 fn non_absent_vote(
     commit_sig: &CommitSig,
     validator_index: ValidatorIndex,
@@ -227,6 +430,19 @@ mod tests {
     use std::fs;
     use std::path::Path;
 
+    use crate::types::LightBlock;
+    use curve25519_dalek::{
+        constants::{ED25519_BASEPOINT_POINT, EIGHT_TORSION},
+        edwards::EdwardsPoint,
+        scalar::Scalar,
+        traits::IsIdentity,
+    };
+    use sha2::{Digest, Sha512};
+    use tendermint::{signature::Ed25519Signature, validator, vote};
+    use tendermint_testgen::{
+        light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header, Validator,
+    };
+
     const TEST_FILES_PATH: &str = "./tests/support/voting_power/";
 
     #[test]
@@ -268,12 +484,22 @@ mod tests {
     fn run_test(tc: TestCase, file: String) {
         println!("- Test '{}' in {}", tc.description, file);
 
-        let calculator = ProdVotingPowerCalculator::default();
+        let calculator = ProdVotingPowerCalculator;
         let trust_threshold = TrustThreshold::default();
 
         let tally =
             calculator.voting_power_in(&tc.signed_header, &tc.validator_set, trust_threshold);
 
+        assert_eq!(
+            BatchVotingPowerCalculator.voting_power_in(
+                &tc.signed_header,
+                &tc.validator_set,
+                trust_threshold
+            ),
+            tally,
+            "batch verification disagrees with sequential verification"
+        );
+
         match tc.result {
             TestResult::Ok { total, tallied } => {
                 assert!(tally.is_ok(), "unexpected error");
@@ -291,4 +517,247 @@ mod tests {
 
         println!("  => SUCCESS");
     }
+
+    fn light_block(validator_count: usize) -> LightBlock {
+        let validators: Vec<_> = (0..validator_count)
+            .map(|i| Validator::new(&format!("val-{}", i)).voting_power(10))
+            .collect();
+
+        let header = Header::new(&validators)
+            .height(1)
+            .chain_id("test-chain")
+            .next_validators(&validators)
+            .time(1);
+        let commit = Commit::new(header.clone(), 1);
+
+        TestgenLightBlock::new(header, commit)
+            .generate()
+            .unwrap()
+            .into()
+    }
+
+    fn signature_mut(commit_sig: &mut CommitSig) -> &mut Signature {
+        match commit_sig {
+            CommitSig::BlockIDFlagCommit { signature, .. } => signature,
+            CommitSig::BlockIDFlagNil { signature, .. } => signature,
+            CommitSig::BlockIDFlagAbsent => panic!("absent vote"),
+        }
+    }
+
+    #[test]
+    fn batch_verification_matches_sequential_verification() {
+        let light_block = light_block(10);
+        let validators = &light_block.validators;
+        let trust_threshold = TrustThreshold::default();
+
+        let sequential = |signed_header: &SignedHeader| {
+            ProdVotingPowerCalculator.voting_power_in(signed_header, validators, trust_threshold)
+        };
+        let batch = |signed_header: &SignedHeader| {
+            BatchVotingPowerCalculator.voting_power_in(signed_header, validators, trust_threshold)
+        };
+
+        // 1. valid commit
+        let signed_header = light_block.signed_header.clone();
+        let (votes, _) = collect_votes(&signed_header, validators);
+        assert!(verify_batch(&votes));
+
+        let tally = batch(&signed_header).unwrap();
+        assert_eq!(tally.total, 100);
+        assert_eq!(tally.tallied, 100);
+        assert_eq!(Ok(tally), sequential(&signed_header));
+
+        // 2. invalid signature, reported for the offending signer
+        let mut bad_signature = light_block.signed_header.clone();
+        let signatures = &mut bad_signature.commit.signatures;
+        let other_signature = *signature_mut(&mut signatures[3]);
+        *signature_mut(&mut signatures[7]) = other_signature;
+
+        let (votes, _) = collect_votes(&bad_signature, validators);
+        assert!(!verify_batch(&votes));
+
+        let error = batch(&bad_signature).unwrap_err();
+        match &error {
+            VerificationError::InvalidSignature { validator, .. } => assert_eq!(
+                Some(validator.address),
+                bad_signature.commit.signatures[7].validator_address()
+            ),
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(Err(error), sequential(&bad_signature));
+
+        // 3. duplicate vote, after the invalid signature
+        let mut bad_signature_and_duplicate = bad_signature.clone();
+        let duplicate = bad_signature_and_duplicate.commit.signatures[0].clone();
+        bad_signature_and_duplicate.commit.signatures[9] = duplicate;
+
+        let error = batch(&bad_signature_and_duplicate).unwrap_err();
+        assert!(matches!(error, VerificationError::InvalidSignature { .. }));
+        assert_eq!(Err(error), sequential(&bad_signature_and_duplicate));
+
+        // 4. duplicate vote alone
+        let mut duplicate_vote = light_block.signed_header.clone();
+        let duplicate = duplicate_vote.commit.signatures[0].clone();
+        duplicate_vote.commit.signatures[9] = duplicate;
+
+        let error = batch(&duplicate_vote).unwrap_err();
+        assert!(matches!(error, VerificationError::DuplicateValidator(_)));
+        assert_eq!(Err(error), sequential(&duplicate_vote));
+    }
+
+    /// The challenge of the signature of `message` by `a_point` with the nonce commitment
+    /// `r_point`, either of which may have a small-order component.
+    fn challenge(r_point: EdwardsPoint, a_point: EdwardsPoint, message: &[u8]) -> Scalar {
+        Scalar::from_hash(
+            Sha512::new()
+                .chain(r_point.compress().as_bytes())
+                .chain(a_point.compress().as_bytes())
+                .chain(message),
+        )
+    }
+
+    /// Replace the vote at `index` with a vote of a new validator, whose public key and
+    /// signature are crafted by `craft` from the sign bytes of the vote.
+    fn with_crafted_vote(
+        light_block: &LightBlock,
+        index: usize,
+        craft: impl FnOnce(&[u8]) -> ([u8; 32], [u8; 64]),
+    ) -> (SignedHeader, ValidatorSet, validator::Info) {
+        let mut signed_header = light_block.signed_header.clone();
+        let (votes, _) = collect_votes(&signed_header, &light_block.validators);
+        let (public_key, signature) = craft(&votes[index].sign_bytes);
+
+        let crafted = validator::Info::new(
+            PublicKey::from_raw_ed25519(&public_key).unwrap(),
+            vote::Power::from(10_u32),
+        );
+
+        let validators = light_block
+            .validators
+            .validators()
+            .iter()
+            .map(|v| {
+                if v.address == votes[index].validator.address {
+                    crafted
+                } else {
+                    *v
+                }
+            })
+            .collect();
+
+        match &mut signed_header.commit.signatures[index] {
+            CommitSig::BlockIDFlagCommit {
+                validator_address,
+                signature: commit_signature,
+                ..
+            } => {
+                *validator_address = crafted.address;
+                *commit_signature = Ed25519Signature::new(signature).into();
+            }
+            _ => unreachable!("all the validators of the light block voted for it"),
+        }
+
+        (
+            signed_header,
+            ValidatorSet::without_proposer(validators),
+            crafted,
+        )
+    }
+
+    type Craft = Box<dyn Fn(&[u8]) -> ([u8; 32], [u8; 64])>;
+
+    fn signature_bytes(r_point: EdwardsPoint, s: Scalar) -> [u8; 64] {
+        let mut signature = [0; 64];
+        signature[..32].copy_from_slice(r_point.compress().as_bytes());
+        signature[32..].copy_from_slice(s.as_bytes());
+        signature
+    }
+
+    /// Craft a signature by the secret scalar `a`, whose public key has the small-order
+    /// component `torsion`, with the first nonce whose challenge cancels that component or not.
+    fn sign_with_small_order_key(a: Scalar, torsion: EdwardsPoint, cancels: bool) -> Craft {
+        Box::new(move |message: &[u8]| {
+            let a_point = a * ED25519_BASEPOINT_POINT + torsion;
+            let (r, r_point, k) = (1_u64..)
+                .map(Scalar::from)
+                .map(|r| {
+                    let r_point = r * ED25519_BASEPOINT_POINT;
+                    (r, r_point, challenge(r_point, a_point, message))
+                })
+                .find(|(_, _, k)| (k * torsion).is_identity() == cancels)
+                .unwrap();
+
+            (
+                a_point.compress().to_bytes(),
+                signature_bytes(r_point, r + k * a),
+            )
+        })
+    }
+
+    #[test]
+    fn both_calculators_reject_signatures_with_small_order_components() {
+        let light_block = light_block(10);
+        let trust_threshold = TrustThreshold::default();
+        let (a, r) = (Scalar::from(1234_u64), Scalar::from(5678_u64));
+        let torsion = EIGHT_TORSION[1];
+
+        let cases: Vec<(&str, Craft, bool)> = vec![
+            (
+                "nonce commitment with a small-order component",
+                Box::new(move |message: &[u8]| {
+                    let a_point = a * ED25519_BASEPOINT_POINT;
+                    let r_point = r * ED25519_BASEPOINT_POINT + torsion;
+                    let s = r + challenge(r_point, a_point, message) * a;
+                    (a_point.compress().to_bytes(), signature_bytes(r_point, s))
+                }),
+                false,
+            ),
+            (
+                "public key with a small-order component",
+                sign_with_small_order_key(a, torsion, false),
+                false,
+            ),
+            (
+                "public key with a small-order component cancelled by the challenge",
+                sign_with_small_order_key(a, torsion, true),
+                true,
+            ),
+        ];
+
+        for (description, craft, is_valid) in cases {
+            let (signed_header, validators, crafted) = with_crafted_vote(&light_block, 4, craft);
+
+            let sequential = ProdVotingPowerCalculator.voting_power_in(
+                &signed_header,
+                &validators,
+                trust_threshold,
+            );
+
+            if is_valid {
+                let tally = sequential.as_ref().expect(description);
+                assert_eq!(tally.tallied, 100, "{}", description);
+            } else {
+                match &sequential {
+                    Err(VerificationError::InvalidSignature { validator, .. }) => {
+                        assert_eq!(validator.address, crafted.address, "{}", description)
+                    }
+                    e => panic!("{}: unexpected result: {:?}", description, e),
+                }
+            }
+
+            // The batch is verified with random coefficients, which must not matter
+            for _ in 0..16 {
+                assert_eq!(
+                    BatchVotingPowerCalculator.voting_power_in(
+                        &signed_header,
+                        &validators,
+                        trust_threshold
+                    ),
+                    sequential,
+                    "{}",
+                    description
+                );
+            }
+        }
+    }
 }
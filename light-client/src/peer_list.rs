@@ -3,17 +3,57 @@
 use crate::{
     bail,
     errors::{Error, ErrorKind},
+    metrics::Metrics,
     types::PeerId,
 };
 
 use contracts::{post, pre};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeSet, HashMap};
+use std::time::Instant;
+
+mod probation;
+pub use probation::{ProbationPolicy, Reputation};
+
+/// Role of a peer in a `PeerList`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerRole {
+    /// The primary, which light blocks are verified from
+    Primary,
+    /// A witness, which the light blocks of the primary are cross-checked with
+    Witness,
+    /// A full node, which can be promoted to witness
+    FullNode,
+    /// A peer on probation, which will be re-admitted as a witness once it responds again
+    OnProbation,
+    /// A peer which sent provably invalid data
+    Faulty,
+}
+
+/// Role and record of the faults of a peer in a `PeerList`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerStatus {
+    /// The peer id
+    pub peer_id: PeerId,
+    /// The role of the peer
+    pub role: PeerRole,
+    /// Number of times the peer timed out
+    pub timeouts: u64,
+    /// Number of times the peer sent provably invalid data
+    pub misbehaviours: u64,
+}
 
 /// A generic container mapping `PeerId`s to some type `T`,
 /// which keeps track of the primary peer, witnesses, full nodes,
-/// and faulty nodes. Provides lifecycle methods to swap the primary,
-/// mark witnesses as faulty, and maintains an `invariant` for
+/// faulty nodes and nodes on probation. Provides lifecycle methods to swap the primary,
+/// mark witnesses as faulty or put them on probation, and maintains an `invariant` for
 /// correctness.
+///
+/// Peers which sent provably invalid data are marked as faulty for good, whereas peers
+/// which timed out are put on probation, with an exponential back-off, until they pass
+/// a health check and are re-admitted as witnesses.
+///
+/// If metrics are set, the replacements of the primary and of the witnesses are recorded.
 #[derive(Clone, Debug)]
 pub struct PeerList<T> {
     values: HashMap<PeerId, T>,
@@ -21,6 +61,10 @@ pub struct PeerList<T> {
     witnesses: BTreeSet<PeerId>,
     full_nodes: BTreeSet<PeerId>,
     faulty_nodes: BTreeSet<PeerId>,
+    probation_nodes: BTreeSet<PeerId>,
+    reputations: HashMap<PeerId, Reputation>,
+    probation_policy: ProbationPolicy,
+    metrics: Option<Metrics>,
 }
 
 impl<T> PeerList<T> {
@@ -32,9 +76,15 @@ impl<T> PeerList<T> {
         peer_list.full_nodes.is_disjoint(&peer_list.witnesses)
             && peer_list.full_nodes.is_disjoint(&peer_list.faulty_nodes)
             && peer_list.witnesses.is_disjoint(&peer_list.faulty_nodes)
+            && peer_list.probation_nodes.is_disjoint(&peer_list.witnesses)
+            && peer_list.probation_nodes.is_disjoint(&peer_list.full_nodes)
+            && peer_list
+                .probation_nodes
+                .is_disjoint(&peer_list.faulty_nodes)
             && !peer_list.witnesses.contains(&peer_list.primary)
             && !peer_list.full_nodes.contains(&peer_list.primary)
             && !peer_list.faulty_nodes.contains(&peer_list.primary)
+            && !peer_list.probation_nodes.contains(&peer_list.primary)
             && peer_list.values.contains_key(&peer_list.primary)
             && peer_list
                 .witnesses
@@ -48,6 +98,10 @@ impl<T> PeerList<T> {
                 .faulty_nodes
                 .iter()
                 .all(|id| peer_list.values.contains_key(id))
+            && peer_list
+                .probation_nodes
+                .iter()
+                .all(|id| peer_list.values.contains_key(id))
     }
 
     /// Transition invariant maintained by a `PeerList`
@@ -106,48 +160,178 @@ impl<T> PeerList<T> {
         &self.faulty_nodes
     }
 
+    /// Get all the peer ids of the nodes on probation
+    pub fn probation_nodes_ids(&self) -> &BTreeSet<PeerId> {
+        &self.probation_nodes
+    }
+
+    /// Get the reputation of the given peer, if it ever committed a fault.
+    pub fn reputation(&self, peer_id: &PeerId) -> Option<&Reputation> {
+        self.reputations.get(peer_id)
+    }
+
+    /// Get the role of the given peer, if it is in the list.
+    pub fn role(&self, peer_id: &PeerId) -> Option<PeerRole> {
+        if *peer_id == self.primary {
+            Some(PeerRole::Primary)
+        } else if self.witnesses.contains(peer_id) {
+            Some(PeerRole::Witness)
+        } else if self.full_nodes.contains(peer_id) {
+            Some(PeerRole::FullNode)
+        } else if self.probation_nodes.contains(peer_id) {
+            Some(PeerRole::OnProbation)
+        } else if self.faulty_nodes.contains(peer_id) {
+            Some(PeerRole::Faulty)
+        } else {
+            None
+        }
+    }
+
+    /// Get the status of all the peers, starting with the primary,
+    /// followed by the witnesses, the full nodes, the nodes on probation and the faulty nodes.
+    pub fn statuses(&self) -> Vec<PeerStatus> {
+        std::iter::once(&self.primary)
+            .chain(&self.witnesses)
+            .chain(&self.full_nodes)
+            .chain(&self.probation_nodes)
+            .chain(&self.faulty_nodes)
+            .filter_map(|peer_id| {
+                let role = self.role(peer_id)?;
+                let reputation = self.reputations.get(peer_id).cloned().unwrap_or_default();
+
+                Some(PeerStatus {
+                    peer_id: *peer_id,
+                    role,
+                    timeouts: reputation.timeouts,
+                    misbehaviours: reputation.misbehaviours,
+                })
+            })
+            .collect()
+    }
+
+    /// Get the policy according to which unresponsive peers are put on probation.
+    pub fn probation_policy(&self) -> &ProbationPolicy {
+        &self.probation_policy
+    }
+
+    /// Record the replacements of the primary and of the witnesses in the given metrics.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = Some(metrics);
+    }
+
     /// Remove the given peer from the list of witnesses,
     /// and mark it as faulty. Get a new witness from
     /// the list of full nodes, if there are any left.
     /// Returns the new witness, if any.
     ///
+    /// Faulty peers are never re-admitted automatically,
+    /// use `suspend_witness` for peers which merely timed out.
+    ///
     /// ## Precondition
     /// - The given peer id must not be the primary peer id.
     /// - The given peer must be in the witness list
     #[pre(faulty_witness != self.primary && self.witnesses.contains(&faulty_witness))]
     #[post(Self::invariant(&self))]
     pub fn replace_faulty_witness(&mut self, faulty_witness: PeerId) -> Option<PeerId> {
-        let mut result = None;
-
-        self.witnesses.remove(&faulty_witness);
-
-        if let Some(new_witness) = self.full_nodes.iter().next().copied() {
-            self.witnesses.insert(new_witness);
-            self.full_nodes.remove(&new_witness);
-            result = Some(new_witness);
-        }
+        let result = self.replace_witness(faulty_witness);
 
         self.faulty_nodes.insert(faulty_witness);
+        self.reputations
+            .entry(faulty_witness)
+            .or_default()
+            .record_misbehaviour();
+
+        self.record_replacement("witness", "faulty");
+        result
+    }
 
+    /// Remove the given peer from the list of witnesses, and put it on probation
+    /// as of `now`. Get a new witness from the list of full nodes, if there are any left.
+    /// Returns the new witness, if any.
+    ///
+    /// ## Precondition
+    /// - The given peer id must not be the primary peer id.
+    /// - The given peer must be in the witness list
+    #[pre(witness != self.primary && self.witnesses.contains(&witness))]
+    #[post(Self::invariant(self))]
+    pub fn suspend_witness(&mut self, witness: PeerId, now: Instant) -> Option<PeerId> {
+        let result = self.replace_witness(witness);
+
+        self.probation_nodes.insert(witness);
+        self.reputations
+            .entry(witness)
+            .or_default()
+            .record_timeout(now, &self.probation_policy);
+
+        self.record_replacement("witness", "probation");
         result
     }
 
+    /// Remove the given witness, and replace it with a full node, if there are any left.
+    fn replace_witness(&mut self, witness: PeerId) -> Option<PeerId> {
+        self.witnesses.remove(&witness);
+
+        let new_witness = self.full_nodes.iter().next().copied()?;
+        self.full_nodes.remove(&new_witness);
+        self.witnesses.insert(new_witness);
+
+        Some(new_witness)
+    }
+
     /// Mark the primary as faulty and swap it for the next available witness, if any.
     /// Returns the new primary on success.
     ///
     /// ## Errors
-    /// - If there are no witness left, returns `ErrorKind::NoWitnessLeft`.
+    /// - If there are no witness left, returns `ErrorKind::NoWitnessLeft`,
+    ///   and the primary is left in place.
     #[post(ret.is_ok() ==> Self::invariant(&self))]
     pub fn replace_faulty_primary(
         &mut self,
         primary_error: Option<Error>,
     ) -> Result<PeerId, Error> {
-        self.faulty_nodes.insert(self.primary);
+        let (faulty_primary, new_primary) = self.replace_primary(primary_error)?;
+
+        self.faulty_nodes.insert(faulty_primary);
+        self.reputations
+            .entry(faulty_primary)
+            .or_default()
+            .record_misbehaviour();
+
+        self.record_replacement("primary", "faulty");
+        Ok(new_primary)
+    }
+
+    /// Put the primary on probation as of `now`, and swap it for the next available
+    /// witness, if any. Returns the new primary on success.
+    ///
+    /// ## Errors
+    /// - If there are no witness left, returns `ErrorKind::NoWitnessLeft`,
+    ///   and the primary is left in place.
+    #[post(ret.is_err() || Self::invariant(self))]
+    pub fn suspend_primary(
+        &mut self,
+        primary_error: Option<Error>,
+        now: Instant,
+    ) -> Result<PeerId, Error> {
+        let (suspended_primary, new_primary) = self.replace_primary(primary_error)?;
+
+        self.probation_nodes.insert(suspended_primary);
+        self.reputations
+            .entry(suspended_primary)
+            .or_default()
+            .record_timeout(now, &self.probation_policy);
 
+        self.record_replacement("primary", "probation");
+        Ok(new_primary)
+    }
+
+    /// Swap the primary for the next available witness, if any.
+    /// Returns the previous and the new primary on success.
+    fn replace_primary(&mut self, primary_error: Option<Error>) -> Result<(PeerId, PeerId), Error> {
         if let Some(new_primary) = self.witnesses.iter().next().copied() {
-            self.primary = new_primary;
             self.witnesses.remove(&new_primary);
-            Ok(new_primary)
+            let previous_primary = std::mem::replace(&mut self.primary, new_primary);
+            Ok((previous_primary, new_primary))
         } else if let Some(err) = primary_error {
             bail!(ErrorKind::NoWitnessLeft.context(err))
         } else {
@@ -155,10 +339,134 @@ impl<T> PeerList<T> {
         }
     }
 
+    /// Record the replacement of a peer with the given role in the metrics, if any.
+    fn record_replacement(&self, role: &str, reason: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_peer_replacement(role, reason);
+        }
+    }
+
+    /// Get the peers on probation whose probation is over at the given instant,
+    /// and which are thus due for a health check.
+    pub fn due_for_health_check(&self, now: Instant) -> Vec<PeerId> {
+        self.probation_nodes
+            .iter()
+            .filter(|id| match self.reputations.get(id) {
+                Some(reputation) => reputation.is_probation_over(now),
+                None => false,
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Re-admit the given peer, which passed its health check, as a witness.
+    ///
+    /// ## Errors
+    /// - If the peer is not on probation, returns `ErrorKind::PeerNotFound`.
+    #[post(ret.is_err() || Self::invariant(self))]
+    pub fn readmit(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        if !self.probation_nodes.remove(&peer_id) {
+            bail!(ErrorKind::PeerNotFound(peer_id));
+        }
+
+        self.witnesses.insert(peer_id);
+        self.reputations
+            .entry(peer_id)
+            .or_default()
+            .record_readmission();
+
+        Ok(())
+    }
+
+    /// Extend the probation of the given peer, which failed its health check at `now`.
+    ///
+    /// ## Errors
+    /// - If the peer is not on probation, returns `ErrorKind::PeerNotFound`.
+    pub fn extend_probation(&mut self, peer_id: PeerId, now: Instant) -> Result<(), Error> {
+        if !self.probation_nodes.contains(&peer_id) {
+            bail!(ErrorKind::PeerNotFound(peer_id));
+        }
+
+        self.reputations
+            .entry(peer_id)
+            .or_default()
+            .record_timeout(now, &self.probation_policy);
+
+        Ok(())
+    }
+
+    /// Add the given peer as a witness.
+    ///
+    /// A peer on probation can be added again, in which case its value is replaced
+    /// by the given one, and it is re-admitted right away. Faulty peers are banned for good,
+    /// and cannot be added again.
+    ///
+    /// ## Errors
+    /// - If the peer is already the primary, a witness or a full node,
+    ///   returns `ErrorKind::PeerAlreadyExists`.
+    /// - If the peer is faulty, returns `ErrorKind::PeerFaulty`.
+    #[post(ret.is_err() || Self::invariant(self))]
+    pub fn add_witness(&mut self, peer_id: PeerId, value: T) -> Result<(), Error> {
+        if self.faulty_nodes.contains(&peer_id) {
+            bail!(ErrorKind::PeerFaulty(peer_id));
+        }
+
+        if self.probation_nodes.remove(&peer_id) {
+            self.reputations
+                .entry(peer_id)
+                .or_default()
+                .record_readmission();
+        } else if self.values.contains_key(&peer_id) {
+            bail!(ErrorKind::PeerAlreadyExists(peer_id));
+        }
+
+        self.values.insert(peer_id, value);
+        self.witnesses.insert(peer_id);
+
+        Ok(())
+    }
+
+    /// Remove the given witness from the peer list, and return its value.
+    /// Unlike `replace_faulty_witness`, the witness is not marked as faulty,
+    /// and is not replaced by a full node.
+    ///
+    /// ## Errors
+    /// - If the peer is not a witness, returns `ErrorKind::PeerNotFound`.
+    #[post(ret.is_err() || Self::invariant(self))]
+    pub fn remove_witness(&mut self, peer_id: PeerId) -> Result<T, Error> {
+        if !self.witnesses.remove(&peer_id) {
+            bail!(ErrorKind::PeerNotFound(peer_id));
+        }
+
+        self.reputations.remove(&peer_id);
+        Ok(self.values.remove(&peer_id).unwrap()) // SAFETY: Enforced by invariant
+    }
+
+    /// Promote the given witness or full node to primary.
+    /// The current primary becomes a witness, and is returned.
+    ///
+    /// ## Errors
+    /// - If the peer is neither a witness nor a full node, returns `ErrorKind::PeerNotFound`.
+    #[post(ret.is_err() || Self::invariant(self))]
+    pub fn promote_to_primary(&mut self, peer_id: PeerId) -> Result<PeerId, Error> {
+        if !self.witnesses.remove(&peer_id) && !self.full_nodes.remove(&peer_id) {
+            bail!(ErrorKind::PeerNotFound(peer_id));
+        }
+
+        let previous_primary = std::mem::replace(&mut self.primary, peer_id);
+        self.witnesses.insert(previous_primary);
+
+        Ok(previous_primary)
+    }
+
     /// Get a reference to the underlying `HashMap`
     pub fn values(&self) -> &HashMap<PeerId, T> {
         &self.values
     }
+    /// Get a mutable iterator over the values of all peers, whatever their role
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.values.values_mut()
+    }
     /// Consume into the underlying `HashMap`
     pub fn into_values(self) -> HashMap<PeerId, T> {
         self.values
@@ -173,6 +481,7 @@ pub struct PeerListBuilder<T> {
     witnesses: BTreeSet<PeerId>,
     full_nodes: BTreeSet<PeerId>,
     faulty_nodes: BTreeSet<PeerId>,
+    probation_policy: ProbationPolicy,
 }
 
 // This instance must be derived manually because the automatically
@@ -186,6 +495,7 @@ impl<T> Default for PeerListBuilder<T> {
             witnesses: Default::default(),
             full_nodes: Default::default(),
             faulty_nodes: Default::default(),
+            probation_policy: Default::default(),
         }
     }
 }
@@ -219,6 +529,12 @@ impl<T> PeerListBuilder<T> {
         self.faulty_nodes.insert(peer_id);
     }
 
+    /// Set the policy according to which unresponsive peers are put on probation.
+    /// Defaults to `ProbationPolicy::default()`.
+    pub fn probation_policy(&mut self, policy: ProbationPolicy) {
+        self.probation_policy = policy;
+    }
+
     /// Builds the `PeerList`.
     ///
     /// ## Precondition
@@ -232,6 +548,10 @@ impl<T> PeerListBuilder<T> {
             witnesses: self.witnesses,
             full_nodes: self.full_nodes,
             faulty_nodes: self.faulty_nodes,
+            probation_nodes: BTreeSet::new(),
+            reputations: HashMap::new(),
+            probation_policy: self.probation_policy,
+            metrics: None,
         }
     }
 }
@@ -239,6 +559,7 @@ impl<T> PeerListBuilder<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     trait BTreeSetExt<T> {
         fn to_vec(&self) -> Vec<T>;
@@ -302,6 +623,48 @@ mod tests {
         assert!(peer_list.witnesses_ids().is_empty());
     }
 
+    #[test]
+    fn statuses_list_all_peers_by_role() {
+        let mut peer_list = dummy_peer_list();
+        peer_list.suspend_witness(b(), Instant::now());
+        peer_list.replace_faulty_witness(c());
+
+        let statuses: Vec<_> = peer_list
+            .statuses()
+            .into_iter()
+            .map(|s| (s.peer_id, s.role, s.timeouts, s.misbehaviours))
+            .collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                (a(), PeerRole::Primary, 0, 0),
+                (b(), PeerRole::OnProbation, 1, 0),
+                (c(), PeerRole::Faulty, 0, 1),
+            ]
+        );
+        assert_eq!(peer_list.role(&d()), None);
+    }
+
+    #[test]
+    fn replacements_are_recorded_in_metrics() {
+        let metrics = Metrics::new();
+        let mut peer_list = dummy_peer_list();
+        peer_list.set_metrics(metrics.clone());
+
+        peer_list.suspend_witness(b(), Instant::now());
+        peer_list.replace_faulty_primary(None).unwrap();
+        peer_list.replace_faulty_primary(None).unwrap_err();
+
+        let encoded = metrics.encode();
+        for line in &[
+            "peer_replacements_total{reason=\"faulty\",role=\"primary\"} 1",
+            "peer_replacements_total{reason=\"probation\",role=\"witness\"} 1",
+        ] {
+            assert!(encoded.contains(line), "missing `{}`", line);
+        }
+    }
+
     #[test]
     fn replace_faulty_primary_fails_if_no_more_witnesses() {
         let mut peer_list = dummy_peer_list();
@@ -325,6 +688,204 @@ mod tests {
         assert!(peer_list.full_nodes_ids().is_empty());
     }
 
+    #[test]
+    fn add_witness_succeeds() {
+        let mut peer_list = dummy_peer_list();
+        peer_list.add_witness(d(), 4_u32).unwrap();
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![b(), d()]);
+        assert_eq!(peer_list.get(&d()), Some(&4));
+
+        // Nodes on probation can be added again, and keep their reputation
+        peer_list.suspend_witness(d(), Instant::now());
+        assert_eq!(peer_list.probation_nodes_ids().to_vec(), vec![d()]);
+        peer_list.add_witness(d(), 5_u32).unwrap();
+        assert!(peer_list.probation_nodes_ids().is_empty());
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![b(), c(), d()]);
+        assert_eq!(peer_list.get(&d()), Some(&5));
+        assert_eq!(peer_list.reputation(&d()).unwrap().timeouts, 1);
+    }
+
+    #[test]
+    fn add_witness_fails_if_faulty() {
+        let mut peer_list = dummy_peer_list();
+        peer_list.replace_faulty_witness(b());
+
+        let err = peer_list.add_witness(b(), 4_u32).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::PeerFaulty(b()));
+        assert_eq!(peer_list.faulty_nodes_ids().to_vec(), vec![b()]);
+        assert_eq!(peer_list.get(&b()), Some(&2));
+        assert!(peer_list.reputation(&b()).unwrap().is_banned());
+    }
+
+    #[test]
+    fn add_witness_fails_if_already_exists() {
+        let mut peer_list = dummy_peer_list();
+        for peer_id in &[a(), b(), c()] {
+            let err = peer_list.add_witness(*peer_id, 4_u32).unwrap_err();
+            assert_eq!(err.kind(), &ErrorKind::PeerAlreadyExists(*peer_id));
+        }
+        assert!(PeerList::invariant(&peer_list));
+    }
+
+    #[test]
+    fn remove_witness_succeeds() {
+        let mut peer_list = dummy_peer_list();
+        assert_eq!(peer_list.remove_witness(b()).unwrap(), 2);
+        assert!(peer_list.witnesses_ids().is_empty());
+        assert!(peer_list.faulty_nodes_ids().is_empty());
+        assert_eq!(peer_list.get(&b()), None);
+
+        let err = peer_list.remove_witness(b()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::PeerNotFound(b()));
+        let err = peer_list.remove_witness(a()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::PeerNotFound(a()));
+    }
+
+    #[test]
+    fn promote_to_primary_succeeds() {
+        let mut peer_list = dummy_peer_list();
+        assert_eq!(peer_list.promote_to_primary(c()).unwrap(), a());
+        assert_eq!(peer_list.primary(), &3);
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![b(), a()]);
+        assert!(peer_list.full_nodes_ids().is_empty());
+
+        assert_eq!(peer_list.promote_to_primary(b()).unwrap(), c());
+        assert_eq!(peer_list.primary(), &2);
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![c(), a()]);
+    }
+
+    #[test]
+    fn promote_to_primary_fails_if_not_witness_or_full_node() {
+        let mut peer_list = dummy_peer_list();
+        peer_list.replace_faulty_witness(b());
+        for peer_id in &[a(), b(), d()] {
+            let err = peer_list.promote_to_primary(*peer_id).unwrap_err();
+            assert_eq!(err.kind(), &ErrorKind::PeerNotFound(*peer_id));
+        }
+        assert_eq!(peer_list.primary(), &1);
+    }
+
+    #[test]
+    fn suspended_witnesses_are_readmitted_after_probation() {
+        let mut peer_list = dummy_peer_list();
+        let policy = *peer_list.probation_policy();
+        let now = Instant::now();
+
+        assert_eq!(peer_list.suspend_witness(b(), now), Some(c()));
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![c()]);
+        assert_eq!(peer_list.probation_nodes_ids().to_vec(), vec![b()]);
+        assert!(peer_list.faulty_nodes_ids().is_empty());
+
+        let reputation = peer_list.reputation(&b()).unwrap();
+        assert_eq!(reputation.timeouts, 1);
+        assert!(!reputation.is_banned());
+
+        // Not due until the probation is over
+        assert!(peer_list.due_for_health_check(now).is_empty());
+        let later = now + policy.initial_backoff;
+        assert_eq!(peer_list.due_for_health_check(later), vec![b()]);
+
+        // A failed health check doubles the probation
+        peer_list.extend_probation(b(), later).unwrap();
+        assert!(peer_list
+            .due_for_health_check(later + policy.initial_backoff)
+            .is_empty());
+        let later = later + policy.initial_backoff * 2;
+        assert_eq!(peer_list.due_for_health_check(later), vec![b()]);
+
+        peer_list.readmit(b()).unwrap();
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![b(), c()]);
+        assert!(peer_list.probation_nodes_ids().is_empty());
+
+        let reputation = peer_list.reputation(&b()).unwrap();
+        assert_eq!(reputation.timeouts, 2);
+        assert_eq!(reputation.consecutive_timeouts, 0);
+        assert_eq!(reputation.probation_until, None);
+
+        let err = peer_list.readmit(b()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::PeerNotFound(b()));
+    }
+
+    #[test]
+    fn faulty_witnesses_are_never_readmitted() {
+        let mut peer_list = dummy_peer_list();
+        let now = Instant::now();
+
+        peer_list.replace_faulty_witness(b());
+        assert!(peer_list.reputation(&b()).unwrap().is_banned());
+        assert!(peer_list
+            .due_for_health_check(now + Duration::from_secs(3600))
+            .is_empty());
+
+        let err = peer_list.readmit(b()).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::PeerNotFound(b()));
+        let err = peer_list.extend_probation(b(), now).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::PeerNotFound(b()));
+    }
+
+    #[test]
+    fn suspend_primary_succeeds() {
+        let mut peer_list = dummy_peer_list();
+        let now = Instant::now();
+
+        assert_eq!(peer_list.suspend_primary(None, now).unwrap(), b());
+        assert_eq!(peer_list.primary(), &2);
+        assert_eq!(peer_list.probation_nodes_ids().to_vec(), vec![a()]);
+        assert!(peer_list.faulty_nodes_ids().is_empty());
+
+        let later = now + peer_list.probation_policy().initial_backoff;
+        assert_eq!(peer_list.due_for_health_check(later), vec![a()]);
+        peer_list.readmit(a()).unwrap();
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![a()]);
+    }
+
+    #[test]
+    fn suspend_primary_fails_if_no_more_witnesses() {
+        let mut builder = PeerList::builder();
+        builder.primary(a(), 1_u32);
+        builder.witness(b(), 2_u32);
+        let mut peer_list = builder.build();
+        let now = Instant::now();
+
+        assert_eq!(peer_list.suspend_primary(None, now).unwrap(), b());
+        let err = peer_list.suspend_primary(None, now).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::NoWitnessLeft);
+
+        // The primary stays in place, and is not put on probation
+        assert_eq!(peer_list.primary_id(), b());
+        assert_eq!(peer_list.probation_nodes_ids().to_vec(), vec![a()]);
+        assert_eq!(peer_list.reputation(&b()), None);
+        assert!(PeerList::invariant(&peer_list));
+
+        // Re-admitting the previous primary thus never turns the current one into a witness
+        let later = now + peer_list.probation_policy().initial_backoff;
+        assert_eq!(peer_list.due_for_health_check(later), vec![a()]);
+        peer_list.readmit(a()).unwrap();
+        assert_eq!(peer_list.witnesses_ids().to_vec(), vec![a()]);
+        assert_eq!(peer_list.primary_id(), b());
+    }
+
+    #[test]
+    fn probation_policy_is_configurable() {
+        let policy = ProbationPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(2),
+        };
+
+        let mut builder = PeerList::builder();
+        builder.primary(a(), 1_u32);
+        builder.witness(b(), 2_u32);
+        builder.probation_policy(policy);
+        let mut peer_list = builder.build();
+
+        let now = Instant::now();
+        peer_list.suspend_witness(b(), now);
+        assert_eq!(
+            peer_list.reputation(&b()).unwrap().probation_until,
+            Some(now + Duration::from_secs(1))
+        );
+    }
+
     #[test]
     #[should_panic(expected = "Pre-condition of replace_faulty_witness violated")]
     fn replace_faulty_witness_fails_if_not_witness() {
@@ -0,0 +1,98 @@
+//! Reputation of the peers, and probation of the unresponsive ones.
+
+use std::time::{Duration, Instant};
+
+/// Policy according to which unresponsive peers are put on probation.
+///
+/// A peer which times out is put on probation for `initial_backoff`. If it fails
+/// the health check performed at the end of its probation, the probation is extended
+/// for twice as long as the previous one, up to `max_backoff`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProbationPolicy {
+    /// Duration of the first probation of a peer
+    pub initial_backoff: Duration,
+    /// Maximum duration of a probation
+    pub max_backoff: Duration,
+}
+
+impl ProbationPolicy {
+    /// Duration of the probation of a peer which timed out `consecutive_timeouts` times in a row.
+    pub fn backoff(&self, consecutive_timeouts: u32) -> Duration {
+        let exponent = consecutive_timeouts.saturating_sub(1).min(31);
+
+        self.initial_backoff
+            .checked_mul(1 << exponent)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+impl Default for ProbationPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// Record of the faults committed by a peer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reputation {
+    /// Number of times the peer timed out
+    pub timeouts: u64,
+    /// Number of times the peer timed out since it was last re-admitted
+    pub consecutive_timeouts: u32,
+    /// Number of times the peer sent provably invalid data
+    pub misbehaviours: u64,
+    /// Instant at which the probation of the peer ends, if it is on probation
+    pub probation_until: Option<Instant>,
+}
+
+impl Reputation {
+    /// Whether the peer sent provably invalid data, in which case it is banned for good.
+    pub fn is_banned(&self) -> bool {
+        self.misbehaviours > 0
+    }
+
+    /// Whether the probation of the peer is over at the given instant,
+    /// ie. whether it is due for a health check.
+    pub fn is_probation_over(&self, now: Instant) -> bool {
+        !self.is_banned() && matches!(self.probation_until, Some(until) if until <= now)
+    }
+
+    /// Record a timeout at the given instant, and extend the probation of the peer accordingly.
+    pub(super) fn record_timeout(&mut self, now: Instant, policy: &ProbationPolicy) {
+        self.timeouts += 1;
+        self.consecutive_timeouts = self.consecutive_timeouts.saturating_add(1);
+        self.probation_until = Some(now + policy.backoff(self.consecutive_timeouts));
+    }
+
+    /// Record that the peer sent provably invalid data.
+    pub(super) fn record_misbehaviour(&mut self) {
+        self.misbehaviours += 1;
+        self.probation_until = None;
+    }
+
+    /// Record that the peer passed its health check.
+    pub(super) fn record_readmission(&mut self) {
+        self.consecutive_timeouts = 0;
+        self.probation_until = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_exponential_and_bounded() {
+        let policy = ProbationPolicy {
+            initial_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(60),
+        };
+
+        let backoffs: Vec<_> = (1..=5).map(|n| policy.backoff(n).as_secs()).collect();
+        assert_eq!(backoffs, vec![10, 20, 40, 60, 60]);
+        assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+    }
+}
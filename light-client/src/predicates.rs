@@ -239,21 +239,8 @@ pub fn verify(
     // Ensure the header isn't from a future time
     vp.is_header_from_past(&untrusted.signed_header.header, options.clock_drift, now)?;
 
-    // Ensure the header validator hashes match the given validators
-    vp.validator_sets_match(&untrusted, &*hasher)?;
-
-    // Ensure the header next validator hashes match the given next validators
-    vp.next_validators_match(&untrusted, &*hasher)?;
-
-    // Ensure the header matches the commit
-    vp.header_matches_commit(&untrusted.signed_header, hasher)?;
-
-    // Additional implementation specific validation
-    vp.valid_commit(
-        &untrusted.signed_header,
-        &untrusted.validators,
-        commit_validator,
-    )?;
+    // Ensure the validator sets and the commit match the header
+    validate(vp, commit_validator, hasher, untrusted)?;
 
     // Check that the untrusted block is more recent than the trusted state
     vp.is_monotonic_bft_time(
@@ -295,6 +282,36 @@ pub fn verify(
     Ok(())
 }
 
+/// Validate the given light block on its own, without checking whether it can be trusted:
+///
+/// - Check that the hashes of its validator sets match the ones in the header.
+/// - Check that the header matches the commit.
+/// - Perform additional implementation specific validation of the commit.
+pub fn validate(
+    vp: &dyn VerificationPredicates,
+    commit_validator: &dyn CommitValidator,
+    hasher: &dyn Hasher,
+    light_block: &LightBlock,
+) -> Result<(), VerificationError> {
+    // Ensure the header validator hashes match the given validators
+    vp.validator_sets_match(light_block, &*hasher)?;
+
+    // Ensure the header next validator hashes match the given next validators
+    vp.next_validators_match(light_block, &*hasher)?;
+
+    // Ensure the header matches the commit
+    vp.header_matches_commit(&light_block.signed_header, hasher)?;
+
+    // Additional implementation specific validation
+    vp.valid_commit(
+        &light_block.signed_header,
+        &light_block.validators,
+        commit_validator,
+    )?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::ops::Sub;
@@ -0,0 +1,197 @@
+//! Verification of the results of ABCI queries against the app hash of light blocks
+//! verified by the light client.
+
+use tendermint::merkle::runtime::ProofRuntime;
+use tendermint_rpc::endpoint::abci_query::AbciQuery;
+
+use crate::{
+    bail,
+    errors::{Error, ErrorKind},
+    types::LightBlock,
+};
+
+/// Verify that the value returned by an ABCI query made with `prove = true` is committed
+/// in the state of the application at the height of the query, by checking its proof
+/// against the app hash of the given light block.
+///
+/// As the app hash of a header commits to the state of the application after the previous
+/// block, the light block must be at height `result.height + 1`. It must moreover have been
+/// verified by the light client beforehand.
+///
+/// The `keys` form the path under which the value is committed, from the outermost to the
+/// innermost key, eg. the name of the store and the key within that store for a Cosmos SDK
+/// application. See [`store_key_path`].
+pub fn verify_abci_query(
+    runtime: &ProofRuntime,
+    result: &AbciQuery,
+    light_block: &LightBlock,
+    keys: &[Vec<u8>],
+) -> Result<(), Error> {
+    if result.code.is_err() {
+        bail!(ErrorKind::InvalidQueryResult(format!(
+            "query failed with code {}: {}",
+            result.code.value(),
+            result.log
+        )));
+    }
+
+    if light_block.height() != result.height.increment() {
+        bail!(ErrorKind::InvalidQueryResult(format!(
+            "light block at height {} cannot be used to verify a query at height {}",
+            light_block.height(),
+            result.height
+        )));
+    }
+
+    let proof = result
+        .proof
+        .as_ref()
+        .ok_or_else(|| ErrorKind::InvalidQueryResult("missing proof".to_string()))?;
+
+    if result.value.is_empty() {
+        bail!(ErrorKind::InvalidQueryResult(
+            "absence proofs are not supported".to_string()
+        ));
+    }
+
+    let app_hash = light_block.signed_header.header.app_hash.value();
+
+    runtime
+        .verify_value(proof, &app_hash, keys, &result.value)
+        .map_err(|e| ErrorKind::InvalidQueryResult("invalid proof".to_string()).context(e))?;
+
+    Ok(())
+}
+
+/// Key path of the given key within the store named in the given query path, for queries
+/// against the Cosmos SDK multistore, whose path are of the form `/store/<name>/key`.
+///
+/// Returns `None` if the path is not of that form.
+pub fn store_key_path(path: &str, key: &[u8]) -> Option<Vec<Vec<u8>>> {
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        ["", "store", store, "key"] if !store.is_empty() => {
+            Some(vec![store.as_bytes().to_vec(), key.to_vec()])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use prost::Message;
+    use sha2::{Digest, Sha256};
+    use tendermint::hash::AppHash;
+    use tendermint::merkle::{
+        self,
+        ops::PROOF_OP_SIMPLE_VALUE,
+        proof::{Proof, ProofOp},
+    };
+    use tendermint_proto::crypto::{Proof as RawProof, ValueOp as RawValueOp};
+    use tendermint_testgen::{light_block::LightBlock as TestgenLightBlock, Generator};
+
+    /// Result of a query of the given key, at height 2, whose value is committed
+    /// in a simple Merkle tree of a single key-value pair, together with the root of that tree.
+    fn make_query(key: &[u8], value: &[u8]) -> (AbciQuery, Vec<u8>) {
+        let mut kv_pair = vec![key.len() as u8];
+        kv_pair.extend_from_slice(key);
+        kv_pair.push(32);
+        kv_pair.extend_from_slice(&Sha256::digest(value));
+
+        let raw = RawValueOp {
+            key: key.to_vec(),
+            proof: Some(RawProof {
+                total: 1,
+                index: 0,
+                leaf_hash: merkle::leaf_hash(&kv_pair).to_vec(),
+                aunts: vec![],
+            }),
+        };
+        let mut data = Vec::new();
+        raw.encode(&mut data).unwrap();
+
+        let result = AbciQuery {
+            key: key.to_vec(),
+            value: value.to_vec(),
+            proof: Some(Proof {
+                ops: vec![ProofOp {
+                    field_type: PROOF_OP_SIMPLE_VALUE.to_string(),
+                    key: key.to_vec(),
+                    data,
+                }],
+            }),
+            height: 2_u32.into(),
+            ..AbciQuery::default()
+        };
+
+        let root = merkle::simple_hash_from_byte_vectors(vec![kv_pair]).to_vec();
+        (result, root)
+    }
+
+    fn make_light_block(height: u64, app_hash: Vec<u8>) -> LightBlock {
+        let mut light_block: LightBlock = TestgenLightBlock::new_default(height)
+            .generate()
+            .unwrap()
+            .into();
+
+        light_block.signed_header.header.app_hash = AppHash::try_from(app_hash).unwrap();
+        light_block
+    }
+
+    #[test]
+    fn verify_query_against_app_hash() {
+        let runtime = ProofRuntime::default();
+        let (result, root) = make_query(b"key", b"value");
+        let keys = [b"key".to_vec()];
+
+        let light_block = make_light_block(3, root.clone());
+        assert!(verify_abci_query(&runtime, &result, &light_block, &keys).is_ok());
+
+        // The app hash of the block at the height of the query does not commit to its result
+        let light_block = make_light_block(2, root);
+        assert!(verify_abci_query(&runtime, &result, &light_block, &keys).is_err());
+
+        let light_block = make_light_block(3, vec![0; 32]);
+        assert!(verify_abci_query(&runtime, &result, &light_block, &keys).is_err());
+    }
+
+    #[test]
+    fn reject_unproven_results() {
+        let runtime = ProofRuntime::default();
+        let (result, root) = make_query(b"key", b"value");
+        let light_block = make_light_block(3, root);
+        let keys = [b"key".to_vec()];
+
+        let tampered = AbciQuery {
+            value: b"other".to_vec(),
+            ..result.clone()
+        };
+        assert!(verify_abci_query(&runtime, &tampered, &light_block, &keys).is_err());
+
+        let unproven = AbciQuery {
+            proof: None,
+            ..result.clone()
+        };
+        assert!(verify_abci_query(&runtime, &unproven, &light_block, &keys).is_err());
+
+        let failed = AbciQuery {
+            code: 1.into(),
+            ..result
+        };
+        assert!(verify_abci_query(&runtime, &failed, &light_block, &keys).is_err());
+    }
+
+    #[test]
+    fn parse_store_key_path() {
+        assert_eq!(
+            store_key_path("/store/ibc/key", b"k"),
+            Some(vec![b"ibc".to_vec(), b"k".to_vec()])
+        );
+        assert_eq!(store_key_path("/store/ibc/subspace", b"k"), None);
+        assert_eq!(store_key_path("/store//key", b"k"), None);
+        assert_eq!(store_key_path("/custom/ibc", b"k"), None);
+    }
+}
@@ -0,0 +1,373 @@
+//! Portable snapshots of the trusted state of a light client, to move it between
+//! machines or to initialize another client with it, eg. an IBC client.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use tendermint::chain;
+
+use crate::{
+    bail,
+    errors::{Error, ErrorKind},
+    light_client::Options,
+    operations::{
+        CommitValidator, Hasher, ProdCommitValidator, ProdHasher, ProdVotingPowerCalculator,
+        VotingPowerCalculator,
+    },
+    predicates::{errors::VerificationError, ProdPredicates, VerificationPredicates},
+    store::LightStore,
+    types::{LightBlock, Status},
+};
+
+/// Version of the snapshot format produced by this library.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Encoding of a snapshot
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// JSON encoding
+    #[default]
+    Json,
+    /// CBOR encoding
+    Cbor,
+}
+
+impl fmt::Display for SnapshotFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "json"),
+            Self::Cbor => write!(f, "cbor"),
+        }
+    }
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "cbor" => Ok(Self::Cbor),
+            _ => Err(format!("unknown snapshot format: {}", s)),
+        }
+    }
+}
+
+/// Snapshot of the trusted state of a light client.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Version of the snapshot format
+    pub version: u32,
+    /// Identifier of the chain the light blocks belong to
+    pub chain_id: chain::Id,
+    /// Verification parameters of the light client
+    pub options: Options,
+    /// Latest trusted light blocks, in ascending order of height
+    pub light_blocks: Vec<LightBlock>,
+}
+
+/// Header of a snapshot, decoded first to reject unsupported versions upfront.
+#[derive(Deserialize)]
+struct SnapshotVersion {
+    version: u32,
+}
+
+impl Snapshot {
+    /// Take a snapshot of the (at most) `max_blocks` trusted light blocks
+    /// of greatest height in the given light store.
+    pub fn export(
+        light_store: &dyn LightStore,
+        options: Options,
+        max_blocks: usize,
+    ) -> Result<Self, Error> {
+        let mut light_blocks: Vec<_> = light_store.all(Status::Trusted).collect();
+        light_blocks.sort_by_key(LightBlock::height);
+
+        let skip = light_blocks.len().saturating_sub(max_blocks.max(1));
+        let light_blocks: Vec<_> = light_blocks.into_iter().skip(skip).collect();
+
+        let chain_id = match light_blocks.last() {
+            Some(latest) => latest.signed_header.header.chain_id.clone(),
+            None => bail!(ErrorKind::NoTrustedState(Status::Trusted)),
+        };
+
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            chain_id,
+            options,
+            light_blocks,
+        })
+    }
+
+    /// Encode this snapshot in the given format.
+    pub fn encode(&self, format: SnapshotFormat) -> Result<Vec<u8>, Error> {
+        let bytes = match format {
+            SnapshotFormat::Json => serde_json::to_vec_pretty(self).map_err(|e| e.to_string()),
+            SnapshotFormat::Cbor => serde_cbor::to_vec(self).map_err(|e| e.to_string()),
+        };
+
+        bytes.map_err(|e| {
+            ErrorKind::InvalidSnapshot(format!("failed to encode snapshot: {}", e)).into()
+        })
+    }
+
+    /// Decode a snapshot encoded in the given format.
+    ///
+    /// The snapshot is not checked for consistency, see [`Snapshot::validate`].
+    pub fn decode(bytes: &[u8], format: SnapshotFormat) -> Result<Self, Error> {
+        let version = decode::<SnapshotVersion>(bytes, format)?.version;
+
+        if version != SNAPSHOT_VERSION {
+            bail!(ErrorKind::InvalidSnapshot(format!(
+                "unsupported snapshot version {}, expected {}",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+
+        decode(bytes, format)
+    }
+
+    /// Check that this snapshot is internally consistent, ie. that every light block
+    /// is valid and belongs to the chain of the snapshot, and that every light block
+    /// could have been verified from the previous one with the options of the snapshot.
+    ///
+    /// As opposed to regular verification, the light blocks are not checked against
+    /// the current time, since a snapshot may be imported long after it was taken.
+    pub fn validate(
+        &self,
+        vp: &dyn VerificationPredicates,
+        voting_power_calculator: &dyn VotingPowerCalculator,
+        commit_validator: &dyn CommitValidator,
+        hasher: &dyn Hasher,
+    ) -> Result<(), Error> {
+        if self.light_blocks.is_empty() {
+            bail!(ErrorKind::InvalidSnapshot(
+                "snapshot holds no light block".to_string()
+            ));
+        }
+
+        let mut trusted: Option<&LightBlock> = None;
+
+        for light_block in &self.light_blocks {
+            let header = &light_block.signed_header.header;
+
+            if header.chain_id != self.chain_id {
+                bail!(ErrorKind::InvalidSnapshot(format!(
+                    "light block at height {} belongs to chain {}, expected {}",
+                    header.height, header.chain_id, self.chain_id
+                )));
+            }
+
+            validate_light_block(
+                vp,
+                voting_power_calculator,
+                commit_validator,
+                hasher,
+                trusted,
+                light_block,
+                &self.options,
+            )
+            .map_err(|e| {
+                ErrorKind::InvalidSnapshot(format!(
+                    "invalid light block at height {}: {}",
+                    header.height, e
+                ))
+            })?;
+
+            trusted = Some(light_block);
+        }
+
+        Ok(())
+    }
+
+    /// Check that this snapshot is internally consistent with the production
+    /// predicates and operations, and insert its light blocks as trusted
+    /// in the given light store.
+    pub fn import(&self, light_store: &mut dyn LightStore) -> Result<(), Error> {
+        self.validate(
+            &ProdPredicates,
+            &ProdVotingPowerCalculator,
+            &ProdCommitValidator::default(),
+            &ProdHasher,
+        )?;
+
+        for light_block in &self.light_blocks {
+            light_store.insert(light_block.clone(), Status::Trusted);
+        }
+
+        Ok(())
+    }
+}
+
+fn decode<T>(bytes: &[u8], format: SnapshotFormat) -> Result<T, Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let value = match format {
+        SnapshotFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+        SnapshotFormat::Cbor => serde_cbor::from_slice(bytes).map_err(|e| e.to_string()),
+    };
+
+    value
+        .map_err(|e| ErrorKind::InvalidSnapshot(format!("failed to decode snapshot: {}", e)).into())
+}
+
+/// Validate the given light block on its own, and against the previous light block
+/// of the snapshot if any, as per `predicates::verify` minus the time-dependent checks.
+fn validate_light_block(
+    vp: &dyn VerificationPredicates,
+    voting_power_calculator: &dyn VotingPowerCalculator,
+    commit_validator: &dyn CommitValidator,
+    hasher: &dyn Hasher,
+    trusted: Option<&LightBlock>,
+    untrusted: &LightBlock,
+    options: &Options,
+) -> Result<(), VerificationError> {
+    vp.validator_sets_match(untrusted, hasher)?;
+    vp.next_validators_match(untrusted, hasher)?;
+    vp.header_matches_commit(&untrusted.signed_header, hasher)?;
+    vp.valid_commit(
+        &untrusted.signed_header,
+        &untrusted.validators,
+        commit_validator,
+    )?;
+
+    if let Some(trusted) = trusted {
+        vp.is_monotonic_bft_time(
+            &untrusted.signed_header.header,
+            &trusted.signed_header.header,
+        )?;
+
+        if untrusted.height() == trusted.height().increment() {
+            vp.valid_next_validator_set(untrusted, trusted)?;
+        } else {
+            vp.is_monotonic_height(
+                &untrusted.signed_header.header,
+                &trusted.signed_header.header,
+            )?;
+
+            vp.has_sufficient_validators_overlap(
+                &untrusted.signed_header,
+                &trusted.next_validators,
+                &options.trust_threshold,
+                voting_power_calculator,
+            )?;
+        }
+    }
+
+    vp.has_sufficient_signers_overlap(
+        &untrusted.signed_header,
+        &untrusted.validators,
+        voting_power_calculator,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use tendermint_testgen::{light_block::default_peer_id, Generator, LightChain};
+
+    use crate::store::memory::MemoryStore;
+
+    fn make_store(length: u64) -> MemoryStore {
+        let mut light_store = MemoryStore::new();
+
+        for lb in LightChain::default_with_length(length).light_blocks {
+            let lb = lb.generate().unwrap();
+            let light_block = LightBlock::new(
+                lb.signed_header,
+                lb.validators,
+                lb.next_validators,
+                default_peer_id(),
+            );
+
+            light_store.insert(light_block, Status::Trusted);
+        }
+
+        light_store
+    }
+
+    fn options() -> Options {
+        Options {
+            trust_threshold: Default::default(),
+            trusting_period: Duration::from_secs(60 * 60 * 24 * 10),
+            clock_drift: Duration::from_secs(10),
+            verification_mode: Default::default(),
+        }
+    }
+
+    #[test]
+    fn export_keeps_latest_trusted_blocks() {
+        let snapshot = Snapshot::export(&make_store(5), options(), 3).unwrap();
+
+        let heights: Vec<_> = snapshot
+            .light_blocks
+            .iter()
+            .map(|lb| lb.height().value())
+            .collect();
+        assert_eq!(heights, vec![3, 4, 5]);
+        assert_eq!(snapshot.chain_id.as_str(), "test-chain");
+
+        let err = Snapshot::export(&MemoryStore::new(), options(), 3).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::NoTrustedState(Status::Trusted));
+    }
+
+    #[test]
+    fn roundtrip_and_import() {
+        let snapshot = Snapshot::export(&make_store(5), options(), 5).unwrap();
+
+        for format in &[SnapshotFormat::Json, SnapshotFormat::Cbor] {
+            let bytes = snapshot.encode(*format).unwrap();
+            let decoded = Snapshot::decode(&bytes, *format).unwrap();
+            assert_eq!(decoded, snapshot);
+
+            let mut light_store = MemoryStore::new();
+            decoded.import(&mut light_store).unwrap();
+            assert_eq!(
+                light_store.latest(Status::Trusted),
+                snapshot.light_blocks.last().cloned()
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_version_is_rejected() {
+        let mut snapshot = Snapshot::export(&make_store(2), options(), 2).unwrap();
+        snapshot.version = SNAPSHOT_VERSION + 1;
+
+        let bytes = snapshot.encode(SnapshotFormat::Cbor).unwrap();
+        let err = Snapshot::decode(&bytes, SnapshotFormat::Cbor).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidSnapshot(_)));
+    }
+
+    #[test]
+    fn inconsistent_snapshot_is_not_imported() {
+        let snapshot = Snapshot::export(&make_store(5), options(), 5).unwrap();
+
+        // Light block of another chain
+        let mut other_chain = snapshot.clone();
+        other_chain.chain_id = "other-chain".parse().unwrap();
+
+        // Light blocks out of order
+        let mut out_of_order = snapshot.clone();
+        out_of_order.light_blocks.swap(1, 2);
+
+        // Light block whose validators do not match its header
+        let mut tampered = snapshot;
+        tampered.light_blocks[3].validators = tendermint::validator::Set::new(vec![], None);
+
+        for snapshot in &[other_chain, out_of_order, tampered] {
+            let mut light_store = MemoryStore::new();
+            let err = snapshot.import(&mut light_store).unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidSnapshot(_)));
+            assert_eq!(light_store.latest(Status::Trusted), None);
+        }
+    }
+}
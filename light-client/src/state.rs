@@ -1,7 +1,8 @@
 //! State maintained by the light client.
 
 use crate::{
-    store::LightStore,
+    errors::Error,
+    store::{LightStore, Trace, TraceEntry, TraceStore},
     types::{Height, LightBlock, Status},
 };
 
@@ -19,6 +20,9 @@ pub struct State {
 
     /// Records which blocks were needed to verify a target block, eg. during bisection.
     pub verification_trace: VerificationTrace,
+
+    /// Store in which the traces of the verified blocks are persisted, if any.
+    pub trace_store: Option<Box<dyn TraceStore>>,
 }
 
 impl State {
@@ -27,9 +31,16 @@ impl State {
         Self {
             light_store: Box::new(light_store),
             verification_trace: VerificationTrace::new(),
+            trace_store: None,
         }
     }
 
+    /// Persist the traces of the blocks verified from now on in the given trace store.
+    pub fn with_trace_store(mut self, trace_store: impl TraceStore + 'static) -> Self {
+        self.trace_store = Some(Box::new(trace_store));
+        self
+    }
+
     /// Record that the block at `height` was needed to verify the block at `target_height`.
     ///
     /// ## Preconditions
@@ -56,4 +67,67 @@ impl State {
         trace.reverse();
         trace
     }
+
+    /// Persist the verification trace of the block at `target_height`, which was verified
+    /// starting from the trusted block at `trusted_height`, in the trace store if any.
+    ///
+    /// The persisted trace holds the validator set hashes of the trusted or verified blocks
+    /// the verification relied on, including the initial trusted block. The heights which
+    /// could not be verified are left out.
+    ///
+    /// Fails if the trace cannot be written to the trace store.
+    ///
+    /// ## Preconditions
+    /// - `trusted_height` <= `target_height`
+    #[pre(trusted_height <= target_height)]
+    pub fn persist_trace(
+        &mut self,
+        target_height: Height,
+        trusted_height: Height,
+    ) -> Result<(), Error> {
+        let trace_store = match self.trace_store.as_mut() {
+            Some(trace_store) => trace_store,
+            None => return Ok(()),
+        };
+
+        let mut heights: Vec<_> = self
+            .verification_trace
+            .get(&target_height)
+            .into_iter()
+            .flatten()
+            .copied()
+            .chain(std::iter::once(trusted_height))
+            .collect();
+
+        heights.sort_unstable();
+        heights.dedup();
+
+        let light_store = &self.light_store;
+        let entries = heights
+            .into_iter()
+            .flat_map(|h| light_store.get_trusted_or_verified(h))
+            .map(|lb| TraceEntry::from(&lb))
+            .collect();
+
+        trace_store.insert(Trace {
+            target_height,
+            entries,
+        })
+    }
+
+    /// Get the persisted trace of the verification of the block at `target_height`, if any.
+    pub fn get_persisted_trace(&self, target_height: Height) -> Option<Trace> {
+        self.trace_store
+            .as_ref()
+            .and_then(|trace_store| trace_store.get(target_height))
+    }
+
+    /// Remove the persisted traces of the blocks which are no longer in the light store,
+    /// and return how many were removed.
+    pub fn prune_traces(&mut self) -> Result<usize, Error> {
+        match self.trace_store.as_mut() {
+            Some(trace_store) => trace_store.prune(self.light_store.as_ref()),
+            None => Ok(0),
+        }
+    }
 }
@@ -4,14 +4,41 @@
 //! - a transient, in-memory implementation for testing purposes
 //! - a persistent, on-disk, sled-backed implementation for production
 
+use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use std::time::Duration;
 
-use crate::types::{Height, LightBlock, Status};
+use serde::{Deserialize, Serialize};
+
+use crate::contracts::is_within_trust_period;
+use crate::errors::Error;
+use crate::types::{Hash, Height, LightBlock, Status, Time};
 use crate::utils::std_ext;
 
 pub mod memory;
 pub mod sled;
 
+/// Retention policy of a light store, which determines which light blocks
+/// are removed from the store when it is pruned.
+///
+/// See [`LightStore::prune`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Number of trusted light blocks of greatest height to keep. The verified light blocks
+    /// below the lowest of them are removed as well, as verification carries on from above.
+    /// All trusted and verified blocks are kept if `None`.
+    ///
+    /// Unverified and failed blocks are only ever removed according to `max_unverified_age`.
+    pub max_trusted_blocks: Option<usize>,
+    /// Remove the trusted and verified light blocks which fall outside of this trusting period.
+    /// Expired blocks are kept if `None`.
+    pub trusting_period: Option<Duration>,
+    /// Remove the unverified and failed light blocks which are older than this.
+    /// Unverified and failed blocks are kept if `None`.
+    pub max_unverified_age: Option<Duration>,
+}
+
 /// Store for light blocks.
 ///
 /// The light store records light blocks received from peers, and their verification status.
@@ -40,6 +67,30 @@ pub trait LightStore: Debug + Send + Sync {
     /// Get an iterator of all light blocks with the given status.
     fn all(&self, status: Status) -> Box<dyn Iterator<Item = LightBlock>>;
 
+    /// Get an iterator of the light blocks with the given status whose height
+    /// lies within the given range, in ascending order of height.
+    fn range(
+        &self,
+        heights: RangeInclusive<Height>,
+        status: Status,
+    ) -> Box<dyn DoubleEndedIterator<Item = LightBlock>>;
+
+    /// Get the light block of lowest height with the given status.
+    fn lowest(&self, status: Status) -> Option<LightBlock> {
+        self.range(all_heights(), status).next()
+    }
+
+    /// Get the light block of greatest height at or below the given height, with the given status.
+    fn highest_at_or_below(&self, height: Height, status: Status) -> Option<LightBlock> {
+        self.range(*all_heights().start()..=height, status)
+            .next_back()
+    }
+
+    /// Get the light block of lowest height at or above the given height, with the given status.
+    fn lowest_at_or_above(&self, height: Height, status: Status) -> Option<LightBlock> {
+        self.range(height..=*all_heights().end(), status).next()
+    }
+
     /// Get a block at a given height whatever its verification status as long as it hasn't failed
     /// verification (ie. its status is not `Status::Failed`).
     fn get_non_failed(&self, height: Height) -> Option<(LightBlock, Status)> {
@@ -72,4 +123,327 @@ pub trait LightStore: Debug + Send + Sync {
         self.get(height, Status::Trusted)
             .or_else(|| self.get(height, Status::Verified))
     }
+
+    /// Remove the light blocks which are not to be retained according to the given
+    /// retention policy, relative to the given time, and return how many were removed.
+    ///
+    /// The age of a light block is determined by the time of its header.
+    /// The trusted light block of greatest height is always retained, so that
+    /// the light client can carry on verifying from it.
+    fn prune(&mut self, policy: &RetentionPolicy, now: Time) -> usize {
+        let latest_trusted = self.latest(Status::Trusted).map(|lb| lb.height());
+        let mut to_remove = Vec::new();
+
+        if let Some(max_age) = policy.max_unverified_age {
+            for status in &[Status::Unverified, Status::Failed] {
+                to_remove.extend(
+                    self.all(*status)
+                        .filter(|lb| !is_within_trust_period(lb, max_age, now))
+                        .map(|lb| (lb.height(), *status)),
+                );
+            }
+        }
+
+        if let Some(trusting_period) = policy.trusting_period {
+            for status in &[Status::Trusted, Status::Verified] {
+                to_remove.extend(
+                    self.all(*status)
+                        .filter(|lb| Some(lb.height()) != latest_trusted)
+                        .filter(|lb| !is_within_trust_period(lb, trusting_period, now))
+                        .map(|lb| (lb.height(), *status)),
+                );
+            }
+        }
+
+        if let Some(max_trusted_blocks) = policy.max_trusted_blocks {
+            let mut trusted: Vec<_> = self.all(Status::Trusted).map(|lb| lb.height()).collect();
+            trusted.sort_unstable_by(|a, b| b.cmp(a));
+
+            let retained = max_trusted_blocks.max(1).min(trusted.len());
+
+            if let Some(&lowest_retained) = trusted[..retained].last() {
+                to_remove.extend(
+                    self.all(Status::Verified)
+                        .filter(|lb| lb.height() < lowest_retained)
+                        .map(|lb| (lb.height(), Status::Verified)),
+                );
+            }
+
+            to_remove.extend(
+                trusted
+                    .into_iter()
+                    .skip(retained)
+                    .map(|height| (height, Status::Trusted)),
+            );
+        }
+
+        to_remove.sort_unstable();
+        to_remove.dedup();
+
+        for (height, status) in &to_remove {
+            self.remove(*height, *status);
+        }
+
+        to_remove.len()
+    }
+}
+
+/// Light block the verification of a target block relied on, as recorded in a [`TraceStore`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// Height of the light block
+    pub height: Height,
+    /// Hash of the validator set of the light block
+    pub validators_hash: Hash,
+    /// Hash of the next validator set of the light block
+    pub next_validators_hash: Hash,
+}
+
+impl From<&LightBlock> for TraceEntry {
+    fn from(light_block: &LightBlock) -> Self {
+        let header = &light_block.signed_header.header;
+
+        Self {
+            height: header.height,
+            validators_hash: header.validators_hash,
+            next_validators_hash: header.next_validators_hash,
+        }
+    }
+}
+
+/// Record of why a target block was trusted, ie. of the light blocks its verification
+/// relied on, from the trusted state it started from up to the target block itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trace {
+    /// Height of the target block
+    pub target_height: Height,
+    /// Light blocks the verification relied on, in ascending order of height
+    pub entries: Vec<TraceEntry>,
+}
+
+/// Store for the verification traces of the light blocks verified by a light client,
+/// which outlive the in-memory `VerificationTrace` of its `State`.
+pub trait TraceStore: Debug + Send + Sync {
+    /// Get the trace of the verification of the block at the given target height, if any.
+    fn get(&self, target_height: Height) -> Option<Trace>;
+
+    /// Insert the given trace in the store.
+    /// Overrides any other trace with the same target height.
+    fn insert(&mut self, trace: Trace) -> Result<(), Error>;
+
+    /// Remove the trace of the verification of the block at the given target height, if any.
+    fn remove(&mut self, target_height: Height) -> Result<(), Error>;
+
+    /// Get an iterator of all the traces, in ascending order of target height.
+    fn all(&self) -> Box<dyn Iterator<Item = Trace>>;
+
+    /// Remove the traces of the blocks which are no longer trusted or verified
+    /// in the given light store, eg. because it was pruned, and return how many were removed.
+    fn prune(&mut self, light_store: &dyn LightStore) -> Result<usize, Error> {
+        let to_remove: Vec<_> = self
+            .all()
+            .map(|trace| trace.target_height)
+            .filter(|height| light_store.get_trusted_or_verified(*height).is_none())
+            .collect();
+
+        for height in &to_remove {
+            self.remove(*height)?;
+        }
+
+        Ok(to_remove.len())
+    }
+}
+
+/// The range of all the heights a light block can be at.
+fn all_heights() -> RangeInclusive<Height> {
+    let max = Height::try_from(i64::MAX as u64).unwrap(); // Cannot fail, see `Height::try_from`
+    Height::from(0_u32)..=max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::{
+        memory::{MemoryStore, MemoryTraceStore},
+        sled::{SledStore, SledTraceStore},
+    };
+    use tendermint_testgen::{
+        light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header, Validator,
+    };
+
+    fn make_block(height: u64) -> LightBlock {
+        let validators = [Validator::new("1").voting_power(50)];
+        let header = Header::new(&validators)
+            .chain_id("test-chain")
+            .height(height)
+            .time(height * 10);
+
+        let commit = Commit::new(header.clone(), 1);
+
+        TestgenLightBlock::new(header, commit)
+            .generate()
+            .unwrap()
+            .into()
+    }
+
+    fn populate(store: &mut dyn LightStore) {
+        for height in 1..=5 {
+            store.insert(make_block(height), Status::Trusted);
+        }
+        store.insert(make_block(6), Status::Verified);
+        store.insert(make_block(7), Status::Failed);
+        store.insert(make_block(8), Status::Unverified);
+    }
+
+    fn heights(store: &dyn LightStore, status: Status) -> Vec<u64> {
+        let mut heights: Vec<_> = store.all(status).map(|lb| lb.height().value()).collect();
+        heights.sort_unstable();
+        heights
+    }
+
+    fn at(secs: u64) -> Time {
+        Time::unix_epoch() + Duration::from_secs(secs)
+    }
+
+    fn check_prune(store: &mut dyn LightStore) {
+        populate(store);
+
+        // Nothing to do with the default policy
+        assert_eq!(store.prune(&RetentionPolicy::default(), at(100)), 0);
+
+        let policy = RetentionPolicy {
+            max_trusted_blocks: Some(4),
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(store.prune(&policy, at(100)), 1);
+        assert_eq!(heights(store, Status::Trusted), vec![2, 3, 4, 5]);
+
+        // Blocks 7 and 8 are 30 and 20 seconds old
+        let policy = RetentionPolicy {
+            max_unverified_age: Some(Duration::from_secs(25)),
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(store.prune(&policy, at(100)), 1);
+        assert!(heights(store, Status::Failed).is_empty());
+        assert_eq!(heights(store, Status::Unverified), vec![8]);
+
+        // Blocks 2 to 6 are all expired, but the latest trusted one is retained
+        let policy = RetentionPolicy {
+            trusting_period: Some(Duration::from_secs(10)),
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(store.prune(&policy, at(100)), 4);
+        assert_eq!(heights(store, Status::Trusted), vec![5]);
+        assert!(heights(store, Status::Verified).is_empty());
+        assert_eq!(heights(store, Status::Unverified), vec![8]);
+
+        // The verified blocks below the retained trusted ones are removed as well,
+        // but not the unverified ones
+        store.insert(make_block(6), Status::Verified);
+        store.insert(make_block(9), Status::Trusted);
+
+        let policy = RetentionPolicy {
+            max_trusted_blocks: Some(1),
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(store.prune(&policy, at(100)), 2);
+        assert_eq!(heights(store, Status::Trusted), vec![9]);
+        assert!(heights(store, Status::Verified).is_empty());
+        assert_eq!(heights(store, Status::Unverified), vec![8]);
+    }
+
+    fn check_range_queries(store: &mut dyn LightStore) {
+        populate(store);
+        let height = |h: u64| Height::try_from(h).unwrap();
+
+        let trusted: Vec<_> = store
+            .range(height(2)..=height(4), Status::Trusted)
+            .map(|lb| lb.height().value())
+            .collect();
+        assert_eq!(trusted, vec![2, 3, 4]);
+
+        let unverified: Vec<_> = store
+            .range(height(2)..=height(4), Status::Unverified)
+            .collect();
+        assert!(unverified.is_empty());
+
+        let lowest = store.lowest(Status::Trusted).map(|lb| lb.height());
+        assert_eq!(lowest, Some(height(1)));
+
+        let highest = store.highest_at_or_below(height(7), Status::Trusted);
+        assert_eq!(highest.map(|lb| lb.height()), Some(height(5)));
+        let highest = store.highest_at_or_below(height(3), Status::Trusted);
+        assert_eq!(highest.map(|lb| lb.height()), Some(height(3)));
+        let highest = store.highest_at_or_below(height(5), Status::Failed);
+        assert_eq!(highest, None);
+
+        let lowest = store.lowest_at_or_above(height(3), Status::Unverified);
+        assert_eq!(lowest.map(|lb| lb.height()), Some(height(8)));
+        let lowest = store.lowest_at_or_above(height(9), Status::Unverified);
+        assert_eq!(lowest, None);
+    }
+
+    fn check_prune_traces(trace_store: &mut dyn TraceStore, light_store: &mut dyn LightStore) {
+        populate(light_store);
+        let height = |h: u64| Height::try_from(h).unwrap();
+
+        for target in &[3, 6, 7, 9] {
+            trace_store
+                .insert(Trace {
+                    target_height: height(*target),
+                    entries: vec![(&make_block(1)).into(), (&make_block(*target)).into()],
+                })
+                .unwrap();
+        }
+
+        // Blocks 7 and 9 are neither trusted nor verified
+        assert_eq!(trace_store.prune(light_store).unwrap(), 2);
+
+        let targets: Vec<_> = trace_store
+            .all()
+            .map(|trace| trace.target_height.value())
+            .collect();
+        assert_eq!(targets, vec![3, 6]);
+
+        let trace = trace_store.get(height(6)).unwrap();
+        assert_eq!(trace.entries[1], TraceEntry::from(&make_block(6)));
+        assert_eq!(trace_store.get(height(7)), None);
+    }
+
+    #[test]
+    fn prune_traces_memory_store() {
+        check_prune_traces(&mut MemoryTraceStore::new(), &mut MemoryStore::new());
+    }
+
+    #[test]
+    fn prune_traces_sled_store() {
+        let db = ::sled::Config::new().temporary(true).open().unwrap();
+        check_prune_traces(
+            &mut SledTraceStore::new(db.clone()),
+            &mut SledStore::new(db),
+        );
+    }
+
+    #[test]
+    fn range_queries_memory_store() {
+        check_range_queries(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn range_queries_sled_store() {
+        let db = ::sled::Config::new().temporary(true).open().unwrap();
+        check_range_queries(&mut SledStore::new(db));
+    }
+
+    #[test]
+    fn prune_memory_store() {
+        check_prune(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn prune_sled_store() {
+        let db = ::sled::Config::new().temporary(true).open().unwrap();
+        check_prune(&mut SledStore::new(db));
+    }
 }
@@ -1,12 +1,14 @@
 //! Transient in-memory store
 
 use crate::{
-    store::{LightStore, Status},
+    errors::Error,
+    store::{LightStore, Status, Trace, TraceStore},
     types::{Height, LightBlock},
 };
 
 use std::collections::btree_map::Entry::*;
 use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
 
 /// Internal entry for the memory store
 #[derive(Clone, Debug, PartialEq)]
@@ -83,4 +85,77 @@ impl LightStore for MemoryStore {
 
         Box::new(light_blocks.into_iter())
     }
+
+    fn range(
+        &self,
+        heights: RangeInclusive<Height>,
+        status: Status,
+    ) -> Box<dyn DoubleEndedIterator<Item = LightBlock>> {
+        let light_blocks: Vec<_> = self
+            .store
+            .range(heights)
+            .filter(|(_, e)| e.status == status)
+            .map(|(_, e)| e.light_block.clone())
+            .collect();
+
+        Box::new(light_blocks.into_iter())
+    }
+
+    fn lowest(&self, status: Status) -> Option<LightBlock> {
+        self.store
+            .values()
+            .find(|e| e.status == status)
+            .map(|e| e.light_block.clone())
+    }
+
+    fn highest_at_or_below(&self, height: Height, status: Status) -> Option<LightBlock> {
+        self.store
+            .range(..=height)
+            .rev()
+            .find(|(_, e)| e.status == status)
+            .map(|(_, e)| e.light_block.clone())
+    }
+
+    fn lowest_at_or_above(&self, height: Height, status: Status) -> Option<LightBlock> {
+        self.store
+            .range(height..)
+            .find(|(_, e)| e.status == status)
+            .map(|(_, e)| e.light_block.clone())
+    }
+}
+
+/// Transient in-memory store for verification traces.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTraceStore {
+    traces: BTreeMap<Height, Trace>,
+}
+
+impl MemoryTraceStore {
+    /// Create a new, empty, in-memory trace store
+    pub fn new() -> Self {
+        Self {
+            traces: BTreeMap::new(),
+        }
+    }
+}
+
+impl TraceStore for MemoryTraceStore {
+    fn get(&self, target_height: Height) -> Option<Trace> {
+        self.traces.get(&target_height).cloned()
+    }
+
+    fn insert(&mut self, trace: Trace) -> Result<(), Error> {
+        self.traces.insert(trace.target_height, trace);
+        Ok(())
+    }
+
+    fn remove(&mut self, target_height: Height) -> Result<(), Error> {
+        self.traces.remove(&target_height);
+        Ok(())
+    }
+
+    fn all(&self) -> Box<dyn Iterator<Item = Trace>> {
+        let traces: Vec<_> = self.traces.values().cloned().collect();
+        Box::new(traces.into_iter())
+    }
 }
@@ -3,17 +3,20 @@
 pub mod utils;
 
 use crate::{
+    errors::Error,
     store::sled::utils::*,
     types::{Height, LightBlock},
 };
 
-use super::{LightStore, Status};
+use super::{LightStore, Status, Trace, TraceStore};
 use ::sled::Db as SledDb;
+use std::ops::RangeInclusive;
 
 const UNVERIFIED_PREFIX: &str = "light_store/unverified";
 const VERIFIED_PREFIX: &str = "light_store/verified";
 const TRUSTED_PREFIX: &str = "light_store/trusted";
 const FAILED_PREFIX: &str = "light_store/failed";
+const TRACE_PREFIX: &str = "trace_store/traces";
 
 /// Persistent store backed by an on-disk `sled` database.
 #[derive(Debug, Clone)]
@@ -75,12 +78,60 @@ impl LightStore for SledStore {
     }
 
     fn latest(&self, status: Status) -> Option<LightBlock> {
-        self.db(status)
-            .iter(&self.db)
-            .max_by(|first, second| first.height().cmp(&second.height()))
+        self.db(status).iter(&self.db).next_back()
     }
 
     fn all(&self, status: Status) -> Box<dyn Iterator<Item = LightBlock>> {
         Box::new(self.db(status).iter(&self.db))
     }
+
+    fn range(
+        &self,
+        heights: RangeInclusive<Height>,
+        status: Status,
+    ) -> Box<dyn DoubleEndedIterator<Item = LightBlock>> {
+        Box::new(
+            self.db(status)
+                .range(&self.db, heights)
+                .into_iter()
+                .flatten(),
+        )
+    }
+}
+
+/// Persistent store for verification traces backed by an on-disk `sled` database,
+/// typically the same one as the `SledStore` holding the traced light blocks.
+#[derive(Debug, Clone)]
+pub struct SledTraceStore {
+    db: SledDb,
+    traces_db: KeyValueDb<Height, Trace>,
+}
+
+impl SledTraceStore {
+    /// Create a new persistent trace store from a sled database
+    pub fn new(db: SledDb) -> Self {
+        Self {
+            db,
+            traces_db: KeyValueDb::new(TRACE_PREFIX),
+        }
+    }
+}
+
+impl TraceStore for SledTraceStore {
+    fn get(&self, target_height: Height) -> Option<Trace> {
+        self.traces_db.get(&self.db, &target_height).ok().flatten()
+    }
+
+    fn insert(&mut self, trace: Trace) -> Result<(), Error> {
+        self.traces_db
+            .insert(&self.db, &trace.target_height, &trace)
+    }
+
+    fn remove(&mut self, target_height: Height) -> Result<(), Error> {
+        self.traces_db.remove(&self.db, &target_height)
+    }
+
+    fn all(&self) -> Box<dyn Iterator<Item = Trace>> {
+        Box::new(self.traces_db.iter(&self.db))
+    }
 }
@@ -4,6 +4,7 @@
 
 use serde::{de::DeserializeOwned, Serialize};
 use std::marker::PhantomData;
+use std::ops::RangeInclusive;
 
 use crate::errors::{Error, ErrorKind};
 
@@ -125,40 +126,88 @@ where
         Ok(())
     }
 
-    /// Iterate over all values within this view in the given sled database.
+    /// Iterate over all values within this view in the given sled database,
+    /// in the order of their encoded keys.
     pub fn iter(&self, db: &sled::Db) -> impl DoubleEndedIterator<Item = V> {
-        db.iter()
+        db.scan_prefix(&self.prefix)
             .flatten()
             .map(|(_, v)| serde_cbor::from_slice(&v))
             .flatten()
     }
+
+    /// Iterate over the values within this view whose key lies within the given range,
+    /// in the order of their encoded keys.
+    ///
+    /// This only yields the expected values if the encoding of the keys preserves
+    /// their ordering, as is the case for `Height`s, which are encoded as strings
+    /// of decimal digits, prefixed by their length.
+    pub fn range(
+        &self,
+        db: &sled::Db,
+        range: RangeInclusive<K>,
+    ) -> Result<impl DoubleEndedIterator<Item = V>, Error> {
+        let (start, end) = range.into_inner();
+        let start_bytes = serde_cbor::to_vec(&start).map_err(|e| ErrorKind::Store.context(e))?;
+        let end_bytes = serde_cbor::to_vec(&end).map_err(|e| ErrorKind::Store.context(e))?;
+
+        let values = db
+            .range(self.prefixed_key(start_bytes)..=self.prefixed_key(end_bytes))
+            .flatten()
+            .flat_map(|(_, v)| serde_cbor::from_slice(&v));
+
+        Ok(values)
+    }
 }
 
-// TODO: The test below is currently disabled because it fails on CI as we don't have
-// access to `/tmp`. Need to figure out how to specify a proper temp dir.
-
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::types::Height;
-
-//     #[test]
-//     fn iter_next_back_returns_highest_height() {
-//         const DB_PATH: &str = "/tmp/tendermint_light_client_sled_test/";
-//         std::fs::remove_dir_all(DB_PATH).unwrap();
-//         let db = sled::open(DB_PATH).unwrap();
-//         let kv: KeyValueDb<Height, Height> = key_value("light_store/verified");
-
-//         kv.insert(&db, &1, &1).unwrap();
-//         kv.insert(&db, &589473798493, &589473798493).unwrap();
-//         kv.insert(&db, &12342425, &12342425).unwrap();
-//         kv.insert(&db, &4, &4).unwrap();
-
-//         let mut iter = kv.iter(&db);
-//         assert_eq!(iter.next_back(), Some(589473798493));
-//         assert_eq!(iter.next_back(), Some(12342425));
-//         assert_eq!(iter.next_back(), Some(4));
-//         assert_eq!(iter.next_back(), Some(1));
-//         assert_eq!(iter.next_back(), None);
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Height;
+    use std::convert::TryFrom;
+
+    fn height(h: u64) -> Height {
+        Height::try_from(h).unwrap()
+    }
+
+    fn make_db() -> (sled::Db, KeyValueDb<Height, Height>) {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let kv = key_value("light_store/verified");
+
+        for h in &[1, 589473798493, 12342425, 4, 10] {
+            kv.insert(&db, &height(*h), &height(*h)).unwrap();
+        }
+
+        // Values under another prefix must not be yielded
+        let other: KeyValueDb<Height, Height> = key_value("light_store/unverified");
+        other.insert(&db, &height(5), &height(5)).unwrap();
+
+        (db, kv)
+    }
+
+    #[test]
+    fn iter_next_back_returns_highest_height() {
+        let (db, kv) = make_db();
+
+        let mut iter = kv.iter(&db);
+        assert_eq!(iter.next_back(), Some(height(589473798493)));
+        assert_eq!(iter.next_back(), Some(height(12342425)));
+        assert_eq!(iter.next_back(), Some(height(10)));
+        assert_eq!(iter.next_back(), Some(height(4)));
+        assert_eq!(iter.next_back(), Some(height(1)));
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn range_is_ordered_by_height() {
+        let (db, kv) = make_db();
+
+        let values: Vec<_> = kv
+            .range(&db, height(2)..=height(12342425))
+            .unwrap()
+            .collect();
+        assert_eq!(values, vec![height(4), height(10), height(12342425)]);
+
+        let values: Vec<_> = kv.range(&db, height(5)..=height(9)).unwrap().collect();
+        assert!(values.is_empty());
+    }
+}
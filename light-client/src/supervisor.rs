@@ -1,18 +1,40 @@
 //! Supervisor and Handle implementation.
 
+use async_trait::async_trait;
 use crossbeam_channel as channel;
+use futures::{
+    channel::{mpsc, oneshot},
+    future, FutureExt, StreamExt,
+};
+use std::time::Duration;
 
-use tendermint::evidence::{ConflictingHeadersEvidence, Evidence};
+use tendermint::evidence::Evidence;
+use tendermint::net;
 
 use crate::bail;
 use crate::errors::{Error, ErrorKind};
-use crate::evidence::EvidenceReporter;
-use crate::fork_detector::{Fork, ForkDetection, ForkDetector};
-use crate::light_client::LightClient;
-use crate::peer_list::PeerList;
+use crate::evidence::{AsyncEvidenceReporter, EvidenceReporter};
+use crate::fork_detector::{AsyncForkDetector, ForkDetection, ForkDetector};
+use crate::light_client::{AsyncLightClient, LightClient};
+use crate::metrics::Metrics;
+use crate::peer_list::{PeerList, PeerStatus};
 use crate::state::State;
+use crate::store::{RetentionPolicy, Trace};
 use crate::types::{Height, LatestStatus, LightBlock, PeerId, Status};
 
+mod fork_report;
+use fork_report::ForkReports;
+pub use fork_report::{ForkReport, MAX_FORK_REPORTS};
+
+mod subscription;
+pub use subscription::{AsyncSubscription, Event, Subscription, SUBSCRIPTION_CAPACITY};
+
+mod processing;
+use processing::{witnesses, Action};
+
+mod supervision;
+use supervision::{Core, Input, PeerInstance, Step, Supervision};
+
 /// Provides an interface to the supervisor for use in downstream code.
 pub trait Handle: Send + Sync {
     /// Get latest trusted block.
@@ -21,12 +43,74 @@ pub trait Handle: Send + Sync {
     /// Get the latest status.
     fn latest_status(&self) -> Result<LatestStatus, Error>;
 
+    /// Get the persisted trace of the verification of the block at the given height, if any.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn verification_trace(&self, _height: Height) -> Result<Option<Trace>, Error> {
+        bail!(ErrorKind::Unsupported("verification_trace".to_string()))
+    }
+
+    /// Get the block at the given height, if it was verified and is trusted by the primary.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn trusted_block(&self, _height: Height) -> Result<Option<LightBlock>, Error> {
+        bail!(ErrorKind::Unsupported("trusted_block".to_string()))
+    }
+
+    /// Get the status of the primary, the witnesses, and the other peers.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn witnesses(&self) -> Result<Vec<PeerStatus>, Error> {
+        bail!(ErrorKind::Unsupported("witnesses".to_string()))
+    }
+
+    /// Get the reports of the latest forks detected, from the oldest to the latest.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn fork_reports(&self) -> Result<Vec<ForkReport>, Error> {
+        bail!(ErrorKind::Unsupported("fork_reports".to_string()))
+    }
+
     /// Verify to the highest block.
     fn verify_to_highest(&self) -> Result<LightBlock, Error>;
 
     /// Verify to the block at the given height.
     fn verify_to_target(&self, _height: Height) -> Result<LightBlock, Error>;
 
+    /// Subscribe to the events emitted by the underlying [`Supervisor`] from now on.
+    ///
+    /// The default implementation returns a subscription which has already ended.
+    fn subscribe(&self) -> Result<Subscription, Error> {
+        Ok(Subscription::ended())
+    }
+
+    /// Add the given instance as a witness, whose RPC endpoint is at the given address.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn add_witness(
+        &self,
+        _peer_id: PeerId,
+        _address: net::Address,
+        _instance: Instance,
+    ) -> Result<(), Error> {
+        bail!(ErrorKind::Unsupported("add_witness".to_string()))
+    }
+
+    /// Remove the given witness.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn remove_witness(&self, _peer_id: PeerId) -> Result<(), Error> {
+        bail!(ErrorKind::Unsupported("remove_witness".to_string()))
+    }
+
+    /// Promote the given witness or full node to primary.
+    /// The current primary becomes a witness.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    fn promote_to_primary(&self, _peer_id: PeerId) -> Result<(), Error> {
+        bail!(ErrorKind::Unsupported("promote_to_primary".to_string()))
+    }
+
     /// Terminate the underlying [`Supervisor`].
     fn terminate(&self) -> Result<(), Error>;
 }
@@ -49,6 +133,35 @@ enum HandleInput {
 
     /// Get the current status of the LightClient
     GetStatus(channel::Sender<LatestStatus>),
+
+    /// Get the persisted trace of the verification of the block at the given height
+    GetVerificationTrace(Height, channel::Sender<Option<Trace>>),
+
+    /// Get the trusted block at the given height
+    GetTrustedBlock(Height, channel::Sender<Option<LightBlock>>),
+
+    /// Get the status of the peers
+    GetWitnesses(channel::Sender<Vec<PeerStatus>>),
+
+    /// Get the reports of the latest forks detected
+    GetForkReports(channel::Sender<Vec<ForkReport>>),
+
+    /// Send the subsequent events to the given channel
+    Subscribe(channel::Sender<Event>),
+
+    /// Add the given instance as a witness
+    AddWitness(
+        PeerId,
+        net::Address,
+        Box<Instance>,
+        channel::Sender<Result<(), Error>>,
+    ),
+
+    /// Remove the given witness
+    RemoveWitness(PeerId, channel::Sender<Result<(), Error>>),
+
+    /// Promote the given witness or full node to primary
+    PromoteToPrimary(PeerId, channel::Sender<Result<(), Error>>),
 }
 
 /// A light client `Instance` packages a `LightClient` together with its `State`.
@@ -79,6 +192,25 @@ impl Instance {
     pub fn trust_block(&mut self, lb: &LightBlock) {
         self.state.light_store.update(lb, Status::Trusted);
     }
+
+    /// Prune the light store of this instance according to the given retention policy.
+    pub fn prune(&mut self, policy: &RetentionPolicy) -> Result<usize, Error> {
+        self.light_client.prune(&mut self.state, policy)
+    }
+}
+
+impl PeerInstance for Instance {
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn prune(&mut self, policy: &RetentionPolicy) -> Result<usize, Error> {
+        Instance::prune(self, policy)
+    }
 }
 
 /// The supervisor manages multiple light client instances, of which one
@@ -90,6 +222,10 @@ impl Instance {
 /// is promoted to primary. If a witness is deemed faulty, then the witness is
 /// removed.
 ///
+/// Peers which merely timed out or could not be reached are put on probation
+/// rather than removed for good, and are re-admitted as witnesses once they pass
+/// a health check, which is performed before verification when their probation is over.
+///
 /// The supervisor is intended to be ran in its own thread, and queried
 /// via a `Handle`.
 ///
@@ -118,8 +254,8 @@ impl Instance {
 /// }
 /// ```
 pub struct Supervisor {
-    /// List of peers and their instances (primary, witnesses, full and faulty nodes)
-    peers: PeerList<Instance>,
+    /// List of peers, retention policy, metrics and fork reports
+    core: Core<Instance>,
     /// An instance of the fork detector
     fork_detector: Box<dyn ForkDetector>,
     /// Reporter of fork evidence
@@ -128,12 +264,14 @@ pub struct Supervisor {
     sender: channel::Sender<HandleInput>,
     /// Channel through which to receive events from the `Handle`s
     receiver: channel::Receiver<HandleInput>,
+    /// Channels through which to emit events to the subscribers
+    subscribers: Vec<channel::Sender<Event>>,
 }
 
 impl std::fmt::Debug for Supervisor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Supervisor")
-            .field("peers", &self.peers)
+            .field("peers", &self.core.peers)
             .finish()
     }
 }
@@ -151,22 +289,50 @@ impl Supervisor {
         let (sender, receiver) = channel::unbounded::<HandleInput>();
 
         Self {
-            peers,
+            core: Core::new(peers),
             sender,
             receiver,
             fork_detector: Box::new(fork_detector),
             evidence_reporter: Box::new(evidence_reporter),
+            subscribers: Vec::new(),
         }
     }
 
+    /// Periodically prune the light stores of all the instances according to the given
+    /// retention policy, every `interval`, while the supervisor is running.
+    pub fn with_retention_policy(mut self, policy: RetentionPolicy, interval: Duration) -> Self {
+        self.core.retention = Some((policy, interval));
+        self
+    }
+
+    /// Record the outcome of fork detection and the replacements of peers
+    /// in the given metrics.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.core.set_metrics(metrics);
+        self
+    }
+
     /// Create a new handle to this supervisor.
     pub fn handle(&self) -> SupervisorHandle {
         SupervisorHandle::new(self.sender.clone())
     }
 
+    /// Subscribe to the events emitted by this supervisor from now on.
+    pub fn subscribe(&mut self) -> Subscription {
+        let (sender, subscription) = Subscription::channel();
+        self.subscribers.push(sender);
+        subscription
+    }
+
+    /// Emit the given event to all subscribers, and forget about the ones which are gone
+    /// or which fell too far behind (see `SUBSCRIPTION_CAPACITY`).
+    fn emit(&mut self, event: Event) {
+        subscription::emit(&mut self.subscribers, &event);
+    }
+
     /// Get the latest trusted state of the primary peer, if any
     pub fn latest_trusted(&self) -> Option<LightBlock> {
-        self.peers.primary().latest_trusted()
+        self.core.latest_trusted()
     }
 
     /// Verify to the highest block.
@@ -174,23 +340,69 @@ impl Supervisor {
         self.verify(None)
     }
 
+    /// Add the given instance as a witness, whose RPC endpoint is at the given address.
+    ///
+    /// If the instance does not have any trusted block yet, it is initialized
+    /// with the latest trusted block of the primary.
+    ///
+    /// ## Errors
+    /// - If the peer is already known, returns `ErrorKind::PeerAlreadyExists`.
+    /// - If the peer was found to be faulty, returns `ErrorKind::PeerFaulty`.
+    pub fn add_witness(
+        &mut self,
+        peer_id: PeerId,
+        address: net::Address,
+        instance: Instance,
+    ) -> Result<(), Error> {
+        self.core.add_witness(peer_id, instance)?;
+        self.evidence_reporter.add_peer(peer_id, address);
+
+        Ok(())
+    }
+
+    /// Remove the given witness.
+    ///
+    /// ## Errors
+    /// - If the peer is not a witness, returns `ErrorKind::PeerNotFound`.
+    pub fn remove_witness(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        self.core.remove_witness(peer_id)
+    }
+
+    /// Promote the given witness or full node to primary. The current primary becomes a witness.
+    ///
+    /// The latest block trusted by the current primary is trusted by the new primary as well,
+    /// so that verification carries on from there.
+    ///
+    /// ## Errors
+    /// - If the peer is neither a witness nor a full node, returns `ErrorKind::PeerNotFound`.
+    pub fn promote_to_primary(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        self.core.promote_to_primary(peer_id)
+    }
+
     /// Return latest trusted status summary.
-    fn latest_status(&mut self) -> LatestStatus {
-        let latest_trusted = self.peers.primary().latest_trusted();
-        let mut connected_nodes: Vec<PeerId> = Vec::new();
-        connected_nodes.push(self.peers.primary_id());
-        connected_nodes.append(&mut self.peers.witnesses_ids().iter().copied().collect());
-
-        match latest_trusted {
-            Some(trusted) => LatestStatus::new(
-                Some(trusted.signed_header.header.height.value()),
-                Some(trusted.signed_header.header.hash()),
-                Some(trusted.next_validators.hash()),
-                connected_nodes,
-            ),
-            // only return connected nodes to see what is going on:
-            None => LatestStatus::new(None, None, None, connected_nodes),
-        }
+    fn latest_status(&self) -> LatestStatus {
+        self.core.latest_status()
+    }
+
+    /// Get the persisted trace of the verification of the block at the given height
+    /// by the primary, if any.
+    pub fn verification_trace(&self, height: Height) -> Option<Trace> {
+        self.core.verification_trace(height)
+    }
+
+    /// Get the block at the given height, if it is trusted by the primary.
+    pub fn trusted_block(&self, height: Height) -> Option<LightBlock> {
+        self.core.trusted_block(height)
+    }
+
+    /// Get the status of the primary, the witnesses, and the other peers.
+    pub fn witnesses(&self) -> Vec<PeerStatus> {
+        self.core.witnesses()
+    }
+
+    /// Get the reports of the latest forks detected, from the oldest to the latest.
+    pub fn fork_reports(&self) -> Vec<ForkReport> {
+        self.core.fork_reports()
     }
 
     /// Verify to the block at the given height.
@@ -200,106 +412,69 @@ impl Supervisor {
 
     /// Verify either to the latest block (if `height == None`) or to a given block (if `height ==
     /// Some(height)`).
+    ///
+    /// The peers whose probation is over are health-checked first. The ones which respond
+    /// are re-admitted as witnesses, while the probation of the others is extended.
     fn verify(&mut self, height: Option<Height>) -> Result<LightBlock, Error> {
-        let primary = self.peers.primary_mut();
-
-        // Perform light client core verification for the given height (or highest).
-        let verdict = match height {
-            None => primary.light_client.verify_to_highest(&mut primary.state),
-            Some(height) => primary
-                .light_client
-                .verify_to_target(height, &mut primary.state),
-        };
+        let mut supervision = Supervision::new(height);
+        let mut input = Input::Start;
 
-        match verdict {
-            // Verification succeeded, let's perform fork detection
-            Ok(verified_block) => {
-                let trusted_block = primary
-                    .latest_trusted()
-                    .ok_or(ErrorKind::NoTrustedState(Status::Trusted))?;
-
-                // Perform fork detection with the highest verified block and the trusted block.
-                let outcome = self.detect_forks(&verified_block, &trusted_block)?;
-
-                match outcome {
-                    // There was a fork or a faulty peer
-                    ForkDetection::Detected(forks) => {
-                        let forked = self.process_forks(forks)?;
-                        if !forked.is_empty() {
-                            // Fork detected, exiting
-                            bail!(ErrorKind::ForkDetected(forked))
-                        }
+        loop {
+            input = match self.core.step(&mut supervision, input) {
+                Step::HealthCheck(peer_id) => Input::HealthChecked(self.health_check(peer_id)),
+                Step::Verify(height) => {
+                    let primary = self.core.peers.primary_mut();
 
-                        // If there were no hard forks, perform verification again
-                        self.verify(height)
-                    }
-                    ForkDetection::NotDetected => {
-                        // We need to re-ask for the primary here as the compiler
-                        // is not smart enough to realize that we do not mutate
-                        // the `primary` field of `PeerList` between the initial
-                        // borrow of the primary and here (can't blame it, it's
-                        // not that obvious).
-                        self.peers.primary_mut().trust_block(&verified_block);
-
-                        // No fork detected, exiting
-                        Ok(verified_block)
-                    }
+                    // Perform light client core verification for the given height (or highest).
+                    let verdict = match height {
+                        None => primary.light_client.verify_to_highest(&mut primary.state),
+                        Some(height) => primary
+                            .light_client
+                            .verify_to_target(height, &mut primary.state),
+                    };
+
+                    Input::Verified(Box::new(verdict))
                 }
-            }
-            // Verification failed
-            Err(err) => {
-                // Swap primary, and continue with new primary, if there is any witness left.
-                self.peers.replace_faulty_primary(Some(err))?;
-                self.verify(height)
-            }
+                Step::DetectForks { verified, trusted } => {
+                    Input::ForksDetected(self.detect_forks(&verified, &trusted))
+                }
+                Step::Perform(actions) => Input::Performed(self.perform(actions)),
+                Step::Done(outcome) => return *outcome,
+            };
         }
     }
 
-    fn process_forks(&mut self, forks: Vec<Fork>) -> Result<Vec<PeerId>, Error> {
-        let mut forked = Vec::with_capacity(forks.len());
-
-        for fork in forks {
-            match fork {
-                // An actual fork was detected, report evidence and record forked peer.
-                // TODO: also report to primary
-                Fork::Forked { primary, witness } => {
-                    let provider = witness.provider;
-                    self.report_evidence(provider, &primary, &witness)?;
+    /// Perform a health check of the given peer.
+    fn health_check(&self, peer_id: PeerId) -> Result<(), Error> {
+        match self.core.peers.get(&peer_id) {
+            Some(instance) => instance.light_client.health_check(),
+            None => Err(ErrorKind::PeerNotFound(peer_id).into()),
+        }
+    }
 
-                    forked.push(provider);
-                }
-                // A witness has timed out, remove it from the peer list.
-                Fork::Timeout(provider, _error) => {
-                    self.peers.replace_faulty_witness(provider);
-                    // TODO: Log/record the error
-                }
-                // A witness has been deemed faulty, remove it from the peer list.
-                Fork::Faulty(block, _error) => {
-                    self.peers.replace_faulty_witness(block.provider);
-                    // TODO: Log/record the error
-                }
+    /// Perform the given actions, in this order.
+    fn perform(&mut self, actions: Vec<Action>) -> Result<(), Error> {
+        for action in actions {
+            match action {
+                Action::Emit(event) => self.emit(event),
+                Action::Report(provider, evidence) => self.report_evidence(provider, evidence)?,
             }
         }
 
-        Ok(forked)
+        Ok(())
     }
 
-    /// Report the given evidence of a fork.
-    fn report_evidence(
-        &mut self,
-        provider: PeerId,
-        primary: &LightBlock,
-        witness: &LightBlock,
-    ) -> Result<(), Error> {
-        let evidence = ConflictingHeadersEvidence::new(
-            primary.signed_header.clone(),
-            witness.signed_header.clone(),
-        );
-
+    /// Report the given evidence of a light client attack to the given peer.
+    fn report_evidence(&mut self, provider: PeerId, evidence: Box<Evidence>) -> Result<(), Error> {
         self.evidence_reporter
-            .report(Evidence::ConflictingHeaders(Box::new(evidence)), provider)
+            .report(evidence.as_ref().clone(), provider)
             .map_err(ErrorKind::Io)?;
 
+        self.emit(Event::EvidenceReported {
+            peer: provider,
+            evidence,
+        });
+
         Ok(())
     }
 
@@ -309,27 +484,39 @@ impl Supervisor {
         verified_block: &LightBlock,
         trusted_block: &LightBlock,
     ) -> Result<ForkDetection, Error> {
-        if self.peers.witnesses_ids().is_empty() {
-            bail!(ErrorKind::NoWitnesses);
-        }
-
-        let witnesses = self
-            .peers
-            .witnesses_ids()
-            .iter()
-            .filter_map(|id| self.peers.get(id))
-            .collect();
+        let witnesses = witnesses(&self.core.peers)?;
+        let primary_trace = self.core.primary_trace(verified_block.height());
 
         self.fork_detector
-            .detect_forks(verified_block, &trusted_block, witnesses)
+            .detect_forks(verified_block, trusted_block, primary_trace, witnesses)
+    }
+
+    /// Prune the light stores of all the instances according to the retention policy
+    /// of the supervisor, if any, and return the number of light blocks which were removed.
+    pub fn prune(&mut self) -> Result<usize, Error> {
+        self.core.prune()
     }
 
     /// Run the supervisor event loop in the same thread.
     ///
+    /// If a retention policy was set, the light stores are pruned periodically
+    /// in between the events.
+    ///
     /// This method should typically be called within a new thread with `std::thread::spawn`.
     pub fn run(mut self) -> Result<(), Error> {
+        let pruning = match self.core.retention {
+            Some((_, interval)) => channel::tick(interval),
+            None => channel::never(),
+        };
+
         loop {
-            let event = self.receiver.recv().map_err(ErrorKind::from)?;
+            let event = channel::select! {
+                recv(self.receiver) -> event => event.map_err(ErrorKind::from)?,
+                recv(pruning) -> _ => {
+                    self.prune()?;
+                    continue;
+                }
+            };
 
             match event {
                 HandleInput::LatestTrusted(sender) => {
@@ -352,6 +539,37 @@ impl Supervisor {
                     let outcome = self.latest_status();
                     sender.send(outcome).map_err(ErrorKind::from)?;
                 }
+                HandleInput::GetVerificationTrace(height, sender) => {
+                    let outcome = self.verification_trace(height);
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
+                HandleInput::GetTrustedBlock(height, sender) => {
+                    let outcome = self.trusted_block(height);
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
+                HandleInput::GetWitnesses(sender) => {
+                    let outcome = self.witnesses();
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
+                HandleInput::GetForkReports(sender) => {
+                    let outcome = self.fork_reports();
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
+                HandleInput::Subscribe(sender) => {
+                    self.subscribers.push(sender);
+                }
+                HandleInput::AddWitness(peer_id, address, instance, sender) => {
+                    let outcome = self.add_witness(peer_id, address, *instance);
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
+                HandleInput::RemoveWitness(peer_id, sender) => {
+                    let outcome = self.remove_witness(peer_id);
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
+                HandleInput::PromoteToPrimary(peer_id, sender) => {
+                    let outcome = self.promote_to_primary(peer_id);
+                    sender.send(outcome).map_err(ErrorKind::from)?;
+                }
             }
         }
     }
@@ -382,6 +600,18 @@ impl SupervisorHandle {
 
         receiver.recv().map_err(ErrorKind::from)?
     }
+
+    fn update_peers(
+        &self,
+        make_event: impl FnOnce(channel::Sender<Result<(), Error>>) -> HandleInput,
+    ) -> Result<(), Error> {
+        let (sender, receiver) = channel::bounded::<Result<(), Error>>(1);
+
+        let event = make_event(sender);
+        self.sender.send(event).map_err(ErrorKind::from)?;
+
+        receiver.recv().map_err(ErrorKind::from)?
+    }
 }
 
 impl Handle for SupervisorHandle {
@@ -403,6 +633,38 @@ impl Handle for SupervisorHandle {
         Ok(receiver.recv().map_err(ErrorKind::from)?)
     }
 
+    fn verification_trace(&self, height: Height) -> Result<Option<Trace>, Error> {
+        let (sender, receiver) = channel::bounded::<Option<Trace>>(1);
+        self.sender
+            .send(HandleInput::GetVerificationTrace(height, sender))
+            .map_err(ErrorKind::from)?;
+        Ok(receiver.recv().map_err(ErrorKind::from)?)
+    }
+
+    fn trusted_block(&self, height: Height) -> Result<Option<LightBlock>, Error> {
+        let (sender, receiver) = channel::bounded::<Option<LightBlock>>(1);
+        self.sender
+            .send(HandleInput::GetTrustedBlock(height, sender))
+            .map_err(ErrorKind::from)?;
+        Ok(receiver.recv().map_err(ErrorKind::from)?)
+    }
+
+    fn witnesses(&self) -> Result<Vec<PeerStatus>, Error> {
+        let (sender, receiver) = channel::bounded::<Vec<PeerStatus>>(1);
+        self.sender
+            .send(HandleInput::GetWitnesses(sender))
+            .map_err(ErrorKind::from)?;
+        Ok(receiver.recv().map_err(ErrorKind::from)?)
+    }
+
+    fn fork_reports(&self) -> Result<Vec<ForkReport>, Error> {
+        let (sender, receiver) = channel::bounded::<Vec<ForkReport>>(1);
+        self.sender
+            .send(HandleInput::GetForkReports(sender))
+            .map_err(ErrorKind::from)?;
+        Ok(receiver.recv().map_err(ErrorKind::from)?)
+    }
+
     fn verify_to_highest(&self) -> Result<LightBlock, Error> {
         self.verify(HandleInput::VerifyToHighest)
     }
@@ -411,6 +673,35 @@ impl Handle for SupervisorHandle {
         self.verify(|sender| HandleInput::VerifyToTarget(height, sender))
     }
 
+    fn subscribe(&self) -> Result<Subscription, Error> {
+        let (sender, subscription) = Subscription::channel();
+
+        self.sender
+            .send(HandleInput::Subscribe(sender))
+            .map_err(ErrorKind::from)?;
+
+        Ok(subscription)
+    }
+
+    fn add_witness(
+        &self,
+        peer_id: PeerId,
+        address: net::Address,
+        instance: Instance,
+    ) -> Result<(), Error> {
+        self.update_peers(|sender| {
+            HandleInput::AddWitness(peer_id, address, Box::new(instance), sender)
+        })
+    }
+
+    fn remove_witness(&self, peer_id: PeerId) -> Result<(), Error> {
+        self.update_peers(|sender| HandleInput::RemoveWitness(peer_id, sender))
+    }
+
+    fn promote_to_primary(&self, peer_id: PeerId) -> Result<(), Error> {
+        self.update_peers(|sender| HandleInput::PromoteToPrimary(peer_id, sender))
+    }
+
     fn terminate(&self) -> Result<(), Error> {
         let (sender, receiver) = channel::bounded::<()>(1);
 
@@ -421,3 +712,661 @@ impl Handle for SupervisorHandle {
         Ok(receiver.recv().map_err(ErrorKind::from)?)
     }
 }
+
+/// Provides an asynchronous interface to the [`AsyncSupervisor`] for use in downstream code.
+///
+/// This is the asynchronous counterpart of [`Handle`]: none of its methods
+/// block the calling task while waiting for the supervisor to respond.
+#[async_trait]
+pub trait AsyncHandle: Send + Sync {
+    /// Get latest trusted block.
+    async fn latest_trusted(&self) -> Result<Option<LightBlock>, Error>;
+
+    /// Get the latest status.
+    async fn latest_status(&self) -> Result<LatestStatus, Error>;
+
+    /// Get the persisted trace of the verification of the block at the given height, if any.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn verification_trace(&self, _height: Height) -> Result<Option<Trace>, Error> {
+        bail!(ErrorKind::Unsupported("verification_trace".to_string()))
+    }
+
+    /// Get the block at the given height, if it was verified and is trusted by the primary.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn trusted_block(&self, _height: Height) -> Result<Option<LightBlock>, Error> {
+        bail!(ErrorKind::Unsupported("trusted_block".to_string()))
+    }
+
+    /// Get the status of the primary, the witnesses, and the other peers.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn witnesses(&self) -> Result<Vec<PeerStatus>, Error> {
+        bail!(ErrorKind::Unsupported("witnesses".to_string()))
+    }
+
+    /// Get the reports of the latest forks detected, from the oldest to the latest.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn fork_reports(&self) -> Result<Vec<ForkReport>, Error> {
+        bail!(ErrorKind::Unsupported("fork_reports".to_string()))
+    }
+
+    /// Verify to the highest block.
+    async fn verify_to_highest(&self) -> Result<LightBlock, Error>;
+
+    /// Verify to the block at the given height.
+    async fn verify_to_target(&self, _height: Height) -> Result<LightBlock, Error>;
+
+    /// Subscribe to the events emitted by the underlying [`AsyncSupervisor`] from now on.
+    ///
+    /// The default implementation returns a subscription which has already ended.
+    async fn subscribe(&self) -> Result<AsyncSubscription, Error> {
+        Ok(AsyncSubscription::ended())
+    }
+
+    /// Add the given instance as a witness, whose RPC endpoint is at the given address.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn add_witness(
+        &self,
+        _peer_id: PeerId,
+        _address: net::Address,
+        _instance: AsyncInstance,
+    ) -> Result<(), Error> {
+        bail!(ErrorKind::Unsupported("add_witness".to_string()))
+    }
+
+    /// Remove the given witness.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn remove_witness(&self, _peer_id: PeerId) -> Result<(), Error> {
+        bail!(ErrorKind::Unsupported("remove_witness".to_string()))
+    }
+
+    /// Promote the given witness or full node to primary.
+    /// The current primary becomes a witness.
+    ///
+    /// The default implementation fails with `ErrorKind::Unsupported`.
+    async fn promote_to_primary(&self, _peer_id: PeerId) -> Result<(), Error> {
+        bail!(ErrorKind::Unsupported("promote_to_primary".to_string()))
+    }
+
+    /// Terminate the underlying [`AsyncSupervisor`].
+    async fn terminate(&self) -> Result<(), Error>;
+}
+
+/// Input events sent by the [`AsyncHandle`]s to the [`AsyncSupervisor`].
+/// They carry a channel which is used to communicate back the responses of the requests.
+#[derive(Debug)]
+enum AsyncHandleInput {
+    /// Terminate the supervisor process
+    Terminate(oneshot::Sender<()>),
+
+    /// Verify to the highest height, call the provided callback with result
+    VerifyToHighest(oneshot::Sender<Result<LightBlock, Error>>),
+
+    /// Verify to the given height, call the provided callback with result
+    VerifyToTarget(Height, oneshot::Sender<Result<LightBlock, Error>>),
+
+    /// Get the latest trusted block.
+    LatestTrusted(oneshot::Sender<Option<LightBlock>>),
+
+    /// Get the current status of the LightClient
+    GetStatus(oneshot::Sender<LatestStatus>),
+
+    /// Get the persisted trace of the verification of the block at the given height
+    GetVerificationTrace(Height, oneshot::Sender<Option<Trace>>),
+
+    /// Get the trusted block at the given height
+    GetTrustedBlock(Height, oneshot::Sender<Option<LightBlock>>),
+
+    /// Get the status of the peers
+    GetWitnesses(oneshot::Sender<Vec<PeerStatus>>),
+
+    /// Get the reports of the latest forks detected
+    GetForkReports(oneshot::Sender<Vec<ForkReport>>),
+
+    /// Send the subsequent events to the given channel
+    Subscribe(mpsc::Sender<Event>),
+
+    /// Add the given instance as a witness
+    AddWitness(
+        PeerId,
+        net::Address,
+        Box<AsyncInstance>,
+        oneshot::Sender<Result<(), Error>>,
+    ),
+
+    /// Remove the given witness
+    RemoveWitness(PeerId, oneshot::Sender<Result<(), Error>>),
+
+    /// Promote the given witness or full node to primary
+    PromoteToPrimary(PeerId, oneshot::Sender<Result<(), Error>>),
+}
+
+/// An `AsyncInstance` packages an `AsyncLightClient` together with its `State`.
+#[derive(Debug)]
+pub struct AsyncInstance {
+    /// The light client for this instance
+    pub light_client: AsyncLightClient,
+
+    /// The state of the light client for this instance
+    pub state: State,
+}
+
+impl AsyncInstance {
+    /// Constructs a new instance from the given light client and its state.
+    pub fn new(light_client: AsyncLightClient, state: State) -> Self {
+        Self {
+            light_client,
+            state,
+        }
+    }
+
+    /// Get the latest trusted block.
+    pub fn latest_trusted(&self) -> Option<LightBlock> {
+        self.state.light_store.latest(Status::Trusted)
+    }
+
+    /// Trust the given block.
+    pub fn trust_block(&mut self, lb: &LightBlock) {
+        self.state.light_store.update(lb, Status::Trusted);
+    }
+
+    /// Prune the light store of this instance according to the given retention policy.
+    pub fn prune(&mut self, policy: &RetentionPolicy) -> Result<usize, Error> {
+        self.light_client.prune(&mut self.state, policy)
+    }
+}
+
+impl PeerInstance for AsyncInstance {
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+
+    fn prune(&mut self, policy: &RetentionPolicy) -> Result<usize, Error> {
+        AsyncInstance::prune(self, policy)
+    }
+}
+
+/// Asynchronous counterpart of the [`Supervisor`], which manages multiple
+/// [`AsyncInstance`]s and performs fork detection between them.
+///
+/// Rather than in its own thread, the supervisor is intended to be ran as a task on
+/// the executor of the application, and queried via an [`AsyncHandle`].
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// let supervisor: AsyncSupervisor = todo!();
+/// let handle = supervisor.handle();
+///
+/// // Spawn the supervisor as a task on the current runtime.
+/// tokio::spawn(supervisor.run());
+///
+/// match handle.verify_to_highest().await {
+///     Ok(light_block) => println!("[info] synced to block {}", light_block.height()),
+///     Err(e) => println!("[error] sync failed: {}", e),
+/// }
+/// ```
+pub struct AsyncSupervisor {
+    /// List of peers, retention policy, metrics and fork reports
+    core: Core<AsyncInstance>,
+    /// An instance of the fork detector
+    fork_detector: Box<dyn AsyncForkDetector>,
+    /// Reporter of fork evidence
+    evidence_reporter: Box<dyn AsyncEvidenceReporter>,
+    /// Channel through which to reply to `AsyncHandle`s
+    sender: mpsc::UnboundedSender<AsyncHandleInput>,
+    /// Channel through which to receive events from the `AsyncHandle`s
+    receiver: mpsc::UnboundedReceiver<AsyncHandleInput>,
+    /// Channels through which to emit events to the subscribers
+    subscribers: Vec<mpsc::Sender<Event>>,
+}
+
+impl std::fmt::Debug for AsyncSupervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncSupervisor")
+            .field("peers", &self.core.peers)
+            .finish()
+    }
+}
+
+// Ensure the `AsyncSupervisor` can be sent across thread boundaries.
+static_assertions::assert_impl_all!(AsyncSupervisor: Send);
+
+impl AsyncSupervisor {
+    /// Constructs a new supevisor from the given list of peers and fork detector instance.
+    pub fn new(
+        peers: PeerList<AsyncInstance>,
+        fork_detector: impl AsyncForkDetector + 'static,
+        evidence_reporter: impl AsyncEvidenceReporter + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded::<AsyncHandleInput>();
+
+        Self {
+            core: Core::new(peers),
+            sender,
+            receiver,
+            fork_detector: Box::new(fork_detector),
+            evidence_reporter: Box::new(evidence_reporter),
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Periodically prune the light stores of all the instances according to the given
+    /// retention policy, every `interval`, while the supervisor is running.
+    ///
+    /// See `AsyncSupervisor::run`.
+    pub fn with_retention_policy(mut self, policy: RetentionPolicy, interval: Duration) -> Self {
+        self.core.retention = Some((policy, interval));
+        self
+    }
+
+    /// Record the outcome of fork detection and the replacements of peers
+    /// in the given metrics.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.core.set_metrics(metrics);
+        self
+    }
+
+    /// Create a new handle to this supervisor.
+    pub fn handle(&self) -> AsyncSupervisorHandle {
+        AsyncSupervisorHandle::new(self.sender.clone())
+    }
+
+    /// Subscribe to the events emitted by this supervisor from now on.
+    pub fn subscribe(&mut self) -> AsyncSubscription {
+        let (sender, subscription) = AsyncSubscription::channel();
+        self.subscribers.push(sender);
+        subscription
+    }
+
+    /// Emit the given event to all subscribers, and forget about the ones which are gone
+    /// or which fell too far behind (see `SUBSCRIPTION_CAPACITY`).
+    fn emit(&mut self, event: Event) {
+        subscription::emit_async(&mut self.subscribers, &event);
+    }
+
+    /// Get the latest trusted state of the primary peer, if any
+    pub fn latest_trusted(&self) -> Option<LightBlock> {
+        self.core.latest_trusted()
+    }
+
+    /// Verify to the highest block.
+    pub async fn verify_to_highest(&mut self) -> Result<LightBlock, Error> {
+        self.verify(None).await
+    }
+
+    /// Add the given instance as a witness, whose RPC endpoint is at the given address.
+    ///
+    /// See `Supervisor::add_witness`.
+    pub fn add_witness(
+        &mut self,
+        peer_id: PeerId,
+        address: net::Address,
+        instance: AsyncInstance,
+    ) -> Result<(), Error> {
+        self.core.add_witness(peer_id, instance)?;
+        self.evidence_reporter.add_peer(peer_id, address);
+
+        Ok(())
+    }
+
+    /// Remove the given witness.
+    ///
+    /// See `Supervisor::remove_witness`.
+    pub fn remove_witness(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        self.core.remove_witness(peer_id)
+    }
+
+    /// Promote the given witness or full node to primary.
+    ///
+    /// See `Supervisor::promote_to_primary`.
+    pub fn promote_to_primary(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        self.core.promote_to_primary(peer_id)
+    }
+
+    /// Return latest trusted status summary.
+    fn latest_status(&self) -> LatestStatus {
+        self.core.latest_status()
+    }
+
+    /// Get the persisted trace of the verification of the block at the given height
+    /// by the primary, if any.
+    pub fn verification_trace(&self, height: Height) -> Option<Trace> {
+        self.core.verification_trace(height)
+    }
+
+    /// Get the block at the given height, if it is trusted by the primary.
+    pub fn trusted_block(&self, height: Height) -> Option<LightBlock> {
+        self.core.trusted_block(height)
+    }
+
+    /// Get the status of the primary, the witnesses, and the other peers.
+    pub fn witnesses(&self) -> Vec<PeerStatus> {
+        self.core.witnesses()
+    }
+
+    /// Get the reports of the latest forks detected, from the oldest to the latest.
+    pub fn fork_reports(&self) -> Vec<ForkReport> {
+        self.core.fork_reports()
+    }
+
+    /// Verify to the block at the given height.
+    pub async fn verify_to_target(&mut self, height: Height) -> Result<LightBlock, Error> {
+        self.verify(Some(height)).await
+    }
+
+    /// Verify either to the latest block (if `height == None`) or to a given block (if `height ==
+    /// Some(height)`).
+    ///
+    /// See `Supervisor::verify`.
+    async fn verify(&mut self, height: Option<Height>) -> Result<LightBlock, Error> {
+        let mut supervision = Supervision::new(height);
+        let mut input = Input::Start;
+
+        loop {
+            input = match self.core.step(&mut supervision, input) {
+                Step::HealthCheck(peer_id) => {
+                    Input::HealthChecked(self.health_check(peer_id).await)
+                }
+                Step::Verify(height) => {
+                    let primary = self.core.peers.primary_mut();
+
+                    // Perform light client core verification for the given height (or highest).
+                    let verdict = match height {
+                        None => {
+                            primary
+                                .light_client
+                                .verify_to_highest(&mut primary.state)
+                                .await
+                        }
+                        Some(height) => {
+                            primary
+                                .light_client
+                                .verify_to_target(height, &mut primary.state)
+                                .await
+                        }
+                    };
+
+                    Input::Verified(Box::new(verdict))
+                }
+                Step::DetectForks { verified, trusted } => {
+                    Input::ForksDetected(self.detect_forks(&verified, &trusted).await)
+                }
+                Step::Perform(actions) => Input::Performed(self.perform(actions).await),
+                Step::Done(outcome) => return *outcome,
+            };
+        }
+    }
+
+    /// Perform a health check of the given peer.
+    async fn health_check(&self, peer_id: PeerId) -> Result<(), Error> {
+        match self.core.peers.get(&peer_id) {
+            Some(instance) => instance.light_client.health_check().await,
+            None => Err(ErrorKind::PeerNotFound(peer_id).into()),
+        }
+    }
+
+    /// Perform the given actions, in this order.
+    async fn perform(&mut self, actions: Vec<Action>) -> Result<(), Error> {
+        for action in actions {
+            match action {
+                Action::Emit(event) => self.emit(event),
+                Action::Report(provider, evidence) => {
+                    self.report_evidence(provider, evidence).await?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Report the given evidence of a light client attack to the given peer.
+    async fn report_evidence(
+        &mut self,
+        provider: PeerId,
+        evidence: Box<Evidence>,
+    ) -> Result<(), Error> {
+        self.evidence_reporter
+            .report(evidence.as_ref().clone(), provider)
+            .await
+            .map_err(ErrorKind::Io)?;
+
+        self.emit(Event::EvidenceReported {
+            peer: provider,
+            evidence,
+        });
+
+        Ok(())
+    }
+
+    /// Perform fork detection with the given verified block and trusted block.
+    async fn detect_forks(
+        &self,
+        verified_block: &LightBlock,
+        trusted_block: &LightBlock,
+    ) -> Result<ForkDetection, Error> {
+        let witnesses = witnesses(&self.core.peers)?;
+        let primary_trace = self.core.primary_trace(verified_block.height());
+
+        self.fork_detector
+            .detect_forks(verified_block, trusted_block, primary_trace, witnesses)
+            .await
+    }
+
+    /// Prune the light stores of all the instances according to the retention policy
+    /// of the supervisor, if any, and return the number of light blocks which were removed.
+    pub fn prune(&mut self) -> Result<usize, Error> {
+        self.core.prune()
+    }
+
+    /// Run the supervisor event loop.
+    ///
+    /// If a retention policy was set, the light stores are pruned periodically
+    /// in between the events. The returned future must then be ran on a Tokio runtime
+    /// with the time driver enabled.
+    ///
+    /// The returned future should typically be spawned as a task on the executor
+    /// of the application, eg. with `tokio::spawn`.
+    pub async fn run(mut self) -> Result<(), Error> {
+        let mut pruning = self.core.retention.map(|(_, interval)| {
+            tokio::time::interval_at(tokio::time::Instant::now() + interval, interval)
+        });
+
+        loop {
+            let pruning_due = async {
+                match &mut pruning {
+                    Some(pruning) => {
+                        pruning.tick().await;
+                    }
+                    None => future::pending().await,
+                }
+            };
+
+            let event = futures::select! {
+                event = self.receiver.next() => Some(event),
+                () = pruning_due.fuse() => None,
+            };
+
+            let event = match event {
+                Some(event) => event.ok_or(ErrorKind::ChannelDisconnected)?,
+                None => {
+                    self.prune()?;
+                    continue;
+                }
+            };
+
+            match event {
+                AsyncHandleInput::LatestTrusted(sender) => {
+                    let outcome = self.latest_trusted();
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::Terminate(sender) => {
+                    reply(sender, ())?;
+                    return Ok(());
+                }
+                AsyncHandleInput::VerifyToTarget(height, sender) => {
+                    let outcome = self.verify_to_target(height).await;
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::VerifyToHighest(sender) => {
+                    let outcome = self.verify_to_highest().await;
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::GetStatus(sender) => {
+                    let outcome = self.latest_status();
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::GetVerificationTrace(height, sender) => {
+                    let outcome = self.verification_trace(height);
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::GetTrustedBlock(height, sender) => {
+                    let outcome = self.trusted_block(height);
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::GetWitnesses(sender) => {
+                    let outcome = self.witnesses();
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::GetForkReports(sender) => {
+                    let outcome = self.fork_reports();
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::Subscribe(sender) => {
+                    self.subscribers.push(sender);
+                }
+                AsyncHandleInput::AddWitness(peer_id, address, instance, sender) => {
+                    let outcome = self.add_witness(peer_id, address, *instance);
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::RemoveWitness(peer_id, sender) => {
+                    let outcome = self.remove_witness(peer_id);
+                    reply(sender, outcome)?;
+                }
+                AsyncHandleInput::PromoteToPrimary(peer_id, sender) => {
+                    let outcome = self.promote_to_primary(peer_id);
+                    reply(sender, outcome)?;
+                }
+            }
+        }
+    }
+}
+
+/// Send the given reply to an [`AsyncHandle`] through the given channel.
+fn reply<T>(sender: oneshot::Sender<T>, value: T) -> Result<(), Error> {
+    sender
+        .send(value)
+        .map_err(|_| ErrorKind::ChannelDisconnected.into())
+}
+
+/// An [`AsyncHandle`] to the [`AsyncSupervisor`] which allows to communicate with
+/// the supervisor across task boundaries via message passing.
+#[derive(Clone)]
+pub struct AsyncSupervisorHandle {
+    sender: mpsc::UnboundedSender<AsyncHandleInput>,
+}
+
+impl AsyncSupervisorHandle {
+    /// Crate a new handle that sends events to the supervisor via
+    /// the given channel. For internal use only.
+    fn new(sender: mpsc::UnboundedSender<AsyncHandleInput>) -> Self {
+        Self { sender }
+    }
+
+    /// Send the event built from a reply channel to the supervisor, and wait for its reply.
+    async fn request<T>(
+        &self,
+        make_event: impl FnOnce(oneshot::Sender<T>) -> AsyncHandleInput,
+    ) -> Result<T, Error> {
+        let (sender, receiver) = oneshot::channel::<T>();
+
+        self.sender
+            .unbounded_send(make_event(sender))
+            .map_err(|_| ErrorKind::ChannelDisconnected)?;
+
+        Ok(receiver.await.map_err(|_| ErrorKind::ChannelDisconnected)?)
+    }
+}
+
+#[async_trait]
+impl AsyncHandle for AsyncSupervisorHandle {
+    async fn latest_trusted(&self) -> Result<Option<LightBlock>, Error> {
+        self.request(AsyncHandleInput::LatestTrusted).await
+    }
+
+    async fn latest_status(&self) -> Result<LatestStatus, Error> {
+        self.request(AsyncHandleInput::GetStatus).await
+    }
+
+    async fn verification_trace(&self, height: Height) -> Result<Option<Trace>, Error> {
+        self.request(|sender| AsyncHandleInput::GetVerificationTrace(height, sender))
+            .await
+    }
+
+    async fn trusted_block(&self, height: Height) -> Result<Option<LightBlock>, Error> {
+        self.request(|sender| AsyncHandleInput::GetTrustedBlock(height, sender))
+            .await
+    }
+
+    async fn witnesses(&self) -> Result<Vec<PeerStatus>, Error> {
+        self.request(AsyncHandleInput::GetWitnesses).await
+    }
+
+    async fn fork_reports(&self) -> Result<Vec<ForkReport>, Error> {
+        self.request(AsyncHandleInput::GetForkReports).await
+    }
+
+    async fn verify_to_highest(&self) -> Result<LightBlock, Error> {
+        self.request(AsyncHandleInput::VerifyToHighest).await?
+    }
+
+    async fn verify_to_target(&self, height: Height) -> Result<LightBlock, Error> {
+        self.request(|sender| AsyncHandleInput::VerifyToTarget(height, sender))
+            .await?
+    }
+
+    async fn subscribe(&self) -> Result<AsyncSubscription, Error> {
+        let (sender, subscription) = AsyncSubscription::channel();
+
+        self.sender
+            .unbounded_send(AsyncHandleInput::Subscribe(sender))
+            .map_err(|_| ErrorKind::ChannelDisconnected)?;
+
+        Ok(subscription)
+    }
+
+    async fn add_witness(
+        &self,
+        peer_id: PeerId,
+        address: net::Address,
+        instance: AsyncInstance,
+    ) -> Result<(), Error> {
+        self.request(|sender| {
+            AsyncHandleInput::AddWitness(peer_id, address, Box::new(instance), sender)
+        })
+        .await?
+    }
+
+    async fn remove_witness(&self, peer_id: PeerId) -> Result<(), Error> {
+        self.request(|sender| AsyncHandleInput::RemoveWitness(peer_id, sender))
+            .await?
+    }
+
+    async fn promote_to_primary(&self, peer_id: PeerId) -> Result<(), Error> {
+        self.request(|sender| AsyncHandleInput::PromoteToPrimary(peer_id, sender))
+            .await?
+    }
+
+    async fn terminate(&self) -> Result<(), Error> {
+        self.request(AsyncHandleInput::Terminate).await
+    }
+}
@@ -0,0 +1,48 @@
+//! Reports of the forks detected by the supervisor.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::evidence::Attack;
+use crate::types::{PeerId, Time};
+
+/// Maximum number of fork reports kept by the supervisor, the oldest ones being dropped first.
+pub const MAX_FORK_REPORTS: usize = 100;
+
+/// Report of a fork detected between the primary and a witness.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ForkReport {
+    /// The primary
+    pub primary: PeerId,
+    /// The witness whose chain conflicts with the one of the primary
+    pub witness: PeerId,
+    /// Attack by which the primary's chain conflicts with the witness's chain
+    pub attack: Attack,
+    /// Attack by which the witness's chain conflicts with the primary's chain
+    pub witness_attack: Attack,
+    /// Time at which the fork was detected
+    pub detected_at: Time,
+}
+
+/// Log of the latest `MAX_FORK_REPORTS` fork reports, in the order they were recorded.
+#[derive(Clone, Debug, Default)]
+pub(super) struct ForkReports {
+    reports: VecDeque<ForkReport>,
+}
+
+impl ForkReports {
+    /// Record the given report, and drop the oldest one if the log is full.
+    pub(super) fn record(&mut self, report: ForkReport) {
+        if self.reports.len() == MAX_FORK_REPORTS {
+            self.reports.pop_front();
+        }
+
+        self.reports.push_back(report);
+    }
+
+    /// All the reports in the log, from the oldest to the latest.
+    pub(super) fn all(&self) -> Vec<ForkReport> {
+        self.reports.iter().cloned().collect()
+    }
+}
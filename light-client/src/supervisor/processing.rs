@@ -0,0 +1,421 @@
+//! The processing of the outcomes of verification and fork detection, which is shared by
+//! the `Supervisor` and the `AsyncSupervisor`.
+//!
+//! It does not perform any I/O: the peer list and the fork reports are updated right away,
+//! while the events to emit and the evidence to report are handed back to the supervisor.
+
+use std::time::Instant;
+
+use tendermint::evidence::{ConflictingBlock, Evidence, LightClientAttackEvidence};
+
+use crate::bail;
+use crate::errors::{Error, ErrorKind};
+use crate::evidence::{classify_attack, Attack, AttackType};
+use crate::fork_detector::Fork;
+use crate::peer_list::PeerList;
+use crate::state::State;
+use crate::types::{LightBlock, PeerId, Status, Time};
+
+use super::{Event, ForkReport, ForkReports};
+
+/// What a supervisor must do once forks have been processed, in this order.
+pub(super) enum Action {
+    /// Emit the given event to the subscribers
+    Emit(Event),
+    /// Report the given evidence to the given peer, and emit an `Event::EvidenceReported`
+    Report(PeerId, Box<Evidence>),
+}
+
+/// Outcome of the processing of the forks detected by a supervisor.
+pub(super) struct ProcessedForks {
+    /// The actions the supervisor must perform
+    pub actions: Vec<Action>,
+    /// The witnesses which forked from the primary
+    pub forked: Vec<PeerId>,
+    /// The attacks by which the primary and the forked witnesses conflict with each other
+    pub attacks: Vec<Attack>,
+}
+
+/// Fail with `ErrorKind::ForkDetected` if any witness forked from the primary.
+pub(super) fn check_forked(forked: Vec<PeerId>, attacks: Vec<Attack>) -> Result<(), Error> {
+    if !forked.is_empty() {
+        bail!(ErrorKind::ForkDetected {
+            peers: forked,
+            attacks
+        })
+    }
+
+    Ok(())
+}
+
+/// Replace the primary, whose verification failed with the given error, with a witness.
+/// If the primary could merely not be reached, it is put on probation rather than banned.
+///
+/// Returns the event to emit.
+pub(super) fn replace_primary<T>(peers: &mut PeerList<T>, error: Error) -> Result<Event, Error> {
+    let faulty_primary = peers.primary_id();
+    let reason = error.kind().clone();
+
+    let replacement = if is_unresponsive(&reason) {
+        peers.suspend_primary(Some(error), Instant::now())?
+    } else {
+        peers.replace_faulty_primary(Some(error))?
+    };
+
+    Ok(Event::PrimaryReplaced {
+        primary: faulty_primary,
+        replacement,
+        reason,
+    })
+}
+
+/// Trust the given block, verified by the primary whose state is given, as no fork was detected.
+///
+/// Returns the event to emit, if the block was not trusted already.
+pub(super) fn trust_verified_block(
+    state: &mut State,
+    verified_block: &LightBlock,
+) -> Option<Event> {
+    let newly_trusted = state
+        .light_store
+        .get(verified_block.height(), Status::Trusted)
+        .is_none();
+
+    state.light_store.update(verified_block, Status::Trusted);
+
+    if newly_trusted {
+        Some(Event::NewTrustedBlock(Box::new(verified_block.clone())))
+    } else {
+        None
+    }
+}
+
+/// Whether fork detection must be performed for the given verified block,
+/// given the latest trusted block.
+///
+/// A block below the latest trusted block was verified backward, by following the hash chain
+/// down from a trusted block which was already cross-checked with the witnesses, so it cannot
+/// conflict with the chain of an honest witness.
+pub(super) fn needs_fork_detection(
+    verified_block: &LightBlock,
+    trusted_block: &LightBlock,
+) -> bool {
+    verified_block.height() >= trusted_block.height()
+}
+
+/// The witnesses to perform fork detection against.
+///
+/// ## Errors
+/// - If there are no witnesses, returns `ErrorKind::NoWitnesses`.
+pub(super) fn witnesses<T>(peers: &PeerList<T>) -> Result<Vec<&T>, Error> {
+    if peers.witnesses_ids().is_empty() {
+        bail!(ErrorKind::NoWitnesses);
+    }
+
+    let witnesses = peers
+        .witnesses_ids()
+        .iter()
+        .filter_map(|id| peers.get(id))
+        .collect();
+
+    Ok(witnesses)
+}
+
+/// Process the given forks: record the actual forks in the fork reports, and remove
+/// the faulty witnesses from the peer list.
+pub(super) fn process_forks<T>(
+    peers: &mut PeerList<T>,
+    fork_reports: &mut ForkReports,
+    forks: Vec<Fork>,
+) -> Result<ProcessedForks, Error> {
+    let mut actions = Vec::with_capacity(forks.len() * 3);
+    let mut forked = Vec::with_capacity(forks.len());
+    let mut attacks = Vec::with_capacity(forks.len() * 2);
+
+    for fork in forks {
+        match fork {
+            // An actual fork was detected, report evidence and record forked peer.
+            // The primary's block is reported to the witness, and vice versa.
+            Fork::Forked {
+                primary,
+                witness,
+                common,
+                attack,
+            } => {
+                let provider = witness.provider;
+                let witness_attack = classify_attack(&common, &primary, &witness);
+
+                actions.push(Action::Emit(Event::ForkDetected {
+                    primary: primary.provider,
+                    witness: provider,
+                    attack: attack.clone(),
+                }));
+
+                fork_reports.record(ForkReport {
+                    primary: primary.provider,
+                    witness: provider,
+                    attack: attack.clone(),
+                    witness_attack: witness_attack.clone(),
+                    detected_at: Time::now(),
+                });
+
+                actions.push(Action::Report(
+                    provider,
+                    make_evidence(&primary, &witness, &common, &attack)?,
+                ));
+                actions.push(Action::Report(
+                    primary.provider,
+                    make_evidence(&witness, &primary, &common, &witness_attack)?,
+                ));
+
+                forked.push(provider);
+                attacks.push(attack);
+                attacks.push(witness_attack);
+            }
+            // A witness has timed out, put it on probation.
+            Fork::Timeout(provider, error) => {
+                let event = remove_faulty_witness(peers, provider, error);
+                actions.push(Action::Emit(event));
+            }
+            // A witness has been deemed faulty, remove it from the peer list,
+            // unless it could merely not be reached.
+            Fork::Faulty(block, error) => {
+                let event = remove_faulty_witness(peers, block.provider, error);
+                actions.push(Action::Emit(event));
+            }
+        }
+    }
+
+    Ok(ProcessedForks {
+        actions,
+        forked,
+        attacks,
+    })
+}
+
+/// Remove the given faulty witness from the peer list. Witnesses which are
+/// unresponsive are put on probation, while the others are banned for good.
+///
+/// Returns the event to emit.
+fn remove_faulty_witness<T>(peers: &mut PeerList<T>, witness: PeerId, reason: ErrorKind) -> Event {
+    let replacement = if is_unresponsive(&reason) {
+        peers.suspend_witness(witness, Instant::now())
+    } else {
+        peers.replace_faulty_witness(witness)
+    };
+
+    Event::WitnessRemoved {
+        witness,
+        replacement,
+        reason,
+    }
+}
+
+/// Whether the given error means that the peer could not be reached, or timed out,
+/// as opposed to having sent provably invalid data.
+fn is_unresponsive(error: &ErrorKind) -> bool {
+    matches!(error, ErrorKind::Io(_))
+}
+
+/// Build the evidence of a light client attack, where `conflicting` is the conflicting block,
+/// `trusted` is the block at the same height on the other chain, `common` is the last block
+/// both chains have in common, and `attack` is the attack by which `conflicting` conflicts
+/// with `trusted`.
+///
+/// The evidence of a lunatic attack refers to the common block, since the validators of the
+/// conflicting block cannot be trusted. The evidence of an equivocation or amnesia attack
+/// refers to the trusted block instead, as expected by full nodes, since both chains share
+/// the same validators at the conflicting height.
+fn make_evidence(
+    conflicting: &LightBlock,
+    trusted: &LightBlock,
+    common: &LightBlock,
+    attack: &Attack,
+) -> Result<Box<Evidence>, Error> {
+    let reference = match attack.attack_type {
+        AttackType::Lunatic => common,
+        AttackType::Equivocation | AttackType::Amnesia => trusted,
+    };
+
+    let conflicting_block = ConflictingBlock::new(
+        conflicting.signed_header.clone(),
+        conflicting.validators.clone(),
+    );
+
+    let evidence = LightClientAttackEvidence::new(
+        conflicting_block,
+        reference.height(),
+        attack.byzantine_validators.clone(),
+        reference.validators.total_voting_power(),
+        reference.signed_header.header.time,
+    )
+    .map_err(|e| ErrorKind::InvalidEvidence.context(e))?;
+
+    Ok(Box::new(Evidence::LightClientAttack(Box::new(evidence))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tendermint_testgen::{
+        light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header,
+        Validator as TestgenValidator,
+    };
+
+    fn primary_id() -> PeerId {
+        "6de6deefcc12585340af922a0dd332084546a207".parse().unwrap()
+    }
+
+    fn witness_id() -> PeerId {
+        "17a7e0367b3bcf7323d96217b51c5fe5b096a7b5".parse().unwrap()
+    }
+
+    fn validators() -> Vec<TestgenValidator> {
+        vec![
+            TestgenValidator::new("1").voting_power(30),
+            TestgenValidator::new("2").voting_power(50),
+        ]
+    }
+
+    fn make_block(
+        provider: PeerId,
+        validators: &[TestgenValidator],
+        height: u64,
+        time: u64,
+        round: u32,
+    ) -> LightBlock {
+        let header = Header::new(validators)
+            .chain_id("test-chain")
+            .height(height)
+            .time(time);
+
+        let commit = Commit::new(header.clone(), round);
+
+        let mut block: LightBlock = TestgenLightBlock::new(header, commit)
+            .generate()
+            .unwrap()
+            .into();
+        block.provider = provider;
+        block
+    }
+
+    fn peers() -> PeerList<u32> {
+        let mut builder = PeerList::builder();
+        builder.primary(primary_id(), 1);
+        builder.witness(witness_id(), 2);
+        builder.build()
+    }
+
+    /// Process the fork between the given blocks of the primary and of the witness,
+    /// and return the evidence reported to the witness, and to the primary.
+    fn reported_evidence(
+        common: LightBlock,
+        primary: LightBlock,
+        witness: LightBlock,
+    ) -> (LightClientAttackEvidence, LightClientAttackEvidence) {
+        let attack = classify_attack(&common, &witness, &primary);
+        let fork = Fork::Forked {
+            primary: Box::new(primary),
+            witness: Box::new(witness),
+            common: Box::new(common),
+            attack,
+        };
+
+        let processed =
+            process_forks(&mut peers(), &mut ForkReports::default(), vec![fork]).unwrap();
+
+        let mut evidence = processed
+            .actions
+            .into_iter()
+            .filter_map(|action| match action {
+                Action::Report(peer, evidence) => match *evidence {
+                    Evidence::LightClientAttack(evidence) => Some((peer, *evidence)),
+                    _ => None,
+                },
+                Action::Emit(_) => None,
+            });
+
+        let (to_witness, against_primary) = evidence.next().unwrap();
+        let (to_primary, against_witness) = evidence.next().unwrap();
+        assert_eq!(to_witness, witness_id());
+        assert_eq!(to_primary, primary_id());
+        assert!(evidence.next().is_none());
+
+        (against_primary, against_witness)
+    }
+
+    fn assert_refers_to(evidence: &LightClientAttackEvidence, block: &LightBlock) {
+        assert_eq!(evidence.common_height(), block.height());
+        assert_eq!(evidence.timestamp(), block.signed_header.header.time);
+        assert_eq!(
+            evidence.total_voting_power(),
+            block.validators.total_voting_power()
+        );
+    }
+
+    #[test]
+    fn lunatic_evidence_refers_to_the_common_block() {
+        let lunatic_validators = [
+            TestgenValidator::new("1").voting_power(30),
+            TestgenValidator::new("3").voting_power(70),
+        ];
+
+        let common = make_block(primary_id(), &validators()[..1], 1, 1, 1);
+        let primary = make_block(primary_id(), &lunatic_validators, 3, 3, 1);
+        let witness = make_block(witness_id(), &validators(), 3, 3, 1);
+
+        let (against_primary, against_witness) =
+            reported_evidence(common.clone(), primary.clone(), witness.clone());
+
+        assert_eq!(
+            against_primary.conflicting_block().signed_header,
+            primary.signed_header
+        );
+        assert_refers_to(&against_primary, &common);
+        assert_eq!(against_primary.byzantine_validators().len(), 1);
+
+        assert_eq!(
+            against_witness.conflicting_block().signed_header,
+            witness.signed_header
+        );
+        assert_refers_to(&against_witness, &common);
+    }
+
+    #[test]
+    fn equivocation_evidence_refers_to_the_trusted_block() {
+        let common = make_block(primary_id(), &validators()[..1], 1, 1, 1);
+        let primary = make_block(primary_id(), &validators(), 3, 4, 1);
+        let witness = make_block(witness_id(), &validators(), 3, 3, 1);
+
+        let (against_primary, against_witness) =
+            reported_evidence(common, primary.clone(), witness.clone());
+
+        assert_eq!(
+            against_primary.conflicting_block().signed_header,
+            primary.signed_header
+        );
+        assert_refers_to(&against_primary, &witness);
+        assert_eq!(against_primary.byzantine_validators().len(), 2);
+
+        assert_eq!(
+            against_witness.conflicting_block().signed_header,
+            witness.signed_header
+        );
+        assert_refers_to(&against_witness, &primary);
+    }
+
+    #[test]
+    fn amnesia_evidence_refers_to_the_trusted_block() {
+        let common = make_block(primary_id(), &validators()[..1], 1, 1, 1);
+        let primary = make_block(primary_id(), &validators(), 3, 4, 2);
+        let witness = make_block(witness_id(), &validators(), 3, 3, 1);
+
+        let (against_primary, against_witness) =
+            reported_evidence(common, primary.clone(), witness.clone());
+
+        assert_refers_to(&against_primary, &witness);
+        assert!(against_primary.byzantine_validators().is_empty());
+        assert_refers_to(&against_witness, &primary);
+    }
+}
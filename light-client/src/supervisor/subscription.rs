@@ -0,0 +1,232 @@
+//! Subscriptions to the events emitted by the supervisor.
+//!
+//! Each subscription buffers up to `SUBSCRIPTION_CAPACITY` events which were not received yet.
+//! The supervisor never waits for its subscribers: a subscriber which falls that far behind
+//! is unsubscribed, and its subscription ends once the buffered events have been received.
+//! It must then subscribe again, knowing that it missed some events.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crossbeam_channel as channel;
+use futures::{channel::mpsc, Stream};
+use serde::{Deserialize, Serialize};
+
+use tendermint::evidence::Evidence;
+
+use crate::errors::ErrorKind;
+use crate::evidence::Attack;
+use crate::types::{LightBlock, PeerId};
+
+/// Maximum number of events buffered by a subscription before its subscriber is dropped.
+pub const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// Emit the given event through the given channels, and forget about the subscribers
+/// which are gone or which fell too far behind.
+pub(super) fn emit(subscribers: &mut Vec<channel::Sender<Event>>, event: &Event) {
+    subscribers.retain(|subscriber| subscriber.try_send(event.clone()).is_ok());
+}
+
+/// Asynchronous counterpart of `emit`.
+pub(super) fn emit_async(subscribers: &mut Vec<mpsc::Sender<Event>>, event: &Event) {
+    *subscribers = std::mem::take(subscribers)
+        .into_iter()
+        .filter_map(|mut subscriber| {
+            subscriber.try_send(event.clone()).ok()?;
+            Some(subscriber)
+        })
+        .collect();
+}
+
+/// Events emitted by the supervisor to its subscribers, as they happen.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// A light block was verified and is now trusted.
+    NewTrustedBlock(Box<LightBlock>),
+
+    /// A fork was detected between the primary and a witness.
+    ForkDetected {
+        /// The primary
+        primary: PeerId,
+        /// The witness whose chain conflicts with the one of the primary
+        witness: PeerId,
+        /// Attack by which the primary's chain conflicts with the witness's chain
+        attack: Attack,
+    },
+
+    /// A witness was deemed faulty, or timed out, and was removed.
+    WitnessRemoved {
+        /// The faulty witness
+        witness: PeerId,
+        /// The full node promoted to witness in its place, if any
+        replacement: Option<PeerId>,
+        /// The reason why the witness was removed
+        reason: ErrorKind,
+    },
+
+    /// The primary failed to verify a block, and was replaced by a witness.
+    PrimaryReplaced {
+        /// The faulty primary
+        primary: PeerId,
+        /// The witness promoted to primary
+        replacement: PeerId,
+        /// The reason why the primary was replaced
+        reason: ErrorKind,
+    },
+
+    /// Evidence of a light client attack was reported to a peer.
+    EvidenceReported {
+        /// The peer to which the evidence was reported
+        peer: PeerId,
+        /// The evidence
+        evidence: Box<Evidence>,
+    },
+
+    /// A peer on probation passed its health check, and was re-admitted as a witness.
+    PeerReadmitted {
+        /// The re-admitted peer
+        peer: PeerId,
+    },
+}
+
+/// A subscription to the events emitted by a [`Supervisor`](super::Supervisor),
+/// obtained via [`Handle::subscribe`](super::Handle::subscribe).
+///
+/// The events are buffered until received, and the subscription ends once the supervisor
+/// terminates, or once the subscriber falls behind by more than `SUBSCRIPTION_CAPACITY`
+/// events. Iterating over a subscription blocks until the next event.
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: channel::Receiver<Event>,
+}
+
+impl Subscription {
+    /// Create a new subscription, along with the channel through which to emit its events.
+    pub(super) fn channel() -> (channel::Sender<Event>, Self) {
+        let (sender, receiver) = channel::bounded(SUBSCRIPTION_CAPACITY);
+        (sender, Self { receiver })
+    }
+
+    /// Create a subscription which has already ended.
+    pub(super) fn ended() -> Self {
+        Self::channel().1
+    }
+
+    /// Wait for the next event, or return `None` if the supervisor has terminated.
+    pub fn recv(&self) -> Option<Event> {
+        self.receiver.recv().ok()
+    }
+
+    /// Return the next event if one is available, without blocking.
+    pub fn try_recv(&self) -> Option<Event> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.recv()
+    }
+}
+
+/// A subscription to the events emitted by an [`AsyncSupervisor`](super::AsyncSupervisor),
+/// obtained via [`AsyncHandle::subscribe`](super::AsyncHandle::subscribe).
+///
+/// The events are buffered until received, and the stream ends once the supervisor
+/// terminates, or once the subscriber falls behind by more than `SUBSCRIPTION_CAPACITY`
+/// events.
+#[derive(Debug)]
+pub struct AsyncSubscription {
+    receiver: mpsc::Receiver<Event>,
+}
+
+impl AsyncSubscription {
+    /// Create a new subscription, along with the channel through which to emit its events.
+    pub(super) fn channel() -> (mpsc::Sender<Event>, Self) {
+        // The capacity of the channel is its buffer size plus one slot per sender
+        let (sender, receiver) = mpsc::channel(SUBSCRIPTION_CAPACITY - 1);
+        (sender, Self { receiver })
+    }
+
+    /// Create a subscription which has already ended.
+    pub(super) fn ended() -> Self {
+        Self::channel().1
+    }
+}
+
+impl Stream for AsyncSubscription {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on_stream;
+
+    fn event(i: usize) -> Event {
+        Event::PeerReadmitted {
+            peer: format!("{:040x}", i).parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn slow_subscribers_are_dropped() {
+        let (sender, subscription) = Subscription::channel();
+        let (other_sender, other_subscription) = Subscription::channel();
+        let mut subscribers = vec![sender, other_sender];
+
+        for i in 0..SUBSCRIPTION_CAPACITY {
+            emit(&mut subscribers, &event(i));
+        }
+        assert_eq!(other_subscription.try_recv(), Some(event(0)));
+
+        // Only the subscriber which did not receive any event is too far behind
+        emit(&mut subscribers, &event(SUBSCRIPTION_CAPACITY));
+        assert_eq!(subscribers.len(), 1);
+
+        // Its subscription ends once the buffered events are received
+        let events: Vec<_> = subscription.collect();
+        assert_eq!(
+            events,
+            (0..SUBSCRIPTION_CAPACITY).map(event).collect::<Vec<_>>()
+        );
+        assert_eq!(other_subscription.try_recv(), Some(event(1)));
+    }
+
+    #[test]
+    fn slow_async_subscribers_are_dropped() {
+        let (sender, subscription) = AsyncSubscription::channel();
+        let (other_sender, mut other_subscription) = AsyncSubscription::channel();
+        let mut subscribers = vec![sender, other_sender];
+
+        for i in 0..SUBSCRIPTION_CAPACITY {
+            emit_async(&mut subscribers, &event(i));
+        }
+        assert_eq!(
+            block_on_stream(&mut other_subscription).next(),
+            Some(event(0))
+        );
+
+        emit_async(&mut subscribers, &event(SUBSCRIPTION_CAPACITY));
+        assert_eq!(subscribers.len(), 1);
+
+        let events: Vec<_> = block_on_stream(subscription).collect();
+        assert_eq!(
+            events,
+            (0..SUBSCRIPTION_CAPACITY).map(event).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn default_subscriptions_have_ended() {
+        assert_eq!(Subscription::ended().recv(), None);
+        assert_eq!(block_on_stream(AsyncSubscription::ended()).next(), None);
+    }
+}
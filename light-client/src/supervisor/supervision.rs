@@ -0,0 +1,537 @@
+//! The supervision logic shared by the `Supervisor` and the `AsyncSupervisor`.
+//!
+//! It does not perform any I/O: a `Supervision` is driven step by step by either supervisor,
+//! which health-checks its peers, verifies blocks with its primary, detects forks with its
+//! witnesses and performs the actions the `Core` asks for, and hands the outcomes over
+//! to the next step.
+
+use std::time::{Duration, Instant};
+
+use crate::errors::{Error, ErrorKind};
+use crate::fork_detector::ForkDetection;
+use crate::metrics::Metrics;
+use crate::peer_list::{PeerList, PeerStatus};
+use crate::state::State;
+use crate::store::{RetentionPolicy, Trace};
+use crate::types::{Height, LatestStatus, LightBlock, PeerId, Status};
+
+use super::processing::{
+    check_forked, needs_fork_detection, process_forks, replace_primary, trust_verified_block,
+    Action, ProcessedForks,
+};
+use super::{Event, ForkReport, ForkReports};
+
+/// A light client instance managed by a supervisor.
+pub(super) trait PeerInstance {
+    /// The state of the light client of this instance
+    fn state(&self) -> &State;
+
+    /// The state of the light client of this instance, mutably
+    fn state_mut(&mut self) -> &mut State;
+
+    /// Prune the light store of this instance according to the given retention policy.
+    fn prune(&mut self, policy: &RetentionPolicy) -> Result<usize, Error>;
+}
+
+/// The state of a supervisor, except for its components and its channels.
+pub(super) struct Core<T> {
+    /// List of peers and their instances (primary, witnesses, full and faulty nodes)
+    pub peers: PeerList<T>,
+    /// Retention policy of the light stores, and interval at which they are pruned
+    pub retention: Option<(RetentionPolicy, Duration)>,
+    /// Metrics in which to record the outcome of fork detection, if any
+    pub metrics: Option<Metrics>,
+    /// Reports of the latest forks detected
+    pub fork_reports: ForkReports,
+}
+
+/// The verification of a block by a supervisor, while it is in progress.
+pub(super) struct Supervision {
+    height: Option<Height>,
+    now: Instant,
+    /// The peers left to health-check, the next one last
+    due_for_health_check: Vec<PeerId>,
+    progress: Progress,
+}
+
+impl Supervision {
+    /// Start the verification of the block at the given height, or of the highest block
+    /// if none is given. The peers whose probation is over are health-checked first.
+    pub fn new(height: Option<Height>) -> Self {
+        Self {
+            height,
+            now: Instant::now(),
+            due_for_health_check: Vec::new(),
+            progress: Progress::Pending,
+        }
+    }
+}
+
+/// How far a `Supervision` went.
+enum Progress {
+    /// Nothing was done yet
+    Pending,
+    /// The given peer is being health-checked
+    HealthChecking(PeerId),
+    /// The primary is verifying the block
+    Verifying,
+    /// Forks are being detected with the given block, verified by the primary
+    DetectingForks(Box<LightBlock>),
+    /// Actions are being performed, after which the supervision carries on as given
+    Performing(AfterActions),
+}
+
+/// How a `Supervision` carries on once the actions it asked for were performed.
+enum AfterActions {
+    /// Health-check the next peer whose probation is over, if any, then verify the block
+    HealthCheck,
+    /// Verify the block again, with the new primary
+    Verify,
+    /// Fail if any witness forked from the primary, otherwise verify the block again
+    CheckForked(ProcessedForks),
+    /// The supervision is over, as the given block is now trusted
+    Trusted(Box<LightBlock>),
+}
+
+/// The outcome of the previous step of a `Supervision`.
+pub(super) enum Input {
+    /// There was no previous step
+    Start,
+    /// The outcome of the health check requested by `Step::HealthCheck`
+    HealthChecked(Result<(), Error>),
+    /// The outcome of the verification requested by `Step::Verify`
+    Verified(Box<Result<LightBlock, Error>>),
+    /// The outcome of the fork detection requested by `Step::DetectForks`
+    ForksDetected(Result<ForkDetection, Error>),
+    /// The outcome of the actions requested by `Step::Perform`
+    Performed(Result<(), Error>),
+}
+
+/// What a supervisor must do to carry on with a `Supervision`.
+pub(super) enum Step {
+    /// Health-check the given peer, which is on probation
+    HealthCheck(PeerId),
+    /// Verify the block at the given height (or the highest block) with the primary
+    Verify(Option<Height>),
+    /// Detect forks between the primary and the witnesses, with the given block verified
+    /// by the primary and the latest block it trusted before
+    DetectForks {
+        verified: Box<LightBlock>,
+        trusted: Box<LightBlock>,
+    },
+    /// Perform the given actions, in this order
+    Perform(Vec<Action>),
+    /// The supervision is over, with the given outcome.
+    Done(Box<Result<LightBlock, Error>>),
+}
+
+impl<T: PeerInstance> Core<T> {
+    /// Constructs the state of a supervisor managing the given list of peers.
+    pub fn new(peers: PeerList<T>) -> Self {
+        Self {
+            peers,
+            retention: None,
+            metrics: None,
+            fork_reports: ForkReports::default(),
+        }
+    }
+
+    /// Record the outcome of fork detection and the replacements of peers
+    /// in the given metrics.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.peers.set_metrics(metrics.clone());
+        self.metrics = Some(metrics);
+    }
+
+    /// Get the latest trusted state of the primary peer, if any
+    pub fn latest_trusted(&self) -> Option<LightBlock> {
+        self.peers
+            .primary()
+            .state()
+            .light_store
+            .latest(Status::Trusted)
+    }
+
+    /// Add the given instance as a witness, see `Supervisor::add_witness`.
+    pub fn add_witness(&mut self, peer_id: PeerId, mut instance: T) -> Result<(), Error> {
+        let light_store = &mut instance.state_mut().light_store;
+
+        if light_store.latest(Status::Trusted).is_none() {
+            if let Some(trusted) = self.latest_trusted() {
+                light_store.update(&trusted, Status::Trusted);
+            }
+        }
+
+        self.peers.add_witness(peer_id, instance)
+    }
+
+    /// Remove the given witness, see `Supervisor::remove_witness`.
+    pub fn remove_witness(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        self.peers.remove_witness(peer_id)?;
+        Ok(())
+    }
+
+    /// Promote the given peer to primary, see `Supervisor::promote_to_primary`.
+    pub fn promote_to_primary(&mut self, peer_id: PeerId) -> Result<(), Error> {
+        let latest_trusted = self.latest_trusted();
+        self.peers.promote_to_primary(peer_id)?;
+
+        if let Some(trusted) = latest_trusted {
+            let primary = self.peers.primary_mut();
+            primary
+                .state_mut()
+                .light_store
+                .update(&trusted, Status::Trusted);
+        }
+
+        Ok(())
+    }
+
+    /// Return latest trusted status summary.
+    pub fn latest_status(&self) -> LatestStatus {
+        let mut connected_nodes: Vec<PeerId> = Vec::new();
+        connected_nodes.push(self.peers.primary_id());
+        connected_nodes.append(&mut self.peers.witnesses_ids().iter().copied().collect());
+
+        match self.latest_trusted() {
+            Some(trusted) => LatestStatus::new(
+                Some(trusted.signed_header.header.height.value()),
+                Some(trusted.signed_header.header.hash()),
+                Some(trusted.next_validators.hash()),
+                connected_nodes,
+            ),
+            // only return connected nodes to see what is going on:
+            None => LatestStatus::new(None, None, None, connected_nodes),
+        }
+    }
+
+    /// Get the persisted trace of the verification of the block at the given height
+    /// by the primary, if any.
+    pub fn verification_trace(&self, height: Height) -> Option<Trace> {
+        self.peers.primary().state().get_persisted_trace(height)
+    }
+
+    /// Get the block at the given height, if it is trusted by the primary.
+    pub fn trusted_block(&self, height: Height) -> Option<LightBlock> {
+        self.peers
+            .primary()
+            .state()
+            .light_store
+            .get(height, Status::Trusted)
+    }
+
+    /// Get the status of the primary, the witnesses, and the other peers.
+    pub fn witnesses(&self) -> Vec<PeerStatus> {
+        self.peers.statuses()
+    }
+
+    /// Get the reports of the latest forks detected, from the oldest to the latest.
+    pub fn fork_reports(&self) -> Vec<ForkReport> {
+        self.fork_reports.all()
+    }
+
+    /// Get the trace of the verification of the block at the given height by the primary.
+    pub fn primary_trace(&self, height: Height) -> Vec<LightBlock> {
+        self.peers.primary().state().get_trace(height)
+    }
+
+    /// Prune the light stores of all the instances according to the retention policy,
+    /// if any, and return the number of light blocks which were removed.
+    pub fn prune(&mut self) -> Result<usize, Error> {
+        match self.retention {
+            Some((policy, _)) => self
+                .peers
+                .values_mut()
+                .map(|instance| instance.prune(&policy))
+                .sum(),
+            None => Ok(0),
+        }
+    }
+
+    /// Carry on with the given supervision, given the outcome of the previous step.
+    pub fn step(&mut self, supervision: &mut Supervision, input: Input) -> Step {
+        match self.advance(supervision, input) {
+            Ok(step) => step,
+            Err(error) => Step::Done(Box::new(Err(error))),
+        }
+    }
+
+    fn advance(&mut self, supervision: &mut Supervision, input: Input) -> Result<Step, Error> {
+        let progress = std::mem::replace(&mut supervision.progress, Progress::Pending);
+
+        match (progress, input) {
+            (Progress::Pending, Input::Start) => {
+                let mut due = self.peers.due_for_health_check(supervision.now);
+                due.reverse();
+                supervision.due_for_health_check = due;
+                Ok(self.health_check_next(supervision))
+            }
+            (Progress::HealthChecking(peer_id), Input::HealthChecked(outcome)) => {
+                self.health_checked(supervision, peer_id, outcome)
+            }
+            (Progress::Verifying, Input::Verified(verdict)) => match *verdict {
+                Ok(verified_block) => self.verified(supervision, verified_block),
+                // Verification failed, swap primary, and continue with new primary,
+                // if there is any witness left.
+                Err(error) => {
+                    let event = replace_primary(&mut self.peers, error)?;
+                    Ok(perform(
+                        supervision,
+                        vec![Action::Emit(event)],
+                        AfterActions::Verify,
+                    ))
+                }
+            },
+            (Progress::DetectingForks(verified_block), Input::ForksDetected(outcome)) => {
+                let outcome = outcome?;
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_fork_detection(&outcome);
+                }
+
+                match outcome {
+                    // There was a fork or a faulty peer
+                    ForkDetection::Detected(forks) => {
+                        let mut processed =
+                            process_forks(&mut self.peers, &mut self.fork_reports, forks)?;
+                        let actions = std::mem::take(&mut processed.actions);
+                        Ok(perform(
+                            supervision,
+                            actions,
+                            AfterActions::CheckForked(processed),
+                        ))
+                    }
+                    ForkDetection::NotDetected => Ok(self.trust(supervision, *verified_block)),
+                }
+            }
+            (Progress::Performing(after), Input::Performed(outcome)) => {
+                outcome?;
+
+                match after {
+                    AfterActions::HealthCheck => Ok(self.health_check_next(supervision)),
+                    AfterActions::Verify => Ok(verify(supervision)),
+                    // Exit if a fork was detected, otherwise perform verification again
+                    AfterActions::CheckForked(processed) => {
+                        check_forked(processed.forked, processed.attacks)?;
+                        Ok(verify(supervision))
+                    }
+                    // No fork detected, exiting
+                    AfterActions::Trusted(verified_block) => {
+                        Ok(Step::Done(Box::new(Ok(*verified_block))))
+                    }
+                }
+            }
+            _ => unreachable!("unexpected input for the current step of the supervision"),
+        }
+    }
+
+    /// Health-check the next peer whose probation is over, if any is left,
+    /// otherwise verify the block.
+    fn health_check_next(&self, supervision: &mut Supervision) -> Step {
+        while let Some(peer_id) = supervision.due_for_health_check.pop() {
+            if self.peers.get(&peer_id).is_some() {
+                supervision.progress = Progress::HealthChecking(peer_id);
+                return Step::HealthCheck(peer_id);
+            }
+        }
+
+        verify(supervision)
+    }
+
+    /// Re-admit the given peer as a witness if it passed its health check,
+    /// or extend its probation otherwise.
+    fn health_checked(
+        &mut self,
+        supervision: &mut Supervision,
+        peer_id: PeerId,
+        outcome: Result<(), Error>,
+    ) -> Result<Step, Error> {
+        if outcome.is_err() {
+            self.peers.extend_probation(peer_id, supervision.now)?;
+            return Ok(self.health_check_next(supervision));
+        }
+
+        let latest_trusted = self.latest_trusted();
+
+        if let Some(instance) = self.peers.get_mut(&peer_id) {
+            // Carry on from the latest trusted block, as the ones trusted by the peer
+            // before its probation may have expired since.
+            if let Some(trusted) = &latest_trusted {
+                instance
+                    .state_mut()
+                    .light_store
+                    .update(trusted, Status::Trusted);
+            }
+        }
+
+        let actions = match self.peers.readmit(peer_id) {
+            Ok(()) => vec![Action::Emit(Event::PeerReadmitted { peer: peer_id })],
+            Err(_) => Vec::new(),
+        };
+
+        Ok(perform(supervision, actions, AfterActions::HealthCheck))
+    }
+
+    /// Perform fork detection with the block verified by the primary and the block it trusted
+    /// before, unless the verified block is below the trusted one (see `needs_fork_detection`).
+    fn verified(
+        &mut self,
+        supervision: &mut Supervision,
+        verified_block: LightBlock,
+    ) -> Result<Step, Error> {
+        let trusted_block = self
+            .latest_trusted()
+            .ok_or(ErrorKind::NoTrustedState(Status::Trusted))?;
+
+        if !needs_fork_detection(&verified_block, &trusted_block) {
+            return Ok(self.trust(supervision, verified_block));
+        }
+
+        let verified = Box::new(verified_block);
+        supervision.progress = Progress::DetectingForks(verified.clone());
+
+        Ok(Step::DetectForks {
+            verified,
+            trusted: Box::new(trusted_block),
+        })
+    }
+
+    /// Trust the given block, verified by the primary, as no fork was detected.
+    fn trust(&mut self, supervision: &mut Supervision, verified_block: LightBlock) -> Step {
+        let primary = self.peers.primary_mut();
+        let actions = trust_verified_block(primary.state_mut(), &verified_block)
+            .map(Action::Emit)
+            .into_iter()
+            .collect();
+
+        perform(
+            supervision,
+            actions,
+            AfterActions::Trusted(Box::new(verified_block)),
+        )
+    }
+}
+
+/// Perform the given actions, then carry on as given.
+fn perform(supervision: &mut Supervision, actions: Vec<Action>, after: AfterActions) -> Step {
+    supervision.progress = Progress::Performing(after);
+    Step::Perform(actions)
+}
+
+/// Verify the block with the primary.
+fn verify(supervision: &mut Supervision) -> Step {
+    supervision.progress = Progress::Verifying;
+    Step::Verify(supervision.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::{memory::MemoryStore, LightStore};
+    use tendermint_testgen::{
+        light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header,
+        Validator as TestgenValidator,
+    };
+
+    struct TestInstance(State);
+
+    impl PeerInstance for TestInstance {
+        fn state(&self) -> &State {
+            &self.0
+        }
+
+        fn state_mut(&mut self) -> &mut State {
+            &mut self.0
+        }
+
+        fn prune(&mut self, _policy: &RetentionPolicy) -> Result<usize, Error> {
+            Ok(0)
+        }
+    }
+
+    fn primary_id() -> PeerId {
+        "6de6deefcc12585340af922a0dd332084546a207".parse().unwrap()
+    }
+
+    fn witness_id() -> PeerId {
+        "17a7e0367b3bcf7323d96217b51c5fe5b096a7b5".parse().unwrap()
+    }
+
+    fn make_block(height: u64) -> LightBlock {
+        let validators = [TestgenValidator::new("1"), TestgenValidator::new("2")];
+        let header = Header::new(&validators)
+            .chain_id("test-chain")
+            .height(height)
+            .time(height);
+        let commit = Commit::new(header.clone(), 1);
+
+        TestgenLightBlock::new(header, commit)
+            .generate()
+            .unwrap()
+            .into()
+    }
+
+    fn trusting(block: &LightBlock) -> TestInstance {
+        let mut store = MemoryStore::new();
+        store.insert(block.clone(), Status::Trusted);
+        TestInstance(State::new(store))
+    }
+
+    #[test]
+    fn replaces_the_primary_then_detects_forks_and_trusts_the_verified_block() {
+        let trusted = make_block(1);
+        let verified = make_block(2);
+
+        let mut builder = PeerList::builder();
+        builder.primary(primary_id(), trusting(&trusted));
+        builder.witness(witness_id(), trusting(&trusted));
+        let mut core = Core::new(builder.build());
+
+        let mut supervision = Supervision::new(None);
+
+        let step = core.step(&mut supervision, Input::Start);
+        assert!(matches!(step, Step::Verify(None)));
+
+        let invalid = ErrorKind::MissingLastBlockId(verified.height()).into();
+        let step = core.step(&mut supervision, Input::Verified(Box::new(Err(invalid))));
+        assert!(matches!(
+            step,
+            Step::Perform(actions) if matches!(
+                actions.as_slice(),
+                [Action::Emit(Event::PrimaryReplaced { replacement, .. })] if *replacement == witness_id()
+            )
+        ));
+        assert_eq!(core.peers.primary_id(), witness_id());
+
+        let step = core.step(&mut supervision, Input::Performed(Ok(())));
+        assert!(matches!(step, Step::Verify(None)));
+
+        let step = core.step(
+            &mut supervision,
+            Input::Verified(Box::new(Ok(verified.clone()))),
+        );
+        assert!(matches!(
+            step,
+            Step::DetectForks { verified: ref v, trusted: ref t } if **v == verified && **t == trusted
+        ));
+
+        let step = core.step(
+            &mut supervision,
+            Input::ForksDetected(Ok(ForkDetection::NotDetected)),
+        );
+        assert!(matches!(
+            step,
+            Step::Perform(actions) if matches!(
+                actions.as_slice(),
+                [Action::Emit(Event::NewTrustedBlock(block))] if **block == verified
+            )
+        ));
+
+        let step = core.step(&mut supervision, Input::Performed(Ok(())));
+        assert!(
+            matches!(step, Step::Done(outcome) if matches!(*outcome, Ok(ref block) if *block == verified))
+        );
+        assert_eq!(core.latest_trusted(), Some(verified));
+    }
+}
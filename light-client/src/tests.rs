@@ -7,12 +7,13 @@ use tendermint::abci::transaction::Hash;
 use tendermint_rpc as rpc;
 
 use crate::components::clock::Clock;
-use crate::components::io::{AtHeight, Io, IoError};
+use crate::components::io::{AsyncIo, AtHeight, Io, IoError};
 use crate::components::verifier::{ProdVerifier, Verdict, Verifier};
 use crate::errors::Error;
-use crate::evidence::EvidenceReporter;
-use crate::light_client::{LightClient, Options};
+use crate::evidence::{AsyncEvidenceReporter, EvidenceReporter};
+use crate::light_client::{LightClient, Options, VerificationMode};
 use crate::state::State;
+use async_trait::async_trait;
 use contracts::contract_trait;
 use std::collections::HashMap;
 use std::time::Duration;
@@ -121,6 +122,13 @@ impl Io for MockIo {
     }
 }
 
+#[async_trait]
+impl AsyncIo for MockIo {
+    async fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+        Io::fetch_light_block(self, height)
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct MockEvidenceReporter;
 
@@ -131,6 +139,13 @@ impl EvidenceReporter for MockEvidenceReporter {
     }
 }
 
+#[async_trait]
+impl AsyncEvidenceReporter for MockEvidenceReporter {
+    async fn report(&self, _e: Evidence, _peer: PeerId) -> Result<Hash, IoError> {
+        Ok(Hash::new([0; 32]))
+    }
+}
+
 impl MockEvidenceReporter {
     pub fn new() -> Self {
         Self
@@ -151,6 +166,7 @@ pub fn verify_single(
         trust_threshold,
         trusting_period,
         clock_drift,
+        verification_mode: VerificationMode::default(),
     };
 
     let result = verifier.verify(&input, &trusted_state, &options, now);
@@ -8,6 +8,15 @@ pub mod cmp {
             b
         }
     }
+
+    /// Stable version of `std::cmp::min_by_key`.
+    pub fn min_by_key<A, B: Ord>(a: A, b: A, key: impl Fn(&A) -> B) -> A {
+        if key(&a) <= key(&b) {
+            a
+        } else {
+            b
+        }
+    }
 }
 
 pub mod option {
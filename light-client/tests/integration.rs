@@ -84,6 +84,7 @@ fn sync() {
         },
         trusting_period: Duration::from_secs(60 * 60), // 60 minutes
         clock_drift: Duration::from_secs(5 * 60),      // 5 minutes
+        verification_mode: light_client::VerificationMode::default(),
     };
 
     let primary_instance = make_instance(primary, options, node_address.clone());
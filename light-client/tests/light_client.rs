@@ -1,23 +1,33 @@
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use tendermint_light_client::{
     components::{
-        io::{AtHeight, Io},
+        io::{AtHeight, Io, IoError},
         scheduler,
         verifier::ProdVerifier,
     },
     errors::{Error, ErrorKind},
-    light_client::{LightClient, Options},
+    light_client::{LightClient, Options, VerificationMode},
+    metrics::Metrics,
+    operations::ProdHasher,
+    predicates::errors::VerificationError,
     state::State,
-    store::{memory::MemoryStore, LightStore},
+    store::{
+        memory::{MemoryStore, MemoryTraceStore},
+        LightStore,
+    },
     tests::*,
-    types::{LightBlock, Status},
+    types::{Height, LightBlock, Status, Time, ValidatorSet},
 };
 
 use std::convert::TryInto;
 use tendermint_testgen::light_block::default_peer_id;
-use tendermint_testgen::Tester;
+use tendermint_testgen::{
+    light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header, LightChain, Tester,
+    Validator,
+};
 
 // Link to JSON test files repo:
 // https://github.com/informalsystems/conformance-tests
@@ -26,6 +36,7 @@ const TEST_FILES_PATH: &str = "./tests/support/";
 struct BisectionTestResult {
     untrusted_light_block: LightBlock,
     new_states: Result<Vec<LightBlock>, Error>,
+    state: State,
 }
 
 fn run_bisection_test(tc: TestBisection<LightBlock>) -> BisectionTestResult {
@@ -45,6 +56,7 @@ fn run_bisection_test(tc: TestBisection<LightBlock>) -> BisectionTestResult {
         trust_threshold,
         trusting_period: trusting_period.into(),
         clock_drift,
+        verification_mode: VerificationMode::default(),
     };
 
     let provider = tc.primary;
@@ -61,6 +73,7 @@ fn run_bisection_test(tc: TestBisection<LightBlock>) -> BisectionTestResult {
     let mut state = State {
         light_store: Box::new(light_store),
         verification_trace: HashMap::new(),
+        trace_store: None,
     };
 
     let verifier = ProdVerifier::default();
@@ -71,6 +84,7 @@ fn run_bisection_test(tc: TestBisection<LightBlock>) -> BisectionTestResult {
         clock,
         scheduler::basic_bisecting_schedule,
         verifier,
+        ProdHasher,
         io.clone(),
     );
 
@@ -83,6 +97,7 @@ fn run_bisection_test(tc: TestBisection<LightBlock>) -> BisectionTestResult {
     BisectionTestResult {
         untrusted_light_block,
         new_states: result,
+        state,
     }
 }
 
@@ -110,12 +125,19 @@ fn bisection_test(tc: TestBisection<LightBlock>) {
     }
 }
 
-/// Test that the light client fails with `ErrorKind::TargetLowerThanTrustedState`
+/// Test that the light client performs backward verification
 /// when the target height is lower than the last trusted state height.
 ///
 /// To do this, we override increment the trusted height by 1
 /// and set the target height to `trusted_height - 1`, then run
-/// the bisection test as normal. We then assert that we get the expected error.
+/// the bisection test as normal.
+///
+/// Note that the hashes of the headers of the conformance tests do not match
+/// the ones computed by `ProdHasher`, so that their hash chain is always found
+/// to be broken, unless the trusted state has expired already. We thus assert
+/// that backward verification fails for either reason, and that the target block
+/// does not end up verified in the light store. See `backward_verification_follows_the_hash_chain`
+/// for a test of backward verification which succeeds.
 fn bisection_lower_test(mut tc: TestBisection<LightBlock>) {
     let mut trusted_height = tc.trust_options.height;
 
@@ -127,17 +149,27 @@ fn bisection_lower_test(mut tc: TestBisection<LightBlock>) {
     tc.height_to_verify = (trusted_height.value() - 1).try_into().unwrap();
 
     let test_result = run_bisection_test(tc);
-    match test_result.new_states {
-        Ok(_) => {
-            panic!("test unexpectedly succeeded, expected TargetLowerThanTrustedState error");
-        }
-        Err(e) => match e.kind() {
-            ErrorKind::TargetLowerThanTrustedState { .. } => (),
-            kind => panic!(
-                "unexpected error, expected: TargetLowerThanTrustedState, got: {}",
-                kind
-            ),
-        },
+    let target_height = test_result.untrusted_light_block.height();
+    let light_store = &test_result.state.light_store;
+
+    // The trusted state is left untouched either way
+    assert_eq!(
+        light_store
+            .latest_trusted_or_verified()
+            .map(|lb| lb.height()),
+        Some(trusted_height)
+    );
+    assert_eq!(light_store.get_trusted_or_verified(target_height), None);
+
+    let failed = light_store
+        .all(Status::Failed)
+        .map(|lb| lb.height())
+        .collect::<Vec<_>>();
+
+    match test_result.new_states.map_err(|e| e.kind().clone()) {
+        Err(ErrorKind::TrustedStateOutsideTrustingPeriod { .. }) => assert!(failed.is_empty()),
+        Err(ErrorKind::InvalidAdjacentHeaders { .. }) => assert_eq!(failed, vec![target_height]),
+        result => panic!("unexpected result: {:?}", result),
     }
 }
 
@@ -149,3 +181,511 @@ fn run_bisection_tests() {
     tester.run_foreach_in_dir("bisection/single_peer");
     tester.finalize();
 }
+
+/// Generate a chain of the given length, with the same validators at every height.
+fn make_chain(length: u64) -> Vec<LightBlock> {
+    LightChain::default_with_length(length)
+        .light_blocks
+        .into_iter()
+        .map(|lb| {
+            let lb = lb.generate().unwrap();
+            LightBlock::new(
+                lb.signed_header,
+                lb.validators,
+                lb.next_validators,
+                default_peer_id(),
+            )
+        })
+        .collect()
+}
+
+/// Verify the given chain up to its last block with the given verification mode,
+/// starting from its first block.
+fn verify_chain(
+    blocks: Vec<LightBlock>,
+    verification_mode: VerificationMode,
+) -> (Result<LightBlock, Error>, State) {
+    verify_chain_with_metrics(blocks, verification_mode, None)
+}
+
+/// Same as `verify_chain`, but records the metrics of the verification in the given metrics.
+fn verify_chain_with_metrics(
+    blocks: Vec<LightBlock>,
+    verification_mode: VerificationMode,
+    metrics: Option<Metrics>,
+) -> (Result<LightBlock, Error>, State) {
+    let target_height = blocks.last().unwrap().height();
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let options = Options {
+        trust_threshold: Default::default(),
+        trusting_period: Duration::from_secs(60 * 60),
+        clock_drift: Duration::from_secs(10),
+        verification_mode,
+    };
+
+    let mut light_store = MemoryStore::new();
+    light_store.insert(blocks[0].clone(), Status::Trusted);
+    let mut state = State::new(light_store).with_trace_store(MemoryTraceStore::new());
+
+    let light_client = LightClient::new(
+        default_peer_id(),
+        options,
+        MockClock { now },
+        scheduler::basic_bisecting_schedule,
+        ProdVerifier::default(),
+        ProdHasher,
+        MockIo::new("test-chain".to_string(), blocks),
+    );
+
+    let light_client = match metrics {
+        Some(metrics) => light_client.with_metrics(metrics),
+        None => light_client,
+    };
+
+    let result = light_client.verify_to_target(target_height, &mut state);
+    (result, state)
+}
+
+#[test]
+fn sequential_verification_checks_every_header() {
+    let blocks = make_chain(10);
+
+    // Skipping verification goes straight to the target, as the validators never change
+    let (result, state) = verify_chain(blocks.clone(), VerificationMode::Skipping);
+    let target = result.unwrap();
+    let trace: Vec<u64> = state
+        .get_trace(target.height())
+        .iter()
+        .map(|lb| lb.height().value())
+        .collect();
+    assert_eq!(trace, vec![10]);
+
+    let (result, state) = verify_chain(blocks, VerificationMode::Sequential);
+    let target = result.unwrap();
+    let trace: Vec<u64> = state
+        .get_trace(target.height())
+        .iter()
+        .map(|lb| lb.height().value())
+        .collect();
+    assert_eq!(trace, (2..=10).rev().collect::<Vec<_>>());
+    assert_eq!(state.light_store.all(Status::Verified).count(), 9);
+}
+
+#[test]
+fn verification_traces_are_persisted() {
+    let blocks = make_chain(4);
+
+    for (mode, heights) in &[
+        (VerificationMode::Skipping, vec![1, 4]),
+        (VerificationMode::Sequential, vec![1, 2, 3, 4]),
+    ] {
+        let (result, state) = verify_chain(blocks.clone(), *mode);
+        let target = result.unwrap();
+
+        let trace = state.get_persisted_trace(target.height()).unwrap();
+        assert_eq!(trace.target_height, target.height());
+
+        let trace_heights: Vec<u64> = trace.entries.iter().map(|e| e.height.value()).collect();
+        assert_eq!(&trace_heights, heights);
+
+        for (entry, height) in trace.entries.iter().zip(heights) {
+            let header = &blocks[*height as usize - 1].signed_header.header;
+            assert_eq!(entry.validators_hash, header.validators_hash);
+            assert_eq!(entry.next_validators_hash, header.next_validators_hash);
+        }
+    }
+}
+
+#[test]
+fn verification_metrics_are_recorded() {
+    let blocks = make_chain(10);
+    let metrics = Metrics::new();
+
+    for mode in &[VerificationMode::Skipping, VerificationMode::Sequential] {
+        let (result, _) = verify_chain_with_metrics(blocks.clone(), *mode, Some(metrics.clone()));
+        assert!(result.is_ok());
+    }
+
+    let encoded = metrics.encode();
+    let peer = default_peer_id().to_string();
+
+    for line in &[
+        format!(
+            "tendermint_light_client_verification_duration_seconds_count{{peer=\"{}\",result=\"success\"}} 2",
+            peer
+        ),
+        // One step when skipping straight to the target, and nine when verifying sequentially
+        format!(
+            "tendermint_light_client_bisection_steps_sum{{peer=\"{}\"}} 10",
+            peer
+        ),
+        // The target block was just produced, and the trusting period is one hour long
+        format!(
+            "tendermint_light_client_trusting_period_headroom_seconds{{peer=\"{}\"}} 3600",
+            peer
+        ),
+    ] {
+        assert!(encoded.contains(line.as_str()), "missing `{}`", line);
+    }
+}
+
+#[test]
+fn sequential_verification_fails_on_invalid_intermediate_header() {
+    let mut blocks = make_chain(10);
+
+    // The header does not match its commit anymore
+    blocks[4].signed_header.header.time = blocks[3].signed_header.header.time;
+
+    let (result, _) = verify_chain(blocks.clone(), VerificationMode::Skipping);
+    assert!(result.is_ok());
+
+    let (result, state) = verify_chain(blocks, VerificationMode::Sequential);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::InvalidLightBlock(_)
+    ));
+
+    let verified: Vec<u64> = state
+        .light_store
+        .all(Status::Verified)
+        .map(|lb| lb.height().value())
+        .collect();
+    assert_eq!(verified, vec![2, 3, 4]);
+    assert_eq!(
+        state
+            .light_store
+            .get(5_u64.try_into().unwrap(), Status::Failed)
+            .map(|lb| lb.height().value()),
+        Some(5)
+    );
+}
+
+/// Generate a chain of headers linked together by their `last_block_id`, with the given times.
+fn make_chain_with_times(times: &[u64]) -> Vec<LightBlock> {
+    let vals = [
+        Validator::new("1").voting_power(50),
+        Validator::new("2").voting_power(50),
+    ];
+
+    let mut header = Header::new(&vals)
+        .next_validators(&vals)
+        .chain_id("test-chain")
+        .height(1)
+        .time(times[0]);
+
+    let mut blocks = Vec::with_capacity(times.len());
+
+    for (i, time) in times.iter().enumerate() {
+        if i > 0 {
+            header = header.next().time(*time);
+        }
+
+        let commit = Commit::new(header.clone(), 1);
+        let lb = TestgenLightBlock::new(header.clone(), commit)
+            .validators(&vals)
+            .next_validators(&vals)
+            .generate()
+            .unwrap();
+
+        blocks.push(LightBlock::new(
+            lb.signed_header,
+            lb.validators,
+            lb.next_validators,
+            default_peer_id(),
+        ));
+    }
+
+    blocks
+}
+
+/// Verify the block of the given chain at `target_height` at the given time,
+/// starting from the last block of the chain, and thus backward.
+fn verify_chain_backward(
+    blocks: Vec<LightBlock>,
+    target_height: u64,
+    now: Time,
+) -> (Result<LightBlock, Error>, State) {
+    let options = Options {
+        trust_threshold: Default::default(),
+        trusting_period: Duration::from_secs(60 * 60),
+        clock_drift: Duration::from_secs(10),
+        verification_mode: VerificationMode::default(),
+    };
+
+    let mut light_store = MemoryStore::new();
+    light_store.insert(blocks.last().unwrap().clone(), Status::Trusted);
+    let mut state = State::new(light_store);
+
+    let light_client = LightClient::new(
+        default_peer_id(),
+        options,
+        MockClock { now },
+        scheduler::basic_bisecting_schedule,
+        ProdVerifier::default(),
+        ProdHasher,
+        MockIo::new("test-chain".to_string(), blocks),
+    );
+
+    let result = light_client.verify_to_target(target_height.try_into().unwrap(), &mut state);
+    (result, state)
+}
+
+/// The heights of the blocks with the given status in the light store of the given state.
+fn heights_with_status(state: &State, status: Status) -> Vec<u64> {
+    state
+        .light_store
+        .all(status)
+        .map(|lb| lb.height().value())
+        .collect()
+}
+
+#[test]
+fn backward_verification_follows_the_hash_chain() {
+    let blocks = make_chain(10);
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let (result, state) = verify_chain_backward(blocks.clone(), 3, now);
+    assert_eq!(result.unwrap(), blocks[2]);
+
+    assert_eq!(heights_with_status(&state, Status::Trusted), vec![10]);
+    assert_eq!(
+        heights_with_status(&state, Status::Verified),
+        (3..=9).collect::<Vec<_>>()
+    );
+    assert!(state.get_trace(3_u64.try_into().unwrap()).is_empty());
+}
+
+#[test]
+fn backward_verification_fails_on_broken_hash_link() {
+    let mut blocks = make_chain(10);
+
+    // The header at height 5 now points to the block at height 3,
+    // so that its hash does not match the `last_block_id` of the header above it
+    blocks[4].signed_header.header.last_block_id = blocks[3].signed_header.header.last_block_id;
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let (result, state) = verify_chain_backward(blocks, 3, now);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::InvalidAdjacentHeaders { .. }
+    ));
+
+    assert_eq!(heights_with_status(&state, Status::Trusted), vec![10]);
+    assert_eq!(
+        heights_with_status(&state, Status::Verified),
+        vec![6, 7, 8, 9]
+    );
+    assert_eq!(heights_with_status(&state, Status::Failed), vec![5]);
+}
+
+#[test]
+fn backward_verification_fails_on_non_decreasing_time() {
+    // The header at height 5 is not older than the one at height 6
+    let blocks = make_chain_with_times(&[1, 2, 3, 4, 6, 6, 7, 8, 9, 10]);
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let (result, state) = verify_chain_backward(blocks, 3, now);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::InvalidLightBlock(VerificationError::NonMonotonicBftTime { .. })
+    ));
+
+    assert_eq!(heights_with_status(&state, Status::Trusted), vec![10]);
+    assert_eq!(
+        heights_with_status(&state, Status::Verified),
+        vec![6, 7, 8, 9]
+    );
+    assert_eq!(heights_with_status(&state, Status::Failed), vec![5]);
+}
+
+#[test]
+fn backward_verification_fails_on_mismatching_validator_set() {
+    let mut blocks = make_chain(10);
+
+    // The validator set of the block at height 5 is not covered by the hash link,
+    // and does not match the `validators_hash` of its header anymore
+    let validators = blocks[4].validators.validators()[1..].to_vec();
+    blocks[4].validators = ValidatorSet::without_proposer(validators);
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let (result, state) = verify_chain_backward(blocks, 3, now);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::InvalidLightBlock(VerificationError::InvalidValidatorSet { .. })
+    ));
+
+    assert_eq!(
+        heights_with_status(&state, Status::Verified),
+        vec![6, 7, 8, 9]
+    );
+    assert_eq!(heights_with_status(&state, Status::Failed), vec![5]);
+}
+
+#[test]
+fn backward_verification_fails_on_mismatching_commit() {
+    let mut blocks = make_chain(10);
+
+    // The commit of the block at height 5 is for another header
+    blocks[4].signed_header.commit = blocks[3].signed_header.commit.clone();
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let (result, state) = verify_chain_backward(blocks, 3, now);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::InvalidLightBlock(VerificationError::InvalidCommitValue { .. })
+    ));
+
+    assert_eq!(
+        heights_with_status(&state, Status::Verified),
+        vec![6, 7, 8, 9]
+    );
+    assert_eq!(heights_with_status(&state, Status::Failed), vec![5]);
+}
+
+#[test]
+fn backward_verification_fails_on_expired_trusted_root() {
+    let blocks = make_chain(10);
+
+    // The trusting period of the last block is over
+    let now = blocks.last().unwrap().signed_header.header.time + Duration::from_secs(2 * 60 * 60);
+
+    let (result, state) = verify_chain_backward(blocks, 3, now);
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::TrustedStateOutsideTrustingPeriod { .. }
+    ));
+
+    assert_eq!(heights_with_status(&state, Status::Trusted), vec![10]);
+    assert!(heights_with_status(&state, Status::Verified).is_empty());
+    assert!(heights_with_status(&state, Status::Failed).is_empty());
+}
+
+/// Generate a chain of the given length, whose validators entirely change at `change_height`.
+fn make_changing_chain(length: u64, change_height: u64) -> Vec<LightBlock> {
+    let old_vals = [
+        Validator::new("1").voting_power(50),
+        Validator::new("2").voting_power(50),
+    ];
+    let new_vals = [
+        Validator::new("3").voting_power(50),
+        Validator::new("4").voting_power(50),
+    ];
+
+    (1..=length)
+        .map(|height| {
+            let vals = if height < change_height {
+                &old_vals
+            } else {
+                &new_vals
+            };
+            let next_vals = if height + 1 < change_height {
+                &old_vals
+            } else {
+                &new_vals
+            };
+
+            let header = Header::new(vals)
+                .next_validators(next_vals)
+                .chain_id("test-chain")
+                .height(height)
+                .time(height);
+            let commit = Commit::new(header.clone(), 1);
+            let lb = TestgenLightBlock::new(header, commit)
+                .validators(vals)
+                .next_validators(next_vals)
+                .generate()
+                .unwrap();
+
+            LightBlock::new(
+                lb.signed_header,
+                lb.validators,
+                lb.next_validators,
+                default_peer_id(),
+            )
+        })
+        .collect()
+}
+
+/// `Io` component which records the heights of the light blocks fetched in each batch.
+#[derive(Clone)]
+struct RecordingIo {
+    io: MockIo,
+    batches: Arc<Mutex<Vec<Vec<u64>>>>,
+}
+
+impl Io for RecordingIo {
+    fn fetch_light_block(&self, height: AtHeight) -> Result<LightBlock, IoError> {
+        if let AtHeight::At(height) = height {
+            self.batches.lock().unwrap().push(vec![height.value()]);
+        }
+        self.io.fetch_light_block(height)
+    }
+
+    fn fetch_light_blocks(&self, heights: Vec<Height>) -> Vec<Result<LightBlock, IoError>> {
+        self.batches
+            .lock()
+            .unwrap()
+            .push(heights.iter().map(|h| h.value()).collect());
+
+        heights
+            .into_iter()
+            .map(|height| self.io.fetch_light_block(AtHeight::At(height)))
+            .collect()
+    }
+}
+
+/// Verify the given chain up to its last block with bisection and the given prefetch depth,
+/// and return the heights of the light blocks fetched in each batch.
+fn fetched_batches(blocks: Vec<LightBlock>, prefetch_depth: usize) -> Vec<Vec<u64>> {
+    let target_height = blocks.last().unwrap().height();
+    let now = blocks.last().unwrap().signed_header.header.time;
+
+    let options = Options {
+        trust_threshold: Default::default(),
+        trusting_period: Duration::from_secs(60 * 60),
+        clock_drift: Duration::from_secs(10),
+        verification_mode: VerificationMode::Skipping,
+    };
+
+    let mut light_store = MemoryStore::new();
+    light_store.insert(blocks[0].clone(), Status::Trusted);
+    let mut state = State::new(light_store);
+
+    let io = RecordingIo {
+        io: MockIo::new("test-chain".to_string(), blocks),
+        batches: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let light_client = LightClient::new(
+        default_peer_id(),
+        options,
+        MockClock { now },
+        scheduler::basic_bisecting_schedule,
+        ProdVerifier::default(),
+        ProdHasher,
+        io.clone(),
+    )
+    .with_prefetch_depth(prefetch_depth);
+
+    light_client
+        .verify_to_target(target_height, &mut state)
+        .unwrap();
+
+    let batches = io.batches.lock().unwrap().clone();
+    batches
+}
+
+#[test]
+fn bisection_prefetches_pivot_heights() {
+    let blocks = make_changing_chain(10, 5);
+
+    // Without prefetching, the pivots are fetched one at a time
+    assert_eq!(
+        fetched_batches(blocks.clone(), 0),
+        vec![vec![10], vec![6], vec![4]]
+    );
+
+    // With prefetching, the pivots are fetched along with the target block
+    assert_eq!(fetched_batches(blocks, 3), vec![vec![10, 6, 4, 3]]);
+}
@@ -1,19 +1,27 @@
 use tendermint_light_client::{
     components::{
-        io::{AtHeight, Io},
+        io::{AtHeight, Io, IoError},
         scheduler,
         verifier::ProdVerifier,
     },
-    fork_detector::ProdForkDetector,
-    light_client::{self, LightClient},
-    peer_list::PeerList,
+    errors::{ErrorExt, ErrorKind},
+    evidence::AttackType,
+    fork_detector::{Fork, ForkDetection, ForkDetector, ProdForkDetector},
+    light_client::{self, AsyncLightClient, LightClient},
+    operations::ProdHasher,
+    peer_list::{PeerList, PeerRole, ProbationPolicy},
     state::State,
-    store::LightStore,
-    supervisor::{Handle, Instance, Supervisor},
+    store::{LightStore, RetentionPolicy},
+    supervisor::{
+        AsyncHandle, AsyncInstance, AsyncSupervisor, Event, Handle, Instance, Supervisor,
+    },
     types::{LightBlock, PeerId, Status, Time},
 };
 
+use futures::StreamExt;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use tendermint_light_client::store::memory::MemoryStore;
@@ -21,11 +29,23 @@ use tendermint_light_client::tests::{
     MockClock, MockEvidenceReporter, MockIo, TestBisection, TrustOptions,
 };
 
-use tendermint_testgen::Tester;
+use tendermint::evidence::Duration as DurationStr;
+use tendermint::trust_threshold::TrustThresholdFraction;
+use tendermint_testgen::{
+    light_block::LightBlock as TestgenLightBlock, Commit, Generator, Header as TestgenHeader,
+    Tester, Validator,
+};
+
+use std::convert::TryInto;
 
 const TEST_FILES_PATH: &str = "./tests/support/";
 
-fn make_instance(peer_id: PeerId, trust_options: TrustOptions, io: MockIo, now: Time) -> Instance {
+fn make_instance(
+    peer_id: PeerId,
+    trust_options: TrustOptions,
+    io: impl Io + 'static,
+    now: Time,
+) -> Instance {
     let trusted_height = trust_options.height;
     let trusted_state = io
         .fetch_light_block(AtHeight::At(trusted_height))
@@ -37,23 +57,51 @@ fn make_instance(peer_id: PeerId, trust_options: TrustOptions, io: MockIo, now:
     let state = State {
         light_store: Box::new(light_store),
         verification_trace: HashMap::new(),
+        trace_store: None,
     };
 
     let options = light_client::Options {
         trust_threshold: trust_options.trust_level,
         trusting_period: trust_options.period.into(),
         clock_drift: Duration::from_secs(10),
+        verification_mode: light_client::VerificationMode::default(),
     };
 
     let verifier = ProdVerifier::default();
     let clock = MockClock { now };
     let scheduler = scheduler::basic_bisecting_schedule;
 
-    let light_client = LightClient::new(peer_id, options, clock, scheduler, verifier, io);
+    let hasher = ProdHasher;
+
+    let light_client = LightClient::new(peer_id, options, clock, scheduler, verifier, hasher, io);
 
     Instance::new(light_client, state)
 }
 
+fn make_async_instance(
+    peer_id: PeerId,
+    trust_options: TrustOptions,
+    io: MockIo,
+    now: Time,
+) -> AsyncInstance {
+    let Instance {
+        light_client,
+        state,
+    } = make_instance(peer_id, trust_options.clone(), io.clone(), now);
+
+    let light_client = AsyncLightClient::new(
+        peer_id,
+        light_client.options,
+        MockClock { now },
+        scheduler::basic_bisecting_schedule,
+        ProdVerifier::default(),
+        ProdHasher,
+        io,
+    );
+
+    AsyncInstance::new(light_client, state)
+}
+
 fn run_multipeer_test(tc: TestBisection<LightBlock>) {
     let primary = tc.primary.lite_blocks[0].provider;
 
@@ -108,10 +156,7 @@ fn run_multipeer_test(tc: TestBisection<LightBlock>) {
             // Check the verdict
             assert!(!expects_err);
         }
-        Err(e) => {
-            dbg!(e);
-            assert!(expects_err);
-        }
+        Err(e) => assert!(expects_err, "unexpected error: {}", e),
     }
 
     // TODO: Check the peer list
@@ -125,3 +170,779 @@ fn run_multipeer_tests() {
     tester.run_foreach_in_dir("bisection/multi_peer");
     tester.finalize();
 }
+
+fn run_async_multipeer_test(tc: TestBisection<LightBlock>) {
+    let primary = tc.primary.lite_blocks[0].provider;
+
+    let expects_err = match &tc.expected_output {
+        Some(eo) => eo.eq("error"),
+        None => false,
+    };
+
+    let io = MockIo::new(tc.primary.chain_id, tc.primary.lite_blocks);
+    let primary_instance =
+        make_async_instance(primary, tc.trust_options.clone(), io.clone(), tc.now);
+
+    let mut peer_list = PeerList::builder();
+    peer_list.primary(primary, primary_instance);
+
+    for provider in tc.witnesses.into_iter() {
+        let peer_id = provider.value.lite_blocks[0].provider;
+        let io = MockIo::new(provider.value.chain_id, provider.value.lite_blocks);
+        let instance = make_async_instance(peer_id, tc.trust_options.clone(), io, tc.now);
+        peer_list.witness(peer_id, instance);
+    }
+
+    let supervisor = AsyncSupervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    );
+
+    let handle = supervisor.handle();
+    let target_height = tc.height_to_verify;
+
+    // The supervisor must be able to run as a task on a multi-threaded executor
+    let run = supervisor.run();
+    assert_send(&run);
+
+    // Both the supervisor and the handle run on the same thread,
+    // which requires that neither of them blocks.
+    let (result, supervisor_result) = futures::executor::block_on(async {
+        futures::join!(
+            async {
+                let result = handle.verify_to_target(target_height).await;
+                handle.terminate().await.unwrap();
+                result
+            },
+            run
+        )
+    });
+
+    supervisor_result.unwrap();
+
+    match result {
+        Ok(new_state) => {
+            let expected_state = io
+                .fetch_light_block(AtHeight::At(target_height))
+                .expect("header at untrusted height not found");
+
+            assert_eq!(new_state, expected_state);
+            assert!(!expects_err);
+        }
+        Err(e) => assert!(expects_err, "unexpected error: {}", e),
+    }
+}
+
+fn assert_send<T: Send>(_: &T) {}
+
+#[test]
+fn run_async_multipeer_tests() {
+    let mut tester = Tester::new("bisection_multi_peer_async", TEST_FILES_PATH);
+    tester.add_test("async multipeer test", run_async_multipeer_test);
+    tester.run_foreach_in_dir("bisection/multi_peer");
+    tester.finalize();
+}
+
+/// Generates a chain of 10 blocks whose validator set changes at height 5,
+/// such that verifying height 10 from height 1 requires bisection.
+/// If `diverge_at` is given, the blocks from that height on are proposed by another validator.
+fn make_bisection_chain(provider: PeerId, diverge_at: Option<u64>) -> Vec<LightBlock> {
+    let old_vals = [
+        Validator::new("1").voting_power(50),
+        Validator::new("2").voting_power(50),
+    ];
+    let new_vals = [
+        Validator::new("3").voting_power(50),
+        Validator::new("4").voting_power(50),
+    ];
+
+    let mut last_block_id_hash = None;
+    let mut blocks = Vec::new();
+
+    for height in 1..=10 {
+        let vals = if height < 5 { &old_vals } else { &new_vals };
+        let next_vals = if height < 4 { &old_vals } else { &new_vals };
+        let proposer = match diverge_at {
+            Some(diverge_at) if height >= diverge_at => 1,
+            _ => 0,
+        };
+
+        let mut header = TestgenHeader::new(vals)
+            .next_validators(next_vals)
+            .chain_id("test-chain")
+            .height(height)
+            .time(height)
+            .proposer(proposer);
+
+        if let Some(hash) = last_block_id_hash {
+            header = header.last_block_id_hash(hash);
+        }
+
+        let commit = Commit::new(header.clone(), 1);
+        let tm_lb = TestgenLightBlock::new(header, commit)
+            .validators(vals)
+            .next_validators(next_vals)
+            .generate()
+            .unwrap();
+
+        last_block_id_hash = Some(tm_lb.signed_header.header.hash());
+
+        blocks.push(LightBlock::new(
+            tm_lb.signed_header,
+            tm_lb.validators,
+            tm_lb.next_validators,
+            provider,
+        ));
+    }
+
+    blocks
+}
+
+#[test]
+fn fork_detector_finds_bifurcation_point() {
+    let divergence_height = 7;
+
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let primary_id = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let primary_blocks = make_bisection_chain(primary_id, None);
+    let io = MockIo::new("test-chain".to_string(), primary_blocks);
+    let mut primary_instance = make_instance(primary_id, trust_options.clone(), io, now);
+
+    let witness_id = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+    let witness_blocks = make_bisection_chain(witness_id, Some(divergence_height));
+    let io = MockIo::new("test-chain".to_string(), witness_blocks);
+    let witness_instance = make_instance(witness_id, trust_options, io, now);
+
+    let trusted_block = primary_instance.latest_trusted().unwrap();
+    let verified_block = primary_instance
+        .light_client
+        .verify_to_target(10_u64.try_into().unwrap(), &mut primary_instance.state)
+        .unwrap();
+    let primary_trace = primary_instance.state.get_trace(verified_block.height());
+
+    // Bisection must have gone through intermediate blocks for this test to be meaningful
+    assert!(primary_trace.len() >= 2);
+
+    let outcome = ProdForkDetector::default()
+        .detect_forks(
+            &verified_block,
+            &trusted_block,
+            primary_trace.clone(),
+            vec![&witness_instance],
+        )
+        .unwrap();
+
+    let forks = match outcome {
+        ForkDetection::Detected(forks) => forks,
+        ForkDetection::NotDetected => panic!("expected a fork to be detected"),
+    };
+
+    match &forks[..] {
+        [Fork::Forked {
+            primary,
+            witness,
+            common,
+            attack,
+        }] => {
+            let divergence_height = divergence_height.try_into().unwrap();
+
+            assert_eq!(primary.height(), witness.height());
+            assert!(primary.height() >= divergence_height);
+            assert_ne!(primary.signed_header.header, witness.signed_header.header);
+            assert!(primary_trace.contains(primary));
+
+            assert!(common.height() < divergence_height);
+            assert!(common.height() > trusted_block.height());
+            assert!(primary_trace
+                .iter()
+                .any(|lb| lb.signed_header.header == common.signed_header.header));
+
+            // Both chains are signed by the same validators in the same round
+            assert_eq!(attack.attack_type, AttackType::Equivocation);
+            assert_eq!(attack.common_height, common.height());
+            assert_eq!(attack.conflicting_height, primary.height());
+            assert_eq!(attack.byzantine_validators.len(), 2);
+        }
+        forks => panic!("expected a single fork, got: {:?}", forks),
+    }
+}
+
+#[test]
+fn supervisor_prunes_light_stores() {
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let mut peer_list = PeerList::builder();
+    for (i, peer_id) in [
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    ]
+    .iter()
+    .enumerate()
+    {
+        let peer_id = peer_id.parse().unwrap();
+        let io = MockIo::new(
+            "test-chain".to_string(),
+            make_bisection_chain(peer_id, None),
+        );
+        let instance = make_instance(peer_id, trust_options.clone(), io, now);
+
+        if i == 0 {
+            peer_list.primary(peer_id, instance);
+        } else {
+            peer_list.witness(peer_id, instance);
+        }
+    }
+
+    let policy = RetentionPolicy {
+        max_trusted_blocks: Some(1),
+        ..RetentionPolicy::default()
+    };
+
+    let mut supervisor = Supervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    )
+    .with_retention_policy(policy, Duration::from_secs(60));
+
+    let verified_block = supervisor
+        .verify_to_target(10_u64.try_into().unwrap())
+        .unwrap();
+
+    assert!(supervisor.prune().unwrap() > 0);
+    assert_eq!(supervisor.prune().unwrap(), 0);
+    assert_eq!(supervisor.latest_trusted(), Some(verified_block));
+}
+
+#[test]
+fn async_supervisor_prunes_light_stores_periodically() {
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let mut peer_list = PeerList::builder();
+    for (i, peer_id) in [
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    ]
+    .iter()
+    .enumerate()
+    {
+        let peer_id = peer_id.parse().unwrap();
+        let io = MockIo::new(
+            "test-chain".to_string(),
+            make_bisection_chain(peer_id, None),
+        );
+        let instance = make_async_instance(peer_id, trust_options.clone(), io, now);
+
+        if i == 0 {
+            peer_list.primary(peer_id, instance);
+        } else {
+            peer_list.witness(peer_id, instance);
+        }
+    }
+
+    let policy = RetentionPolicy {
+        max_trusted_blocks: Some(1),
+        ..RetentionPolicy::default()
+    };
+
+    let supervisor = AsyncSupervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    )
+    .with_retention_policy(policy, Duration::from_millis(10));
+    let handle = supervisor.handle();
+
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    let run = supervisor.run();
+    let ((verified_block, initial, pruned, latest), supervisor_result) = runtime.block_on(async {
+        futures::join!(
+            async {
+                let verified_block = handle
+                    .verify_to_target(10_u64.try_into().unwrap())
+                    .await
+                    .unwrap();
+                let initial = handle.trusted_block(1_u64.try_into().unwrap()).await;
+
+                // Let the supervisor prune the light stores in the meantime
+                tokio::time::delay_for(Duration::from_millis(50)).await;
+
+                let pruned = handle.trusted_block(1_u64.try_into().unwrap()).await;
+                let latest = handle.latest_trusted().await;
+                handle.terminate().await.unwrap();
+
+                (verified_block, initial, pruned, latest)
+            },
+            run
+        )
+    });
+
+    supervisor_result.unwrap();
+    assert!(initial.unwrap().is_some());
+    assert_eq!(pruned.unwrap(), None);
+    assert_eq!(latest.unwrap(), Some(verified_block));
+}
+
+/// A supervisor with an honest primary and a single witness, whose chain diverges
+/// from the one of the primary at the given height, if any.
+fn make_supervisor(witness_diverge_at: Option<u64>) -> Supervisor {
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let primary_id = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let io = MockIo::new(
+        "test-chain".to_string(),
+        make_bisection_chain(primary_id, None),
+    );
+    let primary_instance = make_instance(primary_id, trust_options.clone(), io, now);
+
+    let witness_id = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+    let io = MockIo::new(
+        "test-chain".to_string(),
+        make_bisection_chain(witness_id, witness_diverge_at),
+    );
+    let witness_instance = make_instance(witness_id, trust_options, io, now);
+
+    let mut peer_list = PeerList::builder();
+    peer_list.primary(primary_id, primary_instance);
+    peer_list.witness(witness_id, witness_instance);
+
+    Supervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    )
+}
+
+#[test]
+fn backward_targets_are_not_cross_checked_with_witnesses() {
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 10_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let primary_id = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let io = MockIo::new(
+        "test-chain".to_string(),
+        make_bisection_chain(primary_id, None),
+    );
+    let primary_instance = make_instance(primary_id, trust_options.clone(), io, now);
+
+    // The blocks of the witness below the trusted height are not linked to the trusted block
+    // of the primary, so that the witness would be deemed faulty if it was asked to verify them
+    let witness_id = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+    let io = MockIo::new(
+        "test-chain".to_string(),
+        make_bisection_chain(witness_id, Some(3)),
+    );
+    let witness_instance = make_instance(witness_id, trust_options, io, now);
+
+    let mut peer_list = PeerList::builder();
+    peer_list.primary(primary_id, primary_instance);
+    peer_list.witness(witness_id, witness_instance);
+
+    let mut supervisor = Supervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    );
+    let subscription = supervisor.subscribe();
+
+    let target = 5_u64.try_into().unwrap();
+    let verified_block = supervisor.verify_to_target(target).unwrap();
+    assert_eq!(verified_block.height(), target);
+    assert_eq!(verified_block.provider, primary_id);
+    assert_eq!(
+        supervisor.trusted_block(target),
+        Some(verified_block.clone())
+    );
+
+    // The latest trusted block is left untouched
+    assert_eq!(
+        supervisor.latest_trusted().map(|lb| lb.height().value()),
+        Some(10)
+    );
+
+    // The witness is neither removed nor reported
+    assert_eq!(
+        subscription.try_recv(),
+        Some(Event::NewTrustedBlock(Box::new(verified_block)))
+    );
+    assert_eq!(subscription.try_recv(), None);
+    assert_eq!(supervisor.fork_reports(), vec![]);
+
+    let roles: Vec<_> = supervisor
+        .witnesses()
+        .into_iter()
+        .map(|status| (status.peer_id, status.role))
+        .collect();
+    assert_eq!(
+        roles,
+        vec![
+            (primary_id, PeerRole::Primary),
+            (witness_id, PeerRole::Witness)
+        ]
+    );
+}
+
+#[test]
+fn subscribers_receive_new_trusted_blocks() {
+    let supervisor = make_supervisor(None);
+    let handle = supervisor.handle();
+    std::thread::spawn(|| supervisor.run());
+
+    let subscription = handle.subscribe().unwrap();
+    let target = 10_u64.try_into().unwrap();
+
+    let verified_block = handle.verify_to_target(target).unwrap();
+    assert_eq!(
+        subscription.try_recv(),
+        Some(Event::NewTrustedBlock(Box::new(verified_block)))
+    );
+
+    // The block is already trusted
+    handle.verify_to_target(target).unwrap();
+    assert_eq!(subscription.try_recv(), None);
+
+    // The subscription ends with the supervisor
+    handle.terminate().unwrap();
+    assert_eq!(subscription.recv(), None);
+}
+
+#[test]
+fn subscribers_are_notified_of_forks() {
+    let mut supervisor = make_supervisor(Some(7));
+    let subscription = supervisor.subscribe();
+
+    let result = supervisor.verify_to_target(10_u64.try_into().unwrap());
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::ForkDetected { .. }
+    ));
+
+    let primary_id = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let witness_id = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+
+    match subscription.try_recv() {
+        Some(Event::ForkDetected {
+            primary,
+            witness,
+            attack,
+        }) => {
+            assert_eq!(primary, primary_id);
+            assert_eq!(witness, witness_id);
+            assert_eq!(attack.attack_type, AttackType::Equivocation);
+        }
+        event => panic!("expected a fork to be detected, got: {:?}", event),
+    }
+
+    // Evidence is reported to the witness, then to the primary
+    let peers: Vec<_> = std::iter::from_fn(|| subscription.try_recv())
+        .map(|event| match event {
+            Event::EvidenceReported { peer, .. } => peer,
+            event => panic!("expected evidence to be reported, got: {:?}", event),
+        })
+        .collect();
+    assert_eq!(peers, vec![witness_id, primary_id]);
+}
+
+#[test]
+fn handle_reports_trusted_blocks_peers_and_forks() {
+    let supervisor = make_supervisor(Some(7));
+    let handle = supervisor.handle();
+    std::thread::spawn(|| supervisor.run());
+
+    let primary_id = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let witness_id = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+
+    // Below the divergence point, the witness agrees with the primary
+    let verified_block = handle.verify_to_target(5_u64.try_into().unwrap()).unwrap();
+    assert_eq!(
+        handle.trusted_block(5_u64.try_into().unwrap()).unwrap(),
+        Some(verified_block)
+    );
+    assert_eq!(
+        handle.trusted_block(9_u64.try_into().unwrap()).unwrap(),
+        None
+    );
+    assert_eq!(handle.fork_reports().unwrap(), vec![]);
+
+    let roles: Vec<_> = handle
+        .witnesses()
+        .unwrap()
+        .into_iter()
+        .map(|status| (status.peer_id, status.role))
+        .collect();
+    assert_eq!(
+        roles,
+        vec![
+            (primary_id, PeerRole::Primary),
+            (witness_id, PeerRole::Witness)
+        ]
+    );
+
+    let result = handle.verify_to_target(10_u64.try_into().unwrap());
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::ForkDetected { .. }
+    ));
+
+    let reports = handle.fork_reports().unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].primary, primary_id);
+    assert_eq!(reports[0].witness, witness_id);
+    assert_eq!(reports[0].attack.attack_type, AttackType::Equivocation);
+
+    handle.terminate().unwrap();
+}
+
+#[test]
+fn async_subscribers_receive_new_trusted_blocks() {
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let mut peer_list = PeerList::builder();
+    for (i, peer_id) in [
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    ]
+    .iter()
+    .enumerate()
+    {
+        let peer_id = peer_id.parse().unwrap();
+        let io = MockIo::new(
+            "test-chain".to_string(),
+            make_bisection_chain(peer_id, None),
+        );
+        let instance = make_async_instance(peer_id, trust_options.clone(), io, now);
+
+        if i == 0 {
+            peer_list.primary(peer_id, instance);
+        } else {
+            peer_list.witness(peer_id, instance);
+        }
+    }
+
+    let supervisor = AsyncSupervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    );
+    let handle = supervisor.handle();
+
+    let run = supervisor.run();
+    let ((verified_block, events), supervisor_result) = futures::executor::block_on(async {
+        futures::join!(
+            async {
+                let subscription = handle.subscribe().await.unwrap();
+                let verified_block = handle
+                    .verify_to_target(10_u64.try_into().unwrap())
+                    .await
+                    .unwrap();
+                handle.terminate().await.unwrap();
+
+                // The subscription ends with the supervisor
+                let events: Vec<Event> = subscription.collect().await;
+                (verified_block, events)
+            },
+            run
+        )
+    });
+
+    supervisor_result.unwrap();
+    assert_eq!(
+        events,
+        vec![Event::NewTrustedBlock(Box::new(verified_block))]
+    );
+}
+
+#[test]
+fn witnesses_can_be_managed_at_runtime() {
+    let supervisor = make_supervisor(None);
+    let handle = supervisor.handle();
+    std::thread::spawn(|| supervisor.run());
+
+    let primary_id: PeerId = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let witness_id: PeerId = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+    let new_witness_id: PeerId = "cccccccccccccccccccccccccccccccccccccccc".parse().unwrap();
+
+    handle.verify_to_target(5_u64.try_into().unwrap()).unwrap();
+
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+    let make_new_witness = || {
+        let io = MockIo::new(
+            "test-chain".to_string(),
+            make_bisection_chain(new_witness_id, None),
+        );
+        make_instance(new_witness_id, trust_options.clone(), io, now)
+    };
+    let address: tendermint::net::Address = "tcp://127.0.0.1:26657".parse().unwrap();
+
+    handle
+        .add_witness(new_witness_id, address.clone(), make_new_witness())
+        .unwrap();
+    let err = handle
+        .add_witness(new_witness_id, address, make_new_witness())
+        .unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::PeerAlreadyExists(new_witness_id));
+
+    let status = handle.latest_status().unwrap();
+    assert_eq!(
+        status.connected_nodes,
+        vec![primary_id, witness_id, new_witness_id]
+    );
+
+    // The new primary carries on from the latest block trusted by the previous one
+    handle.promote_to_primary(new_witness_id).unwrap();
+    let status = handle.latest_status().unwrap();
+    assert_eq!(status.height, Some(5));
+    assert_eq!(
+        status.connected_nodes,
+        vec![new_witness_id, primary_id, witness_id]
+    );
+
+    let verified_block = handle.verify_to_target(10_u64.try_into().unwrap()).unwrap();
+    assert_eq!(verified_block.provider, new_witness_id);
+
+    handle.remove_witness(witness_id).unwrap();
+    let err = handle.remove_witness(witness_id).unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::PeerNotFound(witness_id));
+
+    let status = handle.latest_status().unwrap();
+    assert_eq!(status.connected_nodes, vec![new_witness_id, primary_id]);
+
+    handle.terminate().unwrap();
+}
+
+#[test]
+fn unresponsive_witnesses_are_readmitted_after_probation() {
+    let trust_options = TrustOptions {
+        period: DurationStr(Duration::from_secs(3600)),
+        height: 1_u64.try_into().unwrap(),
+        hash: tendermint::abci::transaction::Hash::new([0; 32]),
+        trust_level: TrustThresholdFraction::default(),
+    };
+    let now = (std::time::UNIX_EPOCH + Duration::from_secs(20)).into();
+
+    let primary_id = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".parse().unwrap();
+    let io = MockIo::new(
+        "test-chain".to_string(),
+        make_bisection_chain(primary_id, None),
+    );
+    let primary_instance = make_instance(primary_id, trust_options.clone(), io, now);
+
+    // The witness times out while it is down
+    let witness_id = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".parse().unwrap();
+    let witness_down = Arc::new(AtomicBool::new(false));
+    let io = {
+        let mock_io = MockIo::new(
+            "test-chain".to_string(),
+            make_bisection_chain(witness_id, None),
+        );
+        let witness_down = witness_down.clone();
+
+        move |height| {
+            if witness_down.load(Ordering::SeqCst) {
+                Err(IoError::Timeout(Duration::from_secs(1)))
+            } else {
+                mock_io.fetch_light_block(height)
+            }
+        }
+    };
+    let witness_instance = make_instance(witness_id, trust_options, io, now);
+
+    let mut peer_list = PeerList::builder();
+    peer_list.primary(primary_id, primary_instance);
+    peer_list.witness(witness_id, witness_instance);
+    peer_list.probation_policy(ProbationPolicy {
+        initial_backoff: Duration::from_secs(0),
+        max_backoff: Duration::from_secs(0),
+    });
+
+    let mut supervisor = Supervisor::new(
+        peer_list.build(),
+        ProdForkDetector::default(),
+        MockEvidenceReporter::new(),
+    );
+    let subscription = supervisor.subscribe();
+
+    witness_down.store(true, Ordering::SeqCst);
+    let result = supervisor.verify_to_target(5_u64.try_into().unwrap());
+    assert_eq!(result.unwrap_err().kind(), &ErrorKind::NoWitnesses);
+
+    match subscription.try_recv() {
+        Some(Event::WitnessRemoved {
+            witness,
+            replacement,
+            reason,
+        }) => {
+            assert_eq!(witness, witness_id);
+            assert_eq!(replacement, None);
+            assert!(reason.is_timeout());
+        }
+        event => panic!("expected the witness to be removed, got: {:?}", event),
+    }
+
+    // The witness is still down when its probation is over
+    let result = supervisor.verify_to_target(5_u64.try_into().unwrap());
+    assert_eq!(result.unwrap_err().kind(), &ErrorKind::NoWitnesses);
+    assert_eq!(subscription.try_recv(), None);
+
+    // The witness is back up, and is re-admitted
+    witness_down.store(false, Ordering::SeqCst);
+    let verified_block = supervisor
+        .verify_to_target(10_u64.try_into().unwrap())
+        .unwrap();
+    assert_eq!(
+        subscription.try_recv(),
+        Some(Event::PeerReadmitted { peer: witness_id })
+    );
+    assert_eq!(
+        subscription.try_recv(),
+        Some(Event::NewTrustedBlock(Box::new(verified_block)))
+    );
+}
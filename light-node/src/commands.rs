@@ -1,18 +1,23 @@
 //! LightNode Subcommands
 //!
 //! The light client supports the following subcommands:
-//! - `initialize`: subjectively initializes the light node with a given height and hash
+//! - `initialize`: subjectively initializes the light node with a given height and hash,
+//!   or with a checkpoint which the primary and all the witnesses agree with
 //! - `start`: launches the light client
+//! - `export`: exports the trusted state of the primary to a snapshot file
+//! - `import`: imports the trusted state of a snapshot file into all light clients
 //! - `version`: print application version
 //!
 //! See the `impl Configurable` below for how to specify the path to the
 //! application's configuration file.
 
+mod export;
+mod import;
 mod initialize;
 mod start;
 mod version;
 
-use self::{start::StartCmd, version::VersionCmd};
+use self::{export::ExportCmd, import::ImportCmd, start::StartCmd, version::VersionCmd};
 use crate::commands::initialize::InitCmd;
 use crate::config::LightNodeConfig;
 use abscissa_core::{
@@ -40,6 +45,14 @@ pub enum LightNodeCmd {
     #[options(help = "start the light node daemon with the given config or command line params")]
     Start(StartCmd),
 
+    /// `export` the trusted state of the light node
+    #[options(help = "export the trusted state of the primary to a snapshot file")]
+    Export(ExportCmd),
+
+    /// `import` a trusted state into the light node
+    #[options(help = "import the trusted state of a snapshot file into all light clients of its chain")]
+    Import(ImportCmd),
+
     /// `version` of the light node
     #[options(help = "display version information")]
     Version(VersionCmd),
@@ -0,0 +1,98 @@
+//! `export` subcommand
+
+use std::ops::Deref;
+
+use crate::application::app_config;
+use crate::config::LightNodeConfig;
+
+use abscissa_core::path::PathBuf;
+use abscissa_core::status_err;
+use abscissa_core::status_info;
+use abscissa_core::Command;
+use abscissa_core::Options;
+use abscissa_core::Runnable;
+
+use tendermint::chain;
+use tendermint_light_client::snapshot::{Snapshot, SnapshotFormat};
+use tendermint_light_client::store::sled::SledStore;
+
+/// `export` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct ExportCmd {
+    #[options(free, required, help = "path of the snapshot file to write")]
+    pub path: PathBuf,
+
+    #[options(
+        short = "f",
+        long = "format",
+        help = "format of the snapshot: json or cbor"
+    )]
+    pub format: SnapshotFormat,
+
+    #[options(
+        short = "n",
+        long = "max-blocks",
+        meta = "N",
+        help = "maximum number of trusted light blocks to export (default: 1)"
+    )]
+    pub max_blocks: Option<usize>,
+
+    #[options(
+        no_short,
+        long = "chain",
+        meta = "ID",
+        help = "identifier of the chain to export, if several are configured"
+    )]
+    pub chain: Option<chain::Id>,
+}
+
+impl Runnable for ExportCmd {
+    fn run(&self) {
+        let node_config = app_config().deref().clone();
+
+        match self.export(&node_config) {
+            Ok(snapshot) => status_info!(
+                "exported",
+                "{} light block(s) up to height {} to {}",
+                snapshot.light_blocks.len(),
+                snapshot.light_blocks.last().unwrap().height(), // Cannot fail, see `Snapshot::export`
+                self.path.display()
+            ),
+            Err(e) => {
+                status_err!("failed to export snapshot: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl ExportCmd {
+    /// Export the trusted state of the primary of the selected chain to the snapshot file.
+    fn export(&self, node_config: &LightNodeConfig) -> Result<Snapshot, String> {
+        let chain = node_config.chain(self.chain.as_ref())?;
+        let config = chain
+            .light_clients
+            .first()
+            .ok_or_else(|| format!("no light client configured for chain {}", chain.id))?;
+
+        let db = sled::open(config.db_path.clone())
+            .map_err(|e| format!("could not open database: {}", e))?;
+
+        let light_store = SledStore::new(db);
+
+        let snapshot = Snapshot::export(&light_store, chain.into(), self.max_blocks.unwrap_or(1))
+            .map_err(|e| e.to_string())?;
+
+        let bytes = snapshot.encode(self.format).map_err(|e| e.to_string())?;
+
+        std::fs::write(&self.path, bytes).map_err(|e| {
+            format!(
+                "could not write snapshot file {}: {}",
+                self.path.display(),
+                e
+            )
+        })?;
+
+        Ok(snapshot)
+    }
+}
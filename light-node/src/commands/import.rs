@@ -0,0 +1,100 @@
+//! `import` subcommand
+
+use std::ops::Deref;
+
+use crate::application::app_config;
+use crate::config::LightNodeConfig;
+
+use abscissa_core::path::PathBuf;
+use abscissa_core::status_err;
+use abscissa_core::status_info;
+use abscissa_core::status_warn;
+use abscissa_core::Command;
+use abscissa_core::Options;
+use abscissa_core::Runnable;
+
+use tendermint_light_client::light_client;
+use tendermint_light_client::snapshot::{Snapshot, SnapshotFormat};
+use tendermint_light_client::store::sled::SledStore;
+use tendermint_light_client::store::LightStore;
+
+/// `import` subcommand
+#[derive(Command, Debug, Default, Options)]
+pub struct ImportCmd {
+    #[options(free, required, help = "path of the snapshot file to read")]
+    pub path: PathBuf,
+
+    #[options(
+        short = "f",
+        long = "format",
+        help = "format of the snapshot: json or cbor"
+    )]
+    pub format: SnapshotFormat,
+}
+
+impl Runnable for ImportCmd {
+    fn run(&self) {
+        let node_config = app_config().deref().clone();
+
+        match self.import(&node_config) {
+            Ok(snapshot) => status_info!(
+                "imported",
+                "{} light block(s) up to height {} of chain {}",
+                snapshot.light_blocks.len(),
+                snapshot.light_blocks.last().unwrap().height(), // Cannot fail, see `Snapshot::validate`
+                snapshot.chain_id
+            ),
+            Err(e) => {
+                status_err!("failed to import snapshot: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+impl ImportCmd {
+    /// Import the snapshot file into the light stores of all the light clients of its chain,
+    /// once it has been checked for consistency.
+    fn import(&self, node_config: &LightNodeConfig) -> Result<Snapshot, String> {
+        let bytes = std::fs::read(&self.path).map_err(|e| {
+            format!(
+                "could not read snapshot file {}: {}",
+                self.path.display(),
+                e
+            )
+        })?;
+
+        let snapshot = Snapshot::decode(&bytes, self.format).map_err(|e| e.to_string())?;
+
+        let chain = node_config.chain(Some(&snapshot.chain_id))?;
+
+        let options: light_client::Options = chain.into();
+        if snapshot.options != options {
+            status_warn!(
+                "the options of the snapshot differ from the configured ones, which will be used: {}",
+                snapshot.options
+            );
+        }
+
+        for config in &chain.light_clients {
+            let db = sled::open(config.db_path.clone())
+                .map_err(|e| format!("could not open database: {}", e))?;
+
+            let mut light_store = SledStore::new(db);
+
+            if let Some(trusted_state) = light_store.latest_trusted_or_verified() {
+                status_warn!(
+                    "already existing trusted or verified state of height {} in database: {:?}",
+                    trusted_state.signed_header.header.height,
+                    config.db_path
+                );
+            }
+
+            snapshot
+                .import(&mut light_store)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(snapshot)
+    }
+}
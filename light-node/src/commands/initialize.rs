@@ -1,22 +1,24 @@
 //! `intialize` subcommand
 
 use std::ops::Deref;
+use std::path::Path;
 use std::time::Duration;
 
 use crate::application::app_config;
+use crate::config::ChainConfig;
 use crate::config::LightClientConfig;
-use crate::config::LightNodeConfig;
 
+use abscissa_core::path::PathBuf;
 use abscissa_core::status_err;
 use abscissa_core::status_warn;
 use abscissa_core::Command;
 use abscissa_core::Options;
 use abscissa_core::Runnable;
 
-use tendermint::{hash, Hash};
+use tendermint::{chain, hash, Hash};
 
 use std::convert::TryInto;
-use tendermint_light_client::builder::LightClientBuilder;
+use tendermint_light_client::builder::{Checkpoint, LightClientBuilder};
 use tendermint_light_client::store::sled::SledStore;
 use tendermint_light_client::store::LightStore;
 use tendermint_light_client::supervisor::Instance;
@@ -38,22 +40,56 @@ pub struct InitCmd {
         help = "hash of the initial subjectively trusted header to initialize the node with"
     )]
     pub header_hash: String,
+
+    #[options(
+        short = "c",
+        long = "checkpoint",
+        help = "path to a JSON checkpoint file to initialize all light clients with, once all their peers agree with it"
+    )]
+    pub checkpoint: Option<PathBuf>,
+
+    #[options(
+        no_short,
+        long = "chain",
+        meta = "ID",
+        help = "identifier of the chain to initialize, if several are configured"
+    )]
+    pub chain: Option<chain::Id>,
 }
 
 impl Runnable for InitCmd {
     fn run(&self) {
+        let node_config = app_config().deref().clone();
+        let timeout = Some(node_config.rpc_config.request_timeout);
+
+        let chain = match node_config.chain(self.chain.as_ref()) {
+            Ok(chain) => chain,
+            Err(e) => {
+                status_err!("failed to select chain to initialize: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        if let Some(path) = &self.checkpoint {
+            if let Err(e) = initialize_from_checkpoint(path, chain, timeout) {
+                status_err!("failed to initialize light clients from checkpoint: {}", e);
+                std::process::exit(1);
+            }
+
+            return;
+        }
+
         let subjective_header_hash =
             Hash::from_hex_upper(hash::Algorithm::Sha256, &self.header_hash).unwrap();
 
-        let node_config = app_config().deref().clone();
-        let light_client_config = node_config.light_clients.first().unwrap();
+        let light_client_config = chain.light_clients.first().unwrap();
 
         if let Err(e) = initialize_subjectively(
             self.height.try_into().unwrap(),
             subjective_header_hash,
-            &node_config,
+            chain,
             &light_client_config,
-            Some(node_config.rpc_config.request_timeout),
+            timeout,
         ) {
             status_err!("failed to initialize light client: {}", e);
             // TODO: Set exit code to 1
@@ -64,7 +100,7 @@ impl Runnable for InitCmd {
 fn initialize_subjectively(
     height: Height,
     subjective_header_hash: Hash,
-    node_config: &LightNodeConfig,
+    chain: &ChainConfig,
     config: &LightClientConfig,
     timeout: Option<Duration>,
 ) -> Result<Instance, String> {
@@ -87,7 +123,7 @@ fn initialize_subjectively(
         config.peer_id,
         rpc_client,
         Box::new(light_store),
-        node_config.clone().into(),
+        chain.into(),
         timeout,
     );
 
@@ -102,3 +138,55 @@ fn initialize_subjectively(
 
     Ok(builder.build())
 }
+
+/// Initialize all the light clients of the given chain with the block at the checkpoint
+/// stored in the given file, provided the primary and all the witnesses agree with it.
+fn initialize_from_checkpoint(
+    path: &Path,
+    chain: &ChainConfig,
+    timeout: Option<Duration>,
+) -> Result<Vec<Instance>, String> {
+    let checkpoint = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read checkpoint file {}: {}", path.display(), e))?;
+    let checkpoint: Checkpoint = serde_json::from_str(&checkpoint)
+        .map_err(|e| format!("could not parse checkpoint file {}: {}", path.display(), e))?;
+
+    let mut builders = Vec::with_capacity(chain.light_clients.len());
+    for config in &chain.light_clients {
+        let db = sled::open(config.db_path.clone())
+            .map_err(|e| format!("could not open database: {}", e))?;
+
+        let light_store = SledStore::new(db);
+
+        if let Some(trusted_state) = light_store.latest_trusted_or_verified() {
+            status_warn!(
+                "already existing trusted or verified state of height {} in database: {:?}",
+                trusted_state.signed_header.header.height,
+                config.db_path
+            );
+        }
+
+        let rpc_client = rpc::HttpClient::new(config.address.clone()).map_err(|e| e.to_string())?;
+
+        builders.push(LightClientBuilder::prod(
+            config.peer_id,
+            rpc_client,
+            Box::new(light_store),
+            chain.into(),
+            timeout,
+        ));
+    }
+
+    let builders = LightClientBuilder::trust_checkpoint_cross_checked(builders, &checkpoint)
+        .map_err(|e| {
+            format!(
+                "could not trust header at height {} and hash {}. Reason: {}",
+                checkpoint.height, checkpoint.hash, e
+            )
+        })?;
+
+    Ok(builders
+        .into_iter()
+        .map(|builder| builder.build())
+        .collect())
+}
@@ -1,9 +1,12 @@
 //! `start` subcommand - start the light node.
 
 use crate::application::app_config;
-use crate::config::{LightClientConfig, LightNodeConfig};
+use crate::config::{ChainConfig, LightClientConfig, LightNodeConfig};
+use crate::metrics;
+use crate::proxy;
 use crate::rpc;
 use crate::rpc::Server;
+use crate::sync;
 
 use abscissa_core::config;
 use abscissa_core::path::PathBuf;
@@ -14,13 +17,19 @@ use abscissa_core::FrameworkError;
 use abscissa_core::Options;
 use abscissa_core::Runnable;
 
+use std::collections::HashSet;
 use std::net::SocketAddr;
-use std::ops::Deref;
 use std::time::Duration;
 
+use tendermint::chain;
+
 use tendermint_light_client::builder::{LightClientBuilder, SupervisorBuilder};
 use tendermint_light_client::light_client;
-use tendermint_light_client::store::{sled::SledStore, LightStore};
+use tendermint_light_client::metrics::Metrics;
+use tendermint_light_client::store::{
+    sled::{SledStore, SledTraceStore},
+    LightStore,
+};
 use tendermint_light_client::supervisor::{Handle, Instance, Supervisor};
 
 /// `start` subcommand
@@ -47,32 +56,56 @@ impl Runnable for StartCmd {
             panic!(e);
         }
 
-        let supervisor = match self.construct_supervisor() {
-            Ok(supervisor) => supervisor,
-            Err(e) => {
-                status_err!(&e);
-                panic!(e);
-            }
-        };
+        let metrics = app_config()
+            .rpc_config
+            .metrics_listen_addr
+            .map(|laddr| (laddr, Metrics::new()));
 
-        let rpc_handler = supervisor.handle();
-        StartCmd::start_rpc_server(rpc_handler);
+        let mut supervisors = Vec::with_capacity(app_config().chains.len());
+        for chain in &app_config().chains {
+            match self.construct_supervisor(chain, metrics.as_ref().map(|(_, m)| m)) {
+                Ok(supervisor) => supervisors.push((chain.clone(), supervisor)),
+                Err(e) => {
+                    status_err!("{}: {}", chain.id, e);
+                    std::process::exit(1);
+                }
+            }
+        }
 
-        let handle = supervisor.handle();
-        std::thread::spawn(|| supervisor.run());
+        let rpc_handlers = supervisors
+            .iter()
+            .map(|(chain, supervisor)| (chain.id.clone(), supervisor.handle()))
+            .collect();
+        StartCmd::start_rpc_server(rpc_handlers);
 
-        loop {
-            match handle.verify_to_highest() {
-                Ok(light_block) => {
-                    status_info!("synced to block:", light_block.height().to_string());
-                }
-                Err(err) => {
-                    status_err!("sync failed: {}", err);
-                }
+        if let Some(laddr) = app_config().rpc_config.proxy_listen_addr {
+            let proxy_handlers = supervisors
+                .iter()
+                .map(|(chain, supervisor)| (chain, supervisor.handle()))
+                .collect();
+            if let Err(e) = StartCmd::start_proxy_server(proxy_handlers, laddr) {
+                status_err!("failed to start RPC proxy: {}", e);
             }
+        }
 
-            // TODO(liamsi): use ticks and make this configurable:
-            std::thread::sleep(Duration::from_millis(800));
+        if let Some((laddr, metrics)) = metrics {
+            StartCmd::start_metrics_server(metrics, laddr);
+        }
+
+        let syncs: Vec<_> = supervisors
+            .into_iter()
+            .map(|(chain, supervisor)| {
+                let handle = supervisor.handle();
+                std::thread::spawn(|| supervisor.run());
+
+                let primary = chain.light_clients[0].address.clone(); // Safe, see `construct_supervisor`
+                std::thread::spawn(move || sync::run(&chain.id, &handle, primary, &chain.sync))
+            })
+            .collect();
+
+        // The syncs never return
+        for sync in syncs {
+            let _ = sync.join();
         }
     }
 }
@@ -96,33 +129,98 @@ impl config::Override<LightNodeConfig> for StartCmd {
 
 impl StartCmd {
     fn assert_init_was_run() -> Result<(), String> {
-        let db_path = app_config().light_clients.first().unwrap().db_path.clone();
-        let db = sled::open(db_path).map_err(|e| format!("could not open database: {}", e))?;
+        let chains = &app_config().chains;
+        if chains.is_empty() {
+            return Err("configuration incomplete: no chain configured".to_string());
+        }
 
-        let primary_store = SledStore::new(db);
-        if primary_store.latest_trusted_or_verified().is_none() {
-            return Err("no trusted or verified state in store for primary, please initialize with the `initialize` subcommand first".to_string());
+        let mut chain_ids = HashSet::with_capacity(chains.len());
+        for chain in chains {
+            if !chain_ids.insert(&chain.id) {
+                return Err(format!("chain {} is configured more than once", chain.id));
+            }
+
+            let db_path = match chain.light_clients.first() {
+                Some(primary_conf) => primary_conf.db_path.clone(),
+                None => {
+                    return Err(format!(
+                        "configuration incomplete: no light client configured for chain {}",
+                        chain.id
+                    ))
+                }
+            };
+            let db = sled::open(db_path).map_err(|e| format!("could not open database: {}", e))?;
+
+            let primary_store = SledStore::new(db);
+            match primary_store.latest_trusted_or_verified() {
+                None => return Err(format!("no trusted or verified state in store for primary of chain {}, please initialize with the `initialize` subcommand first", chain.id)),
+                Some(light_block) if light_block.signed_header.header.chain_id != chain.id => {
+                    return Err(format!(
+                        "the trusted state in store for primary of chain {} belongs to chain {}",
+                        chain.id, light_block.signed_header.header.chain_id
+                    ))
+                }
+                Some(_) => (),
+            }
         }
 
         Ok(())
     }
 
-    fn start_rpc_server<H>(h: H)
+    fn start_rpc_server<H>(handles: Vec<(chain::Id, H)>)
     where
         H: Handle + Send + Sync + 'static,
     {
-        let server = Server::new(h);
+        let servers = handles
+            .into_iter()
+            .map(|(chain_id, handle)| (chain_id, Server::new(handle)))
+            .collect();
         let laddr = app_config().rpc_config.listen_addr;
         // TODO(liamsi): figure out how to handle the potential error on run
-        std::thread::spawn(move || rpc::run(server, &laddr.to_string()));
+        std::thread::spawn(move || rpc::run(servers, &laddr.to_string()));
         status_info!("started RPC server:", laddr.to_string());
     }
 
+    fn start_proxy_server<H>(
+        handles: Vec<(&ChainConfig, H)>,
+        laddr: SocketAddr,
+    ) -> Result<(), String>
+    where
+        H: Handle + Send + Sync + 'static,
+    {
+        let timeout = app_config().rpc_config.request_timeout;
+
+        let mut servers = Vec::with_capacity(handles.len());
+        for (chain, handle) in handles {
+            let primary_conf = &chain.light_clients[0]; // Safe, see `construct_supervisor`
+
+            let rpc_client = tendermint_rpc::HttpClient::new(primary_conf.address.clone())
+                .map_err(|e| format!("failed to create HTTP client: {}", e))?;
+
+            let server = proxy::Server::new(handle, rpc_client, Some(timeout))
+                .map_err(|e| format!("failed to create RPC proxy: {}", e))?;
+            servers.push((chain.id.clone(), server));
+        }
+
+        // TODO(liamsi): figure out how to handle the potential error on run
+        std::thread::spawn(move || proxy::run(servers, &laddr.to_string()));
+        status_info!("started RPC proxy:", laddr.to_string());
+
+        Ok(())
+    }
+
+    fn start_metrics_server(metrics: Metrics, laddr: SocketAddr) {
+        // TODO(liamsi): figure out how to handle the potential error on run
+        std::thread::spawn(move || metrics::run(metrics, &laddr.to_string()));
+        status_info!("started metrics server:", laddr.to_string());
+    }
+
     fn make_instance(
         &self,
         light_config: &LightClientConfig,
         options: light_client::Options,
         timeout: Option<Duration>,
+        metrics: Option<&Metrics>,
     ) -> Result<Instance, String> {
         let rpc_client = tendermint_rpc::HttpClient::new(light_config.address.clone())
             .map_err(|e| format!("failed to create HTTP client: {}", e))?;
@@ -130,29 +228,44 @@ impl StartCmd {
         let db_path = light_config.db_path.clone();
         let db = sled::open(db_path).map_err(|e| format!("could not open database: {}", e))?;
 
-        let light_store = SledStore::new(db);
+        let light_store = SledStore::new(db.clone());
+        let trace_store = SledTraceStore::new(db);
 
-        let builder = LightClientBuilder::prod(
-            light_config.peer_id,
-            rpc_client,
-            Box::new(light_store),
-            options,
-            timeout,
-        );
+        let builder = match metrics {
+            Some(metrics) => LightClientBuilder::prod_with_metrics(
+                light_config.peer_id,
+                rpc_client,
+                Box::new(light_store),
+                options,
+                timeout,
+                metrics.clone(),
+            ),
+            None => LightClientBuilder::prod(
+                light_config.peer_id,
+                rpc_client,
+                Box::new(light_store),
+                options,
+                timeout,
+            ),
+        };
 
         let builder = builder
+            .trace_store(Box::new(trace_store))
             .trust_from_store()
             .map_err(|e| format!("could not set initial trusted state: {}", e))?;
 
         Ok(builder.build())
     }
 
-    fn construct_supervisor(&self) -> Result<Supervisor, String> {
-        let conf = app_config().deref().clone();
+    fn construct_supervisor(
+        &self,
+        chain: &ChainConfig,
+        metrics: Option<&Metrics>,
+    ) -> Result<Supervisor, String> {
         let timeout = app_config().rpc_config.request_timeout;
-        let options: light_client::Options = conf.into();
+        let options: light_client::Options = chain.into();
 
-        let light_confs = &app_config().light_clients;
+        let light_confs = &chain.light_clients;
         if light_confs.len() < 2 {
             return Err(format!("configuration incomplete: not enough light clients configued, minimum: 2, found: {}", light_confs.len()));
         }
@@ -162,7 +275,7 @@ impl StartCmd {
 
         let builder = SupervisorBuilder::new();
 
-        let primary_instance = self.make_instance(primary_conf, options, Some(timeout))?;
+        let primary_instance = self.make_instance(primary_conf, options, Some(timeout), metrics)?;
         let builder = builder.primary(
             primary_conf.peer_id,
             primary_conf.address.clone(),
@@ -171,14 +284,22 @@ impl StartCmd {
 
         let mut witnesses = Vec::with_capacity(witness_confs.len());
         for witness_conf in witness_confs {
-            let instance = self.make_instance(witness_conf, options, Some(timeout))?;
+            let instance = self.make_instance(witness_conf, options, Some(timeout), metrics)?;
             witnesses.push((witness_conf.peer_id, witness_conf.address.clone(), instance));
         }
 
-        let builder = builder
+        let mut builder = builder
             .witnesses(witnesses)
             .map_err(|e| format!("failed to set witnesses: {}", e))?;
 
+        if let Some((policy, interval)) = chain.retention_policy() {
+            builder = builder.retention_policy(policy, interval);
+        }
+
+        if let Some(metrics) = metrics {
+            builder = builder.metrics(metrics.clone());
+        }
+
         Ok(builder.build_prod())
     }
 }
@@ -9,13 +9,33 @@ use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::time::Duration;
 
-use tendermint_light_client::light_client;
+use tendermint::chain;
+use tendermint_light_client::light_client::{self, VerificationMode};
+use tendermint_light_client::store::RetentionPolicy;
 use tendermint_light_client::types::{PeerId, TrustThreshold};
 
 /// LightNode Configuration
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct LightNodeConfig {
+    /// RPC related config parameters.
+    pub rpc_config: RpcConfig,
+
+    /// The chains followed by the light node, each with its own light clients.
+    pub chains: Vec<ChainConfig>,
+}
+
+/// ChainConfig contains the verification options and the light clients of a chain.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+    /// The identifier of the chain, under which it is served over RPC.
+    pub id: chain::Id,
+
+    /// Whether to verify headers with bisection (`skipping`),
+    /// or every header against its predecessor (`sequential`).
+    #[serde(default)]
+    pub verification_mode: VerificationMode,
     /// The fraction of the total voting power of a known
     /// and trusted validator set is sufficient for a commit to be
     /// accepted going forward.
@@ -25,8 +45,13 @@ pub struct LightNodeConfig {
     /// Correction parameter dealing with only approximately synchronized clocks.
     pub clock_drift: Duration,
 
-    /// RPC related config parameters.
-    pub rpc_config: RpcConfig,
+    /// Retention policy of the light stores.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+
+    /// How the light node keeps in sync with the primary.
+    #[serde(default)]
+    pub sync: SyncConfig,
 
     // TODO "now" should probably always be passed in as `Time::now()`
     /// The actual light client instances' configuration.
@@ -57,6 +82,116 @@ pub struct RpcConfig {
     pub listen_addr: SocketAddr,
     /// The duration after which any RPC request to tendermint node will time out.
     pub request_timeout: Duration,
+    /// The address the verifying proxy of the RPC endpoint of the primary will serve.
+    /// The proxy is disabled if not set.
+    #[serde(default)]
+    pub proxy_listen_addr: Option<SocketAddr>,
+    /// The address the Prometheus metrics will be served on, at `/metrics`.
+    /// The metrics are disabled if not set.
+    #[serde(default)]
+    pub metrics_listen_addr: Option<SocketAddr>,
+}
+
+/// RetentionConfig specifies which light blocks are kept in the light stores,
+/// and how often these are pruned.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// The interval at which the light stores are pruned.
+    /// The light stores are never pruned if not set.
+    pub prune_interval: Option<Duration>,
+    /// The number of most recent trusted blocks to keep.
+    pub max_trusted_blocks: Option<usize>,
+    /// Whether to remove the trusted and verified blocks outside of the trusting period.
+    #[serde(default)]
+    pub prune_expired: bool,
+    /// The age after which unverified and failed blocks are removed.
+    pub max_unverified_age: Option<Duration>,
+}
+
+/// SyncConfig specifies how the light node keeps in sync with the primary.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Whether to periodically verify the latest block of the primary (`poll`),
+    /// or every block it announces over its WebSocket endpoint (`subscribe`).
+    pub mode: SyncMode,
+    /// The interval at which the primary is polled, either in `poll` mode,
+    /// or in `subscribe` mode while the subscription is down.
+    pub poll_interval: Duration,
+    /// The interval at which the light node attempts to subscribe again
+    /// to the new blocks of the primary, once the subscription dropped.
+    pub resubscribe_interval: Duration,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            mode: SyncMode::default(),
+            poll_interval: Duration::from_millis(800),
+            resubscribe_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// How the light node learns about new blocks of the primary.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Periodically verify the latest block of the primary.
+    #[default]
+    Poll,
+    /// Subscribe to the `NewBlock` events of the primary, and verify every block it announces.
+    Subscribe,
+}
+
+impl LightNodeConfig {
+    /// The config of the chain with the given id, or of the only configured chain
+    /// if no id is given.
+    pub fn chain(&self, id: Option<&chain::Id>) -> Result<&ChainConfig, String> {
+        match id {
+            Some(id) => self
+                .chains
+                .iter()
+                .find(|chain| chain.id == *id)
+                .ok_or_else(|| format!("chain {} is not configured", id)),
+            None => match self.chains.as_slice() {
+                [chain] => Ok(chain),
+                _ => Err(format!(
+                    "{} chains are configured, please select one of: {}",
+                    self.chains.len(),
+                    self.chain_ids().join(", ")
+                )),
+            },
+        }
+    }
+
+    /// The identifiers of the configured chains.
+    pub fn chain_ids(&self) -> Vec<String> {
+        self.chains
+            .iter()
+            .map(|chain| chain.id.to_string())
+            .collect()
+    }
+}
+
+impl ChainConfig {
+    /// The retention policy of the light stores, if pruning is enabled.
+    pub fn retention_policy(&self) -> Option<(RetentionPolicy, Duration)> {
+        let interval = self.retention.prune_interval?;
+
+        let policy = RetentionPolicy {
+            max_trusted_blocks: self.retention.max_trusted_blocks,
+            trusting_period: if self.retention.prune_expired {
+                Some(self.trusting_period)
+            } else {
+                None
+            },
+            max_unverified_age: self.retention.max_unverified_age,
+        };
+
+        Some((policy, interval))
+    }
 }
 
 /// Default light client config settings.
@@ -76,16 +211,31 @@ impl Default for LightClientConfig {
 impl Default for LightNodeConfig {
     fn default() -> Self {
         Self {
+            rpc_config: RpcConfig {
+                listen_addr: "127.0.0.1:8888".parse().unwrap(),
+                request_timeout: Duration::from_secs(60),
+                proxy_listen_addr: None,
+                metrics_listen_addr: None,
+            },
+            chains: vec![ChainConfig::default()],
+        }
+    }
+}
+
+/// Default chain config settings.
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            id: "test-chain".parse().unwrap(),
+            verification_mode: VerificationMode::default(),
             trusting_period: Duration::from_secs(864_000), // 60*60*24*10
             trust_threshold: TrustThreshold {
                 numerator: 1,
                 denominator: 3,
             },
             clock_drift: Duration::from_secs(1),
-            rpc_config: RpcConfig {
-                listen_addr: "127.0.0.1:8888".parse().unwrap(),
-                request_timeout: Duration::from_secs(60),
-            },
+            retention: RetentionConfig::default(),
+            sync: SyncConfig::default(),
             // TODO(ismail): need at least 2 peers for a proper init
             // otherwise the light node will complain on `start` with `no witness left`
             light_clients: vec![LightClientConfig::default()],
@@ -93,12 +243,47 @@ impl Default for LightNodeConfig {
     }
 }
 
-impl From<LightNodeConfig> for light_client::Options {
-    fn from(lnc: LightNodeConfig) -> Self {
+impl From<&ChainConfig> for light_client::Options {
+    fn from(chain: &ChainConfig) -> Self {
         Self {
-            trust_threshold: lnc.trust_threshold,
-            trusting_period: lnc.trusting_period,
-            clock_drift: lnc.clock_drift,
+            trust_threshold: chain.trust_threshold,
+            trusting_period: chain.trusting_period,
+            clock_drift: chain.clock_drift,
+            verification_mode: chain.verification_mode,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use abscissa_core::Config as _;
+
+    use super::*;
+
+    #[test]
+    fn example_config_is_valid() {
+        let example = std::fs::read_to_string("light_node.toml.example").unwrap();
+        let config = LightNodeConfig::load_toml(example).unwrap();
+
+        let chain = config.chain(None).unwrap();
+        assert_eq!(chain.id.as_str(), "test-chain");
+        assert_eq!(chain.light_clients.len(), 2);
+        assert_eq!(chain.sync.mode, SyncMode::Subscribe);
+    }
+
+    #[test]
+    fn chain_is_selected_by_id() {
+        let mut config = LightNodeConfig::default();
+        config.chains.push(ChainConfig {
+            id: "other-chain".parse().unwrap(),
+            ..ChainConfig::default()
+        });
+
+        let id = "other-chain".parse().unwrap();
+        assert_eq!(config.chain(Some(&id)).unwrap().id, id);
+        assert!(config
+            .chain(Some(&"unknown-chain".parse().unwrap()))
+            .is_err());
+        assert!(config.chain(None).is_err());
+    }
+}
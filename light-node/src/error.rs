@@ -21,6 +21,14 @@ pub enum Kind {
     /// Input/output error
     #[error("i/o error")]
     Io,
+
+    /// Error returned by the RPC endpoint of a full node
+    #[error("rpc error")]
+    Rpc,
+
+    /// Response of a full node inconsistent with the verified light blocks
+    #[error("verification failed")]
+    Verification,
 }
 
 impl Kind {
@@ -23,5 +23,8 @@ pub mod application;
 pub mod commands;
 pub mod config;
 pub mod error;
+pub mod metrics;
 pub mod prelude;
+pub mod proxy;
 pub mod rpc;
+pub mod sync;
@@ -0,0 +1,81 @@
+//! Prometheus endpoint of the light node.
+//!
+//! The metrics of the light clients, of their RPC requests and of the supervisor are served
+//! in the Prometheus text format on `/metrics`.
+use jsonrpc_core::IoHandler;
+use jsonrpc_http_server::hyper::{header::HeaderValue, Body, Method, Request, StatusCode};
+use jsonrpc_http_server::{RequestMiddlewareAction, Response, ServerBuilder};
+
+use tendermint_light_client::metrics::{Metrics, CONTENT_TYPE};
+
+use crate::error;
+
+/// Path the metrics are served on
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Serve the given metrics over HTTP on the given address and blocks until closed.
+///
+/// See [`rpc::run`](crate::rpc::run).
+pub fn run(metrics: Metrics, addr: &str) -> Result<(), error::Error> {
+    let srv = ServerBuilder::new(IoHandler::new())
+        .request_middleware(move |request: Request<Body>| -> RequestMiddlewareAction {
+            respond(&metrics, &request).into()
+        })
+        .start_http(&addr.parse().map_err(error::Kind::from)?)
+        .map_err(|e| error::Kind::Io.context(e))?;
+
+    srv.wait();
+
+    Ok(())
+}
+
+/// Respond to the given request with the encoded metrics, if it is a `GET` request
+/// on [`METRICS_PATH`].
+fn respond(metrics: &Metrics, request: &Request<Body>) -> Response {
+    if request.uri().path() != METRICS_PATH {
+        return Response {
+            code: StatusCode::NOT_FOUND,
+            content_type: HeaderValue::from_static("text/plain; charset=utf-8"),
+            content: "Not Found\n".to_string(),
+        };
+    }
+
+    if request.method() != Method::GET {
+        return Response::method_not_allowed();
+    }
+
+    Response {
+        code: StatusCode::OK,
+        content_type: HeaderValue::from_static(CONTENT_TYPE),
+        content: metrics.encode(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(method: Method, path: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn serves_metrics_on_metrics_path_only() {
+        let metrics = Metrics::new();
+
+        let response = respond(&metrics, &request(Method::GET, "/metrics"));
+        assert_eq!(response.code, StatusCode::OK);
+        assert_eq!(response.content_type, CONTENT_TYPE);
+        assert_eq!(response.content, metrics.encode());
+
+        let response = respond(&metrics, &request(Method::POST, "/metrics"));
+        assert_eq!(response.code, StatusCode::METHOD_NOT_ALLOWED);
+
+        let response = respond(&metrics, &request(Method::GET, "/"));
+        assert_eq!(response.code, StatusCode::NOT_FOUND);
+    }
+}
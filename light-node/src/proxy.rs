@@ -0,0 +1,740 @@
+//! Verifying proxy of the Tendermint RPC endpoint of the primary.
+//!
+//! The proxy serves a subset of the Tendermint JSON-RPC methods, with the same parameters and
+//! responses. It forwards the requests it receives to the primary full node, and only returns
+//! the responses which are consistent with the light blocks verified by the light client.
+use std::convert::TryFrom;
+use std::future::Future;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use tokio::runtime::Runtime;
+
+use tendermint::abci::transaction::Data;
+use tendermint::block::Height;
+use tendermint::chain;
+use tendermint::merkle;
+use tendermint::Hash;
+use tendermint_light_client::types::{LightBlock, ValidatorSet};
+use tendermint_rpc::endpoint::{block, commit, tx, validators};
+
+use crate::error::{self, Kind};
+
+pub use sealed::{Rpc, Server};
+
+/// Run the given [`Server`]s, one per chain, on the given address and blocks until closed.
+///
+/// See [`rpc::run`](crate::rpc::run).
+pub fn run<H, C>(servers: Vec<(chain::Id, Server<H, C>)>, addr: &str) -> Result<(), error::Error>
+where
+    H: tendermint_light_client::supervisor::Handle + Send + Sync + 'static,
+    C: tendermint_rpc::Client + Send + Sync + 'static,
+{
+    let handlers = servers
+        .into_iter()
+        .map(|(chain_id, server)| {
+            let mut io = jsonrpc_core::IoHandler::new();
+            io.extend_with(server.to_delegate());
+            (chain_id, io)
+        })
+        .collect();
+
+    crate::rpc::serve(handlers, addr)
+}
+
+/// Verify that the given block is the one whose header is part of the given verified light block,
+/// and that its transactions are the ones committed to by the header.
+pub fn verify_block(
+    response: &block::Response,
+    light_block: &LightBlock,
+) -> Result<(), error::Error> {
+    let header = &light_block.signed_header.header;
+    let header_hash = header.hash();
+
+    if response.block.header.hash() != header_hash {
+        return Err(verification_error(format!(
+            "header of block at height {} does not match the verified header",
+            response.block.header.height
+        )));
+    }
+
+    if response.block_id.hash != header_hash {
+        return Err(verification_error(format!(
+            "block id {} does not match the hash of the verified header {}",
+            response.block_id.hash, header_hash
+        )));
+    }
+
+    if header.data_hash != Some(data_hash(&response.block.data)) {
+        return Err(verification_error(format!(
+            "transactions of block at height {} do not match the verified data hash",
+            header.height
+        )));
+    }
+
+    Ok(())
+}
+
+/// Verify that the given commit is for the header of the given verified light block.
+pub fn verify_commit(
+    response: &commit::Response,
+    light_block: &LightBlock,
+) -> Result<(), error::Error> {
+    let header_hash = light_block.signed_header.header.hash();
+    let signed_header = &response.signed_header;
+
+    if signed_header.header.hash() != header_hash {
+        return Err(verification_error(format!(
+            "header at height {} does not match the verified header",
+            signed_header.header.height
+        )));
+    }
+
+    if signed_header.commit.block_id.hash != header_hash {
+        return Err(verification_error(format!(
+            "commit for block {} does not match the hash of the verified header {}",
+            signed_header.commit.block_id.hash, header_hash
+        )));
+    }
+
+    Ok(())
+}
+
+/// Verify that the given validators are the ones of the given verified light block.
+pub fn verify_validators(
+    response: &validators::Response,
+    light_block: &LightBlock,
+) -> Result<(), error::Error> {
+    let header = &light_block.signed_header.header;
+
+    if header.height != response.block_height {
+        return Err(verification_error(format!(
+            "light block at height {} cannot be used to verify validators at height {}",
+            header.height, response.block_height
+        )));
+    }
+
+    let validators_hash = ValidatorSet::without_proposer(response.validators.clone()).hash();
+
+    if validators_hash != header.validators_hash {
+        return Err(verification_error(format!(
+            "validators at height {} do not match the verified validators hash {}",
+            response.block_height, header.validators_hash
+        )));
+    }
+
+    Ok(())
+}
+
+/// Verify the proof of inclusion of the given transaction in the block whose header is part of
+/// the given verified light block.
+pub fn verify_tx(response: &tx::Response, light_block: &LightBlock) -> Result<(), error::Error> {
+    let header = &light_block.signed_header.header;
+
+    if header.height != response.height {
+        return Err(verification_error(format!(
+            "light block at height {} cannot be used to verify a transaction at height {}",
+            header.height, response.height
+        )));
+    }
+
+    let tx_hash = Sha256::digest(response.tx.as_bytes());
+    if response.hash.as_bytes() != &tx_hash[..] {
+        return Err(verification_error(format!(
+            "transaction does not match its hash {}",
+            response.hash
+        )));
+    }
+
+    let tx_proof = response
+        .proof
+        .as_ref()
+        .ok_or_else(|| verification_error("missing proof"))?;
+
+    if tx_proof.data != response.tx.as_bytes() {
+        return Err(verification_error("proof is for another transaction"));
+    }
+
+    let data_hash = header.data_hash.unwrap_or(Hash::None);
+    if tx_proof.root_hash != data_hash.as_bytes() {
+        return Err(verification_error(format!(
+            "proof root hash does not match the verified data hash {}",
+            data_hash
+        )));
+    }
+
+    let proof = tx_proof
+        .proof
+        .as_ref()
+        .ok_or_else(|| verification_error("missing Merkle proof"))?;
+
+    let aunts = proof
+        .aunts
+        .iter()
+        .map(|aunt| merkle::Hash::try_from(aunt.as_slice()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| verification_error("invalid aunt hash in Merkle proof"))?;
+
+    let index = u64::try_from(proof.index).map_err(|_| verification_error("invalid index"))?;
+    let total = u64::try_from(proof.total).map_err(|_| verification_error("invalid total"))?;
+    let leaf_hash = merkle::leaf_hash(&tx_hash);
+
+    match merkle::compute_hash_from_aunts(index, total, leaf_hash, &aunts) {
+        Some(root_hash) if root_hash == data_hash.as_bytes() => Ok(()),
+        _ => Err(verification_error(
+            "Merkle proof does not match the verified data hash",
+        )),
+    }
+}
+
+/// Merkle root of the hashes of the given transactions.
+fn data_hash(data: &Data) -> Hash {
+    let tx_hashes = data
+        .iter()
+        .map(|tx| Sha256::digest(tx.as_bytes()).to_vec())
+        .collect();
+
+    Hash::Sha256(merkle::simple_hash_from_byte_vectors(tx_hashes))
+}
+
+fn verification_error(msg: impl Into<String>) -> error::Error {
+    Kind::Verification.context(msg.into()).into()
+}
+
+/// Build the runtime on which the requests to the primary are performed.
+fn build_runtime() -> Result<Runtime, error::Error> {
+    tokio::runtime::Builder::new()
+        .threaded_scheduler()
+        .enable_all()
+        .build()
+        .map_err(|e| Kind::Io.context(e).into())
+}
+
+/// Run the given future to completion within the given timeout, in the context of the given
+/// runtime, whose worker threads drive the I/O and timers the future depends on.
+fn block_on<F: Future>(
+    rt: &Runtime,
+    timeout: Option<Duration>,
+    future: F,
+) -> Result<F::Output, error::Error> {
+    rt.enter(|| match timeout {
+        Some(duration) => futures::executor::block_on(tokio::time::timeout(duration, future))
+            .map_err(|e| Kind::Rpc.context(e).into()),
+        None => Ok(futures::executor::block_on(future)),
+    })
+}
+
+/// Error returned when the result of an ABCI query at the given height, which is the latest
+/// height of the primary, cannot be verified yet, as the application hash it is committed to
+/// is only part of the header of the next block.
+fn unprovable_query_error(height: Height) -> error::Error {
+    verification_error(format!(
+        "the result of the query at height {} cannot be proven until block {} is committed, \
+         retry the query at height {}",
+        height,
+        height.increment(),
+        height.value().saturating_sub(1)
+    ))
+}
+
+mod sealed {
+    use std::fmt;
+    use std::time::Duration;
+
+    use tokio::runtime::Runtime;
+
+    use jsonrpc_core::futures::future::{self, FutureResult};
+    use jsonrpc_core::types::{Error, Params};
+    use jsonrpc_derive::rpc;
+    use serde::de::DeserializeOwned;
+
+    use tendermint::merkle::runtime::ProofRuntime;
+    use tendermint_light_client::query::{store_key_path, verify_abci_query};
+    use tendermint_light_client::supervisor::Handle;
+    use tendermint_rpc::endpoint::{abci_query, block, commit, tx, validators};
+    use tendermint_rpc::Client;
+
+    use super::{
+        block_on, build_runtime, unprovable_query_error, verify_block, verify_commit, verify_tx,
+        verify_validators,
+    };
+    use crate::error::{self, Kind};
+
+    #[rpc(server)]
+    pub trait Rpc {
+        /// Returns the block at the given height, or the latest block, once verified.
+        #[rpc(name = "block", params = "raw")]
+        fn block(&self, params: Params) -> FutureResult<block::Response, Error>;
+
+        /// Returns the commit at the given height, or the latest commit, once verified.
+        #[rpc(name = "commit", params = "raw")]
+        fn commit(&self, params: Params) -> FutureResult<commit::Response, Error>;
+
+        /// Returns the validators at the given height, once verified.
+        #[rpc(name = "validators", params = "raw")]
+        fn validators(&self, params: Params) -> FutureResult<validators::Response, Error>;
+
+        /// Returns the transaction with the given hash, along with its verified proof.
+        #[rpc(name = "tx", params = "raw")]
+        fn tx(&self, params: Params) -> FutureResult<tx::Response, Error>;
+
+        /// Returns the result of the given ABCI query, along with its verified proof.
+        #[rpc(name = "abci_query", params = "raw")]
+        fn abci_query(&self, params: Params) -> FutureResult<abci_query::Response, Error>;
+    }
+
+    pub struct Server<H, C>
+    where
+        H: Handle + Send + Sync,
+        C: Client + Send + Sync,
+    {
+        handle: H,
+        client: C,
+        runtime: ProofRuntime,
+        rt: Runtime,
+        timeout: Option<Duration>,
+    }
+
+    impl<H, C> Server<H, C>
+    where
+        H: Handle + Send + Sync,
+        C: Client + Send + Sync,
+    {
+        /// Creates a proxy which forwards the requests to the primary through the given client,
+        /// and verifies the responses against the light blocks verified by the given handle.
+        ///
+        /// The requests to the primary are performed on a runtime owned by the proxy, and
+        /// shared by all the requests it serves.
+        pub fn new(handle: H, client: C, timeout: Option<Duration>) -> Result<Self, error::Error> {
+            Ok(Self {
+                handle,
+                client,
+                runtime: ProofRuntime::default(),
+                rt: build_runtime()?,
+                timeout,
+            })
+        }
+
+        /// Sets the runtime used to verify the proofs of ABCI queries.
+        pub fn with_proof_runtime(mut self, runtime: ProofRuntime) -> Self {
+            self.runtime = runtime;
+            self
+        }
+
+        fn perform<R>(&self, request: R) -> Result<R::Response, error::Error>
+        where
+            R: tendermint_rpc::SimpleRequest,
+        {
+            block_on(&self.rt, self.timeout, self.client.perform(request))?
+                .map_err(|e| Kind::Rpc.context(e).into())
+        }
+
+        /// The height of the latest block of the primary.
+        fn latest_height(&self) -> Result<tendermint::block::Height, error::Error> {
+            let response = self.perform(commit::Request { height: None })?;
+            Ok(response.signed_header.header.height)
+        }
+
+        fn verify_to_target(
+            &self,
+            height: tendermint::block::Height,
+        ) -> Result<tendermint_light_client::types::LightBlock, error::Error> {
+            self.handle
+                .verify_to_target(height)
+                .map_err(|e| Kind::Verification.context(e).into())
+        }
+
+        fn block(&self, request: block::Request) -> Result<block::Response, error::Error> {
+            let response = self.perform(request)?;
+            let light_block = self.verify_to_target(response.block.header.height)?;
+            verify_block(&response, &light_block)?;
+
+            Ok(response)
+        }
+
+        fn commit(&self, request: commit::Request) -> Result<commit::Response, error::Error> {
+            let response = self.perform(request)?;
+            let light_block = self.verify_to_target(response.signed_header.header.height)?;
+            verify_commit(&response, &light_block)?;
+
+            // The signatures of the primary's commit are not checked, so respond with the
+            // verified ones instead.
+            Ok(commit::Response {
+                signed_header: light_block.signed_header,
+                ..response
+            })
+        }
+
+        fn validators(
+            &self,
+            request: validators::Request,
+        ) -> Result<validators::Response, error::Error> {
+            let light_block = self.verify_to_target(request.height)?;
+            let response = self.perform(request)?;
+            verify_validators(&response, &light_block)?;
+
+            Ok(response)
+        }
+
+        fn tx(&self, mut request: tx::Request) -> Result<tx::Response, error::Error> {
+            request.prove = true;
+
+            let response = self.perform(request)?;
+            let light_block = self.verify_to_target(response.height)?;
+            verify_tx(&response, &light_block)?;
+
+            Ok(response)
+        }
+
+        fn abci_query(
+            &self,
+            mut request: abci_query::Request,
+        ) -> Result<abci_query::Response, error::Error> {
+            request.prove = true;
+
+            let path = request.path.as_ref().map(ToString::to_string);
+            let keys = path
+                .as_deref()
+                .and_then(|path| store_key_path(path, &request.data))
+                .ok_or_else(|| {
+                    Kind::Verification.context(format!(
+                        "cannot verify the result of queries on path {}",
+                        path.as_deref().unwrap_or_default()
+                    ))
+                })?;
+
+            let response = self.perform(request)?;
+            let height = response.response.height;
+
+            // The application hash the result is committed to is part of the next header
+            let light_block = self
+                .verify_to_target(height.increment())
+                .map_err(|e| match self.latest_height() {
+                    Ok(latest) if latest <= height => unprovable_query_error(height),
+                    _ => e,
+                })?;
+
+            verify_abci_query(&self.runtime, &response.response, &light_block, &keys)
+                .map_err(|e| Kind::Verification.context(e))?;
+
+            Ok(response)
+        }
+    }
+
+    impl<H, C> Rpc for Server<H, C>
+    where
+        H: Handle + Send + Sync + 'static,
+        C: Client + Send + Sync + 'static,
+    {
+        fn block(&self, params: Params) -> FutureResult<block::Response, Error> {
+            future::result(parse(params).and_then(|req| self.block(req).map_err(internal_error)))
+        }
+
+        fn commit(&self, params: Params) -> FutureResult<commit::Response, Error> {
+            future::result(parse(params).and_then(|req| self.commit(req).map_err(internal_error)))
+        }
+
+        fn validators(&self, params: Params) -> FutureResult<validators::Response, Error> {
+            future::result(
+                parse(params).and_then(|req| self.validators(req).map_err(internal_error)),
+            )
+        }
+
+        fn tx(&self, params: Params) -> FutureResult<tx::Response, Error> {
+            future::result(parse(params).and_then(|req| self.tx(req).map_err(internal_error)))
+        }
+
+        fn abci_query(&self, params: Params) -> FutureResult<abci_query::Response, Error> {
+            future::result(
+                parse(params).and_then(|req| self.abci_query(req).map_err(internal_error)),
+            )
+        }
+    }
+
+    /// Parse the given parameters, given either by name or by position, as a Tendermint request.
+    fn parse<R: DeserializeOwned>(params: Params) -> Result<R, Error> {
+        match params {
+            Params::None => Params::Map(Default::default()).parse(),
+            Params::Array(values) if values.is_empty() => Params::Map(Default::default()).parse(),
+            params => params.parse(),
+        }
+    }
+
+    fn internal_error(e: impl fmt::Display) -> Error {
+        let mut err = Error::internal_error();
+        err.message = e.to_string();
+        err
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use jsonrpc_core::IoHandler;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    use tendermint::block::Height;
+    use tendermint_light_client::errors::{Error, ErrorKind};
+    use tendermint_light_client::supervisor::Handle;
+    use tendermint_light_client::types::{LatestStatus, LightBlock, ValidatorSet};
+    use tendermint_rpc::endpoint::{block, commit, tx, validators};
+    use tendermint_rpc::{Method, MockClient, MockRequestMethodMatcher, Response as _};
+
+    use super::{verify_block, verify_commit, verify_tx, verify_validators, Rpc as _, Server};
+
+    const BLOCK_JSON: &str = include_str!("../tests/support/proxy/block.json");
+    const COMMIT_JSON: &str = include_str!("../tests/support/proxy/commit.json");
+    const TX_JSON: &str = include_str!("../tests/support/proxy/tx.json");
+    const VALIDATORS_JSON: &str = include_str!("../tests/support/proxy/validators.json");
+    const ABCI_QUERY_JSON: &str = include_str!("../../rpc/tests/support/abci_query.json");
+
+    /// A light block with the signed header of the `commit` and `block` fixtures, and the
+    /// validators of the `validators` fixture.
+    fn light_block() -> LightBlock {
+        let commit = commit::Response::from_string(COMMIT_JSON).unwrap();
+        let validators = validators::Response::from_string(VALIDATORS_JSON).unwrap();
+        let validators = ValidatorSet::without_proposer(validators.validators);
+
+        LightBlock::new(
+            commit.signed_header,
+            validators.clone(),
+            validators,
+            "BADFADAD0BEFEEDC0C0ADEADBEEFC0FFEEFACADE".parse().unwrap(),
+        )
+    }
+
+    fn tx_light_block() -> LightBlock {
+        let tx = tx::Response::from_string(TX_JSON).unwrap();
+        let mut light_block = light_block();
+        let header = &mut light_block.signed_header.header;
+        header.height = tx.height;
+        header.data_hash = Some(
+            tendermint::Hash::from_bytes(
+                tendermint::hash::Algorithm::Sha256,
+                &tx.proof.unwrap().root_hash,
+            )
+            .unwrap(),
+        );
+        light_block
+    }
+
+    fn call(light_block: LightBlock, method: Method, fixture: &str, request: &str) -> Value {
+        call_with(light_block, &[(method, fixture)], request)
+    }
+
+    /// Same as `call`, with a primary which responds to each given method with its fixture.
+    fn call_with(light_block: LightBlock, fixtures: &[(Method, &str)], request: &str) -> Value {
+        let matcher = fixtures.iter().fold(
+            MockRequestMethodMatcher::default(),
+            |matcher, (method, fixture)| matcher.map(*method, Ok(fixture.to_string())),
+        );
+        let client = MockClient::new(matcher).0;
+        let server = Server::new(
+            MockHandle(light_block),
+            client,
+            Some(Duration::from_secs(5)),
+        )
+        .unwrap();
+
+        let mut io = IoHandler::new();
+        io.extend_with(server.to_delegate());
+
+        let response = io.handle_request_sync(request).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    fn result<R: tendermint_rpc::Response + serde::Serialize>(fixture: &str) -> Value {
+        serde_json::to_value(R::from_string(fixture).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn block() {
+        let response = block::Response::from_string(BLOCK_JSON).unwrap();
+        let light_block = light_block();
+        assert!(verify_block(&response, &light_block).is_ok());
+
+        let mut other = response.clone();
+        other.block.data = serde_json::from_str(r#"{"txs":["dHg="]}"#).unwrap();
+        assert!(verify_block(&other, &light_block).is_err());
+
+        let mut other = response;
+        other.block.header.height = other.block.header.height.increment();
+        assert!(verify_block(&other, &light_block).is_err());
+
+        let have = call(
+            light_block,
+            Method::Block,
+            BLOCK_JSON,
+            r#"{"jsonrpc":"2.0","id":1,"method":"block","params":{"height":"10"}}"#,
+        );
+        assert_eq!(have["result"], result::<block::Response>(BLOCK_JSON));
+    }
+
+    #[test]
+    fn commit() {
+        let response = commit::Response::from_string(COMMIT_JSON).unwrap();
+        assert!(verify_commit(&response, &light_block()).is_ok());
+        assert!(verify_commit(&response, &tx_light_block()).is_err());
+
+        let have = call(
+            light_block(),
+            Method::Commit,
+            COMMIT_JSON,
+            r#"{"jsonrpc":"2.0","id":1,"method":"commit","params":[]}"#,
+        );
+        assert_eq!(have["result"], result::<commit::Response>(COMMIT_JSON));
+
+        let have = call(
+            tx_light_block(),
+            Method::Commit,
+            COMMIT_JSON,
+            r#"{"jsonrpc":"2.0","id":1,"method":"commit"}"#,
+        );
+        assert!(have["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("verification failed"));
+    }
+
+    #[test]
+    fn commit_with_tampered_signature() {
+        let tampered = COMMIT_JSON.replace(
+            "wlPr5XjCfaX5u432QUpjnsTQmJkcNJ37R78QaIQNSv3NyzJMMW0jbeSlF2Bi83CKhrGDhGL7aq/mKaIZMrlfCQ==",
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==",
+        );
+        assert_ne!(tampered, COMMIT_JSON);
+
+        let have = call(
+            light_block(),
+            Method::Commit,
+            &tampered,
+            r#"{"jsonrpc":"2.0","id":1,"method":"commit","params":[]}"#,
+        );
+        assert_ne!(have["result"], result::<commit::Response>(&tampered));
+        assert_eq!(have["result"], result::<commit::Response>(COMMIT_JSON));
+    }
+
+    #[test]
+    fn validators() {
+        let response = validators::Response::from_string(VALIDATORS_JSON).unwrap();
+        let mut light_block = light_block();
+        light_block.signed_header.header.height = response.block_height;
+        assert!(verify_validators(&response, &light_block).is_err());
+
+        light_block.signed_header.header.validators_hash = light_block.validators.hash();
+        assert!(verify_validators(&response, &light_block).is_ok());
+
+        let have = call(
+            light_block,
+            Method::Validators,
+            VALIDATORS_JSON,
+            r#"{"jsonrpc":"2.0","id":1,"method":"validators","params":{"height":"42"}}"#,
+        );
+        assert_eq!(
+            have["result"],
+            result::<validators::Response>(VALIDATORS_JSON)
+        );
+    }
+
+    #[test]
+    fn tx() {
+        let response = tx::Response::from_string(TX_JSON).unwrap();
+        assert!(verify_tx(&response, &light_block()).is_err());
+
+        let light_block = tx_light_block();
+        assert!(verify_tx(&response, &light_block).is_ok());
+
+        let mut other = response.clone();
+        other.proof.as_mut().unwrap().proof.as_mut().unwrap().index = 1;
+        assert!(verify_tx(&other, &light_block).is_err());
+
+        let mut other = response;
+        other.proof = None;
+        assert!(verify_tx(&other, &light_block).is_err());
+
+        let have = call(
+            light_block,
+            Method::Tx,
+            TX_JSON,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tx","params":{"hash":"nyiQT5wPOrdKgcukjjkSTaHGgLR/v8ugEmhw23IrzDA=","prove":false}}"#,
+        );
+        assert_eq!(have["result"], result::<tx::Response>(TX_JSON));
+    }
+
+    #[test]
+    fn abci_query_on_unsupported_path() {
+        let have = call(
+            light_block(),
+            Method::AbciQuery,
+            "",
+            r#"{"jsonrpc":"2.0","id":1,"method":"abci_query","params":{"path":"/custom","data":"","prove":true}}"#,
+        );
+        assert!(have["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("cannot verify the result of queries on path /custom"));
+    }
+
+    #[test]
+    fn abci_query_at_latest_height() {
+        // The query was performed at height 1, whose result is proven by the header at height 2
+        let have = call_with(
+            light_block(),
+            &[
+                (Method::AbciQuery, ABCI_QUERY_JSON),
+                (Method::Commit, COMMIT_JSON),
+            ],
+            r#"{"jsonrpc":"2.0","id":1,"method":"abci_query","params":{"path":"/store/ibc/key","data":"61626364","prove":true}}"#,
+        );
+        let message = have["error"]["message"].as_str().unwrap();
+        assert!(message.starts_with("verification failed"));
+        assert!(!message.contains("retry"));
+
+        // The query was performed at the height of the latest commit of the primary
+        let fixture = ABCI_QUERY_JSON.replace(r#""height": "1""#, r#""height": "10""#);
+        let have = call_with(
+            light_block(),
+            &[(Method::AbciQuery, &fixture), (Method::Commit, COMMIT_JSON)],
+            r#"{"jsonrpc":"2.0","id":1,"method":"abci_query","params":{"path":"/store/ibc/key","data":"61626364","prove":true}}"#,
+        );
+        assert!(have["error"]["message"].as_str().unwrap().contains(
+            "the result of the query at height 10 cannot be proven until block 11 is committed, \
+             retry the query at height 9"
+        ));
+    }
+
+    /// Handle to a supervisor which verified a single light block.
+    struct MockHandle(LightBlock);
+
+    impl Handle for MockHandle {
+        fn latest_trusted(&self) -> Result<Option<LightBlock>, Error> {
+            Ok(Some(self.0.clone()))
+        }
+
+        fn latest_status(&self) -> Result<LatestStatus, Error> {
+            unreachable!("the proxy does not query the status of the supervisor")
+        }
+
+        fn trusted_block(&self, height: Height) -> Result<Option<LightBlock>, Error> {
+            Ok(Some(self.0.clone()).filter(|lb| lb.height() == height))
+        }
+
+        fn verify_to_highest(&self) -> Result<LightBlock, Error> {
+            Ok(self.0.clone())
+        }
+
+        fn verify_to_target(&self, height: Height) -> Result<LightBlock, Error> {
+            if height == self.0.height() {
+                Ok(self.0.clone())
+            } else {
+                Err(ErrorKind::BisectionFailed(self.0.height(), height).into())
+            }
+        }
+
+        fn terminate(&self) -> Result<(), Error> {
+            unreachable!("the proxy does not terminate the supervisor")
+        }
+    }
+}
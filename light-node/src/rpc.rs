@@ -1,27 +1,51 @@
 //! JSON-RPC Server and Client for the light-node RPC endpoint.
-use jsonrpc_core::IoHandler;
+use std::collections::HashMap;
+
+use jsonrpc_core::{IoHandler, MetaIoHandler};
 use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation, ServerBuilder};
 
+use tendermint::chain;
 use tendermint_light_client::supervisor::Handle;
 
 use crate::error;
 
+mod router;
+pub use router::CHAIN_PATH_PREFIX;
+use router::{ChainRouter, RequestPath};
+
 pub use sealed::{Client, Rpc, Server};
 
-/// Run the given [`Server`] on the given address and blocks until closed.
+/// Run the given [`Server`]s, one per chain, on the given address and blocks until closed.
+/// The server of each chain is served on `/chain/<id>`, and on `/` as well if it is the only one.
 ///
 /// n.b. The underlying server has semantics to close on drop. Also it does not offer any way
 /// to get the underlying Future to await, so we are left with this rather rudimentary way to
 /// control the lifecycle. Should we be interested in a more controlled way to close the server we
 /// can expose a handle in the future.
-pub fn run<H>(server: Server<H>, addr: &str) -> Result<(), error::Error>
+pub fn run<H>(servers: Vec<(chain::Id, Server<H>)>, addr: &str) -> Result<(), error::Error>
 where
     H: Handle + Send + Sync + 'static,
 {
-    let mut io = IoHandler::new();
-    io.extend_with(server.to_delegate());
+    let handlers = servers
+        .into_iter()
+        .map(|(chain_id, server)| {
+            let mut io = IoHandler::new();
+            io.extend_with(server.to_delegate());
+            (chain_id, io)
+        })
+        .collect();
+
+    serve(handlers, addr)
+}
+
+/// Serve the given handlers, one per chain, over HTTP on the given address and blocks until closed.
+pub(crate) fn serve(
+    handlers: HashMap<chain::Id, IoHandler>,
+    addr: &str,
+) -> Result<(), error::Error> {
+    let io = MetaIoHandler::with_middleware(ChainRouter::new(handlers));
 
-    let srv = ServerBuilder::new(io)
+    let srv = ServerBuilder::with_meta_extractor(io, |request: &_| RequestPath::from(request))
         .cors(DomainsValidation::AllowOnly(vec![
             AccessControlAllowOrigin::Any,
         ]))
@@ -38,9 +62,15 @@ mod sealed {
     use jsonrpc_core::types::Error;
     use jsonrpc_derive::rpc;
 
-    use tendermint_light_client::supervisor::Handle;
+    use tendermint::block::Height;
+
+    use tendermint_light_client::errors::Error as LightClientError;
+    use tendermint_light_client::peer_list::PeerStatus;
+    use tendermint_light_client::store::Trace;
+    use tendermint_light_client::supervisor::{ForkReport, Handle};
     use tendermint_light_client::types::LatestStatus;
     use tendermint_light_client::types::LightBlock;
+    use tendermint_light_client::types::ValidatorSet;
 
     #[rpc]
     pub trait Rpc {
@@ -51,6 +81,32 @@ mod sealed {
         /// Returns the latest status.
         #[rpc(name = "status")]
         fn status(&self) -> FutureResult<LatestStatus, Error>;
+
+        /// Returns the trace of the verification of the block at the given height,
+        /// ie. the heights and validator set hashes of the blocks it relied on.
+        #[rpc(name = "verification_trace")]
+        fn verification_trace(&self, height: Height) -> FutureResult<Option<Trace>, Error>;
+
+        /// Verifies the block at the given height, and returns it.
+        #[rpc(name = "verify_to_target")]
+        fn verify_to_target(&self, height: Height) -> FutureResult<LightBlock, Error>;
+
+        /// Returns the block at the given height, if it was verified and is trusted.
+        #[rpc(name = "trusted_block")]
+        fn trusted_block(&self, height: Height) -> FutureResult<Option<LightBlock>, Error>;
+
+        /// Returns the validator set at the given height, if the block at that height
+        /// was verified and is trusted.
+        #[rpc(name = "validators")]
+        fn validators(&self, height: Height) -> FutureResult<Option<ValidatorSet>, Error>;
+
+        /// Returns the role and the faults of the primary, the witnesses and the other peers.
+        #[rpc(name = "witnesses")]
+        fn witnesses(&self) -> FutureResult<Vec<PeerStatus>, Error>;
+
+        /// Returns the reports of the latest forks detected, from the oldest to the latest.
+        #[rpc(name = "fork_reports")]
+        fn fork_reports(&self) -> FutureResult<Vec<ForkReport>, Error>;
     }
 
     pub use self::rpc_impl_Rpc::gen_client::Client;
@@ -76,27 +132,69 @@ mod sealed {
         H: Handle + Send + Sync + 'static,
     {
         fn state(&self) -> FutureResult<Option<LightBlock>, Error> {
-            let res = self.handle.latest_trusted().map_err(|e| {
-                let mut err = Error::internal_error();
-                err.message = e.to_string();
-                err.data = serde_json::to_value(e.kind()).ok();
-                err
-            });
+            let res = self.handle.latest_trusted().map_err(internal_error);
 
             future::result(res)
         }
 
         fn status(&self) -> FutureResult<LatestStatus, Error> {
-            let res = self.handle.latest_status().map_err(|e| {
-                let mut err = Error::internal_error();
-                err.message = e.to_string();
-                err.data = serde_json::to_value(e.kind()).ok();
-                err
-            });
+            let res = self.handle.latest_status().map_err(internal_error);
+
+            future::result(res)
+        }
+
+        fn verification_trace(&self, height: Height) -> FutureResult<Option<Trace>, Error> {
+            let res = self
+                .handle
+                .verification_trace(height)
+                .map_err(internal_error);
+
+            future::result(res)
+        }
+
+        fn verify_to_target(&self, height: Height) -> FutureResult<LightBlock, Error> {
+            let res = self.handle.verify_to_target(height).map_err(internal_error);
+
+            future::result(res)
+        }
+
+        fn trusted_block(&self, height: Height) -> FutureResult<Option<LightBlock>, Error> {
+            let res = self.handle.trusted_block(height).map_err(internal_error);
+
+            future::result(res)
+        }
+
+        fn validators(&self, height: Height) -> FutureResult<Option<ValidatorSet>, Error> {
+            let res = self
+                .handle
+                .trusted_block(height)
+                .map(|block| block.map(|block| block.validators))
+                .map_err(internal_error);
+
+            future::result(res)
+        }
+
+        fn witnesses(&self) -> FutureResult<Vec<PeerStatus>, Error> {
+            let res = self.handle.witnesses().map_err(internal_error);
+
+            future::result(res)
+        }
+
+        fn fork_reports(&self) -> FutureResult<Vec<ForkReport>, Error> {
+            let res = self.handle.fork_reports().map_err(internal_error);
 
             future::result(res)
         }
     }
+
+    /// Convert an error of the light client into an internal JSON-RPC error,
+    /// whose data is the kind of the error.
+    fn internal_error(e: LightClientError) -> Error {
+        let mut err = Error::internal_error();
+        err.message = e.to_string();
+        err.data = serde_json::to_value(e.kind()).ok();
+        err
+    }
 }
 
 #[cfg(test)]
@@ -108,7 +206,9 @@ mod test {
     use pretty_assertions::assert_eq;
 
     use tendermint_light_client::errors::Error;
-    use tendermint_light_client::supervisor::Handle;
+    use tendermint_light_client::peer_list::{PeerRole, PeerStatus};
+    use tendermint_light_client::store::Trace;
+    use tendermint_light_client::supervisor::{ForkReport, Handle};
     use tendermint_light_client::types::LatestStatus;
     use tendermint_light_client::types::LightBlock;
 
@@ -144,6 +244,77 @@ mod test {
         assert_eq!(have, want);
     }
 
+    #[tokio::test]
+    async fn verification_trace() {
+        let server = Server::new(MockHandle {});
+        let fut = {
+            let mut io = IoHandler::new();
+            io.extend_with(server.to_delegate());
+            let (client, server) = local::connect::<Client, _, _>(io);
+            client.verification_trace(3u32.into()).join(server)
+        };
+        let (have, _) = fut.compat().await.unwrap();
+        let want = serde_json::from_str(TRACE_JSON).unwrap();
+
+        assert_eq!(have, want);
+    }
+
+    #[tokio::test]
+    async fn verify_to_target() {
+        let server = Server::new(MockHandle {});
+        let fut = {
+            let mut io = IoHandler::new();
+            io.extend_with(server.to_delegate());
+            let (client, server) = local::connect::<Client, _, _>(io);
+            client.verify_to_target(1u32.into()).join(server)
+        };
+        let (have, _) = fut.compat().await.unwrap();
+        let want: LightBlock = serde_json::from_str(LIGHTBLOCK_JSON).unwrap();
+
+        assert_eq!(have, want);
+    }
+
+    #[tokio::test]
+    async fn trusted_block_and_validators() {
+        let server = Server::new(MockHandle {});
+        let fut = {
+            let mut io = IoHandler::new();
+            io.extend_with(server.to_delegate());
+            let (client, server) = local::connect::<Client, _, _>(io);
+            client
+                .trusted_block(1u32.into())
+                .join4(
+                    client.trusted_block(2u32.into()),
+                    client.validators(1u32.into()),
+                    client.validators(2u32.into()),
+                )
+                .join(server)
+        };
+        let ((block, missing_block, validators, missing_validators), _) =
+            fut.compat().await.unwrap();
+        let want: LightBlock = serde_json::from_str(LIGHTBLOCK_JSON).unwrap();
+
+        assert_eq!(validators, Some(want.validators.clone()));
+        assert_eq!(block, Some(want));
+        assert_eq!(missing_block, None);
+        assert_eq!(missing_validators, None);
+    }
+
+    #[tokio::test]
+    async fn witnesses_and_fork_reports() {
+        let server = Server::new(MockHandle {});
+        let fut = {
+            let mut io = IoHandler::new();
+            io.extend_with(server.to_delegate());
+            let (client, server) = local::connect::<Client, _, _>(io);
+            client.witnesses().join(client.fork_reports()).join(server)
+        };
+        let ((witnesses, fork_reports), _) = fut.compat().await.unwrap();
+
+        assert_eq!(witnesses, MockHandle.witnesses().unwrap());
+        assert_eq!(fork_reports, vec![]);
+    }
+
     struct MockHandle;
 
     impl Handle for MockHandle {
@@ -159,19 +330,65 @@ mod test {
             Ok(status)
         }
 
+        fn verification_trace(
+            &self,
+            height: tendermint::block::Height,
+        ) -> Result<Option<Trace>, Error> {
+            let trace: Trace = serde_json::from_str(TRACE_JSON).unwrap();
+
+            Ok(Some(trace).filter(|trace| trace.target_height == height))
+        }
+
+        fn trusted_block(
+            &self,
+            height: tendermint::block::Height,
+        ) -> Result<Option<LightBlock>, Error> {
+            let block: LightBlock = serde_json::from_str(LIGHTBLOCK_JSON).unwrap();
+
+            Ok(Some(block).filter(|block| block.height() == height))
+        }
+
+        fn witnesses(&self) -> Result<Vec<PeerStatus>, Error> {
+            let status = |peer_id: &str, role, timeouts| PeerStatus {
+                peer_id: peer_id.parse().unwrap(),
+                role,
+                timeouts,
+                misbehaviours: 0,
+            };
+
+            Ok(vec![
+                status(
+                    "9D61B19DEFFD5A60BA844AF492EC2CC44449C569",
+                    PeerRole::Primary,
+                    0,
+                ),
+                status(
+                    "BADFADAD0BEFEEDC0C0ADEADBEEFC0FFEEFACADE",
+                    PeerRole::OnProbation,
+                    1,
+                ),
+            ])
+        }
+
+        fn fork_reports(&self) -> Result<Vec<ForkReport>, Error> {
+            Ok(vec![])
+        }
+
         fn verify_to_highest(&self) -> Result<LightBlock, Error> {
-            todo!()
+            unreachable!("the RPC server only verifies blocks at a given height")
         }
 
         fn verify_to_target(
             &self,
             _height: tendermint::block::Height,
         ) -> Result<LightBlock, Error> {
-            todo!()
+            let block: LightBlock = serde_json::from_str(LIGHTBLOCK_JSON).unwrap();
+
+            Ok(block)
         }
 
         fn terminate(&self) -> Result<(), Error> {
-            todo!()
+            unreachable!("the RPC server does not terminate the supervisor")
         }
     }
 
@@ -305,5 +522,21 @@ mod test {
     ],
     "height": 1565,
     "valset_hash": "74F2AC2B6622504D08DD2509E28CE731985CFE4D133C9DB0CB85763EDCA95AA3"
+}"#;
+    const TRACE_JSON: &str = r#"
+{
+    "target_height": "3",
+    "entries": [
+        {
+            "height": "1",
+            "validators_hash": "ADAE23D9D908638F3866C11A39E31CE4399AE6DE8EC8EBBCB1916B90C46EDDE3",
+            "next_validators_hash": "ADAE23D9D908638F3866C11A39E31CE4399AE6DE8EC8EBBCB1916B90C46EDDE3"
+        },
+        {
+            "height": "3",
+            "validators_hash": "ADAE23D9D908638F3866C11A39E31CE4399AE6DE8EC8EBBCB1916B90C46EDDE3",
+            "next_validators_hash": "74F2AC2B6622504D08DD2509E28CE731985CFE4D133C9DB0CB85763EDCA95AA3"
+        }
+    ]
 }"#;
 }
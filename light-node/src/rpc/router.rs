@@ -0,0 +1,162 @@
+//! Routing of the JSON-RPC requests to the handler of the chain they are addressed to.
+//!
+//! The handler of each chain is served on `/chain/<id>`. When a single chain is served,
+//! its handler is also served on `/`, so that clients of a single-chain light node
+//! do not need to know about chain ids.
+use std::collections::HashMap;
+
+use jsonrpc_core::futures::future::{self, Either};
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::middleware::{Middleware, NoopCallFuture};
+use jsonrpc_core::types::{Call, Error, ErrorCode, Output, Request, Response, Version};
+use jsonrpc_core::{FutureResponse, IoHandler, Metadata};
+use jsonrpc_http_server::hyper::{Body, Request as HttpRequest};
+
+use tendermint::chain;
+
+/// Prefix of the paths the chains are served on
+pub const CHAIN_PATH_PREFIX: &str = "/chain/";
+
+/// Path of the HTTP request a JSON-RPC request was received on.
+#[derive(Clone, Debug, Default)]
+pub struct RequestPath(String);
+
+impl Metadata for RequestPath {}
+
+impl From<&HttpRequest<Body>> for RequestPath {
+    fn from(request: &HttpRequest<Body>) -> Self {
+        Self(request.uri().path().to_string())
+    }
+}
+
+/// Middleware dispatching each request to the handler of the chain it is addressed to,
+/// as per the path of the request.
+pub struct ChainRouter {
+    handlers: HashMap<chain::Id, IoHandler>,
+}
+
+impl ChainRouter {
+    /// Route the requests to the given handlers, one per chain.
+    pub fn new(handlers: HashMap<chain::Id, IoHandler>) -> Self {
+        Self { handlers }
+    }
+
+    /// The handler of the chain the given path points to, if any.
+    fn route(&self, path: &str) -> Result<&IoHandler, Error> {
+        let path = path.trim_end_matches('/');
+
+        if path.is_empty() && self.handlers.len() == 1 {
+            return Ok(self.handlers.values().next().unwrap()); // Safe, see check above
+        }
+
+        path.strip_prefix(CHAIN_PATH_PREFIX)
+            .and_then(|id| id.parse::<chain::Id>().ok())
+            .and_then(|id| self.handlers.get(&id))
+            .ok_or_else(|| Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!(
+                    "no chain is served on `{}`, chains are served on `{}<id>`",
+                    if path.is_empty() { "/" } else { path },
+                    CHAIN_PATH_PREFIX
+                ),
+                data: None,
+            })
+    }
+}
+
+impl Middleware<RequestPath> for ChainRouter {
+    type Future = FutureResponse;
+    type CallFuture = NoopCallFuture;
+
+    fn on_request<F, X>(
+        &self,
+        request: Request,
+        path: RequestPath,
+        _next: F,
+    ) -> Either<Self::Future, X>
+    where
+        F: Fn(Request, RequestPath) -> X + Send + Sync,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        match self.route(&path.0) {
+            Ok(handler) => Either::A(Box::new(handler.handle_rpc_request(request))),
+            Err(error) => Either::A(Box::new(future::ok(reject(request, error)))),
+        }
+    }
+}
+
+/// Respond to every call of the given request with the given error.
+fn reject(request: Request, error: Error) -> Option<Response> {
+    let reject_call = |call: Call| match call {
+        Call::MethodCall(call) => Some(Output::from(Err(error.clone()), call.id, call.jsonrpc)),
+        Call::Notification(_) => None,
+        Call::Invalid { id } => Some(Output::from(Err(error.clone()), id, Some(Version::V2))),
+    };
+
+    match request {
+        Request::Single(call) => reject_call(call).map(Response::Single),
+        Request::Batch(calls) => {
+            let outputs: Vec<_> = calls.into_iter().filter_map(reject_call).collect();
+
+            if outputs.is_empty() {
+                None
+            } else {
+                Some(Response::Batch(outputs))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jsonrpc_core::{MetaIoHandler, Value};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn handler(chain: &'static str) -> IoHandler {
+        let mut io = IoHandler::new();
+        io.add_method("chain", move |_| Ok(Value::String(chain.to_string())));
+        io
+    }
+
+    fn router(chains: &[&'static str]) -> MetaIoHandler<RequestPath, ChainRouter> {
+        let handlers = chains
+            .iter()
+            .map(|chain| (chain.parse().unwrap(), handler(chain)))
+            .collect();
+
+        MetaIoHandler::with_middleware(ChainRouter::new(handlers))
+    }
+
+    fn call(io: &MetaIoHandler<RequestPath, ChainRouter>, path: &str) -> Value {
+        let request = r#"{"jsonrpc": "2.0", "method": "chain", "params": [], "id": 1}"#;
+        let response = io
+            .handle_request_sync(request, RequestPath(path.to_string()))
+            .unwrap();
+
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn requests_are_routed_to_their_chain() {
+        let io = router(&["chain-a", "chain-b"]);
+
+        assert_eq!(call(&io, "/chain/chain-a")["result"], "chain-a");
+        assert_eq!(call(&io, "/chain/chain-b/")["result"], "chain-b");
+
+        for path in &["/", "/chain/chain-c", "/chains/chain-a"] {
+            let response = call(&io, path);
+            assert_eq!(response["error"]["code"], -32600, "path: {}", path);
+            assert_eq!(response["id"], 1);
+        }
+    }
+
+    #[test]
+    fn single_chain_is_also_served_on_root() {
+        let io = router(&["chain-a"]);
+
+        assert_eq!(call(&io, "/")["result"], "chain-a");
+        assert_eq!(call(&io, "/chain/chain-a")["result"], "chain-a");
+    }
+}
@@ -0,0 +1,316 @@
+//! Synchronization of the light node with its primary.
+//!
+//! In `poll` mode, the latest block of the primary is verified at a fixed interval.
+//! In `subscribe` mode, the light node subscribes to the `NewBlock` events of the primary
+//! over its WebSocket endpoint, and verifies every block it announces. Should the subscription
+//! drop, the light node polls the primary until it manages to subscribe again.
+use std::time::{Duration, Instant};
+
+use abscissa_core::{status_err, status_info};
+use tokio::runtime::Runtime;
+use tokio::stream::StreamExt as _;
+
+use tendermint::block::Height;
+use tendermint::{chain, net};
+use tendermint_light_client::errors::Error;
+use tendermint_light_client::supervisor::Handle;
+use tendermint_light_client::types::LightBlock;
+use tendermint_rpc::event::{Event, EventData};
+use tendermint_rpc::query::EventType;
+use tendermint_rpc::{Subscription, SubscriptionClient, WebSocketClient};
+
+use crate::config::{SyncConfig, SyncMode};
+
+/// Keep the supervisor of the given chain behind the given handle in sync with the primary
+/// at the given address, as per the given config. Never returns.
+pub fn run<H>(chain_id: &chain::Id, handle: &H, primary: net::Address, config: &SyncConfig)
+where
+    H: Handle,
+{
+    let mut syncer = Syncer::new(chain_id, handle);
+
+    if config.mode == SyncMode::Poll {
+        return syncer.poll(config.poll_interval, None);
+    }
+
+    let mut rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            status_err!(
+                "failed to start runtime for {}, falling back to polling: {}",
+                chain_id,
+                e
+            );
+            return syncer.poll(config.poll_interval, None);
+        }
+    };
+
+    loop {
+        match rt.block_on(subscribe(primary.clone())) {
+            Ok((client, mut subscription)) => {
+                status_info!(
+                    "subscribed",
+                    "to new blocks of {} from primary {}",
+                    chain_id,
+                    primary
+                );
+
+                while let Some(result) = rt.block_on(subscription.next()) {
+                    match result {
+                        Ok(event) => {
+                            if let Some(result) =
+                                new_block_height(&event).and_then(|h| syncer.sync_to_target(h))
+                            {
+                                syncer.report(result);
+                            }
+                        }
+                        Err(e) => {
+                            status_err!("subscription to new blocks of {} failed: {}", chain_id, e);
+                            break;
+                        }
+                    }
+                }
+
+                // The driver may already be gone if the connection dropped
+                let _ = client.close();
+                status_err!(
+                    "subscription to new blocks of {} dropped, falling back to polling",
+                    chain_id
+                );
+            }
+            Err(e) => {
+                status_err!(
+                    "failed to subscribe to new blocks of {}, falling back to polling: {}",
+                    chain_id,
+                    e
+                );
+            }
+        }
+
+        syncer.poll(config.poll_interval, Some(config.resubscribe_interval));
+    }
+}
+
+/// Connect to the WebSocket endpoint of the primary at the given address,
+/// and subscribe to its new blocks.
+async fn subscribe(
+    primary: net::Address,
+) -> Result<(WebSocketClient, Subscription), tendermint_rpc::Error> {
+    let (client, driver) = WebSocketClient::new(primary).await?;
+
+    tokio::spawn(async move {
+        if let Err(e) = driver.run().await {
+            status_err!("WebSocket connection to primary failed: {}", e);
+        }
+    });
+
+    let subscription = client.subscribe(EventType::NewBlock.into()).await?;
+
+    Ok((client, subscription))
+}
+
+/// The height of the block announced by the given event, if it is a `NewBlock` event.
+fn new_block_height(event: &Event) -> Option<Height> {
+    match &event.data {
+        EventData::NewBlock {
+            block: Some(block), ..
+        } => Some(block.header.height),
+        _ => None,
+    }
+}
+
+/// Verifies blocks through a handle, keeping track of the latest block it synced to.
+struct Syncer<'a, H> {
+    chain_id: &'a chain::Id,
+    handle: &'a H,
+    latest: Option<Height>,
+}
+
+impl<'a, H> Syncer<'a, H>
+where
+    H: Handle,
+{
+    fn new(chain_id: &'a chain::Id, handle: &'a H) -> Self {
+        Self {
+            chain_id,
+            handle,
+            latest: None,
+        }
+    }
+
+    /// Verify the latest block of the primary every `interval`, for the given duration if any,
+    /// or forever otherwise.
+    fn poll(&mut self, interval: Duration, duration: Option<Duration>) {
+        let start = Instant::now();
+
+        while duration.is_none_or(|duration| start.elapsed() < duration) {
+            let result = self.sync_to_highest();
+            self.report(result);
+            std::thread::sleep(interval);
+        }
+    }
+
+    /// Verify the latest block of the primary.
+    fn sync_to_highest(&mut self) -> Result<LightBlock, Error> {
+        let result = self.handle.verify_to_highest();
+        self.record(result)
+    }
+
+    /// Verify the block at the given height, unless the light node already synced
+    /// to that height or above, eg. when blocks are announced faster than they are verified.
+    fn sync_to_target(&mut self, height: Height) -> Option<Result<LightBlock, Error>> {
+        if matches!(self.latest, Some(latest) if latest >= height) {
+            return None;
+        }
+
+        let result = self.handle.verify_to_target(height);
+        Some(self.record(result))
+    }
+
+    fn record(&mut self, result: Result<LightBlock, Error>) -> Result<LightBlock, Error> {
+        if let Ok(light_block) = &result {
+            self.latest = Some(light_block.height());
+        }
+
+        result
+    }
+
+    /// Report the outcome of a verification on the terminal.
+    fn report(&self, result: Result<LightBlock, Error>) {
+        match result {
+            Ok(light_block) => {
+                status_info!(
+                    "synced",
+                    "{} to block {}",
+                    self.chain_id,
+                    light_block.height()
+                );
+            }
+            Err(err) => {
+                status_err!("sync of {} failed: {}", self.chain_id, err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+    use std::sync::Mutex;
+
+    use tendermint_light_client::types::LatestStatus;
+    use tendermint_testgen::{light_block::default_peer_id, Generator, LightChain};
+
+    use super::*;
+
+    #[test]
+    fn announced_blocks_are_verified_once() {
+        let handle = MockHandle::new(10);
+        let chain_id = "test-chain".parse().unwrap();
+        let mut syncer = Syncer::new(&chain_id, &handle);
+
+        for height in &[3_u64, 2, 3, 5, 4, 6] {
+            let _ = syncer.sync_to_target((*height).try_into().unwrap());
+        }
+
+        assert_eq!(handle.verified_heights(), vec![3, 5, 6]);
+
+        syncer.sync_to_highest().unwrap();
+        assert_eq!(syncer.latest, Some(10_u64.try_into().unwrap()));
+
+        assert!(syncer.sync_to_target(8_u64.try_into().unwrap()).is_none());
+        assert_eq!(handle.verified_heights(), vec![3, 5, 6, 10]);
+    }
+
+    #[test]
+    fn new_block_height_is_extracted_from_new_block_events() {
+        let handle = MockHandle::new(5);
+        let block = handle.light_blocks[4].clone();
+
+        let event = |data| Event {
+            query: "tm.event = 'NewBlock'".to_string(),
+            data,
+            events: None,
+        };
+
+        let new_block = event(EventData::NewBlock {
+            block: Some(
+                tendermint::Block::new(
+                    block.signed_header.header,
+                    Default::default(),
+                    Default::default(),
+                    Some(block.signed_header.commit),
+                )
+                .unwrap(),
+            ),
+            result_begin_block: None,
+            result_end_block: None,
+        });
+        assert_eq!(new_block_height(&new_block), Some(5_u32.into()));
+
+        let empty_block = event(EventData::NewBlock {
+            block: None,
+            result_begin_block: None,
+            result_end_block: None,
+        });
+        assert_eq!(new_block_height(&empty_block), None);
+    }
+
+    /// Handle to a chain of the given length, which records the heights it verified.
+    struct MockHandle {
+        light_blocks: Vec<LightBlock>,
+        verified: Mutex<Vec<Height>>,
+    }
+
+    impl MockHandle {
+        fn new(length: u64) -> Self {
+            let light_blocks = LightChain::default_with_length(length)
+                .light_blocks
+                .into_iter()
+                .map(|lb| {
+                    let lb = lb.generate().unwrap();
+                    LightBlock::new(
+                        lb.signed_header,
+                        lb.validators,
+                        lb.next_validators,
+                        default_peer_id(),
+                    )
+                })
+                .collect();
+
+            Self {
+                light_blocks,
+                verified: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn verified_heights(&self) -> Vec<u64> {
+            let verified = self.verified.lock().unwrap();
+            verified.iter().map(Height::value).collect()
+        }
+    }
+
+    impl Handle for MockHandle {
+        fn latest_trusted(&self) -> Result<Option<LightBlock>, Error> {
+            unreachable!("the syncer does not query the latest trusted block")
+        }
+
+        fn latest_status(&self) -> Result<LatestStatus, Error> {
+            unreachable!("the syncer does not query the status of the supervisor")
+        }
+
+        fn verify_to_highest(&self) -> Result<LightBlock, Error> {
+            let latest = self.light_blocks.last().unwrap().clone();
+            self.verify_to_target(latest.height())
+        }
+
+        fn verify_to_target(&self, height: Height) -> Result<LightBlock, Error> {
+            self.verified.lock().unwrap().push(height);
+            Ok(self.light_blocks[height.value() as usize - 1].clone())
+        }
+
+        fn terminate(&self) -> Result<(), Error> {
+            unreachable!("the syncer does not terminate the supervisor")
+        }
+    }
+}
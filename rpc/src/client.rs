@@ -165,6 +165,12 @@ pub trait Client {
         self.perform(evidence::Request::new(e)).await
     }
 
+    /// `/tx`: get a transaction by its hash, optionally with a proof of its
+    /// inclusion in the block.
+    async fn tx(&self, hash: abci::transaction::Hash, prove: bool) -> Result<tx::Response> {
+        self.perform(tx::Request::new(hash, prove)).await
+    }
+
     /// `/tx_search`: search for transactions with their results.
     async fn tx_search(
         &self,
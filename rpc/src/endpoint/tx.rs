@@ -0,0 +1,71 @@
+//! `/tx` endpoint JSON-RPC wrapper
+
+use serde::{Deserialize, Serialize};
+use tendermint::{abci, block};
+use tendermint_proto::types::TxProof;
+
+/// Request for finding a transaction by its hash.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Request {
+    /// Hash of the transaction
+    #[serde(with = "hash_base64")]
+    pub hash: abci::transaction::Hash,
+
+    /// Include a proof of the transaction inclusion in the block
+    pub prove: bool,
+}
+
+impl Request {
+    /// Constructor.
+    pub fn new(hash: abci::transaction::Hash, prove: bool) -> Self {
+        Self { hash, prove }
+    }
+}
+
+impl crate::Request for Request {
+    type Response = Response;
+
+    fn method(&self) -> crate::Method {
+        crate::Method::Tx
+    }
+}
+
+impl crate::SimpleRequest for Request {}
+
+/// Transaction response, along with its result.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Response {
+    pub hash: abci::transaction::Hash,
+    pub height: block::Height,
+    pub index: u32,
+    pub tx_result: abci::DeliverTx,
+    pub tx: abci::Transaction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<TxProof>,
+}
+
+impl crate::Response for Response {}
+
+/// The `hash` parameter of the `/tx` endpoint is encoded as base64 in JSON-RPC requests,
+/// unlike the transaction hashes in responses, which are hex-encoded.
+mod hash_base64 {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+    use std::convert::TryInto;
+    use subtle_encoding::base64;
+    use tendermint::abci::transaction::Hash;
+
+    pub fn serialize<S: Serializer>(hash: &Hash, serializer: S) -> Result<S::Ok, S::Error> {
+        let encoded = String::from_utf8(base64::encode(hash.as_bytes())).unwrap();
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Hash, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = base64::decode(&encoded).map_err(D::Error::custom)?;
+        let bytes: [u8; 32] = bytes.as_slice().try_into().map_err(|_| {
+            D::Error::custom(format!("invalid transaction hash length: {}", bytes.len()))
+        })?;
+
+        Ok(Hash::new(bytes))
+    }
+}
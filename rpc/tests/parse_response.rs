@@ -355,6 +355,23 @@ fn tx_search_with_prove() {
     assert_eq!(events[0].attributes[0].value.as_ref(), "Cosmoshi Netowoko");
 }
 
+#[test]
+fn tx() {
+    let response = endpoint::tx::Response::from_string(&read_json_fixture("tx")).unwrap();
+
+    assert_eq!(
+        "9F28904F9C0F3AB74A81CBA48E39124DA1C680B47FBFCBA0126870DB722BCC30",
+        response.hash.to_string()
+    );
+    assert_eq!(11, response.height.value());
+    assert_eq!(0, response.index);
+    assert_eq!(response.tx.as_bytes(), b"async-key=value");
+
+    let proof = response.proof.unwrap();
+    assert_eq!(proof.data, response.tx.as_bytes());
+    assert_eq!(proof.proof.unwrap().total, 3);
+}
+
 #[test]
 fn consensus_state() {
     let response =
@@ -177,6 +177,10 @@ pub enum Kind {
     #[error("missing public key")]
     MissingPublicKey,
 
+    /// Missing validator set
+    #[error("missing validator set")]
+    MissingValidatorSet,
+
     /// Invalid validator parameters
     #[error("invalid validator parameters")]
     InvalidValidatorParams,
@@ -193,6 +197,14 @@ pub enum Kind {
     #[error("missing max_age_duration")]
     MissingMaxAgeDuration,
 
+    /// Invalid Merkle proof
+    #[error("invalid merkle proof")]
+    InvalidProof,
+
+    /// Unknown type of Merkle proof operation
+    #[error("unknown proof operation type '{}'", _0)]
+    UnknownProofOp(String),
+
     /// Proposer not found in validator set
     #[error("proposer with address '{}' not found in validator set", _0)]
     ProposerNotFound(account::Id),
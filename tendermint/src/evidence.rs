@@ -1,7 +1,10 @@
 //! Evidence of malfeasance by validators (i.e. signing conflicting votes).
 
 use crate::{
-    block::signed_header::SignedHeader, serializers, vote::Power, Error, Kind, Time, Vote,
+    block::{signed_header::SignedHeader, Height},
+    serializers, validator,
+    vote::Power,
+    Error, Kind, Time, Vote,
 };
 use serde::{Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
@@ -13,11 +16,13 @@ use tendermint_proto::types::DuplicateVoteEvidence as RawDuplicateVoteEvidence;
 use tendermint_proto::types::Evidence as RawEvidence;
 use tendermint_proto::types::EvidenceList as RawEvidenceList;
 use tendermint_proto::types::EvidenceParams as RawEvidenceParams;
+use tendermint_proto::types::LightBlock as RawLightBlock;
+use tendermint_proto::types::LightClientAttackEvidence as RawLightClientAttackEvidence;
 use tendermint_proto::Protobuf;
 
 /// Evidence of malfeasance by validators (i.e. signing conflicting votes).
-/// encoded using an Amino prefix. There is currently only a single type of
-/// evidence: `DuplicateVoteEvidence`.
+/// There are currently two types of evidence encodable with protobuf:
+/// `DuplicateVoteEvidence` and `LightClientAttackEvidence`.
 ///
 /// <https://github.com/tendermint/spec/blob/d46cd7f573a2c6a2399fcab2cde981330aa63f37/spec/core/data_structures.md#evidence>
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -32,8 +37,8 @@ pub enum Evidence {
     //#[serde(rename = "tendermint/ConflictingHeadersEvidence")]
     ConflictingHeaders(Box<ConflictingHeadersEvidence>),
 
-    /// LightClient attack evidence - Todo: Implement details
-    LightClientAttackEvidence,
+    /// Light client attack evidence
+    LightClientAttack(Box<LightClientAttackEvidence>),
 }
 
 impl TryFrom<RawEvidence> for Evidence {
@@ -42,7 +47,9 @@ impl TryFrom<RawEvidence> for Evidence {
     fn try_from(value: RawEvidence) -> Result<Self, Self::Error> {
         match value.sum.ok_or(Kind::InvalidEvidence)? {
             Sum::DuplicateVoteEvidence(ev) => Ok(Evidence::DuplicateVote(ev.try_into()?)),
-            Sum::LightClientAttackEvidence(_ev) => Ok(Evidence::LightClientAttackEvidence),
+            Sum::LightClientAttackEvidence(ev) => {
+                Ok(Evidence::LightClientAttack(Box::new(ev.try_into()?)))
+            }
         }
     }
 }
@@ -54,7 +61,9 @@ impl From<Evidence> for RawEvidence {
                 sum: Some(RawSum::DuplicateVoteEvidence(ev.into())),
             },
             Evidence::ConflictingHeaders(_ev) => RawEvidence { sum: None }, // Todo: implement
-            Evidence::LightClientAttackEvidence => RawEvidence { sum: None }, // Todo: implement
+            Evidence::LightClientAttack(ev) => RawEvidence {
+                sum: Some(RawSum::LightClientAttackEvidence((*ev).into())),
+            },
         }
     }
 }
@@ -133,6 +142,155 @@ impl ConflictingHeadersEvidence {
     }
 }
 
+/// Evidence of a set of validators attempting to mislead a light client,
+/// by signing a block which conflicts with the one on the main chain.
+///
+/// <https://github.com/tendermint/spec/blob/master/spec/core/data_structures.md#lightclientattackevidence>
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightClientAttackEvidence {
+    conflicting_block: ConflictingBlock,
+    common_height: Height,
+    byzantine_validators: Vec<validator::Info>,
+    total_voting_power: Power,
+    timestamp: Time,
+}
+
+impl Protobuf<RawLightClientAttackEvidence> for LightClientAttackEvidence {}
+
+impl TryFrom<RawLightClientAttackEvidence> for LightClientAttackEvidence {
+    type Error = Error;
+
+    fn try_from(value: RawLightClientAttackEvidence) -> Result<Self, Self::Error> {
+        Self::new(
+            value
+                .conflicting_block
+                .ok_or(Kind::MissingEvidence)?
+                .try_into()?,
+            value.common_height.try_into()?,
+            value
+                .byzantine_validators
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<_>, _>>()?,
+            value.total_voting_power.try_into()?,
+            value.timestamp.ok_or(Kind::MissingTimestamp)?.try_into()?,
+        )
+    }
+}
+
+impl From<LightClientAttackEvidence> for RawLightClientAttackEvidence {
+    fn from(value: LightClientAttackEvidence) -> Self {
+        RawLightClientAttackEvidence {
+            conflicting_block: Some(value.conflicting_block.into()),
+            common_height: value.common_height.into(),
+            byzantine_validators: value
+                .byzantine_validators
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            total_voting_power: value.total_voting_power.into(),
+            timestamp: Some(value.timestamp.into()),
+        }
+    }
+}
+
+impl LightClientAttackEvidence {
+    /// Constructor.
+    ///
+    /// The common height cannot be higher than the height of the conflicting block.
+    pub fn new(
+        conflicting_block: ConflictingBlock,
+        common_height: Height,
+        byzantine_validators: Vec<validator::Info>,
+        total_voting_power: Power,
+        timestamp: Time,
+    ) -> Result<Self, Error> {
+        if common_height > conflicting_block.signed_header.header.height {
+            return Err(Kind::InvalidEvidence.into());
+        }
+        Ok(Self {
+            conflicting_block,
+            common_height,
+            byzantine_validators,
+            total_voting_power,
+            timestamp,
+        })
+    }
+
+    /// Get the conflicting block
+    pub fn conflicting_block(&self) -> &ConflictingBlock {
+        &self.conflicting_block
+    }
+
+    /// Get the height of the last block both chains have in common
+    pub fn common_height(&self) -> Height {
+        self.common_height
+    }
+
+    /// Get the validators which took part in the attack
+    pub fn byzantine_validators(&self) -> &[validator::Info] {
+        &self.byzantine_validators
+    }
+
+    /// Get the total voting power of the validator set at the common height
+    pub fn total_voting_power(&self) -> Power {
+        self.total_voting_power
+    }
+
+    /// Get the time of the block at the common height
+    pub fn timestamp(&self) -> Time {
+        self.timestamp
+    }
+}
+
+/// The block a light client was presented with, which conflicts with the main chain.
+/// Consists of a signed header and the validator set which signed it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConflictingBlock {
+    /// Signed header of the conflicting block
+    pub signed_header: SignedHeader,
+    /// Validator set at the height of the conflicting block
+    pub validator_set: validator::Set,
+}
+
+impl Protobuf<RawLightBlock> for ConflictingBlock {}
+
+impl TryFrom<RawLightBlock> for ConflictingBlock {
+    type Error = Error;
+
+    fn try_from(value: RawLightBlock) -> Result<Self, Self::Error> {
+        Ok(Self {
+            signed_header: value
+                .signed_header
+                .ok_or(Kind::InvalidSignedHeader)?
+                .try_into()?,
+            validator_set: value
+                .validator_set
+                .ok_or(Kind::MissingValidatorSet)?
+                .try_into()?,
+        })
+    }
+}
+
+impl From<ConflictingBlock> for RawLightBlock {
+    fn from(value: ConflictingBlock) -> Self {
+        RawLightBlock {
+            signed_header: Some(value.signed_header.into()),
+            validator_set: Some(value.validator_set.into()),
+        }
+    }
+}
+
+impl ConflictingBlock {
+    /// Constructor.
+    pub fn new(signed_header: SignedHeader, validator_set: validator::Set) -> Self {
+        Self {
+            signed_header,
+            validator_set,
+        }
+    }
+}
+
 /// Evidence data is a wrapper for a list of `Evidence`.
 ///
 /// <https://github.com/tendermint/spec/blob/d46cd7f573a2c6a2399fcab2cde981330aa63f37/spec/core/data_structures.md#evidencedata>
@@ -287,3 +445,41 @@ impl From<Duration> for RawDuration {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Evidence, LightClientAttackEvidence};
+    use crate::test::test_serialization_roundtrip;
+    use tendermint_proto::Protobuf;
+
+    const LIGHT_CLIENT_ATTACK_EVIDENCE: &str =
+        include_str!("../tests/support/serialization/evidence/light_client_attack_evidence.json");
+
+    #[test]
+    fn light_client_attack_evidence_serialization_roundtrip() {
+        test_serialization_roundtrip::<Evidence>(LIGHT_CLIENT_ATTACK_EVIDENCE);
+    }
+
+    #[test]
+    fn light_client_attack_evidence_protobuf_roundtrip() {
+        let evidence: Evidence = serde_json::from_str(LIGHT_CLIENT_ATTACK_EVIDENCE).unwrap();
+        let evidence = match evidence {
+            Evidence::LightClientAttack(ev) => *ev,
+            _ => panic!("expected light client attack evidence"),
+        };
+
+        assert_eq!(evidence.common_height().value(), 4);
+        assert_eq!(evidence.byzantine_validators().len(), 2);
+        assert_eq!(
+            evidence.total_voting_power(),
+            evidence
+                .conflicting_block()
+                .validator_set
+                .total_voting_power()
+        );
+
+        let bytes = evidence.encode_vec().unwrap();
+        let decoded = LightClientAttackEvidence::decode_vec(&bytes).unwrap();
+        assert_eq!(decoded, evidence);
+    }
+}
@@ -1,6 +1,8 @@
 //! Merkle tree used in Tendermint networks
 
+pub mod ops;
 pub mod proof;
+pub mod runtime;
 
 use sha2::{Digest, Sha256};
 
@@ -32,6 +34,38 @@ fn simple_hash_from_byte_slices_inner(byte_slices: &[Vec<u8>]) -> Hash {
     }
 }
 
+/// Compute the root hash of a simple Merkle tree with `total` leaves from the hash of the
+/// leaf at the given `index` and the hashes of its aunts, ordered from the bottom of the
+/// tree to its top, or return `None` if these are inconsistent.
+pub fn compute_hash_from_aunts(
+    index: u64,
+    total: u64,
+    leaf_hash: Hash,
+    aunts: &[Hash],
+) -> Option<Hash> {
+    if index >= total || total == 0 {
+        return None;
+    }
+
+    match total {
+        1 if aunts.is_empty() => Some(leaf_hash),
+        1 => None,
+        _ => {
+            let (last, aunts) = aunts.split_last()?;
+            let num_left = get_split_point(total as usize) as u64;
+
+            if index < num_left {
+                let left = compute_hash_from_aunts(index, num_left, leaf_hash, aunts)?;
+                Some(inner_hash(&left, last))
+            } else {
+                let right =
+                    compute_hash_from_aunts(index - num_left, total - num_left, leaf_hash, aunts)?;
+                Some(inner_hash(last, &right))
+            }
+        }
+    }
+}
+
 // returns the largest power of 2 less than length
 fn get_split_point(length: usize) -> usize {
     match length {
@@ -56,8 +90,8 @@ fn empty_hash() -> Hash {
     hash_bytes
 }
 
-// tmhash(0x00 || leaf)
-fn leaf_hash(bytes: &[u8]) -> Hash {
+/// Compute the hash of a leaf of a simple Merkle tree, ie. `tmhash(0x00 || leaf)`.
+pub fn leaf_hash(bytes: &[u8]) -> Hash {
     // make a new array starting with 0 and copy in the bytes
     let mut leaf_bytes = Vec::with_capacity(bytes.len() + 1);
     leaf_bytes.push(0x00);
@@ -108,6 +142,37 @@ mod tests {
         assert_eq!(get_split_point(257), 256);
     }
 
+    #[test]
+    fn test_compute_hash_from_aunts() {
+        let leaves: Vec<Vec<u8>> = (0..5_u8).map(|i| vec![i; 4]).collect();
+        let root = simple_hash_from_byte_vectors(leaves.clone());
+
+        // Aunts of the leaf at index 2, in a tree of shape ((0, 1), (2, 3)), 4
+        let aunts = [
+            leaf_hash(&leaves[3]),
+            inner_hash(&leaf_hash(&leaves[0]), &leaf_hash(&leaves[1])),
+            leaf_hash(&leaves[4]),
+        ];
+        let computed = compute_hash_from_aunts(2, 5, leaf_hash(&leaves[2]), &aunts);
+        assert_eq!(computed, Some(root));
+
+        // Aunts of the leaf at index 4
+        let aunts = [simple_hash_from_byte_vectors(leaves[..4].to_vec())];
+        let computed = compute_hash_from_aunts(4, 5, leaf_hash(&leaves[4]), &aunts);
+        assert_eq!(computed, Some(root));
+
+        let computed = compute_hash_from_aunts(3, 5, leaf_hash(&leaves[4]), &aunts);
+        assert_ne!(computed, Some(root));
+        assert_eq!(
+            compute_hash_from_aunts(5, 5, leaf_hash(&leaves[4]), &aunts),
+            None
+        );
+        assert_eq!(
+            compute_hash_from_aunts(0, 1, leaf_hash(&leaves[0]), &aunts),
+            None
+        );
+    }
+
     #[test]
     fn test_rfc6962_empty_tree() {
         let empty_tree_root_hex =
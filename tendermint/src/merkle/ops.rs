@@ -0,0 +1,639 @@
+//! Merkle proof operations
+//!
+//! A Merkle proof is made of a chain of operations, each of which proves that
+//! a value, or the root hash computed by the previous operation, is committed
+//! under a given key in a Merkle tree, and outputs the root hash of that tree.
+//!
+//! This module provides the operations used by Tendermint and the Cosmos SDK:
+//! - [`ValueOp`] (`simple:v`) for simple Merkle trees
+//! - [`IavlValueOp`] (`iavl:v`) for IAVL trees
+//! - [`MultiStoreOp`] (`multistore`) for the Cosmos SDK multistore
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt::Debug;
+
+use prost::Message;
+use sha2::{Digest, Sha256};
+use tendermint_proto::crypto::ValueOp as RawValueOp;
+
+use super::proof::ProofOp;
+use super::{compute_hash_from_aunts, leaf_hash, simple_hash_from_byte_vectors, Hash, HASH_SIZE};
+use crate::{Error, Kind};
+
+/// Type of the proof operation for simple Merkle trees
+pub const PROOF_OP_SIMPLE_VALUE: &str = "simple:v";
+
+/// Type of the proof operation for IAVL trees
+pub const PROOF_OP_IAVL_VALUE: &str = "iavl:v";
+
+/// Type of the proof operation for the Cosmos SDK multistore
+pub const PROOF_OP_MULTISTORE: &str = "multistore";
+
+/// A decoded Merkle proof operation, which can be ran against its arguments.
+pub trait ProofOperator: Debug + Send + Sync {
+    /// Run the operation against the given arguments, ie. the value to prove
+    /// or the output of the previous operation, and return its output.
+    fn run(&self, args: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, Error>;
+
+    /// The key under which the operation proves its argument is committed,
+    /// or an empty slice if the operation has no key.
+    fn key(&self) -> &[u8];
+}
+
+/// Proof that a value is committed under a key in a simple Merkle tree
+/// made of the key-value pairs of a map.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueOp {
+    key: Vec<u8>,
+    total: u64,
+    index: u64,
+    leaf_hash: Hash,
+    aunts: Vec<Hash>,
+}
+
+impl ValueOp {
+    /// Decode a `simple:v` proof operation.
+    pub fn decode(op: &ProofOp) -> Result<Self, Error> {
+        check_op_type(op, PROOF_OP_SIMPLE_VALUE)?;
+
+        let raw =
+            RawValueOp::decode(op.data.as_slice()).map_err(|e| Kind::InvalidProof.context(e))?;
+        let proof = raw
+            .proof
+            .ok_or_else(|| Kind::InvalidProof.context("missing simple proof"))?;
+
+        Ok(Self {
+            key: op.key.clone(),
+            total: u64::try_from(proof.total).map_err(|e| Kind::InvalidProof.context(e))?,
+            index: u64::try_from(proof.index).map_err(|e| Kind::InvalidProof.context(e))?,
+            leaf_hash: to_hash(&proof.leaf_hash)?,
+            aunts: proof
+                .aunts
+                .iter()
+                .map(|aunt| to_hash(aunt))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl ProofOperator for ValueOp {
+    fn run(&self, args: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, Error> {
+        let value = single_arg(args)?;
+
+        let mut kv_pair = Vec::new();
+        encode_bytes(&mut kv_pair, &self.key);
+        encode_bytes(&mut kv_pair, &Sha256::digest(&value));
+
+        if leaf_hash(&kv_pair) != self.leaf_hash {
+            return Err(Kind::InvalidProof
+                .context("leaf hash does not match the key and value")
+                .into());
+        }
+
+        let root = compute_hash_from_aunts(self.index, self.total, self.leaf_hash, &self.aunts)
+            .ok_or_else(|| Kind::InvalidProof.context("invalid aunts"))?;
+
+        Ok(vec![root.to_vec()])
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+/// Proof that a value is committed under a key in an IAVL tree.
+///
+/// Only the proofs of a single leaf, as produced for `iavl:v` operations, are supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IavlValueOp {
+    key: Vec<u8>,
+    proof: RawRangeProof,
+}
+
+impl IavlValueOp {
+    /// Decode an `iavl:v` proof operation.
+    pub fn decode(op: &ProofOp) -> Result<Self, Error> {
+        check_op_type(op, PROOF_OP_IAVL_VALUE)?;
+
+        let raw = RawIavlValueOp::decode_length_delimited(op.data.as_slice())
+            .map_err(|e| Kind::InvalidProof.context(e))?;
+        let proof = raw
+            .proof
+            .ok_or_else(|| Kind::InvalidProof.context("missing range proof"))?;
+
+        if proof.leaves.len() != 1 || !proof.inner_nodes.is_empty() {
+            return Err(Kind::InvalidProof
+                .context("range proofs of more than one leaf are not supported")
+                .into());
+        }
+
+        Ok(Self {
+            key: op.key.clone(),
+            proof,
+        })
+    }
+
+    /// Compute the root hash of the IAVL tree from the leaf and its path.
+    fn root_hash(&self) -> Hash {
+        let leaf = &self.proof.leaves[0];
+
+        let mut leaf_bytes = Vec::new();
+        encode_varint(&mut leaf_bytes, 0); // height
+        encode_varint(&mut leaf_bytes, 1); // size
+        encode_varint(&mut leaf_bytes, leaf.version);
+        encode_bytes(&mut leaf_bytes, &leaf.key);
+        encode_bytes(&mut leaf_bytes, &leaf.value_hash);
+
+        self.proof
+            .left_path
+            .iter()
+            .rev()
+            .fold(sha256(&leaf_bytes), |hash, node| {
+                let mut node_bytes = Vec::new();
+                encode_varint(&mut node_bytes, node.height.into());
+                encode_varint(&mut node_bytes, node.size);
+                encode_varint(&mut node_bytes, node.version);
+
+                if node.left.is_empty() {
+                    encode_bytes(&mut node_bytes, &hash);
+                    encode_bytes(&mut node_bytes, &node.right);
+                } else {
+                    encode_bytes(&mut node_bytes, &node.left);
+                    encode_bytes(&mut node_bytes, &hash);
+                }
+
+                sha256(&node_bytes)
+            })
+    }
+}
+
+impl ProofOperator for IavlValueOp {
+    fn run(&self, args: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, Error> {
+        let value = single_arg(args)?;
+        let leaf = &self.proof.leaves[0];
+
+        if leaf.key != self.key {
+            return Err(Kind::InvalidProof
+                .context("leaf key does not match the key of the operation")
+                .into());
+        }
+
+        if leaf.value_hash != sha256(&value) {
+            return Err(Kind::InvalidProof
+                .context("leaf value hash does not match the value")
+                .into());
+        }
+
+        Ok(vec![self.root_hash().to_vec()])
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+/// Proof that the root hash of a store is committed under the name of the store
+/// in the Cosmos SDK multistore.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStoreOp {
+    key: Vec<u8>,
+    store_infos: Vec<RawStoreInfo>,
+}
+
+impl MultiStoreOp {
+    /// Decode a `multistore` proof operation.
+    pub fn decode(op: &ProofOp) -> Result<Self, Error> {
+        check_op_type(op, PROOF_OP_MULTISTORE)?;
+
+        let raw = RawMultiStoreProofOp::decode(op.data.as_slice())
+            .map_err(|e| Kind::InvalidProof.context(e))?;
+        let proof = raw
+            .proof
+            .ok_or_else(|| Kind::InvalidProof.context("missing multistore proof"))?;
+
+        if proof.store_infos.is_empty() {
+            return Err(Kind::InvalidProof
+                .context("multistore proof has no stores")
+                .into());
+        }
+
+        // The store hash is checked against the entry found by `run`, while the root hash is
+        // computed from all of them: each store must thus appear once in the proof.
+        let mut names = BTreeSet::new();
+        if let Some(info) = proof
+            .store_infos
+            .iter()
+            .find(|info| !names.insert(info.name.as_str()))
+        {
+            return Err(Kind::InvalidProof
+                .context(format!("duplicate store {} in multistore proof", info.name))
+                .into());
+        }
+
+        Ok(Self {
+            key: op.key.clone(),
+            store_infos: proof.store_infos,
+        })
+    }
+
+    /// Compute the root hash of the multistore, ie. the hash of the simple Merkle tree
+    /// made of the pairs of the names of the stores and the hashes of their root hashes.
+    fn root_hash(&self) -> Hash {
+        let stores: BTreeMap<_, _> = self
+            .store_infos
+            .iter()
+            .map(|info| (info.name.as_bytes(), commit_hash(info)))
+            .collect();
+
+        let kv_pairs = stores
+            .into_iter()
+            .map(|(name, hash)| {
+                let mut kv_pair = Vec::new();
+                encode_bytes(&mut kv_pair, name);
+                encode_bytes(&mut kv_pair, &sha256(&sha256(hash)));
+                kv_pair
+            })
+            .collect();
+
+        simple_hash_from_byte_vectors(kv_pairs)
+    }
+}
+
+impl ProofOperator for MultiStoreOp {
+    fn run(&self, args: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, Error> {
+        let store_hash = single_arg(args)?;
+
+        let info = self
+            .store_infos
+            .iter()
+            .find(|info| info.name.as_bytes() == self.key.as_slice())
+            .ok_or_else(|| {
+                Kind::InvalidProof.context(format!(
+                    "store {} not found in multistore proof",
+                    String::from_utf8_lossy(&self.key)
+                ))
+            })?;
+
+        if commit_hash(info) != store_hash.as_slice() {
+            return Err(Kind::InvalidProof
+                .context(format!(
+                    "hash mismatch for store {}",
+                    String::from_utf8_lossy(&self.key)
+                ))
+                .into());
+        }
+
+        Ok(vec![self.root_hash().to_vec()])
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+/// Check that the given operation is of the expected type.
+fn check_op_type(op: &ProofOp, expected: &str) -> Result<(), Error> {
+    if op.field_type != expected {
+        return Err(Kind::InvalidProof
+            .context(format!(
+                "unexpected proof operation type: expected {}, got {}",
+                expected, op.field_type
+            ))
+            .into());
+    }
+
+    Ok(())
+}
+
+/// Extract the single argument of an operation.
+fn single_arg(mut args: Vec<Vec<u8>>) -> Result<Vec<u8>, Error> {
+    match args.len() {
+        1 => Ok(args.remove(0)),
+        n => Err(Kind::InvalidProof
+            .context(format!("expected 1 argument, got {}", n))
+            .into()),
+    }
+}
+
+fn to_hash(bytes: &[u8]) -> Result<Hash, Error> {
+    if bytes.len() != HASH_SIZE {
+        return Err(Kind::InvalidHashSize
+            .context(format!("hash invalid length: {}", bytes.len()))
+            .into());
+    }
+
+    let mut hash = [0; HASH_SIZE];
+    hash.copy_from_slice(bytes);
+    Ok(hash)
+}
+
+fn commit_hash(info: &RawStoreInfo) -> &[u8] {
+    info.core
+        .as_ref()
+        .and_then(|core| core.commit_id.as_ref())
+        .map(|commit_id| commit_id.hash.as_slice())
+        .unwrap_or_default()
+}
+
+fn sha256(bytes: &[u8]) -> Hash {
+    let mut hash = [0; HASH_SIZE];
+    hash.copy_from_slice(&Sha256::digest(bytes));
+    hash
+}
+
+/// Length-prefixed encoding of a byte slice, as done by Amino.
+fn encode_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    prost::encoding::encode_varint(bytes.len() as u64, buf);
+    buf.extend_from_slice(bytes);
+}
+
+/// Zigzag encoding of a signed integer, as done by Amino.
+fn encode_varint(buf: &mut Vec<u8>, value: i64) {
+    prost::encoding::encode_varint(((value << 1) ^ (value >> 63)) as u64, buf);
+}
+
+// The messages below are not part of the Tendermint protobuf definitions, but are
+// encoded by IAVL and the Cosmos SDK with Amino, which is compatible with protobuf.
+
+#[derive(Clone, PartialEq, Message)]
+struct RawIavlValueOp {
+    #[prost(message, optional, tag = "1")]
+    proof: Option<RawRangeProof>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawRangeProof {
+    #[prost(message, repeated, tag = "1")]
+    left_path: Vec<RawProofInnerNode>,
+    #[prost(message, repeated, tag = "2")]
+    inner_nodes: Vec<RawPathToLeaf>,
+    #[prost(message, repeated, tag = "3")]
+    leaves: Vec<RawProofLeafNode>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawPathToLeaf {
+    #[prost(message, repeated, tag = "1")]
+    nodes: Vec<RawProofInnerNode>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawProofInnerNode {
+    // Amino encodes `int8`s as zigzag varints, but `int64`s as plain varints
+    #[prost(sint32, tag = "1")]
+    height: i32,
+    #[prost(int64, tag = "2")]
+    size: i64,
+    #[prost(int64, tag = "3")]
+    version: i64,
+    #[prost(bytes, tag = "4")]
+    left: Vec<u8>,
+    #[prost(bytes, tag = "5")]
+    right: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawProofLeafNode {
+    #[prost(bytes, tag = "1")]
+    key: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    value_hash: Vec<u8>,
+    #[prost(int64, tag = "3")]
+    version: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawMultiStoreProofOp {
+    #[prost(message, optional, tag = "1")]
+    proof: Option<RawMultiStoreProof>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawMultiStoreProof {
+    #[prost(message, repeated, tag = "1")]
+    store_infos: Vec<RawStoreInfo>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawStoreInfo {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(message, optional, tag = "2")]
+    core: Option<RawStoreCore>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawStoreCore {
+    #[prost(message, optional, tag = "1")]
+    commit_id: Option<RawCommitId>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RawCommitId {
+    #[prost(int64, tag = "1")]
+    version: i64,
+    #[prost(bytes, tag = "2")]
+    hash: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subtle_encoding::base64;
+    use tendermint_proto::crypto::Proof as RawProof;
+
+    // Proof of a key in the `ibc` store of a Cosmos SDK chain, as returned by an `abci_query`
+    // with `prove` set (see `rpc/tests/support/abci_query.json`). The queried value and the
+    // header committing to the app hash are not part of the response, so the IAVL proof is
+    // checked against the hash of the `ibc` store committed in the multistore proof instead.
+    const IAVL_KEY: &[u8] = b"consensusState/ibconeclient/22";
+    const IAVL_DATA: &str = "8QEK7gEKKAgIEAwYHCIgG9RAkJgHlxNjmyzOW6bUAidhiRSja0x6+GXCVENPG1oKKAgGEAUYFyIgwRns+dJvjf1Zk2BaFrXz8inPbvYHB7xx2HCy9ima5f8KKAgEEAMYFyogOr8EGajEV6fG5fzJ2fAAvVMgRLhdMJTzCPlogl9rxlIKKAgCEAIYFyIgcjzX/a+2bFbnNldpawQqZ+kYhIwz5r4wCUzuu1IFW04aRAoeY29uc2Vuc3VzU3RhdGUvaWJjb25lY2xpZW50LzIyEiAZ1uuG60K4NHJZZMuS9QX6o4eEhica5jIHYwflRiYkDBgX";
+    const MULTISTORE_DATA: &str = "CvEECjAKBGJhbmsSKAomCIjYAxIg2MEyyonbZButYnvSRkf2bPQg+nqA+Am1MeDxG6F4p1UKLwoDYWNjEigKJgiI2AMSIN2YHczeuXNvyetrSFQpkCcJzfB6PXVCw0i/XShMgPnIChEKB3VwZ3JhZGUSBgoECIjYAwovCgNnb3YSKAomCIjYAxIgYM0TfBli7KxhY4nWgDSDPykhUJwtKFql9RU5l86WinQKLwoDaWJjEigKJgiI2AMSIFp6aJASeInQKF8y824zjmgcFORN6M+ECbgFfJkobKs8CjAKBG1haW4SKAomCIjYAxIgsZzwmLQ7PH1UeZ/vCUSqlQmfgt3CGfoMgJLkUqKCv0EKMwoHc3Rha2luZxIoCiYIiNgDEiCiBZoBLyDGj5euy3n33ik+SpqYK9eB5xbI+iY8ycYVbwo0CghzbGFzaGluZxIoCiYIiNgDEiAJz3gEYuIhdensHU3b5qH5ons2quepd6EaRgCHXab6PQoyCgZzdXBwbHkSKAomCIjYAxIglWLA5/THPTiTxAlaLHOBYFIzEJTmKPznItUwAc8zD+AKEgoIZXZpZGVuY2USBgoECIjYAwowCgRtaW50EigKJgiI2AMSIMS8dZ1j8F6JVVv+hB1rHBZC+gIFJxHan2hM8qDC64n/CjIKBnBhcmFtcxIoCiYIiNgDEiB8VIzExUHX+SvHZFz/P9NM9THnw/gTDDLVReuZX8htLgo4CgxkaXN0cmlidXRpb24SKAomCIjYAxIg3u/Nd4L+8LT8OXJCh14o8PHIJ/GLQwsmE7KYIl1GdSYKEgoIdHJhbnNmZXISBgoECIjYAw==";
+
+    fn make_op(field_type: &str, key: &[u8], data: &str) -> ProofOp {
+        ProofOp {
+            field_type: field_type.to_string(),
+            key: key.to_vec(),
+            data: base64::decode(data).unwrap(),
+        }
+    }
+
+    fn kv_pair(key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut kv_pair = Vec::new();
+        encode_bytes(&mut kv_pair, key);
+        encode_bytes(&mut kv_pair, &sha256(value));
+        kv_pair
+    }
+
+    fn encode(message: &impl Message) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        message.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn simple_value_op() {
+        let leaves = vec![
+            kv_pair(b"a", b"1"),
+            kv_pair(b"b", b"2"),
+            kv_pair(b"c", b"3"),
+        ];
+        let root = simple_hash_from_byte_vectors(leaves.clone());
+
+        let raw = RawValueOp {
+            key: b"b".to_vec(),
+            proof: Some(RawProof {
+                total: 3,
+                index: 1,
+                leaf_hash: leaf_hash(&leaves[1]).to_vec(),
+                aunts: vec![
+                    leaf_hash(&leaves[0]).to_vec(),
+                    leaf_hash(&leaves[2]).to_vec(),
+                ],
+            }),
+        };
+        let op = ProofOp {
+            field_type: PROOF_OP_SIMPLE_VALUE.to_string(),
+            key: b"b".to_vec(),
+            data: encode(&raw),
+        };
+
+        let op = ValueOp::decode(&op).unwrap();
+        assert_eq!(op.run(vec![b"2".to_vec()]).unwrap(), vec![root.to_vec()]);
+        assert!(op.run(vec![b"3".to_vec()]).is_err());
+        assert!(op.run(vec![]).is_err());
+    }
+
+    fn iavl_op() -> IavlValueOp {
+        IavlValueOp::decode(&make_op(PROOF_OP_IAVL_VALUE, IAVL_KEY, IAVL_DATA)).unwrap()
+    }
+
+    fn multistore_op() -> MultiStoreOp {
+        MultiStoreOp::decode(&make_op(PROOF_OP_MULTISTORE, b"ibc", MULTISTORE_DATA)).unwrap()
+    }
+
+    #[test]
+    fn iavl_root_is_committed_in_multistore() {
+        let iavl_root = iavl_op().root_hash().to_vec();
+        assert!(multistore_op().run(vec![iavl_root]).is_ok());
+    }
+
+    #[test]
+    fn iavl_proof_with_tampered_aunt() {
+        let iavl = iavl_op();
+        let multistore = multistore_op();
+        assert!(!iavl.proof.left_path.is_empty());
+
+        for i in 0..iavl.proof.left_path.len() {
+            let mut op = iavl.clone();
+            let node = &mut op.proof.left_path[i];
+            let aunt = if node.left.is_empty() {
+                &mut node.right
+            } else {
+                &mut node.left
+            };
+            aunt[0] ^= 1;
+
+            assert!(multistore.run(vec![op.root_hash().to_vec()]).is_err());
+        }
+    }
+
+    #[test]
+    fn iavl_proof_with_tampered_value() {
+        let mut op = iavl_op();
+        assert!(op.run(vec![b"value".to_vec()]).is_err());
+
+        // Even once the leaf commits to the tampered value, the root hash of the tree
+        // does not match the one committed in the multistore
+        op.proof.leaves[0].value_hash = sha256(b"value").to_vec();
+        let root = op.run(vec![b"value".to_vec()]).unwrap();
+        assert!(multistore_op().run(root).is_err());
+
+        op.key = b"consensusState/ibconeclient/23".to_vec();
+        assert!(op.run(vec![b"value".to_vec()]).is_err());
+    }
+
+    #[test]
+    fn multistore_with_tampered_store_hash() {
+        let iavl_root = iavl_op().root_hash().to_vec();
+        let multistore = multistore_op();
+        let app_hash = multistore.run(vec![iavl_root.clone()]).unwrap();
+
+        // Another store than the `ibc` one is tampered with, which changes the app hash
+        let mut op = multistore.clone();
+        let info = op
+            .store_infos
+            .iter_mut()
+            .find(|info| info.name == "bank")
+            .unwrap();
+        let commit_id = info.core.as_mut().unwrap().commit_id.as_mut().unwrap();
+        commit_id.hash[0] ^= 1;
+        assert_ne!(op.run(vec![iavl_root.clone()]).unwrap(), app_hash);
+
+        // The `ibc` store is not committed to under another name
+        op.key = b"bank".to_vec();
+        assert!(op.run(vec![iavl_root]).is_err());
+    }
+
+    fn encode_multistore(store_infos: Vec<RawStoreInfo>) -> ProofOp {
+        let raw = RawMultiStoreProofOp {
+            proof: Some(RawMultiStoreProof { store_infos }),
+        };
+        ProofOp {
+            field_type: PROOF_OP_MULTISTORE.to_string(),
+            key: b"ibc".to_vec(),
+            data: encode(&raw),
+        }
+    }
+
+    #[test]
+    fn multistore_with_duplicate_store() {
+        let iavl = iavl_op();
+        let multistore = multistore_op();
+        let app_hash = multistore.run(vec![iavl.root_hash().to_vec()]).unwrap();
+
+        // A forged `ibc` store, committing to a tampered value, is listed before the genuine
+        // one, which is the one the root hash would be computed from.
+        let mut forged_iavl = iavl.clone();
+        forged_iavl.proof.leaves[0].value_hash = sha256(b"value").to_vec();
+        let forged_root = forged_iavl.run(vec![b"value".to_vec()]).unwrap();
+
+        let mut forged_info = multistore
+            .store_infos
+            .iter()
+            .find(|info| info.name == "ibc")
+            .cloned()
+            .unwrap();
+        let commit_id = forged_info
+            .core
+            .as_mut()
+            .unwrap()
+            .commit_id
+            .as_mut()
+            .unwrap();
+        commit_id.hash = forged_root[0].clone();
+
+        let mut store_infos = vec![forged_info];
+        store_infos.extend(multistore.store_infos.iter().cloned());
+
+        let forged = MultiStoreOp {
+            key: b"ibc".to_vec(),
+            store_infos: store_infos.clone(),
+        };
+        assert_eq!(forged.run(forged_root).unwrap(), app_hash);
+
+        assert!(MultiStoreOp::decode(&encode_multistore(store_infos)).is_err());
+    }
+
+    #[test]
+    fn multistore_without_stores() {
+        assert!(MultiStoreOp::decode(&encode_multistore(vec![])).is_err());
+    }
+
+    #[test]
+    fn wrong_op_type() {
+        let op = make_op(PROOF_OP_MULTISTORE, IAVL_KEY, IAVL_DATA);
+        assert!(IavlValueOp::decode(&op).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Runtime verifying chained Merkle proof operations against a root hash
+
+use std::collections::HashMap;
+use std::fmt;
+
+use subtle_encoding::hex;
+
+use super::ops::{
+    IavlValueOp, MultiStoreOp, ProofOperator, ValueOp, PROOF_OP_IAVL_VALUE, PROOF_OP_MULTISTORE,
+    PROOF_OP_SIMPLE_VALUE,
+};
+use super::proof::{Proof, ProofOp};
+use crate::{Error, Kind};
+
+/// Decoder of a proof operation of a given type
+pub type OpDecoder = fn(&ProofOp) -> Result<Box<dyn ProofOperator>, Error>;
+
+/// Decodes the operations of Merkle proofs according to their type, and verifies
+/// these against a root hash.
+///
+/// The default runtime supports the `simple:v`, `iavl:v` and `multistore` operations.
+#[derive(Clone)]
+pub struct ProofRuntime {
+    decoders: HashMap<String, OpDecoder>,
+}
+
+impl ProofRuntime {
+    /// Create a runtime which does not support any proof operation.
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Register the decoder of the proof operations of the given type.
+    pub fn register(&mut self, op_type: impl Into<String>, decoder: OpDecoder) {
+        self.decoders.insert(op_type.into(), decoder);
+    }
+
+    /// Decode the operations of the given proof.
+    pub fn decode(&self, proof: &Proof) -> Result<Vec<Box<dyn ProofOperator>>, Error> {
+        proof
+            .ops
+            .iter()
+            .map(|op| {
+                let decoder = self
+                    .decoders
+                    .get(&op.field_type)
+                    .ok_or_else(|| Kind::UnknownProofOp(op.field_type.clone()))?;
+
+                decoder(op)
+            })
+            .collect()
+    }
+
+    /// Verify that running the operations of the given proof against the given arguments
+    /// yields the given root hash.
+    ///
+    /// The `keys` form the path under which the arguments are committed, from the outermost
+    /// to the innermost one, eg. the name of the store and the key within that store.
+    /// Each operation with a key consumes the innermost key left in the path.
+    pub fn verify(
+        &self,
+        proof: &Proof,
+        root: &[u8],
+        keys: &[Vec<u8>],
+        args: Vec<Vec<u8>>,
+    ) -> Result<(), Error> {
+        let mut keys = keys.iter().rev();
+        let mut args = args;
+
+        for (i, op) in self.decode(proof)?.iter().enumerate() {
+            let key = op.key();
+
+            if !key.is_empty() {
+                match keys.next() {
+                    Some(expected) if expected.as_slice() == key => {}
+                    Some(expected) => {
+                        return Err(Kind::InvalidProof
+                            .context(format!(
+                                "key mismatch on operation #{}: expected {}, got {}",
+                                i,
+                                String::from_utf8_lossy(expected),
+                                String::from_utf8_lossy(key)
+                            ))
+                            .into())
+                    }
+                    None => {
+                        return Err(Kind::InvalidProof
+                            .context(format!(
+                                "key path has insufficient number of parts: no key left for {}",
+                                String::from_utf8_lossy(key)
+                            ))
+                            .into())
+                    }
+                }
+            }
+
+            args = op.run(args)?;
+        }
+
+        match args.first() {
+            Some(computed) if computed.as_slice() == root => {}
+            computed => {
+                return Err(Kind::InvalidProof
+                    .context(format!(
+                        "calculated root hash is invalid: expected {}, got {}",
+                        display_hex(root),
+                        display_hex(computed.map(Vec::as_slice).unwrap_or_default())
+                    ))
+                    .into())
+            }
+        }
+
+        if keys.next().is_some() {
+            return Err(Kind::InvalidProof
+                .context("key path was not entirely consumed")
+                .into());
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the given value is committed under the given key path
+    /// in the tree with the given root hash.
+    ///
+    /// See [`ProofRuntime::verify`].
+    pub fn verify_value(
+        &self,
+        proof: &Proof,
+        root: &[u8],
+        keys: &[Vec<u8>],
+        value: &[u8],
+    ) -> Result<(), Error> {
+        self.verify(proof, root, keys, vec![value.to_vec()])
+    }
+}
+
+impl Default for ProofRuntime {
+    fn default() -> Self {
+        let mut runtime = Self::empty();
+
+        runtime.register(PROOF_OP_SIMPLE_VALUE, |op| {
+            Ok(Box::new(ValueOp::decode(op)?))
+        });
+        runtime.register(PROOF_OP_IAVL_VALUE, |op| {
+            Ok(Box::new(IavlValueOp::decode(op)?))
+        });
+        runtime.register(PROOF_OP_MULTISTORE, |op| {
+            Ok(Box::new(MultiStoreOp::decode(op)?))
+        });
+
+        runtime
+    }
+}
+
+impl fmt::Debug for ProofRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofRuntime")
+            .field("op_types", &self.decoders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn display_hex(bytes: &[u8]) -> String {
+    String::from_utf8(hex::encode_upper(bytes)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{leaf_hash, simple_hash_from_byte_vectors};
+    use prost::Message;
+    use sha2::{Digest, Sha256};
+    use tendermint_proto::crypto::{Proof as RawProof, ValueOp as RawValueOp};
+
+    fn encode(message: &impl Message) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        message.encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Proof of the value of the given key, in a simple Merkle tree of a single key-value pair.
+    fn make_proof(key: &[u8], value: &[u8]) -> (Proof, Vec<u8>) {
+        let mut kv_pair = vec![key.len() as u8];
+        kv_pair.extend_from_slice(key);
+        kv_pair.push(32);
+        kv_pair.extend_from_slice(&Sha256::digest(value));
+
+        let raw = RawValueOp {
+            key: key.to_vec(),
+            proof: Some(RawProof {
+                total: 1,
+                index: 0,
+                leaf_hash: leaf_hash(&kv_pair).to_vec(),
+                aunts: vec![],
+            }),
+        };
+        let proof = Proof {
+            ops: vec![ProofOp {
+                field_type: PROOF_OP_SIMPLE_VALUE.to_string(),
+                key: key.to_vec(),
+                data: encode(&raw),
+            }],
+        };
+
+        (proof, simple_hash_from_byte_vectors(vec![kv_pair]).to_vec())
+    }
+
+    #[test]
+    fn verify_value() {
+        let runtime = ProofRuntime::default();
+        let (proof, root) = make_proof(b"key", b"value");
+
+        let keys = [b"key".to_vec()];
+        assert!(runtime.verify_value(&proof, &root, &keys, b"value").is_ok());
+        assert!(runtime
+            .verify_value(&proof, &root, &keys, b"other")
+            .is_err());
+        assert!(runtime
+            .verify_value(&proof, &[0; 32], &keys, b"value")
+            .is_err());
+
+        // Key path mismatch
+        let keys = [b"other".to_vec()];
+        assert!(runtime
+            .verify_value(&proof, &root, &keys, b"value")
+            .is_err());
+        assert!(runtime.verify_value(&proof, &root, &[], b"value").is_err());
+
+        // Key path not entirely consumed
+        let keys = [b"store".to_vec(), b"key".to_vec()];
+        assert!(runtime
+            .verify_value(&proof, &root, &keys, b"value")
+            .is_err());
+    }
+
+    #[test]
+    fn unknown_op_type() {
+        let (proof, root) = make_proof(b"key", b"value");
+        let keys = [b"key".to_vec()];
+
+        let err = ProofRuntime::empty()
+            .verify_value(&proof, &root, &keys, b"value")
+            .unwrap_err();
+        assert!(err.to_string().contains(PROOF_OP_SIMPLE_VALUE));
+    }
+}